@@ -0,0 +1,695 @@
+# ArchBox CLI messages (en-US)
+#
+# To contribute a translation, copy this file to locales/<lang>/archbox.ftl, translate the
+# messages and register the file in LOCALES in src/i18n/mod.rs. Messages you leave out fall
+# back to English.
+
+## Transaction size summary
+
+size-column-package = Package
+size-column-version = Version
+size-column-download = Download
+size-column-net-change = Net Change
+size-total-download = Total Download Size:
+size-net-upgrade = Net Upgrade Size:
+size-incomplete = (sizes marked ? could not be determined and are not included)
+
+## install
+
+install-start = Installing packages...
+install-nothing-found = No packages found matching the criteria
+install-unknown-category = No packages in category '{ $category }'
+install-category-prompt = Select packages from { $category } to install
+exclude-skipping = Excluding { $count } { $count ->
+        [one] package
+       *[other] packages
+    }: { $packages }
+install-plan = Will install { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+install-plan-dry-run = Would install { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+install-confirm = Continue with installation?
+install-cancelled = Installation cancelled
+install-progress = Installing { $package }
+install-done = Installed { $package }
+install-failed = Failed to install { $package }: { $error }
+install-complete = Installation complete
+install-set-one-package = --set changes one definition, so name a single package to install
+install-overridden = Installing { $package } with { $fields } for this run only
+install-optional-prompt = Also install these optional dependencies?
+install-optional-suggested-by = suggested by { $package }
+install-optional-skipped = Leaving out { $count } optional { $count ->
+        [one] dependency
+       *[other] dependencies
+    }: { $packages } (see installation.install_optional)
+
+## search and list
+
+search-start = Searching for '{ $query }'...
+search-no-results = No packages found matching the search criteria
+list-empty = No packages found
+list-uncategorized = uncategorized
+list-groups = groups: { $groups }
+packages-found = Found { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+status-installed = installed
+status-available = available
+status-update-available = update available (have { $current })
+status-error = check failed
+
+## remove
+
+remove-start = Preparing to remove packages...
+remove-not-installed = Package '{ $package }' is not installed
+remove-nothing = No packages to remove
+remove-required-by = '{ $package }' is required by: { $dependents }
+remove-blocked = Refusing to remove packages required by other installed packages (use --cascade to remove them too)
+remove-plan = Will remove { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+remove-plan-dry-run = Would remove { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+remove-dependent = dependent
+remove-purge-marker = purge
+remove-confirm = Continue with removal?
+remove-cancelled = Removal cancelled
+remove-done = Removed { $package }
+remove-failed = Failed to remove { $package }: { $error }
+remove-pacman-failed = Failed to remove pacman packages: { $error }
+remove-flatpak-failed = Failed to remove Flatpak: { $error }
+remove-unsupported = Removal not implemented for this installation method
+purge-backup-failed = Skipping { $path }: backup failed: { $error }
+purge-delete-failed = Failed to delete { $path }: { $error }
+purge-done = Purged { $path }
+
+## update
+
+update-check-start = Checking for updates...
+update-up-to-date = All packages are up to date
+update-available = { $count } { $count ->
+        [one] update
+       *[other] updates
+    } available:
+update-start = Updating installed packages...
+update-found = Found { $count } { $count ->
+        [one] package
+       *[other] packages
+    } to update:
+update-confirm = Continue with update?
+update-cancelled = Update cancelled
+update-done = Updated { $package }
+update-failed = Failed to update { $package }: { $error }
+update-replaceable = { $old } is replaced by { $new }; `archbox update` offers to migrate
+update-replace-confirm = Replace { $old } with { $new }?
+update-replaced = Replaced { $old } with { $new }
+update-replace-kept = Kept { $old } because { $dependents } still require it
+update-upstream-newer = Newer upstream than the package definitions (run `archbox update --definitions-only` or report it to the definitions maintainers):
+package-deprecated = { $package } is deprecated
+package-deprecated-reason = { $package } is deprecated: { $reason }
+package-deprecated-successor = Use { $successor } instead
+upgrade-start = Upgrading system packages...
+upgrade-holding-back = Holding back packages: { $packages }
+upgrade-failed = System upgrade failed (pacman exited with { $status })
+upgrade-complete = System upgrade complete
+definitions-start = Updating package definitions...
+definitions-downloading = Downloading latest package definitions...
+definitions-updated = Package definitions updated
+definitions-updated-success = Package definitions updated successfully
+definitions-failed-short = Update failed
+definitions-failed = Failed to download package definitions: { $error }
+definitions-no-url = No update URL configured
+definitions-stale = Package definitions are { $days } { $days ->
+        [one] day
+       *[other] days
+    } old, run `archbox update` to refresh
+
+## info
+
+info-status-installed = Installed
+info-status-not-installed = Not Installed
+info-none = None
+info-optional = optional
+info-version = Version:
+info-status = Status:
+info-description = Description:
+info-packages = Packages:
+info-flags = Flags:
+info-package = Package:
+info-helper = Helper:
+info-url = URL:
+info-fallback-download = (fallback, there is no { $arch } build)
+info-fallback-source = (built from source, there is no { $arch } build)
+info-install-path = Install path:
+info-id = ID:
+info-remote = Remote:
+info-author = Author:
+info-homepage = Homepage:
+info-repository = Repository:
+info-license = License:
+info-size = Size:
+info-tags = Tags:
+info-details = Details:
+info-categories = Categories:
+info-dependencies = Dependencies:
+info-installation-method = Installation Method:
+info-metadata = Metadata:
+info-dependency-tree = Dependency Tree:
+tree-installed = installed
+tree-update-available = update available
+tree-error = check failed
+tree-not-installed = not installed
+tree-repeated = (shown above)
+tree-unknown = (no definition)
+info-method = Method:
+info-method-pacman = pacman
+info-method-aur = AUR
+info-method-binary-download = Binary download
+info-method-flatpak = Flatpak
+info-scope = Scope:
+info-platforms = Platforms:
+info-unsupported = (not available for { $arch })
+info-kernel-module = Kernel modules:
+info-yes = yes
+info-only-on = only on { $platform }
+
+## restore
+
+restore-none = No backups found for '{ $package }'
+restore-list-header = { $count } { $count ->
+        [one] backup
+       *[other] backups
+    } for { $package }:
+restore-file-count = { $count } { $count ->
+        [one] file
+       *[other] files
+    }
+restore-unknown-backup = No backup '{ $timestamp }' for package '{ $package }'
+restore-plan = Will restore { $count } { $count ->
+        [one] file
+       *[other] files
+    } from backup { $timestamp }:
+restore-confirm = Overwrite current files with the backup?
+restore-cancelled = Restore cancelled
+restore-done = Restored { $count } { $count ->
+        [one] file
+       *[other] files
+    } for { $package }
+
+## rollback
+
+rollback-not-binary = '{ $package }' is not a binary package; only binaries keep previous versions
+rollback-no-versions = No installed versions found for '{ $package }'
+rollback-list-header = Versions of { $package } kept on disk:
+rollback-active = active
+rollback-unknown-version = Version '{ $version }' of '{ $package }' is not installed (see `archbox rollback { $package } --list`)
+rollback-no-previous = No previous version of '{ $package }' to roll back to
+rollback-already-active = { $package } { $version } is already active
+rollback-unknown = unknown
+rollback-plan = Will switch { $package } from { $from } to { $to }
+rollback-confirm = Continue with rollback?
+rollback-cancelled = Rollback cancelled
+rollback-done = { $package } is now at version { $version }
+
+## completions
+
+completions-installed = Installed { $shell } completions to { $path }
+completions-zsh-fpath = Make sure { $dir } is in your fpath before compinit runs
+
+## config
+
+config-title = ArchBox Configuration
+config-binary-directory = Binary directory:
+config-verify-checksums = Verify checksums:
+config-create-backups = Create backups:
+config-download-timeout = Download timeout:
+config-build-timeout = Build command timeout:
+config-script-timeout = Script timeout:
+config-lock-wait = Database lock wait:
+config-review-pkgbuilds = Review PKGBUILDs:
+config-versions-kept = Versions kept:
+config-remove-build-dependencies = Remove build dependencies:
+config-build-cache = Build cache:
+config-compiler-cache = Compiler cache:
+config-modify-user-groups = Modify user groups:
+config-install-optional = Install optional dependencies:
+config-temp-directory = Temp directory:
+config-update-url = Update URL:
+config-update-interval = Update interval:
+config-auto-update = Auto update:
+config-system-upgrade = System upgrade:
+config-pinned = Pinned:
+config-ignored-on-update = Ignored on update:
+config-use-colors = Use colors:
+config-show-progress = Show progress:
+config-log-level = Log level:
+config-package-paths = Package Paths:
+config-installation = Installation:
+config-repository = Repository:
+config-ui = UI:
+config-template-variables = Template Variables:
+config-aur-helper = AUR Helper:
+config-profile = Profile (applied by sync):
+config-notifications = Notifications:
+config-webhook-url = Webhook:
+config-network = Network:
+config-ca-bundle = CA bundle:
+config-client-certificate = Client certificate:
+config-ignore-system-proxy = Ignore system proxy:
+config-config-file = Config file:
+config-log-file = Log file:
+config-log-file-details = { $path } ({ $level }, rotated at { $size } MB, { $files } kept)
+config-language = Language:
+config-language-from-env = from environment
+config-not-set = not set
+config-set = Set { $key } to: { $value }
+config-set-aur-helper = Set AUR helper to: { $value }
+config-set-variable = Set template variable { $name } to: { $value }
+config-invalid-bool = Invalid boolean value
+config-invalid-number = Invalid number value
+config-unknown-key = Unknown configuration key: { $key }
+config-path-added = Added package path: { $path }
+config-path-removed = Removed package path: { $path }
+config-reset = Configuration reset to defaults
+
+## interactive installer
+
+interactive-select-packages = Select packages to install
+interactive-custom-installation = Custom installation
+interactive-choose-profile = Choose installation profile
+interactive-selected = Following packages selected:
+interactive-conflicts = Package conflicts detected:
+interactive-conflict = { $first } conflicts with { $second }
+interactive-keep = Keep { $package }
+interactive-skip-both = Skip both
+interactive-resolve-conflict = Resolve conflict between { $first } and { $second }
+
+## package tables
+
+table-name = NAME
+table-version = VERSION
+table-method = METHOD
+table-status = STATUS
+table-categories = CATEGORIES
+table-description = DESCRIPTION
+table-size = SIZE
+table-id = ID
+table-date = DATE
+table-command = COMMAND
+table-result = RESULT
+table-rank = RANK
+table-endpoint = ENDPOINT
+table-kind = KIND
+table-latency = LATENCY
+table-throughput = THROUGHPUT
+
+## why
+
+why-not-installed = { $package } is not installed
+why-explicit = { $package } was installed explicitly
+why-dependency = { $package } is installed as a dependency of:
+why-orphan = { $package } was installed as a dependency, but no installed package requires it anymore
+why-also-required = It is also required by:
+
+## size
+
+size-measuring = Measuring installed packages...
+size-empty = No installed packages
+size-by-method = Totals by method:
+size-total = Total:
+
+## history
+
+history-empty = No transactions recorded yet
+history-unknown = No transaction matches '{ $id }'
+history-ambiguous = '{ $id }' matches { $count } transactions; use more of the id
+history-transaction = Transaction { $id }
+history-command = Command:
+history-started = Started:
+history-duration = Duration:
+history-result = Result:
+history-ok = succeeded
+history-failed = failed
+history-interrupted = interrupted
+history-packages = Packages:
+history-commands = Commands run:
+history-exit-code = exit code { $code }
+history-log = Log excerpt:
+history-rollback = Rollback:
+history-not-rollbackable = Not rollback-able: no previous versions or backups are kept for this transaction
+
+## sync
+
+sync-no-profile = No profile configured; only upgrading installed packages (set one with `archbox config set profile <name>`)
+sync-prune-needs-profile = --prune needs a profile to decide what to keep; pass --profile or set `profile` in the config
+sync-unknown-profile = Profile not found: { $profile }
+sync-machine-groups = Including groups for { $class }: { $groups }
+sync-missing-definition = No package definition for { $package }, skipping it
+sync-unsupported-platform = { $package } is not available for this machine's architecture, skipping it
+sync-up-to-date = Everything is in sync
+sync-plan = Sync plan:
+sync-plan-dry-run = Sync plan (dry run):
+sync-summary = { $install } to install, { $remove } to remove, { $upgrade } to upgrade
+sync-confirm = Apply these changes?
+sync-cancelled = Sync cancelled
+sync-removing = Removing { $package }
+sync-upgrading = Upgrading { $package }
+sync-done = Sync complete
+sync-failed = { $count } { $count ->
+        [one] change
+       *[other] changes
+    } failed
+
+## env
+
+env-no-manifest = No { $file } found in this directory or its parents
+env-this-project = this project
+env-all-present = All tools for { $project } are installed
+env-check-header = Tools for { $project }:
+env-present = installed
+env-missing = missing
+env-unknown = no package definition
+env-check-failed = { $count } { $count ->
+        [one] tool is
+       *[other] tools are
+    } not installed; run `archbox env install`
+
+## provision
+
+provision-nothing-requested = Nothing to provision; pass --profile or package names
+provision-not-arch = { $root } does not look like an Arch installation (run pacstrap first)
+provision-plan = Packages to install into { $root }:
+provision-plan-dry-run = Packages to install into { $root } (dry run):
+provision-confirm = Install these packages into the new system?
+provision-cancelled = Provisioning cancelled
+provision-script-failed = Profile post-install script failed: { $error }
+provision-failed = { $count } { $count ->
+        [one] step
+       *[other] steps
+    } failed
+provision-done = Provisioned { $root }
+provision-next-steps = Per-user settings were skipped; run `archbox sync` after booting into the new system to apply them.
+
+## containerize
+
+containerize-nothing-requested = Nothing to put in the image; pass --profile or package names
+containerize-aur = { $package } is an AUR package; makepkg refuses to build as root, so it will fail in the image
+containerize-flatpak = { $package } is a Flatpak, which can't be installed during an image build
+containerize-written = Wrote the build context to { $dir }
+containerize-build-hint = Build it with `podman build -t <tag> { $dir }`, or pass --tag to build directly
+containerize-no-engine = No container engine found; install podman, docker or buildah, or build the written context yourself
+containerize-building = Building { $tag } with { $engine }
+containerize-built = Built image { $tag }
+
+## cloud-init
+
+cloud-init-nothing-requested = Nothing to export; pass --profile or package names
+cloud-init-skipped = Left out for { $package }: { $reason }
+cloud-init-written = Wrote cloud-init user-data for { $count } { $count ->
+        [one] package
+       *[other] packages
+    } to { $path }
+
+## use
+
+use-not-installed = Version '{ $version }' of '{ $package }' is not installed (see `archbox use { $package }`)
+use-pinned = Pinned { $package } { $version } in { $file }
+use-pinned-by = pinned by { $file }
+use-overridden = { $package } { $version } is still pinned here by { $file }
+use-pin-missing = { $file } pins { $package } { $version }, which is not installed
+use-shims-not-on-path = Pins only apply through the shims in { $dir }; add it to PATH ahead of other directories (`archbox init` does this for user installs)
+
+## alternatives
+
+alternatives-none = No package definitions provide a capability
+alternatives-unknown = No package provides '{ $capability }'
+alternatives-chosen = chosen
+alternatives-installed = installed
+alternatives-unresolved = Several packages provide { $capability }; choose one with `archbox alternatives set { $capability } <package>`
+alternatives-not-provider = '{ $package }' does not provide '{ $capability }' (providers: { $providers })
+alternatives-set = { $capability } is now provided by { $package }
+alternatives-install-hint = { $package } is not installed yet; install it with `archbox install { $package }`
+alternatives-not-set = No provider chosen for { $capability }
+alternatives-unset = Forgot the chosen provider for { $capability }
+
+## setup
+
+setup-title = ArchBox setup
+setup-intro = Answer a few questions to create your configuration. Everything can be changed later with `archbox config` or by running `archbox setup` again.
+setup-first-run = No configuration found at { $path }. Set up ArchBox now?
+setup-defaults-written = Wrote the default configuration to { $path }; run `archbox setup` to change it
+setup-saved = Configuration saved to { $path }
+setup-no-terminal = `archbox setup` needs a terminal; use `archbox setup --defaults` to write the defaults instead
+setup-not-installed = not installed
+setup-aur-prompt = AUR helper
+setup-aur-auto = Detect automatically (yay or paru when installed)
+setup-aur-native = Built-in AUR backend
+setup-binary-dir-prompt = Directory for installed binaries
+setup-binary-dir-absolute = Use an absolute path (or one starting with ~/)
+setup-binary-dir-not-on-path = { $dir } is not on PATH; `archbox init` prints the shell setup that adds it
+setup-sources-prompt = Package definition directories to read
+setup-remote-prompt = URL of remote package definitions (empty for local definitions only)
+setup-remote-invalid = Enter an http(s) URL or leave it empty
+setup-auto-update-prompt = Refresh remote definitions automatically every { $hours } hours?
+setup-profile-prompt = Profile `archbox sync` applies to this machine
+setup-profile-none = None
+setup-machine-groups-included = This is { $class }, so the profile also installs: { $groups }
+setup-machine-groups-suggested = This is { $class }; groups suited to it: { $groups }. Include them with `archbox profile create --interactive`
+setup-completions-prompt = Install shell completions for
+setup-completions-failed = Could not install { $shell } completions: { $error }
+
+## profile
+
+profile-source = { $source }
+profile-package-count = { $count ->
+    [one] 1 package
+   *[other] { $count } packages
+}
+profile-name-required = Give the profile a name, or use --interactive to be asked for one
+profile-invalid-name = Invalid profile name '{ $name }': use letters, digits, '-' and '_'
+profile-exists = Profile '{ $name }' already exists; use --force to replace it
+profile-unknown-group = Unknown package group: { $group }
+profile-unknown-package = No package definition for '{ $package }'; it will be skipped when the profile is applied
+profile-saved = Saved profile { $name } to { $path }
+
+## repo
+repo-sources = Definition sources:
+repo-local = local
+repo-remote = remote
+repo-no-url = (no update_url configured)
+repo-synced = synced { $at }
+repo-never-synced = never synced
+repo-mirrors = { $count ->
+        [one] 1 mirror
+       *[other] { $count } mirrors
+    }
+repo-stale-hint = Remote definitions are older than repository.update_interval; run 'archbox update --definitions-only'
+
+profile-apply-hint = Apply it with `archbox config set profile { $name }` and `archbox sync`
+profile-no-terminal = Creating a profile interactively needs a terminal; pass --group and --package instead
+profile-name-prompt = Profile name
+profile-overwrite-prompt = Profile '{ $name }' already exists. Replace it?
+profile-shadows-builtin = This replaces the built-in '{ $name }' profile
+profile-description-prompt = Description
+profile-groups-prompt = Package groups to include
+profile-machine-groups-prompt = Also install the groups suited to each machine it is applied on? ({ $class } here: { $groups })
+profile-machine-groups-none = none
+profile-optional-prompt = Optional packages from these groups
+profile-exclude-prompt = Group packages to leave out
+profile-categories-done = Done
+profile-categories-prompt = Browse a category to add packages ({ $count } added so far)
+profile-category-packages-prompt = Packages from { $category }
+profile-step-prompt = Post-install command { $number } (empty to finish)
+profile-save-prompt = Save this profile?
+profile-summary-groups = Groups:
+profile-summary-packages = Packages:
+profile-summary-excluded = Excluded:
+profile-summary-steps = Post-install:
+profile-summary-machine-groups = Plus the groups suited to the machine
+profile-summary-prefer = Preferred:
+profile-apply-installed = Every package of profile { $name } is already installed
+profile-apply-script = Post-install script of { $name }:
+profile-apply-failed = { $count ->
+        [one] 1 package failed to install; the post-install script was not run
+       *[other] { $count } packages failed to install; the post-install script was not run
+    }
+profile-applied = Applied profile { $name }
+profile-script-confirm = Run the post-install script now?
+profile-script-skipped = Skipped the post-install script
+profile-script-simulated = Simulating: the post-install script was not run
+profile-script-no-terminal = Running the post-install script needs confirmation; pass --yes to run it without asking
+profile-script-failed = Post-install script exited with { $status }
+profile-conflict-prompt = Groups { $first } and { $second } conflict over { $packages }; which one should be installed?
+profile-prefer-hint = Add { $group } to the profile's `prefer` list to keep this choice
+profile-unsettled-conflict = Groups { $first } and { $second } conflict; pass --prefer with the one to keep, or you will be asked when the profile is applied
+
+## group
+group-unknown = No group named { $group }; available groups: { $groups }
+group-optional-count = { $count ->
+        [one] 1 optional
+       *[other] { $count } optional
+    }
+group-hardware-count = { $count ->
+        [one] 1 hardware-specific
+       *[other] { $count } hardware-specific
+    }
+group-for-machine = for { $class }
+group-packages = Packages
+group-optional = Optional packages
+group-hardware = Hardware-specific packages
+group-no-definition = no definition
+group-unsupported = not available for this architecture
+group-not-this-machine = (not for this machine's hardware)
+group-conflicts-with = Conflicts with:
+group-conflict = Groups { $first } and { $second } conflict over { $packages }; list the one to keep in the profile's `prefer` setting
+group-conflicts = Group { $group } conflicts with installed packages: { $packages }
+
+## benchmark
+
+benchmark-no-endpoints = Nothing to benchmark; set repository.update_url, repository.mirrors or installation.artifact_mirrors in the config file
+benchmark-no-sample = No binary or AppImage download starts with { $upstream }, so its mirrors can't be measured
+benchmark-measuring = Measuring { $endpoint }...
+benchmark-failed = failed
+benchmark-saved = Saved the ranking to { $path }; downloads now try the fastest endpoints first
+benchmark-save-hint = Run with --save to try endpoints in this order
+
+## daemon
+
+daemon-listening = Listening on { $socket }; press Ctrl+C to stop
+daemon-stopped = Daemon stopped
+
+## serve
+
+serve-listening = Serving the HTTP API on http://{ $address }; press Ctrl+C to stop
+serve-read-only = server.token is not set, so only the read endpoints are available
+serve-stopped = Server stopped
+
+## errors
+
+stdin-needs-yes = Reading packages from stdin (`-`) needs --yes or --dry-run, since no input is left to confirm with
+
+error-hint-not-found = Run `archbox search { $package }` to find packages with a similar name
+error-hint-permission = Re-run the command with sudo
+error-hint-network = Check the network connection, or pass --no-auto-update to skip the definitions refresh
+error-hint-platform = Run `archbox alternatives list` to see other providers of what { $package } offers
+error-hint-dependency = Run `archbox info <package>` to see the dependencies it declares
+error-hint-yaml = Check the YAML file named in the error above
+error-hint-locked = Run the command again once it has finished, or add --wait to queue behind it
+error-hint-timeout = Raise installation.download_timeout, build_timeout or script_timeout with `archbox config set`, or set it to 0 for no limit
+
+## explain
+
+explain-heading = { $package } { $version } ({ $method }, { $scope } scope)
+explain-dependencies = Installs these packages first: { $packages }
+explain-nothing = Nothing to do beyond recording the package
+explain-secrets = Reads these secrets: { $secrets }
+explain-root = Steps marked [root] need root privileges
+explain-no-root = Nothing here needs root privileges
+explain-no-definitions = { $path } contains no package definitions
+
+## simulate
+simulate-action = [simulate] { $kind }: { $detail }
+simulate-unsupported = `archbox { $command }` can't run with --simulate
+
+## definition loading
+definitions-skipped = Skipped { $count } definition { $count ->
+        [one] file
+       *[other] files
+    } that could not be loaded:
+
+## info --provenance
+info-provenance = Provenance:
+info-provenance-not-installed = Not installed through ArchBox
+info-provenance-not-recorded = Not recorded (installed before ArchBox kept provenance)
+info-installed-version = Installed version:
+info-installed-at = Installed at:
+info-source = Source:
+info-tag = Release tag:
+info-commit = Commit:
+info-sha256 = SHA-256:
+info-signed-by = Signed by:
+info-overrides = Installed with --set:
+
+## diff
+diff-not-installed = { $package } was not installed by ArchBox
+diff-no-files = { $package } has no config files or drop-ins
+diff-in-sync = Config files of { $package } match what ArchBox wrote and what the definition declares
+diff-managed-block = (managed block)
+diff-unchanged = unchanged
+diff-missing = missing
+diff-modified = modified locally
+diff-not-recorded = written before ArchBox kept copies
+diff-undeclared = no longer in the definition
+diff-outdated = differs from the definition
+diff-local-changes = Local changes since ArchBox wrote it:
+diff-would-change = Installing or updating the package would change it to:
+
+## reinstall
+
+reinstall-not-installed = Package '{ $package }' is not installed
+reinstall-nothing = No packages to reinstall
+reinstall-plan = Will reinstall { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+reinstall-plan-dry-run = Would reinstall { $count } { $count ->
+        [one] package
+       *[other] packages
+    }:
+reinstall-confirm = Continue with reinstall?
+reinstall-cancelled = Reinstall cancelled
+reinstall-done = Reinstalled { $package }
+reinstall-failed = Failed to reinstall { $package }: { $error }
+
+## kernel modules
+
+kernel-reboot-needed = The running kernel has been replaced; reboot to load the kernel modules of { $packages }
+kernel-dkms-confirm = Build the kernel modules of { $packages } for the running kernel with dkms autoinstall?
+kernel-dkms-hint = Run `sudo dkms autoinstall` or reboot to load the new kernel modules
+kernel-dkms-done = Kernel modules built for the running kernel
+kernel-dkms-failed = dkms autoinstall failed ({ $status }); reboot to load the new kernel modules
+
+## machine class
+
+machine-class = { $class ->
+        [laptop] a laptop
+        [vm] a virtual machine
+       *[desktop] a desktop
+    }
+
+## transaction lock
+lock-waiting = Waiting for another archbox to finish: { $holder }
+
+## user groups
+relogin-needed = Log out and back in to use your new group membership: { $groups }
+
+## clean
+clean-nothing = No work directories older than { $days } { $days ->
+        [one] day
+       *[other] days
+    } in { $directory }
+clean-done = Removed { $count } work { $count ->
+        [one] directory
+       *[other] directories
+    } from { $directory }
+
+## status
+status-short = { $count } { $count ->
+        [one] update
+       *[other] updates
+    }
+status-never-checked = No update check has run yet; run 'archbox update --check'
+status-up-to-date = All packages were up to date at the last check ({ $checked })
+status-updates = { $count } { $count ->
+        [one] update
+       *[other] updates
+    } found at the last check ({ $checked }):
+status-update-hint = Run 'archbox update' to install them
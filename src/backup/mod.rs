@@ -0,0 +1,96 @@
+//! Backups of files ArchBox overwrites or deletes
+
+use crate::{config::get_state_dir, Result};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::info;
+
+/// Directory holding all package backups
+pub fn backup_dir() -> PathBuf {
+    get_state_dir().join("backups")
+}
+
+/// Copy `path` into a timestamped backup directory for `package`.
+///
+/// The original absolute path is preserved below the timestamp directory,
+/// e.g. `backups/neovim/20240115-093000/home/user/.config/nvim/init.lua`.
+pub async fn backup_file(package: &str, path: &Path) -> Result<PathBuf> {
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let relative = path.strip_prefix("/").unwrap_or(path);
+    let destination = backup_dir().join(package).join(timestamp).join(relative);
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    fs::copy(path, &destination).await?;
+    info!("Backed up {} to {}", path.display(), destination.display());
+
+    Ok(destination)
+}
+
+/// A set of files backed up for a package at one point in time
+#[derive(Debug, Clone)]
+pub struct BackupSet {
+    pub package: String,
+    pub timestamp: String,
+    pub root: PathBuf,
+    /// Original absolute paths of the backed up files
+    pub files: Vec<PathBuf>,
+}
+
+/// List backups for a package, newest first
+pub fn list_backups(package: &str) -> Result<Vec<BackupSet>> {
+    let package_dir = backup_dir().join(package);
+
+    if !package_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sets = Vec::new();
+
+    for entry in std::fs::read_dir(&package_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let root = entry.path();
+        let files = walkdir::WalkDir::new(&root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.path().strip_prefix(&root).ok().map(|p| Path::new("/").join(p)))
+            .collect();
+
+        sets.push(BackupSet {
+            package: package.to_string(),
+            timestamp: entry.file_name().to_string_lossy().to_string(),
+            root,
+            files,
+        });
+    }
+
+    sets.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(sets)
+}
+
+/// Copy every file in a backup set back to its original location
+pub async fn restore_backup(set: &BackupSet) -> Result<Vec<PathBuf>> {
+    let mut restored = Vec::new();
+
+    for original in &set.files {
+        let relative = original.strip_prefix("/").unwrap_or(original);
+        let source = set.root.join(relative);
+
+        if let Some(parent) = original.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::copy(&source, original).await?;
+        info!("Restored {} from {}", original.display(), source.display());
+        restored.push(original.clone());
+    }
+
+    Ok(restored)
+}
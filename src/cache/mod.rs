@@ -1,10 +1,17 @@
-use crate::{package::Package, Error, Result};
+//! Package definitions and dependency resolutions cached between runs
+
+use crate::{package::Package, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use tokio::fs;
 use tracing::{debug, info};
 
+/// Where the cache manager keeps its index
+pub fn default_dir() -> PathBuf {
+    crate::config::get_cache_dir().join("definitions")
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CacheEntry {
     pub package: Package,
@@ -13,13 +20,24 @@ pub struct CacheEntry {
     pub last_accessed: chrono::DateTime<chrono::Utc>,
 }
 
+/// The packages a set of names resolved to, dependencies first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResolutionEntry {
+    pub packages: Vec<String>,
+    pub cached_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CacheIndex {
     pub entries: HashMap<String, CacheEntry>,
+    /// Resolved dependency closures, by a key covering everything resolution reads
+    #[serde(default)]
+    pub resolutions: HashMap<String, ResolutionEntry>,
     pub version: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug)]
 pub struct CacheManager {
     cache_dir: PathBuf,
     index: CacheIndex,
@@ -87,6 +105,41 @@ impl CacheManager {
         Ok(())
     }
     
+    /// Packages `key` resolved to, if that was recent enough
+    pub fn get_resolution(&self, key: &str) -> Option<Vec<String>> {
+        let entry = self.index.resolutions.get(key)?;
+        let age = chrono::Utc::now().signed_duration_since(entry.cached_at);
+        
+        if age.num_hours() < self.ttl_hours as i64 {
+            debug!("Cache hit for resolution {}", key);
+            Some(entry.packages.clone())
+        } else {
+            None
+        }
+    }
+    
+    /// Remember what `key` resolved to, dropping the oldest resolutions while the cache is
+    /// over its size limit
+    pub async fn store_resolution(&mut self, key: String, packages: Vec<String>) -> Result<()> {
+        self.index.resolutions.insert(key, ResolutionEntry {
+            packages,
+            cached_at: chrono::Utc::now(),
+        });
+        
+        while self.estimate_cache_size() > self.max_size_mb as f64 {
+            let oldest = self.index.resolutions
+                .iter()
+                .min_by_key(|(_, entry)| entry.cached_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => self.index.resolutions.remove(&oldest),
+                None => break,
+            };
+        }
+        
+        self.save_index().await
+    }
+    
     pub async fn invalidate(&mut self, name: &str) -> Result<()> {
         self.index.entries.remove(name);
         self.save_index().await?;
@@ -96,6 +149,7 @@ impl CacheManager {
     
     pub async fn clear(&mut self) -> Result<()> {
         self.index.entries.clear();
+        self.index.resolutions.clear();
         self.save_index().await?;
         
         // Remove cached files
@@ -125,7 +179,10 @@ impl CacheManager {
             self.index.entries.remove(&name);
         }
         
-        if !self.index.entries.is_empty() {
+        let expired = self.index.resolutions.len();
+        self.index.resolutions.retain(|_, entry| now.signed_duration_since(entry.cached_at).num_hours() < self.ttl_hours as i64);
+        
+        if !self.index.entries.is_empty() || self.index.resolutions.len() != expired {
             self.save_index().await?;
         }
         
@@ -173,6 +230,7 @@ impl CacheIndex {
     fn new() -> Self {
         Self {
             entries: HashMap::new(),
+            resolutions: HashMap::new(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             created_at: chrono::Utc::now(),
         }
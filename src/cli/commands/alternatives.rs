@@ -0,0 +1,144 @@
+use crate::{t, App, Result};
+use clap::{Args, Subcommand};
+use console::style;
+
+#[derive(Args)]
+pub struct AlternativesArgs {
+    #[command(subcommand)]
+    pub command: AlternativesCommand,
+}
+
+#[derive(Subcommand)]
+pub enum AlternativesCommand {
+    /// List capabilities and the packages that provide them
+    List {
+        /// Only show this capability
+        capability: Option<String>,
+    },
+    
+    /// Choose the package that provides a capability
+    Set {
+        /// Capability, e.g. `editor`
+        capability: String,
+        
+        /// Package to use for it
+        package: String,
+    },
+    
+    /// Forget the chosen provider, going back to the installed or only one
+    Unset {
+        capability: String,
+    },
+}
+
+pub async fn execute(app: &mut App, args: AlternativesArgs) -> Result<()> {
+    match args.command {
+        AlternativesCommand::List { capability } => list_alternatives(app, capability.as_deref()),
+        AlternativesCommand::Set { capability, package } => set_alternative(app, &capability, &package).await,
+        AlternativesCommand::Unset { capability } => unset_alternative(app, &capability),
+    }
+}
+
+fn list_alternatives(app: &App, only: Option<&str>) -> Result<()> {
+    let capabilities = match only {
+        Some(capability) => {
+            if app.repository.providers(capability).is_empty() {
+                return Err(crate::Error::Config(t!("alternatives-unknown", capability = capability)));
+            }
+            vec![capability.to_string()]
+        }
+        None => app.repository.capabilities(),
+    };
+    
+    if capabilities.is_empty() {
+        crate::cli::print_info(&t!("alternatives-none"));
+        return Ok(());
+    }
+    
+    for capability in &capabilities {
+        let chosen = app.repository.alternative(capability);
+        let selected = app.repository.resolve_name(capability).ok();
+    
+        if crate::cli::is_normal_output() {
+            println!("{}", style(capability).bold());
+        }
+    
+        for provider in app.repository.providers(capability) {
+            let name = provider.name.as_str();
+            let is_selected = selected.as_deref() == Some(name);
+            let installed = app.repository.installed_record(name).is_some();
+    
+            crate::cli::print_porcelain(&[
+                "provider",
+                capability,
+                name,
+                if is_selected { "selected" } else { "available" },
+                if installed { "installed" } else { "not-installed" },
+            ]);
+            if !crate::cli::is_normal_output() {
+                continue;
+            }
+    
+            let mut notes = Vec::new();
+            if chosen == Some(name) {
+                notes.push(t!("alternatives-chosen"));
+            }
+            if installed {
+                notes.push(t!("alternatives-installed"));
+            }
+            let notes = if notes.is_empty() {
+                String::new()
+            } else {
+                style(format!(" ({})", notes.join(", "))).dim().to_string()
+            };
+    
+            if is_selected {
+                println!("  {} {}{}", style("*").green(), style(name).green(), notes);
+            } else {
+                println!("    {}{}", name, notes);
+            }
+        }
+    
+        if selected.is_none() && crate::cli::is_normal_output() {
+            println!("  {}", style(t!("alternatives-unresolved", capability = capability.as_str())).yellow());
+        }
+    }
+    
+    Ok(())
+}
+
+async fn set_alternative(app: &mut App, capability: &str, package: &str) -> Result<()> {
+    let providers = app.repository.providers(capability);
+    if providers.is_empty() {
+        return Err(crate::Error::Config(t!("alternatives-unknown", capability = capability)));
+    }
+    if !providers.iter().any(|provider| provider.name == package) {
+        let names: Vec<&str> = providers.iter().map(|provider| provider.name.as_str()).collect();
+        return Err(crate::Error::Config(t!(
+            "alternatives-not-provider",
+            package = package,
+            capability = capability,
+            providers = names.join(", ")
+        )));
+    }
+    
+    app.repository.set_alternative(capability, Some(package))?;
+    crate::cli::print_success(&t!("alternatives-set", capability = capability, package = package));
+    
+    if !app.repository.is_installed(package).await? {
+        crate::cli::print_info(&t!("alternatives-install-hint", package = package));
+    }
+    
+    Ok(())
+}
+
+fn unset_alternative(app: &mut App, capability: &str) -> Result<()> {
+    if app.repository.alternative(capability).is_none() {
+        crate::cli::print_info(&t!("alternatives-not-set", capability = capability));
+        return Ok(());
+    }
+    
+    app.repository.set_alternative(capability, None)?;
+    crate::cli::print_success(&t!("alternatives-unset", capability = capability));
+    Ok(())
+}
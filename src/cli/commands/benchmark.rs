@@ -0,0 +1,172 @@
+use crate::{
+    cli::table::Table,
+    package::{platform::Platform, size::{format_size, parse_size}, Installation},
+    repository::mirrors::{self, Candidate, Measurement, Ranking},
+    t, App, Result,
+};
+use clap::Args;
+use console::style;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Args)]
+pub struct BenchmarkArgs {
+    /// Save the ranking so definitions and downloads try the fastest endpoints first
+    #[arg(long)]
+    pub save: bool,
+    
+    /// Requests per endpoint; the best one counts
+    #[arg(long, default_value_t = 3)]
+    pub samples: usize,
+    
+    /// Stop reading each response after this much, e.g. `4 MiB`
+    #[arg(long, default_value = "4 MiB", value_parser = parse_limit)]
+    pub limit: u64,
+    
+    /// Give up on an endpoint after this many seconds
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+fn parse_limit(value: &str) -> std::result::Result<u64, String> {
+    parse_size(value).ok_or_else(|| format!("invalid size '{}', use something like 4 MiB", value))
+}
+
+pub async fn execute(app: &mut App, args: BenchmarkArgs) -> Result<()> {
+    let mut endpoints: Vec<(&str, Candidate)> = mirrors::definition_endpoints(&app.config.repository)
+        .into_iter()
+        .map(|candidate| ("definitions", candidate))
+        .collect();
+    endpoints.extend(artifact_endpoints(app).into_iter().map(|candidate| ("artifacts", candidate)));
+    
+    if endpoints.is_empty() {
+        crate::cli::print_warning(&t!("benchmark-no-endpoints"));
+        return Ok(());
+    }
+    
+    let client = crate::http::client_builder()?
+        .timeout(Duration::from_secs(args.timeout))
+        .build()?;
+    
+    let mut results = Vec::new();
+    for (kind, candidate) in endpoints {
+        let pb = crate::progress::spinner(t!("benchmark-measuring", endpoint = candidate.endpoint.as_str()));
+        let result = mirrors::measure(&client, &candidate, args.samples, args.limit).await;
+        pb.finish_and_clear();
+        results.push((kind, candidate, result));
+    }
+    
+    // Fastest first; unreachable endpoints stay out of the ranking and are tried last
+    let mut measured: Vec<&Measurement> = results.iter().filter_map(|(_, _, result)| result.as_ref().ok()).collect();
+    measured.sort_by(|a, b| b.throughput.total_cmp(&a.throughput).then(a.latency.cmp(&b.latency)));
+    
+    print_results(&results, &measured);
+    
+    let mut ranking = Ranking {
+        measured_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        endpoints: Vec::new(),
+    };
+    for measurement in &measured {
+        if !ranking.endpoints.contains(&measurement.endpoint) {
+            ranking.endpoints.push(measurement.endpoint.clone());
+        }
+    }
+    
+    if args.save {
+        let path = ranking.save()?;
+        crate::cli::print_success(&t!("benchmark-saved", path = path.display().to_string()));
+    } else if crate::cli::is_normal_output() {
+        crate::cli::print_info(&t!("benchmark-save-hint"));
+    }
+    
+    Ok(())
+}
+
+/// Each artifact mirror alongside its upstream, measured on a real download from the
+/// definitions so throughput reflects files archbox actually fetches
+fn artifact_endpoints(app: &App) -> Vec<Candidate> {
+    let platform = Platform::current();
+    let mut packages: Vec<_> = app.repository.loader.packages().values().collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    let urls: Vec<String> = packages
+        .iter()
+        .filter_map(|package| match &package.installation {
+            Installation::Binary { url, urls, .. } | Installation::AppImage { url, urls, .. } => {
+                platform.resolve_url(url, urls.as_ref()).ok()
+            }
+            _ => None,
+        })
+        .collect();
+    
+    let mut upstreams: Vec<(&String, &Vec<String>)> = app.config.installation.artifact_mirrors.iter().collect();
+    upstreams.sort();
+    
+    let mut candidates = Vec::new();
+    for (upstream, prefixes) in upstreams {
+        let Some(sample) = urls.iter().find(|url| url.starts_with(upstream.as_str())) else {
+            crate::cli::print_warning(&t!("benchmark-no-sample", upstream = upstream.as_str()));
+            continue;
+        };
+    
+        let only = HashMap::from([(upstream.clone(), prefixes.clone())]);
+        for candidate in mirrors::artifact_endpoints(sample, &only) {
+            if !candidates.iter().any(|known: &Candidate| known.endpoint == candidate.endpoint) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    
+    candidates
+}
+
+fn print_results(results: &[(&str, Candidate, Result<Measurement>)], ranked: &[&Measurement]) {
+    for (kind, candidate, result) in results {
+        match result {
+            Ok(measurement) => crate::cli::print_porcelain(&[
+                "endpoint",
+                kind,
+                &candidate.endpoint,
+                &measurement.latency.as_millis().to_string(),
+                &(measurement.throughput as u64).to_string(),
+            ]),
+            Err(_) => crate::cli::print_porcelain(&["endpoint", kind, &candidate.endpoint, "-", "-"]),
+        }
+    }
+    if !crate::cli::is_normal_output() {
+        return;
+    }
+    
+    let mut table = Table::new(vec![
+        t!("table-rank"),
+        t!("table-endpoint"),
+        t!("table-kind"),
+        t!("table-latency"),
+        t!("table-throughput"),
+    ]);
+    for (rank, measurement) in ranked.iter().enumerate() {
+        let kind = results
+            .iter()
+            .find(|(_, candidate, _)| candidate.endpoint == measurement.endpoint)
+            .map_or("", |(kind, _, _)| *kind);
+        table.add_row(vec![
+            (rank + 1).to_string(),
+            style(&measurement.endpoint).bold().to_string(),
+            kind.to_string(),
+            format!("{} ms", measurement.latency.as_millis()),
+            format!("{}/s", format_size(measurement.throughput as u64)),
+        ]);
+    }
+    for (kind, candidate, result) in results {
+        if let Err(e) = result {
+            table.add_row(vec![
+                style("-").dim().to_string(),
+                candidate.endpoint.clone(),
+                kind.to_string(),
+                style(t!("benchmark-failed")).red().to_string(),
+                style(e.to_string()).dim().to_string(),
+            ]);
+        }
+    }
+    table.print(true);
+}
@@ -0,0 +1,28 @@
+use crate::{t, App, Result};
+use clap::Args;
+use std::time::Duration;
+
+#[derive(Args)]
+pub struct CleanArgs {
+    /// Only remove work directories at least this many days old; 0 removes them all
+    #[arg(long, value_name = "DAYS", default_value_t = 7)]
+    pub older_than: u64,
+}
+
+pub async fn execute(app: &App, args: CleanArgs) -> Result<()> {
+    let max_age = Duration::from_secs(args.older_than * 24 * 60 * 60);
+    let removed = crate::workdir::clean(&app.config.installation, max_age)?;
+    
+    for path in &removed {
+        crate::cli::print_porcelain(&["removed-workdir", &path.to_string_lossy()]);
+    }
+    
+    let root = crate::workdir::root(&app.config.installation);
+    if removed.is_empty() {
+        crate::cli::print_info(&t!("clean-nothing", days = args.older_than, directory = root.display().to_string()));
+    } else {
+        crate::cli::print_success(&t!("clean-done", count = removed.len(), directory = root.display().to_string()));
+    }
+    
+    Ok(())
+}
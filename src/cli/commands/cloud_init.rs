@@ -0,0 +1,96 @@
+use crate::{cloud_init::{Skipped, UserData}, groups::GroupManager, package::platform::Platform, t, App, Result};
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct CloudInitArgs {
+    /// Profile whose packages the VM gets
+    #[arg(long)]
+    pub profile: Option<String>,
+    
+    /// Additional packages to include
+    pub packages: Vec<String>,
+    
+    /// File to write, or `-` for standard output
+    #[arg(short, long, default_value = "user-data")]
+    pub output: PathBuf,
+    
+    /// CPU architecture of the VM, for picking download URLs (default: this machine's)
+    #[arg(long)]
+    pub arch: Option<String>,
+}
+
+pub async fn execute(app: &mut App, args: CloudInitArgs) -> Result<()> {
+    if args.profile.is_none() && args.packages.is_empty() {
+        return Err(crate::Error::Config(t!("cloud-init-nothing-requested")));
+    }
+    
+    let mut platform = Platform::current();
+    if let Some(arch) = &args.arch {
+        platform.arch = arch.clone();
+    }
+    app.repository.set_platform(platform.clone());
+    
+    let groups = GroupManager::new();
+    let mut wanted = args.packages.clone();
+    let mut script = None;
+    let mut missing = Vec::new();
+    if let Some(name) = &args.profile {
+        let profile = groups.get_profile(name)
+            .ok_or_else(|| crate::Error::Config(t!("sync-unknown-profile", profile = name.as_str())))?;
+        script = profile.verified_post_install_script()?.map(str::to_string);
+    
+        for package_name in groups.resolve_profile_packages_with(name, |conflict| super::profile::choose_group(conflict, true))? {
+            match app.repository.resolve_name(&package_name) {
+                Ok(name) if !app.repository.is_supported(&name) => {
+                    missing.push(Skipped { package: name, reason: format!("not available for {}", platform.arch) });
+                }
+                Ok(name) => wanted.push(name),
+                Err(crate::Error::PackageNotFound(_)) => {
+                    missing.push(Skipped { package: package_name, reason: "no package definition".to_string() });
+                }
+                Err(crate::Error::UnsupportedPlatform { .. }) => {
+                    missing.push(Skipped { package: package_name, reason: format!("not available for {}", platform.arch) });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
+    let packages = app.repository.resolve_packages(&wanted).await?;
+    
+    let (mut user_data, mut skipped) = UserData::from_packages(&packages, &platform);
+    skipped.splice(0..0, missing);
+    if let (Some(name), Some(script)) = (&args.profile, &script) {
+        user_data.add_script(&format!("profile-{}", name), script);
+    }
+    
+    // Left-out items are noted in the file too, so whoever boots the VM knows what to add
+    let mut header = vec![match &args.profile {
+        Some(profile) => format!("Generated by archbox cloud-init from profile {}", profile),
+        None => "Generated by archbox cloud-init".to_string(),
+    }];
+    for item in &skipped {
+        header.push(format!("Not included for {}: {}", item.package, item.reason));
+    }
+    let document = user_data.render(&header)?;
+    
+    if args.output.as_os_str() == "-" {
+        print!("{}", document);
+        return Ok(());
+    }
+    
+    tokio::fs::write(&args.output, document).await?;
+    
+    for item in &skipped {
+        crate::cli::print_warning(&t!("cloud-init-skipped", package = item.package.as_str(), reason = item.reason.as_str()));
+        crate::cli::print_porcelain(&["skipped", &item.package, &item.reason]);
+    }
+    crate::cli::print_success(&t!(
+        "cloud-init-written",
+        path = args.output.display().to_string(),
+        count = packages.len()
+    ));
+    crate::cli::print_porcelain(&["written", &args.output.display().to_string()]);
+    Ok(())
+}
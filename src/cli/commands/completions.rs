@@ -0,0 +1,47 @@
+use crate::{completions, t, Result};
+use clap::Args;
+use clap_complete::Shell;
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+    
+    /// Install into the shell's completion directory instead of printing
+    #[arg(long)]
+    pub install: bool,
+    
+    /// Install system-wide (the default when running as root)
+    #[arg(long, requires = "install")]
+    pub system: bool,
+    
+    /// Install for the current user even when running as root
+    #[arg(long, requires = "install", conflicts_with = "system")]
+    pub user: bool,
+}
+
+pub fn execute(args: &CompletionsArgs) -> Result<()> {
+    if !args.install {
+        print!("{}", completions::generate_shell_completions(args.shell)?);
+        return Ok(());
+    }
+    
+    let system = args.system || (!args.user && crate::privilege::running_as_root());
+    let path = completions::install_completions(args.shell, system)?;
+    
+    crate::cli::print_success(&t!(
+        "completions-installed",
+        shell = args.shell.to_string(),
+        path = path.display().to_string()
+    ));
+    
+    if args.shell == Shell::Zsh && !system {
+        crate::cli::print_info(&t!(
+            "completions-zsh-fpath",
+            dir = path.parent().unwrap_or(&path).display().to_string()
+        ));
+    }
+    
+    Ok(())
+}
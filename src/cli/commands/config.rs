@@ -1,4 +1,4 @@
-use crate::{App, Result};
+use crate::{t, App, Result};
 use clap::{Args, Subcommand};
 use console::style;
 use std::path::PathBuf;
@@ -46,7 +46,10 @@ pub enum ConfigCommand {
 
 pub async fn execute(app: &mut App, args: ConfigArgs) -> Result<()> {
     match args.command {
-        ConfigCommand::Show => Ok(show_config(&app.config)),
+        ConfigCommand::Show => {
+            show_config(&app.config);
+            Ok(())
+        }
         ConfigCommand::Set { key, value } => set_config(&mut app.config, &key, &value).await,
         ConfigCommand::Get { key } => get_config(&app.config, &key),
         ConfigCommand::AddPath { path } => add_package_path(&mut app.config, path).await,
@@ -56,42 +59,104 @@ pub async fn execute(app: &mut App, args: ConfigArgs) -> Result<()> {
 }
 
 fn show_config(config: &crate::config::Config) {
-    println!("{}", style("ArchBox Configuration").bold().underlined());
+    println!("{}", style(t!("config-title")).bold().underlined());
     println!();
     
-    println!("{}", style("Package Paths:").bold());
+    println!("{}", style(t!("config-package-paths")).bold());
     for (i, path) in config.package_paths.iter().enumerate() {
         println!("  {}. {}", i + 1, path.display());
     }
     
-    println!("\n{}", style("Installation:").bold());
-    println!("  Binary directory: {}", config.installation.binary_dir.display());
-    println!("  Verify checksums: {}", config.installation.verify_checksums);
-    println!("  Create backups: {}", config.installation.create_backups);
-    println!("  Download timeout: {}s", config.installation.download_timeout);
+    println!("\n{}", style(t!("config-installation")).bold());
+    println!("  {} {}", t!("config-binary-directory"), config.installation.binary_dir.display());
+    println!("  {} {}", t!("config-verify-checksums"), config.installation.verify_checksums);
+    println!("  {} {}", t!("config-create-backups"), config.installation.create_backups);
+    println!("  {} {}s", t!("config-download-timeout"), config.installation.download_timeout);
+    println!("  {} {}s", t!("config-build-timeout"), config.installation.build_timeout);
+    println!("  {} {}s", t!("config-script-timeout"), config.installation.script_timeout);
+    println!("  {} {}s", t!("config-lock-wait"), config.installation.lock_wait);
+    println!("  {} {}", t!("config-review-pkgbuilds"), config.installation.review_pkgbuild);
+    println!("  {} {}", t!("config-versions-kept"), config.installation.keep_versions);
+    println!("  {} {}", t!("config-remove-build-dependencies"), config.installation.remove_build_deps);
+    println!("  {} {}", t!("config-build-cache"), config.installation.build_cache);
+    println!("  {} {}", t!("config-compiler-cache"), config.installation.compiler_cache);
+    println!("  {} {}", t!("config-modify-user-groups"), config.installation.modify_user_groups);
+    if let Some(policy) = config.installation.install_optional {
+        println!("  {} {}", t!("config-install-optional"), policy);
+    }
     
     if let Some(ref temp_dir) = config.installation.temp_dir {
-        println!("  Temp directory: {}", temp_dir.display());
+        println!("  {} {}", t!("config-temp-directory"), temp_dir.display());
     }
     
-    println!("\n{}", style("Repository:").bold());
+    println!("\n{}", style(t!("config-repository")).bold());
     if let Some(ref url) = config.repository.update_url {
-        println!("  Update URL: {}", url);
+        println!("  {} {}", t!("config-update-url"), url);
+    }
+    println!("  {} {}h", t!("config-update-interval"), config.repository.update_interval);
+    println!("  {} {}", t!("config-auto-update"), config.repository.auto_update);
+    println!("  {} {}", t!("config-system-upgrade"), config.repository.system_upgrade);
+    if !config.repository.pinned.is_empty() {
+        println!("  {} {}", t!("config-pinned"), config.repository.pinned.join(", "));
+    }
+    if !config.repository.ignore_packages.is_empty() {
+        println!("  {} {}", t!("config-ignored-on-update"), config.repository.ignore_packages.join(", "));
+    }
+    
+    println!("\n{}", style(t!("config-ui")).bold());
+    println!("  {} {}", t!("config-use-colors"), config.ui.use_colors);
+    println!("  {} {}", t!("config-show-progress"), config.ui.show_progress);
+    println!("  {} {}", t!("config-log-level"), config.ui.log_level);
+    if let Some(ref log_file) = config.ui.log_file {
+        println!("  {} {}", t!("config-log-file"), t!(
+            "config-log-file-details",
+            path = log_file.display().to_string(),
+            level = config.ui.log_file_level.as_str(),
+            size = config.ui.log_max_size_mb,
+            files = config.ui.log_max_files
+        ));
+    }
+    println!("  {} {}", t!("config-language"), config.ui.language.clone().unwrap_or_else(|| {
+        format!("{} ({})", crate::i18n::system_locale().unwrap_or_else(|| "en-US".to_string()), t!("config-language-from-env"))
+    }));
+    
+    if !config.variables.is_empty() {
+        println!("\n{}", style(t!("config-template-variables")).bold());
+        let mut names: Vec<&String> = config.variables.keys().collect();
+        names.sort();
+        for name in names {
+            println!("  {} = {}", name, config.variables[name]);
+        }
+    }
+    
+    if let Some(ref url) = config.notifications.webhook_url {
+        println!("\n{}", style(t!("config-notifications")).bold());
+        println!("  {} {} ({})", t!("config-webhook-url"), url, config.notifications.on);
+    }
+    
+    let network = &config.network;
+    if network.ca_bundle.is_some() || network.client_certificate.is_some() || network.ignore_system_proxy {
+        println!("\n{}", style(t!("config-network")).bold());
+        if let Some(ref path) = network.ca_bundle {
+            println!("  {} {}", t!("config-ca-bundle"), path.display());
+        }
+        if let Some(ref path) = network.client_certificate {
+            println!("  {} {}", t!("config-client-certificate"), path.display());
+        }
+        println!("  {} {}", t!("config-ignore-system-proxy"), network.ignore_system_proxy);
     }
-    println!("  Update interval: {}h", config.repository.update_interval);
-    println!("  Auto update: {}", config.repository.auto_update);
     
-    println!("\n{}", style("UI:").bold());
-    println!("  Use colors: {}", config.ui.use_colors);
-    println!("  Show progress: {}", config.ui.show_progress);
-    println!("  Log level: {}", config.ui.log_level);
+    if let Some(ref profile) = config.profile {
+        println!("\n{}", style(t!("config-profile")).bold());
+        println!("  {}", profile);
+    }
     
     if let Some(ref helper) = config.aur_helper {
-        println!("\n{}", style("AUR Helper:").bold());
+        println!("\n{}", style(t!("config-aur-helper")).bold());
         println!("  {}", helper);
     }
     
-    println!("\n{}", style("Config file:").bold());
+    println!("\n{}", style(t!("config-config-file")).bold());
     println!("  {}", crate::config::Config::config_path().display());
 }
 
@@ -99,43 +164,182 @@ async fn set_config(config: &mut crate::config::Config, key: &str, value: &str)
     match key {
         "aur_helper" => {
             config.set_aur_helper(value.to_string());
-            crate::cli::print_success(&format!("Set AUR helper to: {}", value));
+            crate::cli::print_success(&t!("config-set-aur-helper", value = value));
+        }
+        "profile" => {
+            config.profile = if value.is_empty() { None } else { Some(value.to_string()) };
+            crate::cli::print_success(&t!("config-set", key = "profile", value = value));
         }
         "installation.verify_checksums" => {
             config.installation.verify_checksums = value.parse()
-                .map_err(|_| crate::Error::Config("Invalid boolean value".to_string()))?;
-            crate::cli::print_success(&format!("Set verify_checksums to: {}", value));
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "verify_checksums", value = value));
         }
         "installation.create_backups" => {
             config.installation.create_backups = value.parse()
-                .map_err(|_| crate::Error::Config("Invalid boolean value".to_string()))?;
-            crate::cli::print_success(&format!("Set create_backups to: {}", value));
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "create_backups", value = value));
         }
         "installation.download_timeout" => {
             config.installation.download_timeout = value.parse()
-                .map_err(|_| crate::Error::Config("Invalid number value".to_string()))?;
-            crate::cli::print_success(&format!("Set download_timeout to: {}", value));
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "download_timeout", value = value));
+        }
+        "installation.build_timeout" => {
+            config.installation.build_timeout = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "build_timeout", value = value));
+        }
+        "installation.script_timeout" => {
+            config.installation.script_timeout = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "script_timeout", value = value));
+        }
+        "installation.lock_wait" => {
+            config.installation.lock_wait = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "lock_wait", value = value));
+        }
+        "installation.review_pkgbuild" => {
+            config.installation.review_pkgbuild = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "review_pkgbuild", value = value));
+        }
+        "installation.keep_versions" => {
+            config.installation.keep_versions = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "keep_versions", value = value));
+        }
+        "installation.remove_build_deps" => {
+            config.installation.remove_build_deps = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "remove_build_deps", value = value));
+        }
+        "installation.build_cache" => {
+            config.installation.build_cache = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "build_cache", value = value));
+        }
+        "installation.compiler_cache" => {
+            config.installation.compiler_cache = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "compiler_cache", value = value));
+        }
+        "installation.modify_user_groups" => {
+            config.installation.modify_user_groups = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "modify_user_groups", value = value));
+        }
+        "installation.install_optional" => {
+            config.installation.install_optional = if value.is_empty() {
+                None
+            } else {
+                Some(value.parse().map_err(crate::Error::Config)?)
+            };
+            crate::cli::print_success(&t!("config-set", key = "install_optional", value = value));
+        }
+        "installation.temp_dir" => {
+            config.installation.temp_dir = if value.is_empty() { None } else { Some(value.into()) };
+            crate::cli::print_success(&t!("config-set", key = "temp_dir", value = value));
         }
         "repository.update_url" => {
             config.repository.update_url = Some(value.to_string());
-            crate::cli::print_success(&format!("Set update_url to: {}", value));
+            crate::cli::print_success(&t!("config-set", key = "update_url", value = value));
         }
         "repository.auto_update" => {
             config.repository.auto_update = value.parse()
-                .map_err(|_| crate::Error::Config("Invalid boolean value".to_string()))?;
-            crate::cli::print_success(&format!("Set auto_update to: {}", value));
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "auto_update", value = value));
+        }
+        "repository.update_interval" => {
+            config.repository.update_interval = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "update_interval", value = value));
+        }
+        "repository.system_upgrade" => {
+            config.repository.system_upgrade = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "system_upgrade", value = value));
+        }
+        "repository.pinned" => {
+            config.repository.pinned = parse_list(value);
+            crate::cli::print_success(&t!("config-set", key = "pinned", value = value));
+        }
+        "repository.ignore_packages" => {
+            config.repository.ignore_packages = parse_list(value);
+            crate::cli::print_success(&t!("config-set", key = "ignore_packages", value = value));
         }
         "ui.use_colors" => {
             config.ui.use_colors = value.parse()
-                .map_err(|_| crate::Error::Config("Invalid boolean value".to_string()))?;
-            crate::cli::print_success(&format!("Set use_colors to: {}", value));
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "use_colors", value = value));
         }
         "ui.log_level" => {
             config.ui.log_level = value.to_string();
-            crate::cli::print_success(&format!("Set log_level to: {}", value));
+            crate::cli::print_success(&t!("config-set", key = "log_level", value = value));
+        }
+        "ui.log_file" => {
+            config.ui.log_file = if value.is_empty() { None } else { Some(value.into()) };
+            crate::cli::print_success(&t!("config-set", key = "log_file", value = value));
+        }
+        "ui.log_file_level" => {
+            config.ui.log_file_level = value.to_string();
+            crate::cli::print_success(&t!("config-set", key = "log_file_level", value = value));
+        }
+        "ui.log_max_size_mb" => {
+            config.ui.log_max_size_mb = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "log_max_size_mb", value = value));
+        }
+        "ui.log_max_files" => {
+            config.ui.log_max_files = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-number")))?;
+            crate::cli::print_success(&t!("config-set", key = "log_max_files", value = value));
+        }
+        "ui.language" => {
+            config.ui.language = if value.is_empty() { None } else { Some(value.to_string()) };
+            crate::cli::print_success(&t!("config-set", key = "language", value = value));
+        }
+        "notifications.webhook_url" => {
+            config.notifications.webhook_url = if value.is_empty() { None } else { Some(value.to_string()) };
+            crate::cli::print_success(&t!("config-set", key = "webhook_url", value = value));
+        }
+        "notifications.on" => {
+            config.notifications.on = value.parse().map_err(crate::Error::Config)?;
+            crate::cli::print_success(&t!("config-set", key = "notifications.on", value = value));
+        }
+        "server.listen" => {
+            config.server.listen = value.to_string();
+            crate::cli::print_success(&t!("config-set", key = "server.listen", value = value));
+        }
+        "server.token" => {
+            config.server.token = if value.is_empty() { None } else { Some(value.to_string()) };
+            crate::cli::print_success(&t!("config-set", key = "server.token", value = value));
+        }
+        "network.ca_bundle" => {
+            config.network.ca_bundle = if value.is_empty() { None } else { Some(value.into()) };
+            crate::cli::print_success(&t!("config-set", key = "network.ca_bundle", value = value));
+        }
+        "network.client_certificate" => {
+            config.network.client_certificate = if value.is_empty() { None } else { Some(value.into()) };
+            crate::cli::print_success(&t!("config-set", key = "network.client_certificate", value = value));
+        }
+        "network.client_key" => {
+            config.network.client_key = if value.is_empty() { None } else { Some(value.into()) };
+            crate::cli::print_success(&t!("config-set", key = "network.client_key", value = value));
+        }
+        "network.ignore_system_proxy" => {
+            config.network.ignore_system_proxy = value.parse()
+                .map_err(|_| crate::Error::Config(t!("config-invalid-bool")))?;
+            crate::cli::print_success(&t!("config-set", key = "network.ignore_system_proxy", value = value));
+        }
+        _ if key.starts_with("variables.") => {
+            let name = &key["variables.".len()..];
+            config.variables.insert(name.to_string(), value.to_string());
+            crate::cli::print_success(&t!("config-set-variable", name = name, value = value));
         }
         _ => {
-            return Err(crate::Error::Config(format!("Unknown configuration key: {}", key)));
+            return Err(crate::Error::Config(t!("config-unknown-key", key = key)));
         }
     }
     
@@ -145,16 +349,49 @@ async fn set_config(config: &mut crate::config::Config, key: &str, value: &str)
 
 fn get_config(config: &crate::config::Config, key: &str) -> Result<()> {
     let value = match key {
-        "aur_helper" => config.aur_helper.as_deref().unwrap_or("not set").to_string(),
+        "aur_helper" => config.aur_helper.as_deref().map(str::to_string).unwrap_or_else(|| t!("config-not-set")),
+        "profile" => config.profile.clone().unwrap_or_else(|| t!("config-not-set")),
         "installation.verify_checksums" => config.installation.verify_checksums.to_string(),
         "installation.create_backups" => config.installation.create_backups.to_string(),
         "installation.download_timeout" => config.installation.download_timeout.to_string(),
-        "repository.update_url" => config.repository.update_url.as_deref().unwrap_or("not set").to_string(),
+        "installation.build_timeout" => config.installation.build_timeout.to_string(),
+        "installation.script_timeout" => config.installation.script_timeout.to_string(),
+        "installation.lock_wait" => config.installation.lock_wait.to_string(),
+        "installation.review_pkgbuild" => config.installation.review_pkgbuild.to_string(),
+        "installation.keep_versions" => config.installation.keep_versions.to_string(),
+        "installation.remove_build_deps" => config.installation.remove_build_deps.to_string(),
+        "installation.build_cache" => config.installation.build_cache.to_string(),
+        "installation.compiler_cache" => config.installation.compiler_cache.to_string(),
+        "installation.modify_user_groups" => config.installation.modify_user_groups.to_string(),
+        "installation.install_optional" => config.installation.install_optional.map(|policy| policy.to_string()).unwrap_or_else(|| t!("config-not-set")),
+        "installation.temp_dir" => config.installation.temp_dir.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| t!("config-not-set")),
+        "repository.update_url" => config.repository.update_url.as_deref().map(str::to_string).unwrap_or_else(|| t!("config-not-set")),
         "repository.auto_update" => config.repository.auto_update.to_string(),
+        "repository.update_interval" => config.repository.update_interval.to_string(),
+        "repository.system_upgrade" => config.repository.system_upgrade.to_string(),
+        "repository.pinned" => config.repository.pinned.join(","),
+        "repository.ignore_packages" => config.repository.ignore_packages.join(","),
         "ui.use_colors" => config.ui.use_colors.to_string(),
         "ui.log_level" => config.ui.log_level.clone(),
+        "ui.log_file" => config.ui.log_file.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| t!("config-not-set")),
+        "ui.log_file_level" => config.ui.log_file_level.clone(),
+        "ui.log_max_size_mb" => config.ui.log_max_size_mb.to_string(),
+        "ui.log_max_files" => config.ui.log_max_files.to_string(),
+        "ui.language" => config.ui.language.clone().unwrap_or_else(|| t!("config-not-set")),
+        "notifications.webhook_url" => config.notifications.webhook_url.clone().unwrap_or_else(|| t!("config-not-set")),
+        "notifications.on" => config.notifications.on.to_string(),
+        "server.listen" => config.server.listen.clone(),
+        "server.token" => config.server.token.clone().unwrap_or_else(|| t!("config-not-set")),
+        "network.ca_bundle" => config.network.ca_bundle.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| t!("config-not-set")),
+        "network.client_certificate" => config.network.client_certificate.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| t!("config-not-set")),
+        "network.client_key" => config.network.client_key.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| t!("config-not-set")),
+        "network.ignore_system_proxy" => config.network.ignore_system_proxy.to_string(),
+        _ if key.starts_with("variables.") => config.variables
+            .get(&key["variables.".len()..])
+            .cloned()
+            .unwrap_or_else(|| t!("config-not-set")),
         _ => {
-            return Err(crate::Error::Config(format!("Unknown configuration key: {}", key)));
+            return Err(crate::Error::Config(t!("config-unknown-key", key = key)));
         }
     };
     
@@ -162,23 +399,32 @@ fn get_config(config: &crate::config::Config, key: &str) -> Result<()> {
     Ok(())
 }
 
+/// Parse a comma-separated list value, ignoring empty entries
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
 async fn add_package_path(config: &mut crate::config::Config, path: PathBuf) -> Result<()> {
     config.add_package_path(path.clone());
     config.save()?;
-    crate::cli::print_success(&format!("Added package path: {}", path.display()));
+    crate::cli::print_success(&t!("config-path-added", path = path.display().to_string()));
     Ok(())
 }
 
 async fn remove_package_path(config: &mut crate::config::Config, path: &PathBuf) -> Result<()> {
     config.remove_package_path(path);
     config.save()?;
-    crate::cli::print_success(&format!("Removed package path: {}", path.display()));
+    crate::cli::print_success(&t!("config-path-removed", path = path.display().to_string()));
     Ok(())
 }
 
 async fn reset_config(config: &mut crate::config::Config) -> Result<()> {
     *config = crate::config::Config::default();
     config.save()?;
-    crate::cli::print_success("Configuration reset to defaults");
+    crate::cli::print_success(&t!("config-reset"));
     Ok(())
 }
\ No newline at end of file
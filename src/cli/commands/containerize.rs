@@ -0,0 +1,111 @@
+use crate::{
+    container::{self, Engine, ImageSpec},
+    groups::GroupManager,
+    package::Installation,
+    t, App, Result,
+};
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ContainerizeArgs {
+    /// Profile whose packages the image installs
+    #[arg(long)]
+    pub profile: Option<String>,
+    
+    /// Additional packages to install in the image
+    pub packages: Vec<String>,
+    
+    /// Build the image with this tag; without it only the build context is written
+    #[arg(short, long)]
+    pub tag: Option<String>,
+    
+    /// Directory to write the Containerfile and build context to
+    #[arg(short, long, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+    
+    /// Base image
+    #[arg(long, default_value = container::DEFAULT_BASE)]
+    pub base: String,
+    
+    /// Container engine to build with (default: podman, docker or buildah, whichever is installed)
+    #[arg(long, value_enum)]
+    pub engine: Option<Engine>,
+}
+
+pub async fn execute(app: &mut App, args: ContainerizeArgs) -> Result<()> {
+    if args.profile.is_none() && args.packages.is_empty() {
+        return Err(crate::Error::Config(t!("containerize-nothing-requested")));
+    }
+    
+    let groups = GroupManager::new();
+    let mut spec = ImageSpec {
+        base: args.base.clone(),
+        profile: args.profile.clone(),
+        packages: args.packages.clone(),
+        post_install_script: None,
+    };
+    
+    let mut wanted = args.packages.clone();
+    if let Some(name) = &args.profile {
+        let profile = groups.get_profile(name)
+            .ok_or_else(|| crate::Error::Config(t!("sync-unknown-profile", profile = name.as_str())))?;
+        spec.post_install_script = profile.verified_post_install_script()?.map(str::to_string);
+    
+        for package_name in groups.resolve_profile_packages_with(name, |conflict| super::profile::choose_group(conflict, true))? {
+            match app.repository.resolve_name(&package_name) {
+                Ok(name) if !app.repository.is_supported(&name) => {
+                    crate::cli::print_warning(&t!("sync-unsupported-platform", package = name.as_str()));
+                }
+                Ok(name) => wanted.push(name),
+                Err(crate::Error::UnsupportedPlatform { .. }) => {
+                    crate::cli::print_warning(&t!("sync-unsupported-platform", package = package_name.as_str()));
+                }
+                Err(crate::Error::PackageNotFound(_)) => {
+                    crate::cli::print_warning(&t!("sync-missing-definition", package = package_name.as_str()));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
+    // Everything the image installs, dependencies included, goes into the build context
+    let packages = app.repository.resolve_packages(&wanted).await?;
+    for package in &packages {
+        match package.installation {
+            Installation::Aur { .. } => crate::cli::print_warning(&t!("containerize-aur", package = package.name.as_str())),
+            Installation::Flatpak { .. } => crate::cli::print_warning(&t!("containerize-flatpak", package = package.name.as_str())),
+            _ => {}
+        }
+    }
+    
+    let temp_dir;
+    let context = match (&args.output, &args.tag) {
+        (Some(dir), _) => dir.clone(),
+        (None, Some(_)) => {
+            temp_dir = tempfile::Builder::new().prefix("archbox-image-").tempdir()?;
+            temp_dir.path().to_path_buf()
+        }
+        (None, None) => PathBuf::from("archbox-image"),
+    };
+    
+    container::write_context(&context, &spec, &packages).await?;
+    crate::cli::print_porcelain(&["context", &context.display().to_string()]);
+    
+    let Some(tag) = &args.tag else {
+        crate::cli::print_success(&t!("containerize-written", dir = context.display().to_string()));
+        crate::cli::print_info(&t!("containerize-build-hint", dir = context.display().to_string()));
+        return Ok(());
+    };
+    
+    let engine = args.engine
+        .or_else(Engine::detect)
+        .ok_or_else(|| crate::Error::Config(t!("containerize-no-engine")))?;
+    
+    crate::cli::print_info(&t!("containerize-building", tag = tag.as_str(), engine = engine.program()));
+    engine.build(&context, tag).await?;
+    
+    crate::cli::print_success(&t!("containerize-built", tag = tag.as_str()));
+    crate::cli::print_porcelain(&["image", tag]);
+    Ok(())
+}
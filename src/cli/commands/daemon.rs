@@ -0,0 +1,36 @@
+use crate::{config::Config, daemon, t, App, Result};
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Listen on this socket instead of $XDG_RUNTIME_DIR/archbox.sock
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+    
+    /// Also provide org.archbox.Manager on the session D-Bus
+    #[arg(long)]
+    pub dbus: bool,
+}
+
+/// Load the definitions once and serve requests until interrupted
+pub async fn execute(config: Config, args: &DaemonArgs) -> Result<()> {
+    let socket = args.socket.clone().unwrap_or_else(daemon::default_socket_path);
+    
+    // Bind first so a second daemon fails before loading every definition
+    let listener = daemon::bind(&socket).await?;
+    let app = match App::with_config(config).await {
+        Ok(app) => app,
+        Err(e) => {
+            let _ = std::fs::remove_file(&socket);
+            return Err(e);
+        }
+    };
+    crate::cli::print_info(&t!("daemon-listening", socket = socket.display().to_string()));
+    crate::cli::print_porcelain(&["listening", &socket.display().to_string()]);
+    
+    daemon::serve(app, listener, &socket, args.dbus).await?;
+    
+    crate::cli::print_info(&t!("daemon-stopped"));
+    Ok(())
+}
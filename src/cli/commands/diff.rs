@@ -0,0 +1,144 @@
+use crate::{
+    drift::{self, FileDrift},
+    package::diff::labeled_diff,
+    t, App, Result,
+};
+use clap::Args;
+use console::style;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Installed package whose config files to compare
+    #[arg(required = true)]
+    pub package: String,
+}
+
+pub async fn execute(app: &App, args: DiffArgs) -> Result<()> {
+    let name = app.repository.resolve_name(&args.package)?;
+    let package = app.repository.loader.get_package(&name)
+        .ok_or_else(|| app.repository.loader.not_found(&name))?;
+
+    let Some(record) = app.repository.installed_record(&package.name) else {
+        crate::cli::print_warning(&t!("diff-not-installed", package = package.name.as_str()));
+        return Ok(());
+    };
+
+    let files = drift::inspect(package, record, &app.config).await?;
+    if files.is_empty() {
+        crate::cli::print_info(&t!("diff-no-files", package = package.name.as_str()));
+        return Ok(());
+    }
+
+    for file in &files {
+        crate::cli::print_porcelain(&["file", &file.path.display().to_string(), LocalStatus::of(file).name(), DefinitionStatus::of(file).name()]);
+    }
+    if !crate::cli::is_normal_output() {
+        return Ok(());
+    }
+
+    for file in &files {
+        print_file(file);
+    }
+
+    let drifted = files.iter().filter(|file| file.locally_modified() || file.differs_from_definition()).count();
+    if drifted == 0 {
+        crate::cli::print_success(&t!("diff-in-sync", package = package.name.as_str()));
+    }
+
+    Ok(())
+}
+
+/// How the file on disk compares with what ArchBox wrote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalStatus {
+    Missing,
+    /// Nothing was recorded when it was written
+    Unknown,
+    Modified,
+    Unchanged,
+}
+
+impl LocalStatus {
+    fn of(file: &FileDrift) -> Self {
+        if file.current.is_none() {
+            LocalStatus::Missing
+        } else if file.deployed.is_none() {
+            LocalStatus::Unknown
+        } else if file.locally_modified() {
+            LocalStatus::Modified
+        } else {
+            LocalStatus::Unchanged
+        }
+    }
+
+    /// Name in porcelain output
+    fn name(&self) -> &'static str {
+        match self {
+            LocalStatus::Missing => "missing",
+            LocalStatus::Unknown => "unknown",
+            LocalStatus::Modified => "modified",
+            LocalStatus::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// How the file on disk compares with what the definition declares now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefinitionStatus {
+    Undeclared,
+    Differs,
+    Same,
+}
+
+impl DefinitionStatus {
+    fn of(file: &FileDrift) -> Self {
+        if file.declared.is_none() {
+            DefinitionStatus::Undeclared
+        } else if file.differs_from_definition() {
+            DefinitionStatus::Differs
+        } else {
+            DefinitionStatus::Same
+        }
+    }
+
+    /// Name in porcelain output
+    fn name(&self) -> &'static str {
+        match self {
+            DefinitionStatus::Undeclared => "undeclared",
+            DefinitionStatus::Differs => "differs",
+            DefinitionStatus::Same => "same",
+        }
+    }
+}
+
+fn print_file(file: &FileDrift) {
+    let mut notes = Vec::new();
+    match LocalStatus::of(file) {
+        LocalStatus::Missing => notes.push(style(t!("diff-missing")).red().to_string()),
+        LocalStatus::Modified => notes.push(style(t!("diff-modified")).yellow().to_string()),
+        LocalStatus::Unknown => notes.push(style(t!("diff-not-recorded")).dim().to_string()),
+        LocalStatus::Unchanged => {}
+    }
+    match DefinitionStatus::of(file) {
+        DefinitionStatus::Undeclared => notes.push(style(t!("diff-undeclared")).dim().to_string()),
+        DefinitionStatus::Differs => notes.push(style(t!("diff-outdated")).yellow().to_string()),
+        DefinitionStatus::Same => {}
+    }
+    if notes.is_empty() {
+        notes.push(style(t!("diff-unchanged")).green().to_string());
+    }
+
+    let block = if file.block { format!(" {}", style(t!("diff-managed-block")).dim()) } else { String::new() };
+    println!("{}{} {}", style(file.path.display()).bold(), block, notes.join(", "));
+
+    let current = file.current.as_deref().unwrap_or_default();
+    if let (true, Some(deployed)) = (file.locally_modified(), &file.deployed) {
+        println!("{}", t!("diff-local-changes"));
+        print!("{}", labeled_diff(&file.path, ("as written", deployed), ("on disk", current)));
+    }
+    if let (true, Some(declared)) = (file.differs_from_definition(), &file.declared) {
+        println!("{}", t!("diff-would-change"));
+        print!("{}", labeled_diff(&file.path, ("on disk", current), ("definition", declared)));
+    }
+    println!();
+}
@@ -0,0 +1,160 @@
+use crate::{
+    project::{self, ProjectManifest},
+    t, App, Result,
+};
+use clap::{Args, Subcommand};
+use console::style;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct EnvArgs {
+    #[command(subcommand)]
+    pub command: EnvCommand,
+    
+    /// Manifest to use instead of the nearest archbox.yaml
+    #[arg(short, long, global = true)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum EnvCommand {
+    /// Install the packages the project's archbox.yaml declares
+    Install {
+        /// Skip confirmation prompts
+        #[arg(short, long)]
+        yes: bool,
+        
+        /// Show what would be installed without installing
+        #[arg(long)]
+        dry_run: bool,
+        
+        #[command(flatten)]
+        config_conflict: super::install::ConfigConflictArgs,
+        
+        #[command(flatten)]
+        scope: super::install::ScopeArgs,
+    },
+    
+    /// Verify the packages the project's archbox.yaml declares are installed
+    Check,
+}
+
+/// A manifest entry and whether it is installed (`None` if there is no definition for it)
+type EntryStatus = (String, Option<bool>);
+
+pub async fn execute(app: &mut App, args: EnvArgs) -> Result<()> {
+    let path = match args.file {
+        Some(path) => path,
+        None => {
+            let cwd = std::env::current_dir()?;
+            project::find_manifest(&cwd)
+                .ok_or_else(|| crate::Error::Config(t!("env-no-manifest", file = project::MANIFEST_FILE)))?
+        }
+    };
+    let manifest = project::load_manifest(&path)?;
+    tracing::info!("Using project manifest {}", path.display());
+    
+    match args.command {
+        EnvCommand::Install { yes, dry_run, config_conflict, scope } => {
+            install(app, &manifest, yes, dry_run, config_conflict, scope).await
+        }
+        EnvCommand::Check => check(app, &manifest).await,
+    }
+}
+
+async fn install(
+    app: &mut App,
+    manifest: &ProjectManifest,
+    yes: bool,
+    dry_run: bool,
+    config_conflict: super::install::ConfigConflictArgs,
+    scope: super::install::ScopeArgs,
+) -> Result<()> {
+    let statuses = entry_statuses(app, manifest).await?;
+    
+    let unknown: Vec<&str> = statuses.iter().filter(|(_, s)| s.is_none()).map(|(n, _)| n.as_str()).collect();
+    if !unknown.is_empty() {
+        return Err(crate::Error::PackageNotFound(unknown.join(", ")));
+    }
+    
+    let missing: Vec<String> = statuses
+        .into_iter()
+        .filter(|(_, installed)| *installed == Some(false))
+        .map(|(name, _)| name)
+        .collect();
+    
+    if missing.is_empty() {
+        crate::cli::print_success(&t!("env-all-present", project = project_name(manifest)));
+        return Ok(());
+    }
+    
+    app.repository.set_variables(&manifest.variables);
+    
+    super::install::execute(app, super::install::InstallArgs {
+        packages: missing,
+        category: None,
+        interactive: false,
+        yes,
+        dry_run,
+        force: false,
+        keep_build: false,
+        rmdeps: false,
+        set: Vec::new(),
+        config_conflict,
+        scope,
+        exclude: Default::default(),
+    })
+    .await
+}
+
+async fn check(app: &App, manifest: &ProjectManifest) -> Result<()> {
+    let statuses = entry_statuses(app, manifest).await?;
+    
+    for (name, installed) in &statuses {
+        let status = match installed {
+            Some(true) => "present",
+            Some(false) => "missing",
+            None => "unknown",
+        };
+        crate::cli::print_porcelain(&[status, name]);
+    }
+    
+    if crate::cli::is_normal_output() {
+        println!("{}", style(t!("env-check-header", project = project_name(manifest))).bold());
+        for (name, installed) in &statuses {
+            let status = match installed {
+                Some(true) => style(t!("env-present")).green(),
+                Some(false) => style(t!("env-missing")).red(),
+                None => style(t!("env-unknown")).yellow(),
+            };
+            println!("  {} {}", style(name).bold(), status);
+        }
+    }
+    
+    let problems = statuses.iter().filter(|(_, installed)| *installed != Some(true)).count();
+    if problems > 0 {
+        // Non-zero exit so CI and onboarding scripts can rely on it
+        return Err(crate::Error::Dependency(t!("env-check-failed", count = problems)));
+    }
+    
+    crate::cli::print_success(&t!("env-all-present", project = project_name(manifest)));
+    Ok(())
+}
+
+async fn entry_statuses(app: &App, manifest: &ProjectManifest) -> Result<Vec<EntryStatus>> {
+    let mut statuses = Vec::new();
+    
+    for name in &manifest.packages {
+        let installed = match app.repository.loader.get_package(name) {
+            Some(_) => Some(app.repository.is_installed(name).await?),
+            None => None,
+        };
+        statuses.push((name.clone(), installed));
+    }
+    
+    Ok(statuses)
+}
+
+fn project_name(manifest: &ProjectManifest) -> String {
+    manifest.name.clone().unwrap_or_else(|| t!("env-this-project"))
+}
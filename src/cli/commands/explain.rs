@@ -0,0 +1,121 @@
+use crate::{
+    cli::commands::install::ScopeArgs,
+    explain::{Plan, StepKind},
+    package::{platform::Platform, DefinitionLoader, Package},
+    t, App, Result,
+};
+use clap::Args;
+use console::style;
+use std::path::Path;
+
+#[derive(Args)]
+pub struct ExplainArgs {
+    /// Definition file to review, or the name of a package in the repository
+    #[arg(required = true)]
+    pub definition: String,
+    
+    #[command(flatten)]
+    pub scope: ScopeArgs,
+}
+
+pub async fn execute(app: &App, args: ExplainArgs) -> Result<()> {
+    let packages = load(app, &args.definition).await?;
+    let platform = Platform::current();
+    
+    for (i, package) in packages.iter().enumerate() {
+        if !package.supports(&platform) {
+            return Err(package.unsupported_error(&platform));
+        }
+        let plan = Plan::new(package, &app.config, &platform, args.scope.scope())?;
+        
+        if crate::cli::is_normal_output() {
+            if i > 0 {
+                println!();
+            }
+            print_plan(&plan);
+        }
+        print_records(&plan);
+    }
+    
+    Ok(())
+}
+
+/// The definitions in a file, sorted by name, or the repository package of that name
+async fn load(app: &App, definition: &str) -> Result<Vec<Package>> {
+    if !Path::new(definition).is_file() {
+        let name = app.repository.resolve_name(definition)?;
+        let package = app.repository.loader.get_package(&name)
+            .ok_or_else(|| app.repository.loader.not_found(&name))?;
+        return Ok(vec![package.clone()]);
+    }
+    
+    let mut loader = DefinitionLoader::new();
+    loader.load_definition_file(definition).await?;
+    
+    let mut packages: Vec<Package> = loader.packages().values().cloned().collect();
+    if packages.is_empty() {
+        return Err(crate::Error::Config(t!("explain-no-definitions", path = definition)));
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+fn print_plan(plan: &Plan) {
+    println!("{}", style(t!(
+        "explain-heading",
+        package = plan.package.as_str(),
+        version = plan.version.as_str(),
+        method = plan.method,
+        scope = plan.scope.to_string()
+    )).cyan().bold());
+    
+    if !plan.dependencies.is_empty() {
+        println!("{}", t!("explain-dependencies", packages = plan.dependencies.join(", ")));
+    }
+    
+    if plan.steps.is_empty() {
+        println!("  {}", t!("explain-nothing"));
+    }
+    for (i, step) in plan.steps.iter().enumerate() {
+        let kind = format!("{:<9}", step.kind.name());
+        let kind = match step.kind {
+            StepKind::Run => style(kind).blue(),
+            StepKind::Download => style(kind).magenta(),
+            StepKind::Write => style(kind).green(),
+            StepKind::Service | StepKind::Group => style(kind).cyan(),
+            StepKind::Prompt | StepKind::Manual => style(kind).yellow(),
+        };
+        let root = if step.root { style(" [root]").red().bold().to_string() } else { String::new() };
+        println!("  {:>2}. {} {}{}", i + 1, kind, step.detail, root);
+        
+        for line in step.content.iter().flat_map(|content| content.lines()) {
+            println!("      {} {}", style("│").dim(), style(line).dim());
+        }
+    }
+    
+    if !plan.secrets.is_empty() {
+        println!("{}", t!("explain-secrets", secrets = plan.secrets.join(", ")));
+    }
+    if plan.needs_root() {
+        crate::cli::print_warning(&t!("explain-root"));
+    } else {
+        crate::cli::print_info(&t!("explain-no-root"));
+    }
+}
+
+/// `package`, then a `step` record per step in order, then `dependency` and `secret` records
+fn print_records(plan: &Plan) {
+    let scope = plan.scope.to_string();
+    crate::cli::print_porcelain(&["package", &plan.package, &plan.version, plan.method, &scope]);
+    
+    for step in &plan.steps {
+        let privilege = if step.root { "root" } else { "user" };
+        crate::cli::print_porcelain(&["step", step.kind.name(), privilege, &step.detail]);
+    }
+    for dependency in &plan.dependencies {
+        crate::cli::print_porcelain(&["dependency", dependency]);
+    }
+    for secret in &plan.secrets {
+        crate::cli::print_porcelain(&["secret", secret]);
+    }
+}
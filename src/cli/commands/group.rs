@@ -0,0 +1,228 @@
+use super::install::{ConfigConflictArgs, ExcludeArgs, InstallArgs, ScopeArgs};
+use crate::{groups::{GroupManager, PackageGroup}, hardware::Hardware, t, App, Result};
+use clap::{Args, Subcommand};
+use console::style;
+
+#[derive(Args)]
+pub struct GroupArgs {
+    #[command(subcommand)]
+    pub command: GroupCommand,
+}
+
+#[derive(Subcommand)]
+pub enum GroupCommand {
+    /// List package groups
+    List,
+    
+    /// Show the packages of a group, including optional and hardware-specific ones
+    Show {
+        /// Group name
+        name: String,
+    },
+    
+    /// Install a group's packages, with the ones for this machine's hardware
+    Install {
+        /// Group name
+        name: String,
+        
+        /// Also install the group's optional packages
+        #[arg(long)]
+        optional: bool,
+        
+        #[arg(short, long)]
+        yes: bool,
+        
+        #[arg(long)]
+        dry_run: bool,
+        
+        #[command(flatten)]
+        config_conflict: ConfigConflictArgs,
+        
+        #[command(flatten)]
+        scope: ScopeArgs,
+        
+        #[command(flatten)]
+        exclude: ExcludeArgs,
+    },
+}
+
+pub async fn execute(app: &mut App, args: GroupArgs) -> Result<()> {
+    let manager = GroupManager::new();
+    
+    match args.command {
+        GroupCommand::List => {
+            list_groups(&manager);
+            Ok(())
+        }
+        GroupCommand::Show { name } => show_group(app, find_group(&manager, &name)?).await,
+        GroupCommand::Install { name, optional, yes, dry_run, config_conflict, scope, exclude } => {
+            let group = find_group(&manager, &name)?;
+            let conflicting = installed_conflicts(app, group).await?;
+            if !conflicting.is_empty() {
+                return Err(crate::Error::Config(t!(
+                    "group-conflicts",
+                    group = group.name.as_str(),
+                    packages = conflicting.join(", ")
+                )));
+            }
+            
+            let mut names = group.packages_for(&Hardware::current());
+            if optional {
+                names.extend(group.optional_packages.iter().cloned());
+            }
+            
+            super::install::execute(app, InstallArgs {
+                packages: available(app, &names)?,
+                category: None,
+                interactive: false,
+                yes,
+                dry_run,
+                force: false,
+                keep_build: false,
+                rmdeps: false,
+                set: Vec::new(),
+                config_conflict,
+                scope,
+                exclude,
+            })
+            .await
+        }
+    }
+}
+
+fn find_group<'a>(manager: &'a GroupManager, name: &str) -> Result<&'a PackageGroup> {
+    manager.get_group(name).ok_or_else(|| {
+        let mut known: Vec<&str> = manager.list_groups().iter().map(|group| group.name.as_str()).collect();
+        known.sort();
+        crate::Error::Config(t!("group-unknown", group = name, groups = known.join(", ")))
+    })
+}
+
+fn list_groups(manager: &GroupManager) {
+    let mut groups = manager.list_groups();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    for group in groups {
+        let count = group.packages.len().to_string();
+        let optional = group.optional_packages.len().to_string();
+        let source = if manager.is_user_group(&group.name) { "user" } else { "built-in" };
+        crate::cli::print_porcelain(&[&group.name, source, &count, &optional, &group.description]);
+        if !crate::cli::is_normal_output() {
+            continue;
+        }
+        
+        let mut counts = format!("{}, {}", t!("profile-source", source = source), t!("profile-package-count", count = group.packages.len()));
+        if !group.optional_packages.is_empty() {
+            counts = format!("{}, {}", counts, t!("group-optional-count", count = group.optional_packages.len()));
+        }
+        let hardware: usize = group.hardware_packages.iter().map(|conditional| conditional.packages.len()).sum();
+        if hardware > 0 {
+            counts = format!("{}, {}", counts, t!("group-hardware-count", count = hardware));
+        }
+        if let Some(machine) = group.machine {
+            counts = format!("{}, {}", counts, t!("group-for-machine", class = t!("machine-class", class = machine.name())));
+        }
+        println!("{} {} {}", style(&group.name).bold(), style(format!("({})", counts)).dim(), group.description);
+    }
+}
+
+async fn show_group(app: &App, group: &PackageGroup) -> Result<()> {
+    let hardware = Hardware::current();
+    let hardware_packages: Vec<(String, bool)> = group.hardware_packages
+        .iter()
+        .flat_map(|conditional| {
+            let matches = conditional.matches(&hardware);
+            conditional.packages.iter().map(move |name| (name.clone(), matches))
+        })
+        .collect();
+    
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style(&group.name).cyan().bold(), group.description);
+    }
+    
+    let sections = [
+        ("package", t!("group-packages"), group.packages.iter().map(|name| (name.clone(), true)).collect::<Vec<_>>()),
+        ("optional", t!("group-optional"), group.optional_packages.iter().map(|name| (name.clone(), true)).collect()),
+        ("hardware", t!("group-hardware"), hardware_packages),
+    ];
+    for (kind, title, packages) in sections {
+        if packages.is_empty() {
+            continue;
+        }
+        if crate::cli::is_normal_output() {
+            println!("\n{}", style(title).bold());
+        }
+        
+        for (name, applies) in packages {
+            let status = package_status(app, &name).await?;
+            crate::cli::print_porcelain(&[kind, &name, status, if applies { "applies" } else { "skipped" }]);
+            if !crate::cli::is_normal_output() {
+                continue;
+            }
+            
+            let label = match status {
+                "installed" => style(t!("tree-installed")).green(),
+                "available" => style(t!("tree-not-installed")).yellow(),
+                "unsupported" => style(t!("group-unsupported")).red(),
+                _ => style(t!("group-no-definition")).red(),
+            };
+            let note = if applies { String::new() } else { format!(" {}", style(t!("group-not-this-machine")).dim()) };
+            println!("  {} {}{}", style(&name).bold(), label, note);
+        }
+    }
+    
+    if !group.conflicts.is_empty() && crate::cli::is_normal_output() {
+        println!("\n{} {}", style(t!("group-conflicts-with")).bold(), group.conflicts.join(", "));
+    }
+    
+    Ok(())
+}
+
+/// `installed` or `available`, `unsupported` when it isn't built for this machine's
+/// architecture, and `missing` when no definition provides the name
+async fn package_status(app: &App, name: &str) -> Result<&'static str> {
+    let resolved = match app.repository.resolve_name(name) {
+        Ok(resolved) if !app.repository.is_supported(&resolved) => return Ok("unsupported"),
+        Ok(resolved) => resolved,
+        Err(crate::Error::UnsupportedPlatform { .. }) => return Ok("unsupported"),
+        Err(crate::Error::PackageNotFound(_)) => return Ok("missing"),
+        Err(e) => return Err(e),
+    };
+    Ok(if app.repository.is_installed(&resolved).await? { "installed" } else { "available" })
+}
+
+/// The group's conflicting packages that are installed
+async fn installed_conflicts(app: &App, group: &PackageGroup) -> Result<Vec<String>> {
+    let mut installed = Vec::new();
+    for name in &group.conflicts {
+        if package_status(app, name).await? == "installed" {
+            installed.push(name.clone());
+        }
+    }
+    Ok(installed)
+}
+
+/// The names that can be installed here, warning about the others like `sync` does
+pub fn available(app: &App, names: &[String]) -> Result<Vec<String>> {
+    let mut wanted = Vec::new();
+    for package_name in names {
+        match app.repository.resolve_name(package_name) {
+            Ok(name) if !app.repository.is_supported(&name) => {
+                crate::cli::print_warning(&t!("sync-unsupported-platform", package = name.as_str()));
+            }
+            Ok(name) => {
+                if !wanted.contains(&name) {
+                    wanted.push(name);
+                }
+            }
+            Err(crate::Error::UnsupportedPlatform { .. }) => {
+                crate::cli::print_warning(&t!("sync-unsupported-platform", package = package_name.as_str()));
+            }
+            Err(crate::Error::PackageNotFound(_)) => {
+                crate::cli::print_warning(&t!("sync-missing-definition", package = package_name.as_str()));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(wanted)
+}
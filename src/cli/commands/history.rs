@@ -0,0 +1,348 @@
+use crate::{
+    backup,
+    cli::table::Table,
+    history::{self, LogEvent, TransactionSummary},
+    package::{layout, Installation},
+    t, App, Result,
+};
+use clap::{Args, Subcommand};
+use console::style;
+use serde::Serialize;
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    pub command: HistoryCommand,
+}
+
+#[derive(Subcommand)]
+pub enum HistoryCommand {
+    /// List recent transactions, newest first
+    List {
+        /// Number of transactions to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    
+    /// Show the packages, commands and outcome of one transaction
+    Show {
+        /// Transaction id (a unique prefix is enough), or `last`
+        id: String,
+        
+        /// Print the transaction as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// `history show --json` output
+#[derive(Serialize)]
+struct TransactionDetail<'a> {
+    #[serde(flatten)]
+    transaction: &'a TransactionSummary,
+    rollbackable: bool,
+    /// Commands that undo (part of) the transaction
+    rollback: Vec<String>,
+}
+
+pub async fn execute(app: &App, args: HistoryArgs) -> Result<()> {
+    match args.command {
+        HistoryCommand::List { limit } => list_transactions(limit),
+        HistoryCommand::Show { id, json } => show_transaction(app, &id, json),
+    }
+}
+
+/// Recorded transactions other than the one running this command
+fn past_transactions() -> Result<Vec<TransactionSummary>> {
+    let current = history::current_id();
+    let mut transactions = history::transactions()?;
+    transactions.retain(|transaction| Some(&transaction.id) != current.as_ref());
+    Ok(transactions)
+}
+
+fn list_transactions(limit: usize) -> Result<()> {
+    let transactions = past_transactions()?;
+    
+    if transactions.is_empty() {
+        crate::cli::print_warning(&t!("history-empty"));
+        return Ok(());
+    }
+    
+    let recent: Vec<&TransactionSummary> = transactions.iter().rev().take(limit).collect();
+    
+    for transaction in &recent {
+        crate::cli::print_porcelain(&[
+            "transaction",
+            &transaction.id,
+            &transaction.started,
+            &transaction.command,
+            status_name(transaction),
+        ]);
+    }
+    
+    if !crate::cli::is_normal_output() {
+        return Ok(());
+    }
+    
+    let mut table = Table::new(vec![
+        t!("table-id"),
+        t!("table-date"),
+        t!("table-command"),
+        t!("table-result"),
+    ]);
+    for transaction in recent {
+        table.add_row(vec![
+            style(&transaction.id).cyan().to_string(),
+            local_time(&transaction.started),
+            command_line(transaction),
+            status_label(transaction),
+        ]);
+    }
+    table.print(true);
+    
+    Ok(())
+}
+
+fn show_transaction(app: &App, id: &str, json: bool) -> Result<()> {
+    let transactions = past_transactions()?;
+    let transaction = find_transaction(&transactions, id)?;
+    let rollback = rollback_commands(app, transaction);
+    
+    if json {
+        let detail = TransactionDetail {
+            transaction,
+            rollbackable: !rollback.is_empty(),
+            rollback,
+        };
+        println!("{}", serde_json::to_string_pretty(&detail)?);
+        return Ok(());
+    }
+    
+    crate::cli::print_porcelain(&[
+        "transaction",
+        &transaction.id,
+        &transaction.started,
+        &transaction.command,
+        status_name(transaction),
+    ]);
+    for event in &transaction.events {
+        match event {
+            LogEvent::Step { step, package, version, ok, duration_ms, .. } => {
+                crate::cli::print_porcelain(&[
+                    "step",
+                    step,
+                    package.as_deref().unwrap_or_default(),
+                    version.as_deref().unwrap_or_default(),
+                    if *ok { "ok" } else { "failed" },
+                    &duration_ms.to_string(),
+                ]);
+            }
+            LogEvent::Command { command, ok, duration_ms, .. } => {
+                crate::cli::print_porcelain(&[
+                    "command",
+                    command,
+                    if *ok { "ok" } else { "failed" },
+                    &duration_ms.to_string(),
+                ]);
+            }
+            _ => {}
+        }
+    }
+    for command in &rollback {
+        crate::cli::print_porcelain(&["rollback", command]);
+    }
+    
+    if !crate::cli::is_normal_output() {
+        return Ok(());
+    }
+    
+    println!("{}", style(t!("history-transaction", id = transaction.id.as_str())).bold().underlined());
+    println!("{} {}", t!("history-command"), style(command_line(transaction)).bold());
+    println!("{} {}", t!("history-started"), local_time(&transaction.started));
+    if let Some(duration_ms) = transaction.duration_ms {
+        println!("{} {}", t!("history-duration"), format_duration(duration_ms));
+    }
+    println!("{} {}", t!("history-result"), status_label(transaction));
+    
+    let steps: Vec<&LogEvent> = transaction.events.iter().filter(|e| matches!(e, LogEvent::Step { .. })).collect();
+    if !steps.is_empty() {
+        println!("\n{}", style(t!("history-packages")).bold());
+        for event in steps {
+            if let LogEvent::Step { step, package, version, ok, duration_ms, .. } = event {
+                let target = match (package, version) {
+                    (Some(package), Some(version)) => format!("{} {}", style(package).bold(), version),
+                    (Some(package), None) => style(package).bold().to_string(),
+                    _ => String::new(),
+                };
+                println!(
+                    "  {} {} {} {}",
+                    outcome_mark(*ok),
+                    step,
+                    target,
+                    style(format!("({})", format_duration(*duration_ms))).dim()
+                );
+            }
+        }
+    }
+    
+    let commands: Vec<&LogEvent> = transaction.events.iter().filter(|e| matches!(e, LogEvent::Command { .. })).collect();
+    if !commands.is_empty() {
+        println!("\n{}", style(t!("history-commands")).bold());
+        for event in commands {
+            if let LogEvent::Command { command, ok, exit_code, duration_ms, .. } = event {
+                let exit = match (ok, exit_code) {
+                    (false, Some(code)) => format!(" {}", style(format!("[{}]", t!("history-exit-code", code = *code))).red()),
+                    _ => String::new(),
+                };
+                println!(
+                    "  {} {}{} {}",
+                    outcome_mark(*ok),
+                    command,
+                    exit,
+                    style(format!("({})", format_duration(*duration_ms))).dim()
+                );
+            }
+        }
+    }
+    
+    // Errors and the output of failed commands
+    let mut excerpt = Vec::new();
+    for event in &transaction.events {
+        match event {
+            LogEvent::Step { step, package, error: Some(error), .. } => {
+                excerpt.push(format!("{} {}: {}", step, package.as_deref().unwrap_or_default(), error));
+            }
+            LogEvent::Command { command, ok: false, output, .. } if !output.is_empty() => {
+                excerpt.push(format!("$ {}", command));
+                excerpt.extend(output.iter().cloned());
+            }
+            _ => {}
+        }
+    }
+    if excerpt.is_empty() {
+        if let Some(error) = &transaction.error {
+            excerpt.push(error.clone());
+        }
+    }
+    if !excerpt.is_empty() {
+        println!("\n{}", style(t!("history-log")).bold());
+        for line in excerpt {
+            println!("  {} {}", style("│").dim(), line);
+        }
+    }
+    
+    println!("\n{}", style(t!("history-rollback")).bold());
+    if rollback.is_empty() {
+        println!("  {}", t!("history-not-rollbackable"));
+    } else {
+        for command in &rollback {
+            println!("  {}", style(command).cyan());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Look up a transaction by exact id, unique prefix, or `last`
+fn find_transaction<'a>(transactions: &'a [TransactionSummary], id: &str) -> Result<&'a TransactionSummary> {
+    if id == "last" {
+        // Skip earlier `history` runs, which only read the log
+        return transactions
+            .iter()
+            .rev()
+            .find(|transaction| transaction.command != "history")
+            .ok_or_else(|| crate::Error::Config(t!("history-empty")));
+    }
+    
+    if let Some(transaction) = transactions.iter().find(|transaction| transaction.id == id) {
+        return Ok(transaction);
+    }
+    
+    let matches: Vec<&TransactionSummary> = transactions.iter().filter(|transaction| transaction.id.starts_with(id)).collect();
+    match matches.as_slice() {
+        [transaction] => Ok(transaction),
+        [] => Err(crate::Error::Config(t!("history-unknown", id = id))),
+        _ => Err(crate::Error::Config(t!("history-ambiguous", id = id, count = matches.len()))),
+    }
+}
+
+/// Commands that can undo the transaction's successful steps: switching a binary back to a
+/// version still on disk, or restoring files that were backed up
+fn rollback_commands(app: &App, transaction: &TransactionSummary) -> Vec<String> {
+    let mut commands = Vec::new();
+    
+    for event in &transaction.events {
+        let LogEvent::Step { step, package: Some(name), ok: true, .. } = event else {
+            continue;
+        };
+        
+        let binary = app.repository.loader.get_package(name)
+            .is_some_and(|package| matches!(package.installation, Installation::Binary { .. }));
+        let rollback = match step.as_str() {
+            "install" | "rollback" | "use" if binary && layout::installed_versions(name, app.repository.installed_scope(name)).is_ok_and(|v| v.len() > 1) => {
+                Some(format!("archbox rollback {}", name))
+            }
+            "install" | "remove" if backup::list_backups(name).is_ok_and(|sets| !sets.is_empty()) => {
+                Some(format!("archbox restore {}", name))
+            }
+            _ => None,
+        };
+        
+        if let Some(command) = rollback {
+            if !commands.contains(&command) {
+                commands.push(command);
+            }
+        }
+    }
+    
+    commands
+}
+
+fn command_line(transaction: &TransactionSummary) -> String {
+    if transaction.args.is_empty() {
+        transaction.command.clone()
+    } else {
+        transaction.args.join(" ")
+    }
+}
+
+fn status_name(transaction: &TransactionSummary) -> &'static str {
+    match transaction.ok {
+        Some(true) => "ok",
+        Some(false) => "failed",
+        None => "interrupted",
+    }
+}
+
+fn status_label(transaction: &TransactionSummary) -> String {
+    match transaction.ok {
+        Some(true) => style(t!("history-ok")).green().to_string(),
+        Some(false) => match &transaction.error {
+            Some(error) => format!("{}: {}", style(t!("history-failed")).red(), error),
+            None => style(t!("history-failed")).red().to_string(),
+        },
+        None => style(t!("history-interrupted")).yellow().to_string(),
+    }
+}
+
+fn outcome_mark(ok: bool) -> String {
+    if ok {
+        style("✓").green().to_string()
+    } else {
+        style("✗").red().to_string()
+    }
+}
+
+fn local_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+fn format_duration(ms: u64) -> String {
+    match ms {
+        0..=999 => format!("{} ms", ms),
+        1000..=59_999 => format!("{:.1} s", ms as f64 / 1000.0),
+        _ => format!("{}m {}s", ms / 60_000, ms % 60_000 / 1000),
+    }
+}
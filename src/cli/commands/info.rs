@@ -1,6 +1,13 @@
-use crate::{App, Result};
+use crate::{
+    package::{platform::BinaryAsset, DependencyType, InstallScope, Package},
+    state::InstalledPackage,
+    t, App, Result,
+};
 use clap::Args;
 use console::style;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
 #[derive(Args)]
 pub struct InfoArgs {
@@ -15,34 +22,88 @@ pub struct InfoArgs {
     /// Show installation method details
     #[arg(short, long)]
     pub installation: bool,
+    
+    /// Show the full transitive dependency tree
+    #[arg(short, long)]
+    pub tree: bool,
+    
+    /// Show where the installed artifacts came from (URL, commit, tag, checksum, signer)
+    #[arg(long)]
+    pub provenance: bool,
+    
+    /// Print the definition with its install status, dependents, install record and files
+    /// as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// `info --json` output: the definition as loaded, followed by what ArchBox knows about it
+#[derive(Serialize)]
+struct PackageDetail<'a> {
+    #[serde(flatten)]
+    package: &'a Package,
+    status: &'static str,
+    install_scope: InstallScope,
+    /// Whether the package can be installed on this machine
+    supported: bool,
+    /// What the state database recorded when ArchBox installed it, including provenance
+    installed: Option<&'a InstalledPackage>,
+    /// Packages whose definitions require this one
+    required_by: Vec<String>,
+    /// The installed ones among them
+    installed_dependents: Vec<String>,
+    /// Files the install put in place: a binary's install path, config files and drop-ins
+    files: Vec<PathBuf>,
 }
 
 pub async fn execute(app: &App, args: InfoArgs) -> Result<()> {
     let package = app.repository.loader.get_package(&args.package)
-        .ok_or_else(|| crate::Error::PackageNotFound(args.package.clone()))?;
+        .ok_or_else(|| app.repository.loader.not_found(&args.package))?;
     
     let installed = app.repository.is_installed(&package.name).await?;
     let status = if installed {
-        style("Installed").green().bold()
+        style(t!("info-status-installed")).green().bold()
+    } else {
+        style(t!("info-status-not-installed")).yellow().bold()
+    };
+    let scope = if installed {
+        app.repository.installed_scope(&package.name)
     } else {
-        style("Not Installed").yellow().bold()
+        package.install_scope(None)
     };
     
+    if args.json {
+        return print_json(app, package, installed, scope);
+    }
+    
     // Basic information
     println!("{}", style(&package.name).cyan().bold().underlined());
-    println!("Version: {}", style(&package.version).bold());
-    println!("Status: {}", status);
-    println!("Description: {}", package.description);
+    println!("{} {}", t!("info-version"), style(&package.version).bold());
+    println!("{} {}", t!("info-status"), status);
+    println!("{} {}", t!("info-scope"), scope);
+    if !package.platforms.is_empty() {
+        let platform = crate::package::platform::Platform::current();
+        let platforms = package.platforms.join(", ");
+        if package.supports(&platform) {
+            println!("{} {}", t!("info-platforms"), platforms);
+        } else {
+            println!("{} {} {}", t!("info-platforms"), platforms, style(t!("info-unsupported", arch = platform.arch.as_str())).red());
+        }
+    }
+    if package.kernel_module {
+        println!("{} {}", t!("info-kernel-module"), t!("info-yes"));
+    }
+    println!("{} {}", t!("info-description"), package.description);
     
     if let Some(long_desc) = &package.long_description {
-        println!("\n{}", style("Details:").bold());
+        println!("\n{}", style(t!("info-details")).bold());
         println!("{}", long_desc);
     }
     
     // Categories
     if !package.categories.is_empty() {
         println!("\n{} {}", 
-            style("Categories:").bold(),
+            style(t!("info-categories")).bold(),
             package.categories.iter()
                 .map(|c| style(c).cyan().to_string())
                 .collect::<Vec<_>>()
@@ -51,20 +112,27 @@ pub async fn execute(app: &App, args: InfoArgs) -> Result<()> {
     }
     
     // Dependencies
-    if args.dependencies || !package.dependencies.is_empty() {
-        println!("\n{}", style("Dependencies:").bold());
+    if args.tree {
+        println!("\n{}", style(t!("info-dependency-tree")).bold());
+        let status = dependency_status(app, package).await?;
+        let mut expanded = HashSet::from([package.name.clone()]);
+        println!("{} {} {}", style(&package.name).bold(), style(&package.version).dim(), status_label(installed));
+        print_tree(app, package, "", &status, &mut expanded);
+    } else if args.dependencies || !package.dependencies.is_empty() {
+        println!("\n{}", style(t!("info-dependencies")).bold());
         if package.dependencies.is_empty() {
-            println!("  None");
+            println!("  {}", t!("info-none"));
         } else {
             for dep in &package.dependencies {
-                let dep_type = match dep.dep_type {
-                    crate::package::DependencyType::System => "system",
-                    crate::package::DependencyType::Package => "package",
-                    crate::package::DependencyType::Runtime => "runtime",
-                    crate::package::DependencyType::Build => "build",
-                };
-                
-                let optional = if dep.optional { " (optional)" } else { "" };
+                let dep_type = dep_type_name(&dep.dep_type);
+                let mut notes = Vec::new();
+                if dep.optional {
+                    notes.push(t!("info-optional"));
+                }
+                if let Some(platform) = &dep.platform {
+                    notes.push(t!("info-only-on", platform = platform.as_str()));
+                }
+                let optional = if notes.is_empty() { String::new() } else { format!(" ({})", notes.join(", ")) };
                 println!("  {} {} [{}]{}",
                     style("→").blue(),
                     style(&dep.name).bold(),
@@ -77,58 +145,242 @@ pub async fn execute(app: &App, args: InfoArgs) -> Result<()> {
     
     // Installation method
     if args.installation {
-        println!("\n{}", style("Installation Method:").bold());
+        println!("\n{}", style(t!("info-installation-method")).bold());
         match &package.installation {
             crate::package::Installation::Pacman { packages, flags } => {
-                println!("  Method: pacman");
-                println!("  Packages: {}", packages.join(", "));
+                println!("  {} {}", t!("info-method"), t!("info-method-pacman"));
+                println!("  {} {}", t!("info-packages"), packages.join(", "));
                 if let Some(flags) = flags {
-                    println!("  Flags: {}", flags.join(" "));
+                    println!("  {} {}", t!("info-flags"), flags.join(" "));
                 }
             }
             crate::package::Installation::Aur { package: pkg, helper } => {
-                println!("  Method: AUR");
-                println!("  Package: {}", pkg);
+                println!("  {} {}", t!("info-method"), t!("info-method-aur"));
+                println!("  {} {}", t!("info-package"), pkg);
                 if let Some(helper) = helper {
-                    println!("  Helper: {}", helper);
+                    println!("  {} {}", t!("info-helper"), helper);
                 }
             }
-            crate::package::Installation::Binary { url, install_path, .. } => {
-                println!("  Method: Binary download");
-                println!("  URL: {}", url);
-                println!("  Install path: {}", install_path);
+            crate::package::Installation::Binary { url, urls, fallbacks, .. } => {
+                println!("  {} {}", t!("info-method"), t!("info-method-binary-download"));
+                let platform = crate::package::platform::Platform::current();
+                match platform.resolve_binary(url, None, urls.as_ref(), None, fallbacks) {
+                    Ok(BinaryAsset::Download { url, fallback: false, .. }) => println!("  {} {}", t!("info-url"), url),
+                    Ok(BinaryAsset::Download { url, .. }) => {
+                        let note = t!("info-fallback-download", arch = platform.arch.as_str());
+                        println!("  {} {} {}", t!("info-url"), url, style(note).yellow());
+                    }
+                    Ok(BinaryAsset::Source { url, .. }) => {
+                        let note = t!("info-fallback-source", arch = platform.arch.as_str());
+                        println!("  {} {} {}", t!("info-url"), url, style(note).yellow());
+                    }
+                    Err(e) => println!("  {} {}", t!("info-url"), style(e).red()),
+                }
+                let install_path = package.install_path(scope).unwrap_or_default();
+                println!("  {} {}", t!("info-install-path"), install_path.display());
             }
-            crate::package::Installation::Flatpak { id, remote } => {
-                println!("  Method: Flatpak");
-                println!("  ID: {}", id);
-                println!("  Remote: {}", remote.as_deref().unwrap_or("flathub"));
+            crate::package::Installation::Flatpak { id, remote, .. } => {
+                println!("  {} {}", t!("info-method"), t!("info-method-flatpak"));
+                println!("  {} {}", t!("info-id"), id);
+                println!("  {} {}", t!("info-remote"), remote.as_deref().unwrap_or("flathub"));
             }
             _ => {
-                println!("  Method: {:?}", package.installation);
+                println!("  {} {:?}", t!("info-method"), package.installation);
             }
         }
     }
     
+    if args.provenance {
+        print_provenance(app, &package.name);
+    }
+    
     // Metadata
-    println!("\n{}", style("Metadata:").bold());
+    println!("\n{}", style(t!("info-metadata")).bold());
     if let Some(author) = &package.metadata.author {
-        println!("  Author: {}", author);
+        println!("  {} {}", t!("info-author"), author);
     }
     if let Some(homepage) = &package.metadata.homepage {
-        println!("  Homepage: {}", style(homepage).underlined());
+        println!("  {} {}", t!("info-homepage"), style(homepage).underlined());
     }
     if let Some(repository) = &package.metadata.repository {
-        println!("  Repository: {}", style(repository).underlined());
+        println!("  {} {}", t!("info-repository"), style(repository).underlined());
     }
     if let Some(license) = &package.metadata.license {
-        println!("  License: {}", license);
+        println!("  {} {}", t!("info-license"), license);
     }
     if let Some(size) = &package.metadata.size {
-        println!("  Size: {}", size);
+        println!("  {} {}", t!("info-size"), size);
     }
     if let Some(tags) = &package.metadata.tags {
-        println!("  Tags: {}", tags.join(", "));
+        println!("  {} {}", t!("info-tags"), tags.join(", "));
+    }
+    
+    Ok(())
+}
+
+fn print_json(app: &App, package: &Package, installed: bool, scope: InstallScope) -> Result<()> {
+    let names = |packages: Vec<&Package>| packages.into_iter().map(|package| package.name.clone()).collect();
+    let record = app.repository.installed_record(&package.name);
+    
+    let mut files = Vec::new();
+    if installed {
+        files.extend(package.install_path(scope));
+    }
+    if let Some(record) = record {
+        files.extend(record.owned_files());
     }
     
+    let detail = PackageDetail {
+        package,
+        status: if installed { "installed" } else { "not_installed" },
+        install_scope: scope,
+        supported: package.supports(&crate::package::platform::Platform::current()),
+        installed: record,
+        required_by: names(app.repository.dependents(&package.name)),
+        installed_dependents: names(app.repository.installed_dependents(&package.name)),
+        files,
+    };
+    println!("{}", serde_json::to_string_pretty(&detail)?);
     Ok(())
-}
\ No newline at end of file
+}
+
+/// What the state database recorded about the source of an installed package
+fn print_provenance(app: &App, name: &str) {
+    println!("\n{}", style(t!("info-provenance")).bold());
+    
+    let Some(record) = app.repository.installed_record(name) else {
+        println!("  {}", t!("info-provenance-not-installed"));
+        return;
+    };
+    println!("  {} {}", t!("info-installed-version"), record.version);
+    println!("  {} {}", t!("info-installed-at"), record.installed_at);
+    
+    let Some(provenance) = &record.provenance else {
+        println!("  {}", t!("info-provenance-not-recorded"));
+        return;
+    };
+    println!("  {} {}", t!("info-source"), provenance.source);
+    let fields = [
+        (t!("info-tag"), &provenance.tag),
+        (t!("info-commit"), &provenance.commit),
+        (t!("info-sha256"), &provenance.sha256),
+        (t!("info-signed-by"), &provenance.signed_by),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            println!("  {} {}", label, value);
+        }
+    }
+    if !provenance.overrides.is_empty() {
+        println!("  {} {}", t!("info-overrides"), provenance.overrides.join(", "));
+    }
+}
+
+fn dep_type_name(dep_type: &DependencyType) -> &'static str {
+    match dep_type {
+        DependencyType::System => "system",
+        DependencyType::Package => "package",
+        DependencyType::Runtime => "runtime",
+        DependencyType::Build => "build",
+    }
+}
+
+fn status_label(installed: bool) -> String {
+    if installed {
+        style(t!("tree-installed")).green().to_string()
+    } else {
+        style(t!("tree-not-installed")).yellow().to_string()
+    }
+}
+
+/// Installed status of every dependency reachable from `package`. Package dependencies are
+/// looked up in the installed cache, everything else is checked with pacman.
+async fn dependency_status(app: &App, package: &Package) -> Result<HashMap<String, bool>> {
+    let mut status = HashMap::new();
+    let mut pending = vec![package];
+    
+    while let Some(package) = pending.pop() {
+        for dep in &package.dependencies {
+            if status.contains_key(&dep.name) {
+                continue;
+            }
+            
+            let installed = match dep.dep_type {
+                DependencyType::Package => {
+                    if let Some(dependency) = app.repository.loader.get_package(&dep.name) {
+                        pending.push(dependency);
+                    }
+                    app.repository.is_installed(&dep.name).await?
+                }
+                _ => app.repository.is_system_installed(&dep.name).await?,
+            };
+            status.insert(dep.name.clone(), installed);
+        }
+    }
+    
+    Ok(status)
+}
+
+/// Print the dependencies of `package` below `prefix`. A package whose subtree was already
+/// printed is shown once more without its children.
+fn print_tree(
+    app: &App,
+    package: &Package,
+    prefix: &str,
+    status: &HashMap<String, bool>,
+    expanded: &mut HashSet<String>,
+) {
+    let count = package.dependencies.len();
+    
+    for (index, dep) in package.dependencies.iter().enumerate() {
+        let last = index + 1 == count;
+        let definition = match dep.dep_type {
+            DependencyType::Package => app.repository.loader.get_package(&dep.name),
+            _ => None,
+        };
+        
+        let mut label = style(&dep.name).bold().to_string();
+        if let Some(definition) = definition {
+            label.push_str(&format!(" {}", style(&definition.version).dim()));
+        }
+        
+        let mut notes = Vec::new();
+        if !matches!(dep.dep_type, DependencyType::Package) {
+            notes.push(dep_type_name(&dep.dep_type).to_string());
+        }
+        if dep.optional {
+            notes.push(t!("info-optional"));
+        }
+        if !notes.is_empty() {
+            label.push_str(&format!(" {}", style(format!("[{}]", notes.join(", "))).dim()));
+        }
+        
+        label.push(' ');
+        label.push_str(&status_label(status.get(&dep.name).copied().unwrap_or(false)));
+        
+        let children = match definition {
+            Some(definition) if !definition.dependencies.is_empty() => {
+                if expanded.insert(dep.name.clone()) {
+                    Some(definition)
+                } else {
+                    label.push_str(&format!(" {}", style(t!("tree-repeated")).dim()));
+                    None
+                }
+            }
+            Some(_) => None,
+            None => {
+                if matches!(dep.dep_type, DependencyType::Package) {
+                    label.push_str(&format!(" {}", style(t!("tree-unknown")).red()));
+                }
+                None
+            }
+        };
+        
+        println!("{}{}{}", prefix, if last { "└── " } else { "├── " }, label);
+        
+        if let Some(definition) = children {
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            print_tree(app, definition, &prefix, status, expanded);
+        }
+    }
+}
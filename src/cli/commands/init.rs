@@ -0,0 +1,14 @@
+use crate::{config::Config, shell::{self, ShellKind}, Result};
+use clap::Args;
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Shell to print the setup snippet for
+    #[arg(value_enum)]
+    pub shell: ShellKind,
+}
+
+pub fn execute(config: &Config, args: &InitArgs) -> Result<()> {
+    println!("{}", shell::init_script(args.shell, &config.installation.binary_dir));
+    Ok(())
+}
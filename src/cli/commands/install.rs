@@ -1,13 +1,22 @@
-use crate::{App, Result};
+use crate::{config::OptionalPolicy, package::{appimage::wildcard_match, BuildOptions, ConfigConflict, InstallScope, Package}, t, App, Result};
 use clap::Args;
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
 
 #[derive(Args)]
 pub struct InstallArgs {
-    #[arg(required = true)]
+    /// Packages to install; `-` reads names from stdin
+    #[arg(required_unless_present = "category")]
     pub packages: Vec<String>,
     
+    /// Also install the packages in this category
+    #[arg(short = 'C', long)]
+    pub category: Option<String>,
+    
+    /// Choose which packages of --category to install instead of taking all of them
+    #[arg(short, long, requires = "category")]
+    pub interactive: bool,
+    
     #[arg(short, long)]
     pub yes: bool,
     
@@ -16,21 +25,164 @@ pub struct InstallArgs {
     
     #[arg(short, long)]
     pub force: bool,
+    
+    /// Keep the build directory of source packages
+    #[arg(long)]
+    pub keep_build: bool,
+    
+    /// Remove build dependencies installed for source packages afterwards
+    #[arg(long)]
+    pub rmdeps: bool,
+    
+    /// Change a definition field for this install only, e.g. installation.url=URL or
+    /// version=1.2.3 (repeatable; needs a single package)
+    #[arg(long = "set", value_name = "FIELD=VALUE")]
+    pub set: Vec<String>,
+    
+    #[command(flatten)]
+    pub config_conflict: ConfigConflictArgs,
+    
+    #[command(flatten)]
+    pub scope: ScopeArgs,
+    
+    #[command(flatten)]
+    pub exclude: ExcludeArgs,
+}
+
+/// How to handle post-install changes to existing config files instead of asking
+#[derive(Args)]
+pub struct ConfigConflictArgs {
+    /// Back up changed config files, then overwrite them
+    #[arg(long, conflicts_with_all = ["skip", "overwrite"])]
+    pub backup: bool,
+    
+    /// Keep existing config files that would be changed
+    #[arg(long, conflicts_with = "overwrite")]
+    pub skip: bool,
+    
+    /// Overwrite changed config files without a backup
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+impl ConfigConflictArgs {
+    pub fn policy(&self) -> ConfigConflict {
+        if self.backup {
+            ConfigConflict::Backup
+        } else if self.skip {
+            ConfigConflict::Skip
+        } else if self.overwrite {
+            ConfigConflict::Overwrite
+        } else {
+            ConfigConflict::Ask
+        }
+    }
+}
+
+/// Where to install, overriding each definition's `scope`
+#[derive(Args)]
+pub struct ScopeArgs {
+    /// Install for the current user under ~/.local, with user services
+    #[arg(long, conflicts_with = "system")]
+    pub user: bool,
+    
+    /// Install system-wide under /usr/local, with system services (needs root)
+    #[arg(long)]
+    pub system: bool,
+}
+
+impl ScopeArgs {
+    pub fn scope(&self) -> Option<InstallScope> {
+        if self.user {
+            Some(InstallScope::User)
+        } else if self.system {
+            Some(InstallScope::System)
+        } else {
+            None
+        }
+    }
+}
+
+/// Packages to leave out of an operation
+#[derive(Args, Default)]
+pub struct ExcludeArgs {
+    /// Skip packages whose name matches this glob, e.g. 'lib*' (repeatable)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub patterns: Vec<String>,
+}
+
+impl ExcludeArgs {
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns.iter().any(|pattern| wildcard_match(pattern, name))
+    }
+    
+    /// Drop the excluded packages from `packages`, listing them in the plan
+    pub fn apply(&self, packages: &mut Vec<Package>) {
+        self.report(packages.iter());
+        packages.retain(|package| !self.matches(&package.name));
+    }
+    
+    /// List which of `packages` are excluded, as part of the plan
+    pub fn report<'a>(&self, packages: impl Iterator<Item = &'a Package>) {
+        let excluded: Vec<&Package> = packages.filter(|package| self.matches(&package.name)).collect();
+        if excluded.is_empty() {
+            return;
+        }
+        
+        for package in &excluded {
+            crate::cli::print_porcelain(&["excluded", &package.name, &package.version]);
+        }
+        let names: Vec<&str> = excluded.iter().map(|package| package.name.as_str()).collect();
+        crate::cli::print_info(&t!("exclude-skipping", count = names.len(), packages = names.join(", ")));
+    }
 }
 
 pub async fn execute(app: &mut App, args: InstallArgs) -> Result<()> {
-    println!("{} Installing packages...", style("🔧").cyan());
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("🔧").cyan(), t!("install-start"));
+    }
+    
+    // Capabilities like `editor` stand for the package chosen to provide them
+    let mut requested = crate::cli::expand_stdin_packages(&args.packages, args.yes || args.dry_run)?
+        .iter()
+        .map(|name| app.repository.resolve_name(name))
+        .collect::<Result<Vec<_>>>()?;
+    
+    if let Some(category) = &args.category {
+        for name in category_packages(app, category, args.interactive)? {
+            if !requested.contains(&name) {
+                requested.push(name);
+            }
+        }
+    }
+    
+    // Overrides are applied before resolving, so changed dependencies are followed too
+    if !args.set.is_empty() {
+        let [name] = requested.as_slice() else {
+            return Err(crate::Error::Config(t!("install-set-one-package")));
+        };
+        app.repository.override_definition(name, &args.set)?;
+        crate::cli::print_info(&t!("install-overridden", package = name.as_str(), fields = args.set.join(", ")));
+    }
     
     // Resolve package dependencies
-    let packages = app.repository.resolve_packages(&args.packages).await?;
+    let mut packages = app.repository.resolve_packages(&requested).await?;
+    let optional = optional_dependencies(app, &packages, &args).await?;
+    if !optional.is_empty() {
+        packages = app.repository.resolve_packages(&[requested.clone(), optional].concat()).await?;
+    }
+    args.exclude.apply(&mut packages);
     
     if packages.is_empty() {
-        crate::cli::print_warning("No packages found matching the criteria");
+        crate::cli::print_warning(&t!("install-nothing-found"));
         return Ok(());
     }
     
     // Show installation plan
-    show_installation_plan(&packages, args.dry_run);
+    show_installation_plan(app, &packages, args.dry_run).await;
+    for package in &packages {
+        crate::cli::warn_if_deprecated(package);
+    }
     
     if args.dry_run {
         return Ok(());
@@ -38,49 +190,140 @@ pub async fn execute(app: &mut App, args: InstallArgs) -> Result<()> {
     
     // Confirm installation
     if !args.yes && !confirm_installation(&packages)? {
-        crate::cli::print_info("Installation cancelled");
+        crate::cli::print_info(&t!("install-cancelled"));
         return Ok(());
     }
     
-    let pb = create_progress_bar(packages.len());
+    app.repository.set_build_options(BuildOptions {
+        keep_build: args.keep_build,
+        remove_build_deps: args.rmdeps || app.config.installation.remove_build_deps,
+    });
+    app.repository.set_config_conflict(args.config_conflict.policy());
+    app.repository.set_scope(args.scope.scope());
+    
+    let pb = crate::progress::overall(packages.len());
+    let mut installed = Vec::new();
     
     for (i, package) in packages.iter().enumerate() {
-        pb.set_message(format!("Installing {}", package.name));
+        pb.set_message(t!("install-progress", package = package.name.as_str()));
         
-        match app.repository.install_package(package, args.force).await {
+        let explicit = requested.contains(&package.name);
+        // An overridden package is installed again even when some version already is
+        let force = args.force || (explicit && !args.set.is_empty());
+        
+        match app.repository.install_package(package, force, explicit).await {
             Ok(_) => {
-                crate::cli::print_success(&format!("Installed {}", package.name));
+                crate::cli::print_success(&t!("install-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["installed", &package.name, &package.version]);
+                installed.push(package);
             }
             Err(e) => {
-                crate::cli::print_error(&format!("Failed to install {}: {}", package.name, e));
+                crate::cli::report_failure("install_failed", Some(&package.name), &t!("install-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
             }
         }
         
         pb.set_position(i as u64 + 1);
     }
     
-    pb.finish_with_message("Installation complete");
-    Ok(())
+    pb.finish_with_message(t!("install-complete"));
+    crate::cli::relogin_notice(app).await?;
+    crate::cli::kernel_module_notice(app, &installed, args.yes).await
 }
 
-fn show_installation_plan(packages: &[crate::package::Package], dry_run: bool) {
-    let action = if dry_run { "Would install" } else { "Will install" };
-    
-    println!("\n{} {} packages:", action, packages.len());
-    for package in packages {
-        println!("  {} {} ({})", 
-            style("→").blue(),
-            style(&package.name).bold(),
-            package.version
-        );
-    }
-    println!();
+/// Optional dependencies of `packages` to install with them, as `installation.install_optional`
+/// says. Without it, interactive runs ask and the others leave them out.
+async fn optional_dependencies(app: &App, packages: &[Package], args: &InstallArgs) -> Result<Vec<String>> {
+    let mut candidates = app.repository.optional_dependencies(packages).await?;
+    candidates.retain(|(package, _)| !args.exclude.matches(&package.name));
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let interactive = std::io::stdin().is_terminal() && crate::cli::is_normal_output();
+    let policy = app.config.installation.install_optional.unwrap_or(if interactive && !args.yes && !args.dry_run {
+        OptionalPolicy::Ask
+    } else {
+        OptionalPolicy::Never
+    });
+    
+    let names = |candidates: &[(Package, String)]| candidates.iter().map(|(package, _)| package.name.clone()).collect::<Vec<_>>();
+    match policy {
+        OptionalPolicy::Always => Ok(names(&candidates)),
+        OptionalPolicy::Ask if interactive => {
+            let labels: Vec<String> = candidates
+                .iter()
+                .map(|(package, suggested_by)| {
+                    let note = t!("install-optional-suggested-by", package = suggested_by.as_str());
+                    format!("{} - {} ({})", package.name, package.description, note)
+                })
+                .collect();
+            let selected = dialoguer::MultiSelect::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                .with_prompt(t!("install-optional-prompt"))
+                .items(&labels)
+                .interact()?;
+            Ok(selected.into_iter().map(|index| candidates[index].0.name.clone()).collect())
+        }
+        // Nobody to ask, so nothing is added
+        OptionalPolicy::Ask | OptionalPolicy::Never => {
+            let skipped = names(&candidates);
+            crate::cli::print_info(&t!("install-optional-skipped", count = skipped.len(), packages = skipped.join(", ")));
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Packages in `category`, sorted by name, or the ones the user picks from them
+fn category_packages(app: &App, category: &str, interactive: bool) -> Result<Vec<String>> {
+    let mut packages: Vec<Package> = app.repository.loader
+        .get_packages_by_category(category)
+        .into_iter()
+        .cloned()
+        .collect();
+    if packages.is_empty() {
+        return Err(crate::Error::Config(t!("install-unknown-category", category = category)));
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    if interactive {
+        let labels: Vec<String> = packages
+            .iter()
+            .map(|package| format!("{} - {}", package.name, package.description))
+            .collect();
+        let selected = dialoguer::MultiSelect::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt(t!("install-category-prompt", category = category))
+            .items(&labels)
+            .interact()?;
+        return Ok(selected.into_iter().map(|index| packages[index].name.clone()).collect());
+    }
+    
+    Ok(packages.into_iter().map(|package| package.name).collect())
 }
 
-fn confirm_installation(packages: &[crate::package::Package]) -> Result<bool> {
+pub async fn show_installation_plan(app: &App, packages: &[crate::package::Package], dry_run: bool) {
+    if !crate::cli::is_normal_output() {
+        if dry_run {
+            for package in packages {
+                crate::cli::print_porcelain(&["would-install", &package.name, &package.version]);
+            }
+        }
+        return;
+    }
+    
+    let plan = if dry_run {
+        t!("install-plan-dry-run", count = packages.len())
+    } else {
+        t!("install-plan", count = packages.len())
+    };
+    
+    println!("\n{}\n", plan);
+    crate::cli::print_size_summary(app, packages).await;
+}
+
+pub fn confirm_installation(_packages: &[crate::package::Package]) -> Result<bool> {
     use std::io::{self, Write};
     
-    print!("Continue with installation? [Y/n]: ");
+    print!("{} [Y/n]: ", t!("install-confirm"));
     io::stdout().flush()?;
     
     let mut input = String::new();
@@ -89,14 +332,3 @@ fn confirm_installation(packages: &[crate::package::Package]) -> Result<bool> {
     let input = input.trim().to_lowercase();
     Ok(input.is_empty() || input == "y" || input == "yes")
 }
-
-fn create_progress_bar(len: usize) -> ProgressBar {
-    let pb = ProgressBar::new(len as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-")
-    );
-    pb
-}
\ No newline at end of file
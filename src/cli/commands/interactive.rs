@@ -1,4 +1,4 @@
-use crate::{package::Package, Result};
+use crate::{package::Package, t, Result};
 use console::{style, Term};
 use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect, Select};
 
@@ -26,7 +26,7 @@ impl InteractiveInstaller {
             .collect();
         
         let selection = MultiSelect::with_theme(&self.theme)
-            .with_prompt("Select packages to install")
+            .with_prompt(t!("interactive-select-packages"))
             .items(&items)
             .interact()?;
         
@@ -38,11 +38,11 @@ impl InteractiveInstaller {
             return Ok(None);
         }
         
-        let mut items = vec!["Custom installation".to_string()];
+        let mut items = vec![t!("interactive-custom-installation")];
         items.extend(profiles.iter().cloned());
         
         let selection = Select::with_theme(&self.theme)
-            .with_prompt("Choose installation profile")
+            .with_prompt(t!("interactive-choose-profile"))
             .items(&items)
             .default(0)
             .interact()?;
@@ -56,8 +56,8 @@ impl InteractiveInstaller {
     
     pub fn confirm_installation(&self, packages: &[Package]) -> Result<bool> {
         self.term.write_line(&format!(
-            "\n{} packages selected:",
-            style("Following").green().bold()
+            "\n{}",
+            style(t!("interactive-selected")).green().bold()
         ))?;
         
         for pkg in packages {
@@ -72,7 +72,7 @@ impl InteractiveInstaller {
         self.term.write_line("")?;
         
         Ok(Confirm::with_theme(&self.theme)
-            .with_prompt("Continue with installation?")
+            .with_prompt(t!("install-confirm"))
             .default(true)
             .interact()?)
     }
@@ -83,27 +83,31 @@ impl InteractiveInstaller {
         }
         
         self.term.write_line(&format!(
-            "{} Package conflicts detected:",
-            style("⚠").yellow().bold()
+            "{} {}",
+            style("⚠").yellow().bold(),
+            t!("interactive-conflicts")
         ))?;
         
         let mut resolutions = Vec::new();
         
         for (pkg1, pkg2) in conflicts {
             self.term.write_line(&format!(
-                "  {} conflicts with {}",
-                style(pkg1).red().bold(),
-                style(pkg2).red().bold()
+                "  {}",
+                t!(
+                    "interactive-conflict",
+                    first = style(pkg1).red().bold().to_string(),
+                    second = style(pkg2).red().bold().to_string()
+                )
             ))?;
             
             let choices = vec![
-                format!("Keep {}", pkg1),
-                format!("Keep {}", pkg2),
-                "Skip both".to_string(),
+                t!("interactive-keep", package = pkg1.as_str()),
+                t!("interactive-keep", package = pkg2.as_str()),
+                t!("interactive-skip-both"),
             ];
             
             let selection = Select::with_theme(&self.theme)
-                .with_prompt(&format!("Resolve conflict between {} and {}", pkg1, pkg2))
+                .with_prompt(t!("interactive-resolve-conflict", first = pkg1.as_str(), second = pkg2.as_str()))
                 .items(&choices)
                 .default(0)
                 .interact()?;
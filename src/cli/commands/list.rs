@@ -1,6 +1,7 @@
-use crate::{App, Result};
+use crate::{groups::GroupManager, package::{InstallStatus, Package}, t, App, Result};
 use clap::Args;
 use console::style;
+use std::collections::BTreeMap;
 
 #[derive(Args)]
 pub struct ListArgs {
@@ -10,62 +11,114 @@ pub struct ListArgs {
     #[arg(short, long)]
     pub available: bool,
     
-    #[arg(short, long)]
+    #[arg(short = 'C', long)]
     pub category: Option<String>,
     
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// Add a description column
+    #[arg(short = 'l', long)]
+    pub detailed: bool,
+    
+    /// Omit the table header
+    #[arg(long)]
+    pub no_header: bool,
+    
+    /// Group packages under their categories instead of printing a table
+    #[arg(long, conflicts_with = "no_header")]
+    pub tree: bool,
 }
 
 pub async fn execute(app: &App, args: ListArgs) -> Result<()> {
     let packages = app.repository.list_packages(&args).await?;
+    let names: Vec<&str> = packages.iter().map(|package| package.name.as_str()).collect();
+    let mut statuses = app.repository.statuses(&names).await;
     
-    if packages.is_empty() {
-        crate::cli::print_warning("No packages found");
-        return Ok(());
-    }
-    
-    println!("Found {} package(s):\n", packages.len());
-    
+    let mut rows = Vec::new();
     for package in packages {
-        let installed = app.repository.is_installed(&package.name).await?;
+        let status = statuses.remove(&package.name).unwrap_or(InstallStatus::NotInstalled);
         
-        if args.installed && !installed {
+        if args.installed && !status.is_installed() {
             continue;
         }
-        if args.available && installed {
+        if args.available && status.is_installed() {
             continue;
         }
         
-        print_package_entry(&package, installed, args.verbose);
+        rows.push((package, status));
+    }
+    
+    if rows.is_empty() {
+        crate::cli::print_warning(&t!("list-empty"));
+        return Ok(());
+    }
+    
+    for (package, status) in &rows {
+        let status = if status.is_installed() { "installed" } else { "available" };
+        crate::cli::print_porcelain(&[status, &package.name, &package.version]);
+    }
+    
+    if !crate::cli::is_normal_output() {
+        return Ok(());
+    }
+    
+    if args.tree {
+        print_tree(&rows, args.detailed);
+    } else {
+        crate::cli::print_package_table(&rows, args.detailed, !args.no_header, None);
     }
     
     Ok(())
 }
 
-fn print_package_entry(package: &crate::package::Package, installed: bool, verbose: bool) {
-    let status_icon = if installed {
-        style("●").green()
-    } else {
-        style("○").dim()
-    };
+/// Print packages under each of their categories, with the groups that include them.
+/// Packages without a category come last.
+fn print_tree(rows: &[(Package, InstallStatus)], detailed: bool) {
+    let mut categories: BTreeMap<&str, Vec<&(Package, InstallStatus)>> = BTreeMap::new();
+    let mut uncategorized = Vec::new();
+    for row in rows {
+        if row.0.categories.is_empty() {
+            uncategorized.push(row);
+        }
+        for category in &row.0.categories {
+            categories.entry(category.as_str()).or_default().push(row);
+        }
+    }
     
-    println!("{} {} {}", 
-        status_icon,
-        style(&package.name).bold(),
-        style(&package.version).dim()
-    );
+    let uncategorized_label = t!("list-uncategorized");
+    let mut sections: Vec<(&str, Vec<&(Package, InstallStatus)>)> = categories.into_iter().collect();
+    if !uncategorized.is_empty() {
+        sections.push((&uncategorized_label, uncategorized));
+    }
     
-    if verbose {
-        println!("  {}", package.description);
-        if !package.categories.is_empty() {
-            println!("  Categories: {}", 
-                package.categories.iter()
-                    .map(|c| style(c).cyan().to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
+    let groups = GroupManager::new();
+    for (category, mut packages) in sections {
+        packages.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        println!("{} {}", style(category).cyan().bold(), style(format!("({})", packages.len())).dim());
+        
+        for (i, (package, status)) in packages.iter().enumerate() {
+            let last = i == packages.len() - 1;
+            let mut label = format!("{} {}", style(&package.name).bold(), style(&package.version).dim());
+            match status {
+                InstallStatus::Installed { .. } => label.push_str(&format!(" {}", style(t!("tree-installed")).green())),
+                InstallStatus::UpdateAvailable { .. } => label.push_str(&format!(" {}", style(t!("tree-update-available")).yellow())),
+                InstallStatus::Error { .. } => label.push_str(&format!(" {}", style(t!("tree-error")).red())),
+                InstallStatus::NotInstalled => {}
+            }
+            
+            let member_of: Vec<&str> = groups
+                .list_groups()
+                .into_iter()
+                .filter(|group| group.contains(&package.name))
+                .map(|group| group.name.as_str())
+                .collect();
+            if !member_of.is_empty() {
+                label.push_str(&format!(" {}", style(format!("[{}]", t!("list-groups", groups = member_of.join(", ")))).dim()));
+            }
+            
+            println!("{}{}", if last { "└── " } else { "├── " }, label);
+            if detailed {
+                println!("{}{}", if last { "    " } else { "│   " }, style(&package.description).dim());
+            }
         }
         println!();
     }
-}
\ No newline at end of file
+}
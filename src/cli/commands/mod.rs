@@ -3,5 +3,32 @@ pub mod search;
 pub mod list;
 pub mod update;
 pub mod info;
+pub mod reinstall;
 pub mod remove;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod completions;
+pub mod init;
+pub mod restore;
+pub mod rollback;
+pub mod why;
+pub mod diff;
+pub mod size;
+pub mod history;
+pub mod clean;
+pub mod status;
+pub mod sync;
+pub mod env;
+pub mod provision;
+pub mod containerize;
+pub mod cloud_init;
+pub mod use_version;
+pub mod alternatives;
+pub mod setup;
+pub mod profile;
+pub mod repo;
+pub mod group;
+pub mod benchmark;
+pub mod daemon;
+pub mod explain;
+#[cfg(feature = "server")]
+pub mod serve;
\ No newline at end of file
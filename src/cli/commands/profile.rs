@@ -0,0 +1,560 @@
+use super::install::{ConfigConflictArgs, ScopeArgs};
+use crate::{groups::{self, GroupConflict, GroupManager, InstallationProfile}, hardware::Hardware, t, App, Result};
+use clap::{Args, Subcommand};
+use console::style;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+#[derive(Args)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub command: ProfileCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// List built-in and user profiles
+    List,
+    
+    /// Create a profile in the config directory
+    Create {
+        /// Profile name (asked for when --interactive is used without one)
+        name: Option<String>,
+        
+        /// Choose groups, packages and post-install steps step by step
+        #[arg(short, long)]
+        interactive: bool,
+        
+        /// Short description shown in `archbox profile list`
+        #[arg(short, long)]
+        description: Option<String>,
+        
+        /// Package group to include
+        #[arg(short, long = "group", value_name = "GROUP")]
+        groups: Vec<String>,
+        
+        /// Package to include in addition to the groups
+        #[arg(short, long = "package", value_name = "PACKAGE")]
+        packages: Vec<String>,
+        
+        /// Package from the groups to leave out
+        #[arg(short = 'x', long = "exclude", value_name = "PACKAGE")]
+        excluded: Vec<String>,
+        
+        /// Also install the groups suited to the machine the profile is applied on, such as
+        /// `laptop` on laptops and `vm-guest` in virtual machines
+        #[arg(short, long)]
+        machine_groups: bool,
+        
+        /// Group to keep when it conflicts with another of the profile's groups
+        #[arg(long = "prefer", value_name = "GROUP")]
+        prefer: Vec<String>,
+        
+        /// Replace an existing user profile of the same name
+        #[arg(short, long)]
+        force: bool,
+    },
+    
+    /// Install a profile's packages, then run its post-install script
+    Apply {
+        /// Profile name
+        name: String,
+        
+        /// Skip confirmation prompts, including the one before the post-install script
+        #[arg(short, long)]
+        yes: bool,
+        
+        /// Show what would be installed and the script that would run
+        #[arg(long)]
+        dry_run: bool,
+        
+        #[command(flatten)]
+        config_conflict: ConfigConflictArgs,
+        
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+}
+
+pub async fn execute(app: &mut App, args: ProfileArgs) -> Result<()> {
+    match args.command {
+        ProfileCommand::List => list_profiles(),
+        ProfileCommand::Create { name, interactive: true, force, .. } => create_interactive(app, name, force),
+        ProfileCommand::Create { name, interactive: false, description, groups, packages, excluded, machine_groups, prefer, force } => {
+            let name = name.ok_or_else(|| crate::Error::Config(t!("profile-name-required")))?;
+            let profile = InstallationProfile {
+                name,
+                description: description.unwrap_or_default(),
+                groups,
+                additional_packages: packages,
+                excluded_packages: excluded,
+                post_install_script: None,
+                post_install_script_sha256: None,
+                variables: HashMap::new(),
+                machine_groups,
+                prefer,
+            };
+            create_profile(app, profile, force)
+        }
+        ProfileCommand::Apply { name, yes, dry_run, config_conflict, scope } => {
+            apply_profile(app, &name, yes, dry_run, config_conflict, scope).await
+        }
+    }
+}
+
+/// Install the packages a profile resolves to that aren't installed yet, then run its
+/// post-install script once they all succeeded
+async fn apply_profile(
+    app: &mut App,
+    name: &str,
+    yes: bool,
+    dry_run: bool,
+    config_conflict: ConfigConflictArgs,
+    scope: ScopeArgs,
+) -> Result<()> {
+    let manager = GroupManager::new();
+    let profile = manager.get_profile(name)
+        .ok_or_else(|| crate::Error::Config(t!("sync-unknown-profile", profile = name)))?;
+    // A script that doesn't match its checksum is refused before anything is installed
+    let script = profile.verified_post_install_script()?;
+    app.repository.set_variables(&profile.variables);
+    
+    let names = manager.resolve_profile_packages_with(name, |conflict| choose_group(conflict, !yes))?;
+    let wanted = super::group::available(app, &names)?;
+    let mut packages = Vec::new();
+    for package in app.repository.resolve_packages(&wanted).await? {
+        if !app.repository.is_installed(&package.name).await? {
+            packages.push(package);
+        }
+    }
+    
+    if packages.is_empty() {
+        crate::cli::print_success(&t!("profile-apply-installed", name = name));
+    } else {
+        super::install::show_installation_plan(app, &packages, dry_run).await;
+    }
+    if let Some(script) = script {
+        crate::cli::print_porcelain(&[if dry_run { "would-run-script" } else { "script" }, name]);
+        if crate::cli::is_normal_output() {
+            println!("{}", style(t!("profile-apply-script", name = name)).bold());
+            for line in script.trim().lines() {
+                println!("  {}", style(line.trim()).dim());
+            }
+        }
+    }
+    
+    if dry_run {
+        return Ok(());
+    }
+    
+    if !packages.is_empty() {
+        if !yes && !super::install::confirm_installation(&packages)? {
+            crate::cli::print_info(&t!("install-cancelled"));
+            return Ok(());
+        }
+    
+        app.repository.set_config_conflict(config_conflict.policy());
+        app.repository.set_scope(scope.scope());
+    
+        let pb = crate::progress::overall(packages.len());
+        let mut failed = 0;
+        let mut installed = Vec::new();
+        for package in &packages {
+            pb.set_message(t!("install-progress", package = package.name.as_str()));
+            let explicit = wanted.contains(&package.name);
+            match app.repository.install_package(package, false, explicit).await {
+                Ok(_) => {
+                    crate::cli::print_success(&t!("install-done", package = package.name.as_str()));
+                    crate::cli::print_porcelain(&["installed", &package.name, &package.version]);
+                    installed.push(package);
+                }
+                Err(e) => {
+                    failed += 1;
+                    crate::cli::report_failure("install_failed", Some(&package.name), &t!("install-failed", package = package.name.as_str(), error = e.to_string()));
+                    crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+                }
+            }
+            pb.inc(1);
+        }
+        pb.finish_and_clear();
+        crate::cli::relogin_notice(app).await?;
+        crate::cli::kernel_module_notice(app, &installed, yes).await?;
+    
+        // The script may rely on every package, so it only runs after a complete install
+        if failed > 0 {
+            return Err(crate::Error::InstallationFailed(t!("profile-apply-failed", count = failed)));
+        }
+    }
+    
+    if let Some(script) = script {
+        run_post_install_script(app, script, yes).await?;
+    }
+    
+    crate::cli::print_success(&t!("profile-applied", name = name));
+    Ok(())
+}
+
+/// Run a profile's post-install script as a package command, after asking unless `yes` is given
+async fn run_post_install_script(app: &App, script: &str, yes: bool) -> Result<()> {
+    if crate::simulate::is_enabled() {
+        crate::cli::print_info(&t!("profile-script-simulated"));
+        return Ok(());
+    }
+    
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            return Err(crate::Error::Config(t!("profile-script-no-terminal")));
+        }
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(t!("profile-script-confirm"))
+            .default(true)
+            .interact()?;
+        if !confirmed {
+            crate::cli::print_info(&t!("profile-script-skipped"));
+            return Ok(());
+        }
+    }
+    
+    // Run like any other package command, so it is logged, recorded and stopped after the script limit
+    let installer = crate::package::Installer::new(&app.config).with_runner(app.repository.runner());
+    let output = installer.run_shell_command(script, std::path::Path::new("/"), &[], app.config.installation.script_limit()).await?;
+    if crate::cli::is_normal_output() {
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+    }
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(crate::Error::CommandFailed {
+            message: t!("profile-script-failed", status = output.status.to_string()),
+        });
+    }
+    Ok(())
+}
+
+fn list_profiles() -> Result<()> {
+    let manager = GroupManager::new();
+    let mut profiles = manager.list_profiles();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    for profile in profiles {
+        let source = if manager.is_user_profile(&profile.name) { "user" } else { "built-in" };
+        crate::cli::print_porcelain(&[&profile.name, source, &profile.description]);
+        if !crate::cli::is_normal_output() {
+            continue;
+        }
+    
+        // Only a count is shown, so a conflict the profile doesn't settle keeps the first group
+        let packages = manager.resolve_profile_packages_with(&profile.name, |conflict| Ok(conflict.first.clone()))?;
+        println!(
+            "{} {} {}",
+            style(&profile.name).bold(),
+            style(format!("({}, {})", t!("profile-source", source = source), t!("profile-package-count", count = packages.len()))).dim(),
+            profile.description
+        );
+    }
+    
+    Ok(())
+}
+
+/// Check a profile against the known groups and packages, then write it
+fn create_profile(app: &App, profile: InstallationProfile, force: bool) -> Result<()> {
+    validate_name(&profile.name).map_err(crate::Error::Config)?;
+    
+    let mut manager = GroupManager::new();
+    if groups::profile_path(&profile.name).exists() && !force {
+        return Err(crate::Error::Config(t!("profile-exists", name = profile.name.as_str())));
+    }
+    if let Some(group) = profile.groups.iter().chain(&profile.prefer).find(|group| manager.get_group(group).is_none()) {
+        return Err(crate::Error::Config(t!("profile-unknown-group", group = group.as_str())));
+    }
+    let chosen: Vec<_> = profile.groups.iter().filter_map(|name| manager.get_group(name)).collect();
+    groups::settle_conflicts(chosen, &profile.prefer, &Hardware::current(), |conflict| {
+        crate::cli::print_warning(&t!(
+            "profile-unsettled-conflict",
+            first = conflict.first.as_str(),
+            second = conflict.second.as_str()
+        ));
+        Ok(conflict.first.clone())
+    })?;
+    for package in &profile.additional_packages {
+        if app.repository.resolve_name(package).is_err() {
+            crate::cli::print_warning(&t!("profile-unknown-package", package = package.as_str()));
+        }
+    }
+    
+    let name = profile.name.clone();
+    let path = manager.save_profile(profile)?;
+    crate::cli::print_success(&t!("profile-saved", name = name.as_str(), path = path.display().to_string()));
+    crate::cli::print_info(&t!("profile-apply-hint", name = name.as_str()));
+    Ok(())
+}
+
+fn validate_name(name: &str) -> std::result::Result<(), String> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        Ok(())
+    } else {
+        Err(t!("profile-invalid-name", name = name))
+    }
+}
+
+fn create_interactive(app: &App, name: Option<String>, force: bool) -> Result<()> {
+    if !std::io::stdin().is_terminal() {
+        return Err(crate::Error::Config(t!("profile-no-terminal")));
+    }
+    
+    let theme = ColorfulTheme::default();
+    let manager = GroupManager::new();
+    
+    let name = match name {
+        Some(name) => name,
+        None => Input::with_theme(&theme)
+            .with_prompt(t!("profile-name-prompt"))
+            .validate_with(|input: &String| validate_name(input.trim()))
+            .interact_text()?
+            .trim()
+            .to_string(),
+    };
+    validate_name(&name).map_err(crate::Error::Config)?;
+    
+    let mut force = force;
+    if groups::profile_path(&name).exists() && !force {
+        force = Confirm::with_theme(&theme)
+            .with_prompt(t!("profile-overwrite-prompt", name = name.as_str()))
+            .default(false)
+            .interact()?;
+        if !force {
+            return Ok(());
+        }
+    } else if manager.get_profile(&name).is_some() && !manager.is_user_profile(&name) {
+        crate::cli::print_info(&t!("profile-shadows-builtin", name = name.as_str()));
+    }
+    
+    let description: String = Input::with_theme(&theme)
+        .with_prompt(t!("profile-description-prompt"))
+        .allow_empty(true)
+        .interact_text()?;
+    
+    let groups = choose_groups(&theme, &manager)?;
+    let prefer = choose_preferred(&theme, &manager, &groups)?;
+    let machine_groups = choose_machine_groups(&theme, &manager)?;
+    let mut packages = choose_optional_packages(&theme, &manager, &groups)?;
+    let excluded = choose_excluded(&theme, &manager, &groups)?;
+    browse_categories(&theme, app, &mut packages)?;
+    let steps = post_install_steps(&theme)?;
+    
+    let profile = InstallationProfile {
+        name,
+        description: description.trim().to_string(),
+        groups,
+        additional_packages: packages,
+        excluded_packages: excluded,
+        post_install_script: (!steps.is_empty()).then(|| steps.join("\n")),
+        post_install_script_sha256: None,
+        variables: HashMap::new(),
+        machine_groups,
+        prefer,
+    };
+    
+    print_summary(&profile);
+    let confirmed = Confirm::with_theme(&theme)
+        .with_prompt(t!("profile-save-prompt"))
+        .default(true)
+        .interact()?;
+    if !confirmed {
+        return Ok(());
+    }
+    
+    create_profile(app, profile, force)
+}
+
+fn choose_groups(theme: &ColorfulTheme, manager: &GroupManager) -> Result<Vec<String>> {
+    let mut groups = manager.list_groups();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    let labels: Vec<String> = groups
+        .iter()
+        .map(|group| format!("{} - {} ({})", group.name, group.description, group.packages.join(", ")))
+        .collect();
+    let selected = MultiSelect::with_theme(theme)
+        .with_prompt(t!("profile-groups-prompt"))
+        .items(&labels)
+        .interact()?;
+    
+    Ok(selected.into_iter().map(|index| groups[index].name.clone()).collect())
+}
+
+/// Ask whether the profile should bring in the groups suited to each machine, naming the
+/// ones this machine would get
+fn choose_machine_groups(theme: &ColorfulTheme, manager: &GroupManager) -> Result<bool> {
+    let class = crate::hardware::machine_class();
+    let groups: Vec<&str> = manager.machine_groups(class).iter().map(|group| group.name.as_str()).collect();
+    let here = if groups.is_empty() { t!("profile-machine-groups-none") } else { groups.join(", ") };
+    
+    Ok(Confirm::with_theme(theme)
+        .with_prompt(t!("profile-machine-groups-prompt", class = t!("machine-class", class = class.name()), groups = here))
+        .default(true)
+        .interact()?)
+}
+
+fn choose_optional_packages(theme: &ColorfulTheme, manager: &GroupManager, groups: &[String]) -> Result<Vec<String>> {
+    let mut optional: Vec<String> = groups
+        .iter()
+        .filter_map(|group| manager.get_group(group))
+        .flat_map(|group| group.optional_packages.iter().cloned())
+        .collect();
+    optional.sort();
+    optional.dedup();
+    if optional.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let selected = MultiSelect::with_theme(theme)
+        .with_prompt(t!("profile-optional-prompt"))
+        .items(&optional)
+        .interact()?;
+    
+    Ok(selected.into_iter().map(|index| optional[index].clone()).collect())
+}
+
+fn choose_excluded(theme: &ColorfulTheme, manager: &GroupManager, groups: &[String]) -> Result<Vec<String>> {
+    let hardware = crate::hardware::Hardware::current();
+    let mut included: Vec<String> = groups
+        .iter()
+        .filter_map(|group| manager.get_group(group))
+        .flat_map(|group| group.packages_for(&hardware))
+        .collect();
+    included.sort();
+    included.dedup();
+    if included.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let selected = MultiSelect::with_theme(theme)
+        .with_prompt(t!("profile-exclude-prompt"))
+        .items(&included)
+        .interact()?;
+    
+    Ok(selected.into_iter().map(|index| included[index].clone()).collect())
+}
+
+/// Ask which group to keep for each conflicting pair of the chosen groups
+fn choose_preferred(theme: &ColorfulTheme, manager: &GroupManager, names: &[String]) -> Result<Vec<String>> {
+    let mut prefer = Vec::new();
+    let chosen: Vec<_> = names.iter().filter_map(|name| manager.get_group(name)).collect();
+    groups::settle_conflicts(chosen, &[], &Hardware::current(), |conflict| {
+        let keep = select_group(theme, conflict)?;
+        prefer.push(keep.clone());
+        Ok(keep)
+    })?;
+    Ok(prefer)
+}
+
+/// Which of two conflicting groups to keep, asked on a terminal. Without one, or when
+/// `interactive` is false, the conflict is an error naming the `prefer` setting.
+pub fn choose_group(conflict: &GroupConflict, interactive: bool) -> Result<String> {
+    if !interactive || !std::io::stdin().is_terminal() || !crate::cli::is_normal_output() {
+        return Err(conflict.error());
+    }
+    
+    let keep = select_group(&ColorfulTheme::default(), conflict)?;
+    crate::cli::print_info(&t!("profile-prefer-hint", group = keep.as_str()));
+    Ok(keep)
+}
+
+fn select_group(theme: &ColorfulTheme, conflict: &GroupConflict) -> Result<String> {
+    let items = [&conflict.first, &conflict.second];
+    let selection = Select::with_theme(theme)
+        .with_prompt(t!(
+            "profile-conflict-prompt",
+            first = conflict.first.as_str(),
+            second = conflict.second.as_str(),
+            packages = conflict.packages.join(", ")
+        ))
+        .items(&items)
+        .default(0)
+        .interact()?;
+    Ok(items[selection].clone())
+}
+
+/// Pick packages category by category until the user is done
+fn browse_categories(theme: &ColorfulTheme, app: &App, packages: &mut Vec<String>) -> Result<()> {
+    let categories = app.repository.loader.get_categories();
+    if categories.is_empty() {
+        return Ok(());
+    }
+    
+    let mut items = vec![t!("profile-categories-done")];
+    items.extend(categories.iter().cloned());
+    
+    loop {
+        let selection = Select::with_theme(theme)
+            .with_prompt(t!("profile-categories-prompt", count = packages.len()))
+            .items(&items)
+            .default(0)
+            .interact()?;
+        let Some(category) = selection.checked_sub(1).map(|index| &categories[index]) else {
+            return Ok(());
+        };
+    
+        let mut in_category = app.repository.loader.get_packages_by_category(category);
+        in_category.sort_by(|a, b| a.name.cmp(&b.name));
+        let labels: Vec<String> = in_category
+            .iter()
+            .map(|package| format!("{} - {}", package.name, package.description))
+            .collect();
+        let defaults: Vec<bool> = in_category.iter().map(|package| packages.contains(&package.name)).collect();
+    
+        let selected = MultiSelect::with_theme(theme)
+            .with_prompt(t!("profile-category-packages-prompt", category = category.as_str()))
+            .items(&labels)
+            .defaults(&defaults)
+            .interact()?;
+    
+        packages.retain(|name| !in_category.iter().any(|package| &package.name == name));
+        packages.extend(selected.into_iter().map(|index| in_category[index].name.clone()));
+    }
+}
+
+fn post_install_steps(theme: &ColorfulTheme) -> Result<Vec<String>> {
+    let mut steps = Vec::new();
+    
+    loop {
+        let step: String = Input::with_theme(theme)
+            .with_prompt(t!("profile-step-prompt", number = steps.len() + 1))
+            .allow_empty(true)
+            .interact_text()?;
+        let step = step.trim();
+        if step.is_empty() {
+            return Ok(steps);
+        }
+        steps.push(step.to_string());
+    }
+}
+
+fn print_summary(profile: &InstallationProfile) {
+    println!();
+    println!("{}", style(&profile.name).bold());
+    let rows = [
+        (t!("profile-summary-groups"), &profile.groups),
+        (t!("profile-summary-packages"), &profile.additional_packages),
+        (t!("profile-summary-excluded"), &profile.excluded_packages),
+    ];
+    for (label, values) in rows {
+        if !values.is_empty() {
+            println!("  {} {}", style(label).dim(), values.join(", "));
+        }
+    }
+    if !profile.prefer.is_empty() {
+        println!("  {} {}", style(t!("profile-summary-prefer")).dim(), profile.prefer.join(", "));
+    }
+    if profile.machine_groups {
+        println!("  {}", style(t!("profile-summary-machine-groups")).dim());
+    }
+    if let Some(script) = &profile.post_install_script {
+        println!("  {}", style(t!("profile-summary-steps")).dim());
+        for line in script.lines() {
+            println!("    {}", line);
+        }
+    }
+    println!();
+}
@@ -0,0 +1,187 @@
+use crate::{groups::GroupManager, package::Package, t, App, Result};
+use clap::Args;
+use console::style;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct ProvisionArgs {
+    /// Root of the new installation, as mounted from the live ISO
+    #[arg(long, value_name = "DIR")]
+    pub root: PathBuf,
+    
+    /// Profile whose packages to install
+    #[arg(long)]
+    pub profile: Option<String>,
+    
+    /// Additional packages to install
+    pub packages: Vec<String>,
+    
+    /// Show what would be installed without installing
+    #[arg(long)]
+    pub dry_run: bool,
+    
+    /// Skip confirmation prompts
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+pub async fn execute(app: &mut App, args: ProvisionArgs) -> Result<()> {
+    if args.profile.is_none() && args.packages.is_empty() {
+        return Err(crate::Error::Config(t!("provision-nothing-requested")));
+    }
+    
+    // pacman --sysroot needs the new system's pacman database and config
+    if !args.root.join("var/lib/pacman").is_dir() || !args.root.join("etc/pacman.conf").is_file() {
+        return Err(crate::Error::Config(t!("provision-not-arch", root = args.root.display().to_string())));
+    }
+    
+    let groups = GroupManager::new();
+    let profile = match &args.profile {
+        Some(name) => Some(
+            groups.get_profile(name)
+                .ok_or_else(|| crate::Error::Config(t!("sync-unknown-profile", profile = name.as_str())))?,
+        ),
+        None => None,
+    };
+    
+    let mut wanted = args.packages
+        .iter()
+        .map(|name| app.repository.resolve_name(name))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(profile) = profile {
+        app.repository.set_variables(&profile.variables);
+    
+        for package_name in groups.resolve_profile_packages_with(&profile.name, |conflict| super::profile::choose_group(conflict, !args.yes))? {
+            match app.repository.resolve_name(&package_name) {
+                Ok(name) if !app.repository.is_supported(&name) => {
+                    crate::cli::print_warning(&t!("sync-unsupported-platform", package = name.as_str()));
+                }
+                Ok(name) if wanted.contains(&name) => {}
+                Ok(name) => wanted.push(name),
+                Err(crate::Error::UnsupportedPlatform { .. }) => {
+                    crate::cli::print_warning(&t!("sync-unsupported-platform", package = package_name.as_str()));
+                }
+                Err(crate::Error::PackageNotFound(_)) => {
+                    crate::cli::print_warning(&t!("sync-missing-definition", package = package_name.as_str()));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
+    let packages = app.repository.resolve_packages(&wanted).await?;
+    if packages.is_empty() {
+        crate::cli::print_warning(&t!("install-nothing-found"));
+        return Ok(());
+    }
+    
+    show_provision_plan(&args.root, &packages, args.dry_run);
+    
+    if args.dry_run {
+        return Ok(());
+    }
+    
+    crate::privilege::require_root(&format!("Provisioning {}", args.root.display()))?;
+    
+    if !args.yes && !confirm_provision()? {
+        crate::cli::print_info(&t!("provision-cancelled"));
+        return Ok(());
+    }
+    
+    app.repository.set_root(&args.root);
+    
+    let pb = crate::progress::overall(packages.len());
+    let mut failed = 0;
+    
+    for package in &packages {
+        pb.set_message(t!("install-progress", package = package.name.as_str()));
+        let explicit = wanted.contains(&package.name);
+        match app.repository.install_package(package, false, explicit).await {
+            Ok(_) => {
+                crate::cli::print_success(&t!("install-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["installed", &package.name, &package.version]);
+            }
+            Err(e) => {
+                failed += 1;
+                crate::cli::report_failure("install_failed", Some(&package.name), &t!("install-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+            }
+        }
+        pb.inc(1);
+    }
+    
+    pb.finish_and_clear();
+    
+    if let Some(profile) = profile {
+        let result = match profile.verified_post_install_script() {
+            Ok(Some(script)) => run_in_root(app, &args.root, script).await,
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            failed += 1;
+            crate::cli::report_failure("provision_script_failed", None, &t!("provision-script-failed", error = e.to_string()));
+        }
+    }
+    
+    if failed > 0 {
+        return Err(crate::Error::InstallationFailed(t!("provision-failed", count = failed)));
+    }
+    
+    crate::cli::print_success(&t!("provision-done", root = args.root.display().to_string()));
+    if crate::cli::is_normal_output() {
+        println!("{}", style(t!("provision-next-steps")).dim());
+    }
+    Ok(())
+}
+
+/// Run a profile's post-install script inside the new system
+async fn run_in_root(app: &App, root: &Path, script: &str) -> Result<()> {
+    let installer = crate::package::Installer::new(&app.config).with_root(root).with_runner(app.repository.runner());
+    let output = installer.run_shell_command(script, Path::new("/"), &[], app.config.installation.script_limit()).await?;
+    
+    if !output.status.success() {
+        return Err(crate::Error::CommandFailed {
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    
+    Ok(())
+}
+
+fn show_provision_plan(root: &Path, packages: &[Package], dry_run: bool) {
+    if !crate::cli::is_normal_output() {
+        if dry_run {
+            for package in packages {
+                crate::cli::print_porcelain(&["would-install", &package.name, &package.version]);
+            }
+        }
+        return;
+    }
+    
+    let root = root.display().to_string();
+    let header = if dry_run {
+        t!("provision-plan-dry-run", root = root.as_str())
+    } else {
+        t!("provision-plan", root = root.as_str())
+    };
+    println!("\n{}", style(header).bold());
+    
+    for package in packages {
+        println!("  {} {} {}", style("+").green(), style(&package.name).bold(), package.version);
+    }
+    println!();
+}
+
+fn confirm_provision() -> Result<bool> {
+    use std::io::{self, Write};
+    
+    print!("{} [Y/n]: ", t!("provision-confirm"));
+    io::stdout().flush()?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    
+    let input = input.trim().to_lowercase();
+    Ok(input.is_empty() || input == "y" || input == "yes")
+}
@@ -0,0 +1,177 @@
+use crate::{
+    cli::commands::install::ConfigConflictArgs,
+    package::{build_cache, layout, Installation, Installer, Package},
+    state::InstalledPackage,
+    t, App, Result,
+};
+use clap::Args;
+use console::style;
+use std::io::{self, Write};
+
+#[derive(Args)]
+pub struct ReinstallArgs {
+    /// Installed packages to reinstall
+    #[arg(required = true)]
+    pub packages: Vec<String>,
+
+    /// Skip confirmation prompts
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Dry run - show what would be reinstalled without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Also delete config files written during post-install (backed up first), so they are
+    /// written afresh
+    #[arg(long)]
+    pub purge: bool,
+
+    #[command(flatten)]
+    pub config_conflict: ConfigConflictArgs,
+}
+
+pub async fn execute(app: &mut App, args: ReinstallArgs) -> Result<()> {
+    let mut packages = Vec::new();
+
+    for requested in &args.packages {
+        let name = app.repository.resolve_name(requested)?;
+        let package = app.repository.loader.get_package(&name)
+            .ok_or_else(|| app.repository.loader.not_found(&name))?;
+
+        if app.repository.installed_record(&package.name).is_some() {
+            packages.push(package.clone());
+        } else {
+            crate::cli::print_warning(&t!("reinstall-not-installed", package = package.name.as_str()));
+        }
+    }
+
+    if packages.is_empty() {
+        crate::cli::print_info(&t!("reinstall-nothing"));
+        return Ok(());
+    }
+
+    show_plan(&packages, args.dry_run);
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    if !args.yes && !confirm_reinstall()? {
+        crate::cli::print_info(&t!("reinstall-cancelled"));
+        return Ok(());
+    }
+
+    app.repository.set_config_conflict(args.config_conflict.policy());
+    app.repository.set_reinstall(true);
+
+    for package in &packages {
+        match reinstall(app, package, args.purge).await {
+            Ok(_) => {
+                crate::cli::print_success(&t!("reinstall-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["reinstalled", &package.name, &package.version]);
+            }
+            Err(e) => {
+                crate::cli::report_failure("reinstall_failed", Some(&package.name), &t!("reinstall-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Undo a package's post-install changes, delete what it installed and its cached builds,
+/// then install it again. The state record is kept, so the install reason and scope carry
+/// over.
+async fn reinstall(app: &mut App, package: &Package, purge: bool) -> Result<()> {
+    let Some(record) = app.repository.installed_record(&package.name).cloned() else {
+        return Ok(());
+    };
+    let scope = app.repository.installed_scope(&package.name);
+
+    let installer = Installer::new(&app.config).with_runner(app.repository.runner());
+    installer.revert_on_removal(&record.name, &record.post_install).await;
+    if purge {
+        super::remove::purge_config_files(&record).await;
+    }
+
+    remove_installed_files(package, &record, scope).await?;
+    build_cache::clear(&package.name).await?;
+
+    // Not explicit: record_install keeps the reason of the existing record
+    app.repository.install_package(package, true, false).await
+}
+
+/// Delete the files a binary or AppImage package put in place. Packages installed by pacman,
+/// Flatpak or a build script are replaced by their installer instead.
+async fn remove_installed_files(package: &Package, record: &InstalledPackage, scope: crate::package::InstallScope) -> Result<()> {
+    let mut paths = Vec::new();
+    match &package.installation {
+        Installation::Binary { .. } => {
+            paths.extend(package.install_path(scope));
+            paths.push(layout::version_dir(&package.name, scope, &record.version));
+        }
+        Installation::AppImage { .. } => paths.extend(package.install_path(scope)),
+        _ => return Ok(()),
+    }
+
+    if crate::simulate::is_enabled() {
+        for path in &paths {
+            crate::simulate::action("remove", &path.display().to_string());
+        }
+        return Ok(());
+    }
+
+    if scope == crate::package::InstallScope::System {
+        crate::privilege::require_root(&format!("Reinstalling {} system-wide", package.name))?;
+    }
+
+    for path in &paths {
+        // symlink_metadata also catches a link whose target is gone
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            tokio::fs::remove_dir_all(path).await?;
+        } else {
+            tokio::fs::remove_file(path).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn show_plan(packages: &[Package], dry_run: bool) {
+    if !crate::cli::is_normal_output() {
+        if dry_run {
+            for package in packages {
+                crate::cli::print_porcelain(&["would-reinstall", &package.name, &package.version]);
+            }
+        }
+        return;
+    }
+
+    let plan = if dry_run {
+        t!("reinstall-plan-dry-run", count = packages.len())
+    } else {
+        t!("reinstall-plan", count = packages.len())
+    };
+
+    println!("\n{}", plan);
+    for package in packages {
+        println!("  {} {} ({})", style("↻").cyan(), style(&package.name).bold(), package.version);
+    }
+    println!();
+}
+
+fn confirm_reinstall() -> Result<bool> {
+    print!("{} [y/N]: ", t!("reinstall-confirm"));
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
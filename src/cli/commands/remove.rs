@@ -1,11 +1,14 @@
-use crate::{App, Result};
+use crate::{
+    package::{runner::{CommandRunner, CommandSpec}, InstallScope},
+    t, App, Result,
+};
 use clap::Args;
 use console::style;
 use std::io::{self, Write};
 
 #[derive(Args)]
 pub struct RemoveArgs {
-    /// Package names to remove
+    /// Package names to remove; `-` reads names from stdin
     #[arg(required = true)]
     pub packages: Vec<String>,
     
@@ -20,16 +23,27 @@ pub struct RemoveArgs {
     /// Dry run - show what would be removed without removing
     #[arg(long)]
     pub dry_run: bool,
+    
+    /// Also delete config files written during post-install (backed up first)
+    #[arg(long)]
+    pub purge: bool,
+    
+    /// Also remove installed packages that depend on the given packages
+    #[arg(long)]
+    pub cascade: bool,
 }
 
 pub async fn execute(app: &mut App, args: RemoveArgs) -> Result<()> {
-    println!("{} Preparing to remove packages...", style("🗑️").red());
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("🗑️").red(), t!("remove-start"));
+    }
     
+    let requested_names = crate::cli::expand_stdin_packages(&args.packages, args.yes || args.dry_run)?;
     let mut packages_to_remove = Vec::new();
     let mut not_installed = Vec::new();
     
     // Check which packages are actually installed
-    for package_name in &args.packages {
+    for package_name in &requested_names {
         if app.repository.is_installed(package_name).await? {
             if let Some(package) = app.repository.loader.get_package(package_name) {
                 packages_to_remove.push(package.clone());
@@ -42,17 +56,47 @@ pub async fn execute(app: &mut App, args: RemoveArgs) -> Result<()> {
     // Report packages that aren't installed
     if !not_installed.is_empty() {
         for pkg in &not_installed {
-            crate::cli::print_warning(&format!("Package '{}' is not installed", pkg));
+            crate::cli::print_warning(&t!("remove-not-installed", package = pkg.as_str()));
         }
     }
     
     if packages_to_remove.is_empty() {
-        crate::cli::print_info("No packages to remove");
+        crate::cli::print_info(&t!("remove-nothing"));
         return Ok(());
     }
     
+    let requested: Vec<String> = packages_to_remove.iter().map(|p| p.name.clone()).collect();
+    
+    if args.cascade {
+        packages_to_remove = app.repository.removal_cascade(&requested);
+    } else {
+        let mut blocked = false;
+        
+        for name in &requested {
+            let dependents: Vec<&str> = app.repository
+                .installed_dependents(name)
+                .into_iter()
+                .map(|p| p.name.as_str())
+                .filter(|dependent| !requested.iter().any(|r| r == dependent))
+                .collect();
+            
+            if !dependents.is_empty() {
+                crate::cli::report_failure("required_by", Some(name), &t!(
+                    "remove-required-by",
+                    package = name.as_str(),
+                    dependents = dependents.join(", ")
+                ));
+                blocked = true;
+            }
+        }
+        
+        if blocked {
+            return Err(crate::Error::Dependency(t!("remove-blocked")));
+        }
+    }
+    
     // Show removal plan
-    show_removal_plan(&packages_to_remove, args.dry_run);
+    show_removal_plan(app, &packages_to_remove, &requested, args.dry_run, args.purge);
     
     if args.dry_run {
         return Ok(());
@@ -60,18 +104,20 @@ pub async fn execute(app: &mut App, args: RemoveArgs) -> Result<()> {
     
     // Confirm removal
     if !args.yes && !confirm_removal(&packages_to_remove)? {
-        crate::cli::print_info("Removal cancelled");
+        crate::cli::print_info(&t!("remove-cancelled"));
         return Ok(());
     }
     
     // Remove packages
     for package in &packages_to_remove {
-        match remove_package(package, args.autoremove).await {
+        match uninstall(app, package, args.autoremove, args.purge).await {
             Ok(_) => {
-                crate::cli::print_success(&format!("Removed {}", package.name));
+                crate::cli::print_success(&t!("remove-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["removed", &package.name, &package.version]);
             }
             Err(e) => {
-                crate::cli::print_error(&format!("Failed to remove {}: {}", package.name, e));
+                crate::cli::report_failure("remove_failed", Some(&package.name), &t!("remove-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
             }
         }
     }
@@ -79,22 +125,76 @@ pub async fn execute(app: &mut App, args: RemoveArgs) -> Result<()> {
     Ok(())
 }
 
-fn show_removal_plan(packages: &[crate::package::Package], dry_run: bool) {
-    let action = if dry_run { "Would remove" } else { "Will remove" };
+/// Remove an installed package, undo its post-install changes and forget it, recording the
+/// step in the transaction log. With `purge` its config files are deleted as well.
+pub async fn uninstall(app: &mut App, package: &crate::package::Package, autoremove: bool, purge: bool) -> Result<()> {
+    let started = std::time::Instant::now();
+    let scope = app.repository.installed_scope(&package.name);
+    let result = remove_package(app.repository.runner().as_ref(), package, scope, autoremove).await;
+    crate::history::record_step("remove", Some(&package.name), Some(&package.version), started.elapsed(), &result);
+    result?;
+    
+    if let Some(record) = app.repository.record_removal(&package.name)? {
+        let installer = crate::package::Installer::new(&app.config).with_runner(app.repository.runner());
+        installer.revert_on_removal(&record.name, &record.post_install).await;
+        
+        if purge {
+            purge_config_files(&record).await;
+        }
+    }
     
-    println!("\n{} {} packages:", action, packages.len());
+    Ok(())
+}
+
+fn show_removal_plan(
+    app: &App,
+    packages: &[crate::package::Package],
+    requested: &[String],
+    dry_run: bool,
+    purge: bool,
+) {
+    if !crate::cli::is_normal_output() {
+        if dry_run {
+            for package in packages {
+                crate::cli::print_porcelain(&["would-remove", &package.name, &package.version]);
+            }
+        }
+        return;
+    }
+    
+    let plan = if dry_run {
+        t!("remove-plan-dry-run", count = packages.len())
+    } else {
+        t!("remove-plan", count = packages.len())
+    };
+    
+    println!("\n{}", plan);
     for package in packages {
-        println!("  {} {} ({})", 
+        let reason = if requested.contains(&package.name) {
+            String::new()
+        } else {
+            format!(" [{}]", t!("remove-dependent"))
+        };
+        println!("  {} {} ({}){}", 
             style("→").red(),
             style(&package.name).bold(),
-            package.version
+            package.version,
+            style(reason).yellow()
         );
+        
+        if purge {
+            if let Some(record) = app.repository.installed_record(&package.name) {
+                for path in &record.config_files {
+                    println!("      {} {}", style(t!("remove-purge-marker")).dim(), path.display());
+                }
+            }
+        }
     }
     println!();
 }
 
-fn confirm_removal(packages: &[crate::package::Package]) -> Result<bool> {
-    print!("Continue with removal? [y/N]: ");
+fn confirm_removal(_packages: &[crate::package::Package]) -> Result<bool> {
+    print!("{} [y/N]: ", t!("remove-confirm"));
     io::stdout().flush()?;
     
     let mut input = String::new();
@@ -104,13 +204,41 @@ fn confirm_removal(packages: &[crate::package::Package]) -> Result<bool> {
     Ok(input == "y" || input == "yes")
 }
 
-async fn remove_package(package: &crate::package::Package, autoremove: bool) -> Result<()> {
-    use tokio::process::Command;
-    
+/// Delete the config files a package wrote during post-install. Each one is backed up first,
+/// whatever `create_backups` says, since purging is the only way to lose it.
+pub async fn purge_config_files(record: &crate::state::InstalledPackage) {
+    for path in &record.config_files {
+        if !path.exists() {
+            continue;
+        }
+        
+        if let Err(e) = crate::backup::backup_file(&record.name, path).await {
+            crate::cli::print_warning(&t!(
+                "purge-backup-failed",
+                path = path.display().to_string(),
+                error = e.to_string()
+            ));
+            continue;
+        }
+        
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            crate::cli::print_warning(&t!("purge-delete-failed", path = path.display().to_string(), error = e.to_string()));
+        } else {
+            crate::cli::print_info(&t!("purge-done", path = path.display().to_string()));
+        }
+    }
+}
+
+async fn remove_package(
+    runner: &dyn CommandRunner,
+    package: &crate::package::Package,
+    scope: InstallScope,
+    autoremove: bool,
+) -> Result<()> {
     match &package.installation {
         crate::package::Installation::Pacman { packages, .. } => {
-            let mut cmd = Command::new("pacman");
-            cmd.args(&["-R", "--noconfirm"]);
+            let mut cmd = crate::privilege::privileged("pacman");
+            cmd.args(["-R", "--noconfirm"]);
             
             if autoremove {
                 cmd.arg("-s"); // Remove dependencies
@@ -118,39 +246,47 @@ async fn remove_package(package: &crate::package::Package, autoremove: bool) ->
             
             cmd.args(packages);
             
-            let output = cmd.output().await?;
+            let output = runner.output(&cmd).await?;
             if !output.status.success() {
-                return Err(crate::Error::InstallationFailed(format!(
-                    "Failed to remove pacman packages: {}",
-                    String::from_utf8_lossy(&output.stderr)
+                return Err(crate::Error::InstallationFailed(t!(
+                    "remove-pacman-failed",
+                    error = String::from_utf8_lossy(&output.stderr).to_string()
                 )));
             }
         }
         crate::package::Installation::Flatpak { id, .. } => {
-            let output = Command::new("flatpak")
-                .args(&["uninstall", "-y", id])
-                .output()
+            let output = runner
+                .output(CommandSpec::new("flatpak").args(["uninstall", "-y", "--noninteractive", crate::package::FlatpakScope::from(scope).flag(), id]))
                 .await?;
             
             if !output.status.success() {
-                return Err(crate::Error::InstallationFailed(format!(
-                    "Failed to remove Flatpak: {}",
-                    String::from_utf8_lossy(&output.stderr)
+                return Err(crate::Error::InstallationFailed(t!(
+                    "remove-flatpak-failed",
+                    error = String::from_utf8_lossy(&output.stderr).to_string()
                 )));
             }
         }
-        crate::package::Installation::Binary { install_path, .. } => {
-            let expanded_path = shellexpand::tilde(install_path);
-            let path = std::path::Path::new(expanded_path.as_ref());
+        crate::package::Installation::Binary { .. } if crate::simulate::is_enabled() => {
+            let path = package.install_path(scope).unwrap_or_default();
+            crate::simulate::action("remove", &path.display().to_string());
+            crate::simulate::action("remove", &crate::package::layout::package_dir(&package.name, scope).display().to_string());
+        }
+        crate::package::Installation::Binary { .. } => {
+            if scope == InstallScope::System {
+                crate::privilege::require_root(&format!("Removing {} system-wide", package.name))?;
+            }
+            let path = package.install_path(scope).unwrap_or_default();
             
-            if path.exists() {
-                tokio::fs::remove_file(path).await?;
+            // symlink_metadata also catches a link whose version directory is gone
+            if path.symlink_metadata().is_ok() {
+                tokio::fs::remove_file(&path).await?;
             }
+            
+            crate::package::layout::remove_package(&package.name, scope).await?;
+            crate::package::layout::remove_shim(scope, &path).await?;
         }
         _ => {
-            return Err(crate::Error::InstallationFailed(
-                "Removal not implemented for this installation method".to_string()
-            ));
+            return Err(crate::Error::InstallationFailed(t!("remove-unsupported")));
         }
     }
     
@@ -0,0 +1,92 @@
+use crate::{package::DefinitionLoader, repository::remote, t, App, Result};
+use clap::{Args, Subcommand};
+use console::style;
+
+#[derive(Args)]
+pub struct RepoArgs {
+    #[command(subcommand)]
+    pub command: RepoCommand,
+}
+
+#[derive(Subcommand)]
+pub enum RepoCommand {
+    /// List where definitions come from, with how many packages each provides and when the
+    /// remote was last synced
+    List,
+}
+
+pub async fn execute(app: &App, args: RepoArgs) -> Result<()> {
+    match args.command {
+        RepoCommand::List => list_sources(app).await,
+    }
+}
+
+async fn list_sources(app: &App) -> Result<()> {
+    let normal = crate::cli::is_normal_output();
+    if normal {
+        println!("{}", style(t!("repo-sources")).bold());
+    }
+    
+    for path in &app.config.package_paths {
+        if !path.exists() {
+            continue;
+        }
+    
+        let mut loader = DefinitionLoader::new();
+        loader.load_from_directory(path).await?;
+        let count = loader.packages().len();
+        let path = path.display().to_string();
+    
+        crate::cli::print_porcelain(&["local", &path, &count.to_string(), ""]);
+        if normal {
+            println!("  {} {} · {}", style(t!("repo-local")).cyan(), style(&path).bold(), t!("profile-package-count", count = count));
+        }
+    }
+    
+    let repository = &app.config.repository;
+    let cache = remote::definitions_cache_path();
+    if repository.update_url.is_none() && !cache.exists() {
+        return Ok(());
+    }
+    
+    let url = repository.update_url.clone().unwrap_or_else(|| t!("repo-no-url"));
+    let (count, synced) = if cache.exists() {
+        let mut loader = DefinitionLoader::new();
+        // A broken cache provides nothing until the next sync replaces it
+        let count = match loader.load_definition_file(&cache).await {
+            Ok(()) => loader.packages().len(),
+            Err(_) => 0,
+        };
+        let synced = std::fs::metadata(&cache)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(chrono::DateTime::<chrono::Local>::from);
+        (count, synced)
+    } else {
+        (0, None)
+    };
+    
+    let synced_rfc3339 = synced.map(|at| at.to_rfc3339()).unwrap_or_default();
+    crate::cli::print_porcelain(&["remote", &url, &count.to_string(), &synced_rfc3339]);
+    if !normal {
+        return Ok(());
+    }
+    
+    let stale = synced.is_some() && remote::definitions_stale(repository.update_interval);
+    let freshness = match synced {
+        Some(at) => {
+            let text = t!("repo-synced", at = at.format("%Y-%m-%d %H:%M").to_string());
+            if stale { style(text).yellow() } else { style(text).green() }
+        }
+        None => style(t!("repo-never-synced")).yellow(),
+    };
+    println!("  {} {} · {} · {}", style(t!("repo-remote")).cyan(), style(&url).bold(), t!("profile-package-count", count = count), freshness);
+    if !repository.mirrors.is_empty() {
+        println!("    {}", t!("repo-mirrors", count = repository.mirrors.len()));
+    }
+    if stale {
+        crate::cli::print_info(&t!("repo-stale-hint"));
+    }
+    
+    Ok(())
+}
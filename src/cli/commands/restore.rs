@@ -0,0 +1,85 @@
+use crate::{backup, t, App, Result};
+use clap::Args;
+use console::style;
+use std::io::{self, Write};
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Package whose backed up files should be restored
+    #[arg(required = true)]
+    pub package: String,
+    
+    /// List available backups instead of restoring
+    #[arg(short, long)]
+    pub list: bool,
+    
+    /// Restore a specific backup (timestamp as shown by --list) instead of the latest
+    #[arg(long)]
+    pub from: Option<String>,
+    
+    /// Skip confirmation prompts
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+pub async fn execute(_app: &mut App, args: RestoreArgs) -> Result<()> {
+    let backups = backup::list_backups(&args.package)?;
+    
+    if backups.is_empty() {
+        crate::cli::print_warning(&t!("restore-none", package = args.package.as_str()));
+        return Ok(());
+    }
+    
+    if args.list {
+        println!("{}", t!("restore-list-header", count = backups.len(), package = style(&args.package).bold().to_string()));
+        for set in &backups {
+            println!("  {} ({})", style(&set.timestamp).cyan(), t!("restore-file-count", count = set.files.len()));
+            for file in &set.files {
+                println!("      {}", file.display());
+            }
+        }
+        return Ok(());
+    }
+    
+    let set = match &args.from {
+        Some(timestamp) => backups
+            .iter()
+            .find(|set| &set.timestamp == timestamp)
+            .ok_or_else(|| crate::Error::Config(t!(
+                "restore-unknown-backup",
+                timestamp = timestamp.as_str(),
+                package = args.package.as_str()
+            )))?,
+        None => &backups[0],
+    };
+    
+    println!("\n{}", t!("restore-plan", count = set.files.len(), timestamp = style(&set.timestamp).cyan().to_string()));
+    for file in &set.files {
+        println!("  {} {}", style("→").blue(), file.display());
+    }
+    println!();
+    
+    if !args.yes && !confirm_restore()? {
+        crate::cli::print_info(&t!("restore-cancelled"));
+        return Ok(());
+    }
+    
+    let started = std::time::Instant::now();
+    let result = backup::restore_backup(set).await;
+    crate::history::record_step("restore", Some(&args.package), Some(&set.timestamp), started.elapsed(), &result);
+    let restored = result?;
+    crate::cli::print_success(&t!("restore-done", count = restored.len(), package = args.package.as_str()));
+    
+    Ok(())
+}
+
+fn confirm_restore() -> Result<bool> {
+    print!("{} [y/N]: ", t!("restore-confirm"));
+    io::stdout().flush()?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
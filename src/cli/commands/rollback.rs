@@ -0,0 +1,124 @@
+use crate::{package::layout, t, App, Result};
+use clap::Args;
+use console::style;
+use std::io::{self, Write};
+
+#[derive(Args)]
+pub struct RollbackArgs {
+    /// Binary package to switch back to a previous version
+    #[arg(required = true)]
+    pub package: String,
+    
+    /// List the versions kept on disk instead of switching
+    #[arg(short, long)]
+    pub list: bool,
+    
+    /// Version to activate instead of the most recent previous one
+    #[arg(long)]
+    pub to: Option<String>,
+    
+    /// Skip confirmation prompts
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+pub async fn execute(app: &mut App, args: RollbackArgs) -> Result<()> {
+    let package = app.repository.loader.get_package(&args.package)
+        .cloned()
+        .ok_or_else(|| crate::Error::PackageNotFound(args.package.clone()))?;
+    
+    if !matches!(package.installation, crate::package::Installation::Binary { .. }) {
+        return Err(crate::Error::Config(t!("rollback-not-binary", package = package.name.as_str())));
+    }
+    let scope = app.repository.installed_scope(&package.name);
+    let install_path = package.install_path(scope).unwrap_or_default();
+    
+    // A simulated install leaves nothing on disk, so its versions come from the logs
+    let (versions, active) = if crate::simulate::is_enabled() {
+        let active = app.repository.installed_record(&package.name).map(|record| record.version.clone());
+        (crate::simulate::installed_versions(&package.name)?, active)
+    } else {
+        let active = layout::active_version(&package.name, scope, &install_path);
+        (layout::installed_versions(&package.name, scope)?, active)
+    };
+    
+    if versions.is_empty() {
+        crate::cli::print_warning(&t!("rollback-no-versions", package = package.name.as_str()));
+        return Ok(());
+    }
+    
+    if args.list {
+        println!("{}", t!("rollback-list-header", package = style(&package.name).bold().to_string()));
+        for version in &versions {
+            if Some(version) == active.as_ref() {
+                println!("  {} {}", style(version).green(), style(format!("({})", t!("rollback-active"))).dim());
+            } else {
+                println!("  {}", version);
+            }
+        }
+        return Ok(());
+    }
+    
+    let target_version = match &args.to {
+        Some(version) => versions
+            .iter()
+            .find(|v| *v == version)
+            .ok_or_else(|| crate::Error::Config(t!(
+                "rollback-unknown-version",
+                version = version.as_str(),
+                package = package.name.as_str()
+            )))?,
+        None => versions
+            .iter()
+            .find(|v| Some(*v) != active.as_ref())
+            .ok_or_else(|| crate::Error::Config(t!("rollback-no-previous", package = package.name.as_str())))?,
+    };
+    
+    if Some(target_version) == active.as_ref() {
+        crate::cli::print_info(&t!("rollback-already-active", package = package.name.as_str(), version = target_version.as_str()));
+        return Ok(());
+    }
+    
+    println!(
+        "\n{}\n",
+        t!(
+            "rollback-plan",
+            package = style(&package.name).bold().to_string(),
+            from = style(active.clone().unwrap_or_else(|| t!("rollback-unknown"))).red().to_string(),
+            to = style(target_version).green().to_string()
+        )
+    );
+    
+    if !args.yes && !confirm_rollback()? {
+        crate::cli::print_info(&t!("rollback-cancelled"));
+        return Ok(());
+    }
+    
+    let file_name = install_path.file_name().unwrap_or_default();
+    let target = layout::version_dir(&package.name, scope, target_version).join(file_name);
+    let started = std::time::Instant::now();
+    let result = if crate::simulate::is_enabled() {
+        crate::simulate::action("link", &format!("{} -> {}", install_path.display(), target.display()));
+        Ok(())
+    } else {
+        layout::activate(&install_path, &target).await
+    };
+    crate::history::record_step("rollback", Some(&package.name), Some(target_version), started.elapsed(), &result);
+    result?;
+    
+    app.repository.record_version(&package.name, target_version)?;
+    crate::cli::print_success(&t!("rollback-done", package = package.name.as_str(), version = target_version.as_str()));
+    
+    Ok(())
+}
+
+fn confirm_rollback() -> Result<bool> {
+    print!("{} [Y/n]: ", t!("rollback-confirm"));
+    io::stdout().flush()?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    
+    let input = input.trim().to_lowercase();
+    Ok(input.is_empty() || input == "y" || input == "yes")
+}
@@ -1,4 +1,4 @@
-use crate::{App, Result};
+use crate::{package::InstallStatus, t, App, Result};
 use clap::Args;
 use console::style;
 
@@ -10,60 +10,53 @@ pub struct SearchArgs {
     #[arg(short, long)]
     pub description: bool,
     
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// Add a description column
+    #[arg(short = 'l', long)]
+    pub detailed: bool,
     
-    #[arg(short, long)]
+    #[arg(short = 'C', long)]
     pub category: Option<String>,
     
     #[arg(long)]
     pub installed: bool,
+    
+    /// Omit the table header
+    #[arg(long)]
+    pub no_header: bool,
 }
 
 pub async fn execute(app: &App, args: SearchArgs) -> Result<()> {
-    println!("{} Searching for '{}'...", style("🔍").cyan(), args.query);
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("🔍").cyan(), t!("search-start", query = args.query.as_str()));
+    }
     
     let results = app.repository.search_packages(&args.query, &args).await?;
     
     if results.is_empty() {
-        crate::cli::print_warning("No packages found matching the search criteria");
+        crate::cli::print_warning(&t!("search-no-results"));
         return Ok(());
     }
     
-    println!("\nFound {} package(s):\n", results.len());
+    let names: Vec<&str> = results.iter().map(|package| package.name.as_str()).collect();
+    let mut statuses = app.repository.statuses(&names).await;
     
+    let mut rows = Vec::new();
     for package in results {
-        print_package_result(&package, args.verbose, &app).await?;
+        let status = statuses.remove(&package.name).unwrap_or(InstallStatus::NotInstalled);
+        
+        crate::cli::print_porcelain(&[
+            if status.is_installed() { "installed" } else { "available" },
+            &package.name,
+            &package.version,
+        ]);
+        
+        rows.push((package, status));
     }
     
-    Ok(())
-}
-
-async fn print_package_result(
-    package: &crate::package::Package, 
-    verbose: bool,
-    app: &App
-) -> Result<()> {
-    let installed = app.repository.is_installed(&package.name).await?;
-    let status = if installed {
-        style("[installed]").green()
-    } else {
-        style("[available]").blue()
-    };
-    
-    println!("{} {} {}", 
-        style(&package.name).bold().cyan(),
-        style(&package.version).dim(),
-        status
-    );
-    
-    if verbose {
-        println!("  {}", package.description);
-        if !package.categories.is_empty() {
-            println!("  Categories: {}", package.categories.join(", "));
-        }
-        println!();
+    if crate::cli::is_normal_output() {
+        println!("\n{}\n", t!("packages-found", count = rows.len()));
+        crate::cli::print_package_table(&rows, args.detailed, !args.no_header, Some(&args.query));
     }
     
     Ok(())
-}
\ No newline at end of file
+}
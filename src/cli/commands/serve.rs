@@ -0,0 +1,26 @@
+use crate::{config::Config, t, App, Result};
+use clap::Args;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Address and port to listen on, overriding server.listen
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<String>,
+}
+
+/// Load the definitions once and serve the HTTP API until interrupted
+pub async fn execute(config: Config, args: &ServeArgs) -> Result<()> {
+    let listen = args.listen.clone().unwrap_or_else(|| config.server.listen.clone());
+    if config.server.token.is_none() {
+        crate::cli::print_warning(&t!("serve-read-only"));
+    }
+    
+    let app = App::with_config(config).await?;
+    crate::cli::print_info(&t!("serve-listening", address = listen.as_str()));
+    crate::cli::print_porcelain(&["listening", &listen]);
+    
+    crate::server::serve(app, &listen).await?;
+    
+    crate::cli::print_info(&t!("serve-stopped"));
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use crate::{completions, config::Config, groups::GroupManager, shell::{self, ShellKind}, t, Result};
+use clap::Args;
+use clap_complete::Shell;
+use console::style;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SetupArgs {
+    /// Write the detected defaults without asking any questions
+    #[arg(long)]
+    pub defaults: bool,
+}
+
+pub fn execute(config: Config, args: &SetupArgs) -> Result<()> {
+    if args.defaults {
+        return save(&config);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(crate::Error::Config(t!("setup-no-terminal")));
+    }
+    
+    let config = run_wizard(config)?;
+    save(&config)
+}
+
+/// Offered when there is no config file yet. Without a terminal the defaults are used
+/// for this run and nothing is written, so the wizard is offered again next time.
+pub fn first_run(config: Config) -> Result<Config> {
+    if !crate::cli::is_normal_output() || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Ok(config);
+    }
+    
+    let theme = ColorfulTheme::default();
+    let wanted = Confirm::with_theme(&theme)
+        .with_prompt(t!("setup-first-run", path = Config::config_path().display().to_string()))
+        .default(true)
+        .interact()?;
+    
+    if !wanted {
+        config.save()?;
+        crate::cli::print_info(&t!("setup-defaults-written", path = Config::config_path().display().to_string()));
+        return Ok(config);
+    }
+    
+    let config = run_wizard(config)?;
+    save(&config)?;
+    Ok(config)
+}
+
+fn save(config: &Config) -> Result<()> {
+    config.save()?;
+    crate::cli::print_success(&t!("setup-saved", path = Config::config_path().display().to_string()));
+    Ok(())
+}
+
+fn run_wizard(mut config: Config) -> Result<Config> {
+    let theme = ColorfulTheme::default();
+    println!("{}", style(t!("setup-title")).bold());
+    println!("{}", t!("setup-intro"));
+    println!();
+    
+    config.aur_helper = choose_aur_helper(&theme, config.aur_helper.as_deref())?;
+    config.installation.binary_dir = choose_binary_dir(&theme, &config.installation.binary_dir)?;
+    choose_sources(&theme, &mut config)?;
+    config.profile = choose_profile(&theme, config.profile.as_deref())?;
+    suggest_machine_groups(config.profile.as_deref());
+    install_completions(&theme)?;
+    
+    Ok(config)
+}
+
+fn choose_aur_helper(theme: &ColorfulTheme, current: Option<&str>) -> Result<Option<String>> {
+    let mut choices: Vec<(String, Option<String>)> = vec![(t!("setup-aur-auto"), None)];
+    for helper in ["yay", "paru"] {
+        let label = if on_path(helper) {
+            helper.to_string()
+        } else {
+            format!("{} ({})", helper, t!("setup-not-installed"))
+        };
+        choices.push((label, Some(helper.to_string())));
+    }
+    choices.push((t!("setup-aur-native"), Some(crate::package::installer::NATIVE_HELPER.to_string())));
+    
+    let default = choices
+        .iter()
+        .position(|(_, helper)| helper.as_deref() == current)
+        .unwrap_or(0);
+    let labels: Vec<&str> = choices.iter().map(|(label, _)| label.as_str()).collect();
+    let selection = Select::with_theme(theme)
+        .with_prompt(t!("setup-aur-prompt"))
+        .items(&labels)
+        .default(default)
+        .interact()?;
+    
+    Ok(choices.swap_remove(selection).1)
+}
+
+fn choose_binary_dir(theme: &ColorfulTheme, current: &std::path::Path) -> Result<PathBuf> {
+    let dir: String = Input::with_theme(theme)
+        .with_prompt(t!("setup-binary-dir-prompt"))
+        .default(current.display().to_string())
+        .validate_with(|input: &String| {
+            if expand_home(input.trim()).is_absolute() {
+                Ok(())
+            } else {
+                Err(t!("setup-binary-dir-absolute"))
+            }
+        })
+        .interact_text()?;
+    let dir = expand_home(dir.trim());
+    
+    let on_path = std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|path| path == dir));
+    if !on_path {
+        crate::cli::print_info(&t!("setup-binary-dir-not-on-path", dir = dir.display().to_string()));
+    }
+    
+    Ok(dir)
+}
+
+/// Local definition directories and the remote definitions URL
+fn choose_sources(theme: &ColorfulTheme, config: &mut Config) -> Result<()> {
+    let labels: Vec<String> = config
+        .package_paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    let enabled = MultiSelect::with_theme(theme)
+        .with_prompt(t!("setup-sources-prompt"))
+        .items(&labels)
+        .defaults(&vec![true; labels.len()])
+        .interact()?;
+    config.package_paths = enabled.into_iter().map(|index| config.package_paths[index].clone()).collect();
+    
+    let url: String = Input::with_theme(theme)
+        .with_prompt(t!("setup-remote-prompt"))
+        .default(config.repository.update_url.clone().unwrap_or_default())
+        .allow_empty(true)
+        .show_default(config.repository.update_url.is_some())
+        .validate_with(|input: &String| {
+            let input = input.trim();
+            if input.is_empty() || input.starts_with("https://") || input.starts_with("http://") {
+                Ok(())
+            } else {
+                Err(t!("setup-remote-invalid"))
+            }
+        })
+        .interact_text()?;
+    let url = url.trim();
+    config.repository.update_url = (!url.is_empty()).then(|| url.to_string());
+    
+    if config.repository.update_url.is_some() {
+        config.repository.auto_update = Confirm::with_theme(theme)
+            .with_prompt(t!("setup-auto-update-prompt", hours = config.repository.update_interval))
+            .default(config.repository.auto_update)
+            .interact()?;
+    }
+    
+    Ok(())
+}
+
+fn choose_profile(theme: &ColorfulTheme, current: Option<&str>) -> Result<Option<String>> {
+    let groups = GroupManager::new();
+    let mut profiles = groups.list_profiles();
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    let mut labels = vec![t!("setup-profile-none")];
+    labels.extend(profiles.iter().map(|profile| format!("{} - {}", profile.name, profile.description)));
+    
+    let default = current
+        .and_then(|current| profiles.iter().position(|profile| profile.name == current))
+        .map_or(0, |index| index + 1);
+    let selection = Select::with_theme(theme)
+        .with_prompt(t!("setup-profile-prompt"))
+        .items(&labels)
+        .default(default)
+        .interact()?;
+    
+    Ok(selection.checked_sub(1).map(|index| profiles[index].name.clone()))
+}
+
+/// Name the groups suited to this kind of machine, and whether the chosen profile already
+/// brings them in
+fn suggest_machine_groups(profile: Option<&str>) {
+    let manager = GroupManager::new();
+    let class = crate::hardware::machine_class();
+    let suggested: Vec<&str> = manager.machine_groups(class).iter().map(|group| group.name.as_str()).collect();
+    if suggested.is_empty() {
+        return;
+    }
+    
+    let class = t!("machine-class", class = class.name());
+    let groups = suggested.join(", ");
+    let included = profile
+        .and_then(|name| manager.get_profile(name))
+        .is_some_and(|profile| profile.machine_groups || suggested.iter().all(|group| profile.groups.iter().any(|name| name == group)));
+    if included {
+        crate::cli::print_info(&t!("setup-machine-groups-included", class = class, groups = groups));
+    } else {
+        crate::cli::print_info(&t!("setup-machine-groups-suggested", class = class, groups = groups));
+    }
+}
+
+fn install_completions(theme: &ColorfulTheme) -> Result<()> {
+    let shells: Vec<Shell> = shell::detect_shells()
+        .into_iter()
+        .filter_map(|kind| match kind {
+            ShellKind::Bash => Some(Shell::Bash),
+            ShellKind::Zsh => Some(Shell::Zsh),
+            ShellKind::Fish => Some(Shell::Fish),
+            ShellKind::Posix => None,
+        })
+        .collect();
+    if shells.is_empty() {
+        return Ok(());
+    }
+    
+    let labels: Vec<String> = shells.iter().map(ToString::to_string).collect();
+    let selected = MultiSelect::with_theme(theme)
+        .with_prompt(t!("setup-completions-prompt"))
+        .items(&labels)
+        .defaults(&vec![true; labels.len()])
+        .interact()?;
+    
+    let system = crate::privilege::running_as_root();
+    for shell in selected.into_iter().map(|index| shells[index]) {
+        match completions::install_completions(shell, system) {
+            Ok(path) => crate::cli::print_success(&t!(
+                "completions-installed",
+                shell = shell.to_string(),
+                path = path.display().to_string()
+            )),
+            Err(e) => crate::cli::print_warning(&t!("setup-completions-failed", shell = shell.to_string(), error = e.to_string())),
+        }
+    }
+    
+    Ok(())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn on_path(command: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(command).is_file()))
+}
@@ -0,0 +1,93 @@
+use crate::{
+    cli::table::Table,
+    package::size::{format_size, installed_size},
+    progress, t, App, Result,
+};
+use clap::Args;
+use console::style;
+use std::collections::BTreeMap;
+
+#[derive(Args)]
+pub struct SizeArgs {
+    /// Only show the N largest packages
+    #[arg(short = 'n', long, value_name = "N")]
+    pub top: Option<usize>,
+    
+    /// Omit the table header
+    #[arg(long)]
+    pub no_header: bool,
+}
+
+pub async fn execute(app: &App, args: SizeArgs) -> Result<()> {
+    let mut packages: Vec<_> = app.repository.loader.packages().values().collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    let spinner = progress::spinner(t!("size-measuring"));
+    let mut sizes = Vec::new();
+    for package in packages {
+        if !app.repository.is_installed(&package.name).await? {
+            continue;
+        }
+        
+        let owned_files = app.repository
+            .installed_record(&package.name)
+            .map(|record| record.owned_files())
+            .unwrap_or_default();
+        let scope = app.repository.installed_scope(&package.name);
+        sizes.push((package, installed_size(app.repository.runner().as_ref(), package, scope, &owned_files).await));
+    }
+    spinner.finish_and_clear();
+    
+    if sizes.is_empty() {
+        crate::cli::print_warning(&t!("size-empty"));
+        return Ok(());
+    }
+    
+    // Largest first, packages whose size could not be determined last
+    sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    
+    // Totals cover every installed package, not just the --top ones
+    let mut totals: BTreeMap<&str, u64> = BTreeMap::new();
+    for (package, size) in &sizes {
+        *totals.entry(package.installation.method()).or_default() += size.unwrap_or(0);
+    }
+    let total: u64 = totals.values().sum();
+    
+    if let Some(top) = args.top {
+        sizes.truncate(top);
+    }
+    
+    for (package, size) in &sizes {
+        let bytes = size.map(|size| size.to_string()).unwrap_or_default();
+        crate::cli::print_porcelain(&["size", &package.name, package.installation.method(), &bytes]);
+    }
+    for (method, size) in &totals {
+        crate::cli::print_porcelain(&["total", method, &size.to_string()]);
+    }
+    
+    if !crate::cli::is_normal_output() {
+        return Ok(());
+    }
+    
+    let mut table = Table::new(vec![t!("table-name"), t!("table-method"), t!("table-size")]);
+    for (package, size) in &sizes {
+        table.add_row(vec![
+            style(&package.name).bold().to_string(),
+            package.installation.method().to_string(),
+            match size {
+                Some(size) => format_size(*size),
+                None => style("?").dim().to_string(),
+            },
+        ]);
+    }
+    table.print(!args.no_header);
+    
+    println!("\n{}", style(t!("size-by-method")).bold());
+    let width = totals.keys().map(|method| method.len()).max().unwrap_or(0);
+    for (method, size) in &totals {
+        println!("  {:<width$}  {}", method, format_size(*size), width = width);
+    }
+    println!("{} {}", style(t!("size-total")).bold(), style(format_size(total)).bold());
+    
+    Ok(())
+}
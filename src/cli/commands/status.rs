@@ -0,0 +1,69 @@
+use crate::{repository::pending_updates::PendingUpdates, t, Result};
+use clap::Args;
+use console::style;
+
+/// Starship module showing the count from `archbox status --short`
+const STARSHIP_MODULE: &str = r#"[custom.archbox]
+description = "Package updates found by ArchBox"
+command = "archbox status --short"
+when = "test -n \"$(archbox status --short)\""
+symbol = "📦 "
+style = "bold yellow"
+format = "[$symbol$output]($style) "
+"#;
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Print only the number of pending updates, e.g. "3 updates", and nothing when there
+    /// are none, for shell prompts
+    #[arg(long)]
+    pub short: bool,
+    
+    /// Print a starship module showing pending updates, to add to starship.toml
+    #[arg(long, conflicts_with = "short")]
+    pub starship: bool,
+}
+
+/// Show the updates found by the last check. This only reads what `update --check`,
+/// `update`, `sync` and the daemon saved, so it is fast enough to run from a prompt.
+pub fn execute(args: &StatusArgs) -> Result<()> {
+    if args.starship {
+        print!("{}", STARSHIP_MODULE);
+        return Ok(());
+    }
+    
+    let pending = PendingUpdates::load();
+    
+    if args.short {
+        if !pending.packages.is_empty() {
+            println!("{}", t!("status-short", count = pending.packages.len()));
+        }
+        return Ok(());
+    }
+    
+    for update in &pending.packages {
+        crate::cli::print_porcelain(&["outdated", &update.name, &update.installed, &update.available]);
+    }
+    
+    let Some(checked_at) = pending.checked_at else {
+        crate::cli::print_info(&t!("status-never-checked"));
+        return Ok(());
+    };
+    let checked = checked_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string();
+    
+    if pending.packages.is_empty() {
+        crate::cli::print_success(&t!("status-up-to-date", checked = checked));
+    } else if crate::cli::is_normal_output() {
+        println!("{}", t!("status-updates", count = pending.packages.len(), checked = checked));
+        for update in &pending.packages {
+            println!("  {} {} → {}",
+                style(&update.name).bold(),
+                style(&update.installed).dim(),
+                style(&update.available).green()
+            );
+        }
+        crate::cli::print_info(&t!("status-update-hint"));
+    }
+    
+    Ok(())
+}
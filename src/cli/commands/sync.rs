@@ -0,0 +1,310 @@
+use crate::{groups::GroupManager, package::Package, t, App, Result};
+use clap::Args;
+use console::style;
+use std::collections::HashSet;
+
+#[derive(Args)]
+pub struct SyncArgs {
+    /// Profile to apply instead of the configured one
+    #[arg(long)]
+    pub profile: Option<String>,
+    
+    /// Remove packages installed by ArchBox that the profile does not include
+    #[arg(long)]
+    pub prune: bool,
+    
+    /// Use the package definitions already on disk
+    #[arg(long)]
+    pub no_refresh: bool,
+    
+    /// Show what would change without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+    
+    /// Skip confirmation prompts
+    #[arg(short, long)]
+    pub yes: bool,
+    
+    /// Exclude a package from upgrades (repeatable)
+    #[arg(long, value_name = "PKG")]
+    pub ignore: Vec<String>,
+    
+    #[command(flatten)]
+    pub config_conflict: super::install::ConfigConflictArgs,
+    
+    #[command(flatten)]
+    pub scope: super::install::ScopeArgs,
+    
+    #[command(flatten)]
+    pub exclude: super::install::ExcludeArgs,
+}
+
+/// Changes needed to make the machine match its declaration
+#[derive(Default)]
+struct SyncPlan {
+    install: Vec<Package>,
+    remove: Vec<Package>,
+    upgrade: Vec<(Package, String)>,
+}
+
+impl SyncPlan {
+    fn is_empty(&self) -> bool {
+        self.install.is_empty() && self.remove.is_empty() && self.upgrade.is_empty()
+    }
+}
+
+pub async fn execute(app: &mut App, args: SyncArgs) -> Result<()> {
+    let profile_name = args.profile.clone().or_else(|| app.config.profile.clone());
+    if args.prune && profile_name.is_none() {
+        return Err(crate::Error::Config(t!("sync-prune-needs-profile")));
+    }
+    
+    if !args.no_refresh {
+        super::update::update_package_definitions(app).await?;
+    }
+    
+    let mut plan = SyncPlan::default();
+    let mut wanted = Vec::new();
+    
+    match &profile_name {
+        Some(name) => {
+            let groups = GroupManager::new();
+            let profile = groups.get_profile(name)
+                .ok_or_else(|| crate::Error::Config(t!("sync-unknown-profile", profile = name.as_str())))?;
+            app.repository.set_variables(&profile.variables);
+            
+            let class = crate::hardware::machine_class();
+            let added: Vec<&str> = groups
+                .profile_groups(profile, class)
+                .into_iter()
+                .filter(|group| !profile.groups.contains(&group.name))
+                .map(|group| group.name.as_str())
+                .collect();
+            if !added.is_empty() {
+                crate::cli::print_info(&t!(
+                    "sync-machine-groups",
+                    class = t!("machine-class", class = class.name()),
+                    groups = added.join(", ")
+                ));
+            }
+            
+            for package_name in groups.resolve_profile_packages_with(name, |conflict| super::profile::choose_group(conflict, !args.yes))? {
+                match app.repository.resolve_name(&package_name) {
+                    Ok(name) if !app.repository.is_supported(&name) => {
+                        crate::cli::print_warning(&t!("sync-unsupported-platform", package = name.as_str()));
+                    }
+                    Ok(name) => wanted.push(name),
+                    Err(crate::Error::UnsupportedPlatform { .. }) => {
+                        crate::cli::print_warning(&t!("sync-unsupported-platform", package = package_name.as_str()));
+                    }
+                    Err(crate::Error::PackageNotFound(_)) => {
+                        crate::cli::print_warning(&t!("sync-missing-definition", package = package_name.as_str()));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            
+            // The profile's packages plus everything they depend on
+            let closure = app.repository.resolve_packages(&wanted).await?;
+            for package in &closure {
+                if !app.repository.is_installed(&package.name).await? {
+                    plan.install.push(package.clone());
+                }
+            }
+            
+            if args.prune {
+                let keep: HashSet<&str> = closure.iter().map(|p| p.name.as_str()).collect();
+                plan.remove = prune_candidates(app, &keep).await?;
+            }
+        }
+        None => crate::cli::print_info(&t!("sync-no-profile")),
+    }
+    
+    let ignored = super::update::ignored_packages(&app.config, &args.ignore);
+    let installing: HashSet<String> = plan.install.iter().map(|p| p.name.clone()).collect();
+    let removing: HashSet<String> = plan.remove.iter().map(|p| p.name.clone()).collect();
+    plan.upgrade = super::update::outdated_packages(app, &ignored)
+        .await?
+        .into_iter()
+        .filter(|(package, _)| !installing.contains(&package.name) && !removing.contains(&package.name))
+        .collect();
+    
+    // Excluded packages are left as they are: not installed, removed or upgraded
+    args.exclude.report(plan.install.iter().chain(&plan.remove).chain(plan.upgrade.iter().map(|(package, _)| package)));
+    plan.install.retain(|package| !args.exclude.matches(&package.name));
+    plan.remove.retain(|package| !args.exclude.matches(&package.name));
+    plan.upgrade.retain(|(package, _)| !args.exclude.matches(&package.name));
+    
+    if plan.is_empty() {
+        crate::cli::print_success(&t!("sync-up-to-date"));
+        return Ok(());
+    }
+    
+    show_sync_plan(&plan, args.dry_run);
+    
+    if args.dry_run {
+        return Ok(());
+    }
+    
+    if !args.yes && !confirm_sync()? {
+        crate::cli::print_info(&t!("sync-cancelled"));
+        return Ok(());
+    }
+    
+    app.repository.set_config_conflict(args.config_conflict.policy());
+    app.repository.set_scope(args.scope.scope());
+    
+    let total = plan.install.len() + plan.remove.len() + plan.upgrade.len();
+    let pb = crate::progress::overall(total);
+    let mut failed = 0;
+    let mut changed = Vec::new();
+    
+    for package in &plan.install {
+        pb.set_message(t!("install-progress", package = package.name.as_str()));
+        let explicit = wanted.contains(&package.name);
+        match app.repository.install_package(package, false, explicit).await {
+            Ok(_) => {
+                crate::cli::print_success(&t!("install-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["installed", &package.name, &package.version]);
+                changed.push(package);
+            }
+            Err(e) => {
+                failed += 1;
+                crate::cli::report_failure("install_failed", Some(&package.name), &t!("install-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+            }
+        }
+        pb.inc(1);
+    }
+    
+    for package in &plan.remove {
+        pb.set_message(t!("sync-removing", package = package.name.as_str()));
+        match super::remove::uninstall(app, package, false, false).await {
+            Ok(_) => {
+                crate::cli::print_success(&t!("remove-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["removed", &package.name, &package.version]);
+            }
+            Err(e) => {
+                failed += 1;
+                crate::cli::report_failure("remove_failed", Some(&package.name), &t!("remove-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+            }
+        }
+        pb.inc(1);
+    }
+    
+    for (package, _) in &plan.upgrade {
+        pb.set_message(t!("sync-upgrading", package = package.name.as_str()));
+        match app.repository.install_package(package, true, false).await {
+            Ok(_) => {
+                crate::cli::print_success(&t!("update-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["updated", &package.name, &package.version]);
+                changed.push(package);
+            }
+            Err(e) => {
+                failed += 1;
+                crate::cli::report_failure("update_failed", Some(&package.name), &t!("update-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+            }
+        }
+        pb.inc(1);
+    }
+    
+    pb.finish_and_clear();
+    crate::cli::relogin_notice(app).await?;
+    crate::cli::kernel_module_notice(app, &changed, args.yes).await?;
+    
+    // A non-zero exit status lets cron and CI notice a partial sync
+    if failed > 0 {
+        return Err(crate::Error::InstallationFailed(t!("sync-failed", count = failed)));
+    }
+    
+    crate::cli::print_success(&t!("sync-done"));
+    Ok(())
+}
+
+/// Installed packages ArchBox recorded that are not in `keep`, dependents first. A package
+/// still required by something outside the prune set (e.g. installed by hand) is kept.
+async fn prune_candidates(app: &App, keep: &HashSet<&str>) -> Result<Vec<Package>> {
+    let mut candidates: Vec<String> = Vec::new();
+    for record in app.repository.installed_records() {
+        if !keep.contains(record.name.as_str()) && app.repository.is_installed(&record.name).await? {
+            candidates.push(record.name.clone());
+        }
+    }
+    
+    loop {
+        let before = candidates.len();
+        let snapshot = candidates.clone();
+        candidates.retain(|name| {
+            app.repository
+                .installed_dependents(name)
+                .iter()
+                .all(|dependent| snapshot.contains(&dependent.name))
+        });
+        if candidates.len() == before {
+            break;
+        }
+    }
+    
+    Ok(app.repository.removal_cascade(&candidates))
+}
+
+fn show_sync_plan(plan: &SyncPlan, dry_run: bool) {
+    if !crate::cli::is_normal_output() {
+        if dry_run {
+            for package in &plan.install {
+                crate::cli::print_porcelain(&["would-install", &package.name, &package.version]);
+            }
+            for package in &plan.remove {
+                crate::cli::print_porcelain(&["would-remove", &package.name, &package.version]);
+            }
+            for (package, current) in &plan.upgrade {
+                crate::cli::print_porcelain(&["would-update", &package.name, current, &package.version]);
+            }
+        }
+        return;
+    }
+    
+    let header = if dry_run { t!("sync-plan-dry-run") } else { t!("sync-plan") };
+    println!("\n{}", style(header).bold());
+    
+    for package in &plan.install {
+        println!("  {} {} {}", style("+").green(), style(&package.name).bold(), package.version);
+    }
+    for package in &plan.remove {
+        println!("  {} {} {}", style("-").red(), style(&package.name).bold(), package.version);
+    }
+    for (package, current) in &plan.upgrade {
+        println!(
+            "  {} {} {} → {}",
+            style("↑").cyan(),
+            style(&package.name).bold(),
+            style(current).dim(),
+            style(&package.version).green()
+        );
+    }
+    
+    println!(
+        "\n{}\n",
+        t!(
+            "sync-summary",
+            install = plan.install.len(),
+            remove = plan.remove.len(),
+            upgrade = plan.upgrade.len()
+        )
+    );
+}
+
+fn confirm_sync() -> Result<bool> {
+    use std::io::{self, Write};
+    
+    print!("{} [Y/n]: ", t!("sync-confirm"));
+    io::stdout().flush()?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    
+    let input = input.trim().to_lowercase();
+    Ok(input.is_empty() || input == "y" || input == "yes")
+}
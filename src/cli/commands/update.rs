@@ -1,7 +1,8 @@
-use crate::{App, Result};
+use crate::{config::Config, package::{db_lock, upstream, version::vercmp, Package}, repository::{pending_updates::{self, PendingUpdate}, remote}, t, App, Result};
+use std::cmp::Ordering;
+use std::time::Duration;
 use clap::Args;
 use console::style;
-use indicatif::{ProgressBar, ProgressStyle};
 
 #[derive(Args)]
 pub struct UpdateArgs {
@@ -20,43 +21,200 @@ pub struct UpdateArgs {
     /// Check for updates without installing
     #[arg(long)]
     pub check: bool,
+    
+    /// Also perform a full system upgrade (pacman -Syu)
+    #[arg(long)]
+    pub system: bool,
+    
+    /// Exclude a package from update checks and upgrades (repeatable)
+    #[arg(long, value_name = "PKG")]
+    pub ignore: Vec<String>,
+    
+    #[command(flatten)]
+    pub config_conflict: super::install::ConfigConflictArgs,
+    
+    #[command(flatten)]
+    pub exclude: super::install::ExcludeArgs,
 }
 
 pub async fn execute(app: &mut App, args: UpdateArgs) -> Result<()> {
+    let system = args.system || app.config.repository.system_upgrade;
+    let ignored = ignored_packages(&app.config, &args.ignore);
+    app.repository.set_config_conflict(args.config_conflict.policy());
+    
     if args.check {
-        check_for_updates(app).await
+        check_for_updates(app, &ignored, &args.exclude).await
     } else if args.definitions_only {
         update_package_definitions(app).await
-    } else if args.packages_only {
-        update_installed_packages(app, args.yes).await
     } else {
-        // Update both definitions and packages
-        update_package_definitions(app).await?;
-        update_installed_packages(app, args.yes).await
+        // Update definitions, unless only packages were asked for, then packages
+        if !args.packages_only {
+            update_package_definitions(app).await?;
+        }
+        if system {
+            upgrade_system(app, &ignored, args.yes).await?;
+        }
+        let updated = update_installed_packages(app, &ignored, &args.exclude, args.yes).await?;
+        
+        // A system upgrade may have replaced the kernel or the modules of any installed package
+        let changed: Vec<&Package> = if system {
+            app.repository.installed_records()
+                .into_iter()
+                .filter_map(|record| app.repository.loader.get_package(&record.name))
+                .collect()
+        } else {
+            updated.iter().collect()
+        };
+        crate::cli::kernel_module_notice(app, &changed, args.yes).await
     }
 }
 
-async fn check_for_updates(app: &App) -> Result<()> {
-    println!("{} Checking for updates...", style("🔍").cyan());
+/// Packages skipped by this update: pinned and ignored ones from the config plus `--ignore`
+pub fn ignored_packages(config: &Config, extra: &[String]) -> Vec<String> {
+    let mut ignored: Vec<String> = config.repository.pinned
+        .iter()
+        .chain(&config.repository.ignore_packages)
+        .chain(extra)
+        .cloned()
+        .collect();
     
-    // This is a simplified implementation
-    // In practice, you'd compare local and remote package versions
+    ignored.sort();
+    ignored.dedup();
+    ignored
+}
+
+/// Run a full `pacman -Syu`, holding back pinned and ignored packages
+async fn upgrade_system(app: &App, ignored: &[String], skip_confirm: bool) -> Result<()> {
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("🔄").cyan(), t!("upgrade-start"));
+    }
     
-    let installed_packages = get_installed_packages(app).await?;
-    let mut updates_available = Vec::new();
+    let ignored = held_pacman_packages(app, ignored);
     
-    for (name, current_version) in installed_packages {
-        if let Some(package) = app.repository.loader.get_package(&name) {
-            if package.version != current_version {
-                updates_available.push((name, current_version, package.version.clone()));
+    let mut args = vec!["-Syu".to_string()];
+    
+    if skip_confirm {
+        args.push("--noconfirm".to_string());
+    }
+    
+    if !ignored.is_empty() {
+        crate::cli::print_info(&t!("upgrade-holding-back", packages = ignored.join(", ")));
+        args.push("--ignore".to_string());
+        args.push(ignored.join(","));
+    }
+    
+    let started = std::time::Instant::now();
+    let result = if crate::simulate::is_enabled() {
+        crate::simulate::action("run", &format!("pacman {}", args.join(" ")));
+        Ok(())
+    } else {
+        // Inherit the terminal so pacman can show its own transaction summary and prompts.
+        // Its output can't be checked for lock errors, so another transaction is waited
+        // out beforehand.
+        let lock_file = db_lock::lock_file(None);
+        if lock_file.exists() {
+            db_lock::wait_until_unlocked(&lock_file, Duration::from_secs(app.config.installation.lock_wait)).await;
+        }
+        let mut command = crate::privilege::privileged("pacman");
+        command.args(&args).interactive();
+        let status = app.repository.runner().output(&command).await?.status;
+        
+        if status.success() {
+            Ok(())
+        } else {
+            Err(crate::Error::CommandFailed {
+                message: t!("upgrade-failed", status = status.to_string()),
+            })
+        }
+    };
+    crate::history::record_step("system_upgrade", None, None, started.elapsed(), &result);
+    result?;
+    
+    crate::cli::print_success(&t!("upgrade-complete"));
+    Ok(())
+}
+
+/// Map held package names to the pacman packages they correspond to
+fn held_pacman_packages(app: &App, names: &[String]) -> Vec<String> {
+    let mut ignored = Vec::new();
+    
+    for name in names {
+        match app.repository.loader.get_package(name).map(|p| &p.installation) {
+            Some(crate::package::Installation::Pacman { packages, .. }) => {
+                ignored.extend(packages.iter().cloned());
             }
+            Some(crate::package::Installation::Aur { package, .. }) => {
+                ignored.push(package.clone());
+            }
+            Some(_) => {}
+            // Not an archbox package, assume it names a pacman package directly
+            None => ignored.push(name.clone()),
         }
     }
     
-    if updates_available.is_empty() {
-        crate::cli::print_success("All packages are up to date");
-    } else {
-        println!("\n{} updates available:", updates_available.len());
+    ignored.sort();
+    ignored.dedup();
+    ignored
+}
+
+/// Start a background definitions refresh if `repository.auto_update` is enabled and the
+/// update interval has elapsed. When auto-update is off, print a hint for stale definitions instead.
+pub fn spawn_auto_update(config: &Config) -> Option<tokio::task::JoinHandle<()>> {
+    let interval = config.repository.update_interval;
+    
+    if !remote::definitions_stale(interval) {
+        return None;
+    }
+    
+    if !config.repository.auto_update {
+        if let Some(age) = remote::definitions_age() {
+            let days = age.as_secs() / 86_400;
+            crate::cli::print_info(&t!("definitions-stale", days = days));
+        }
+        return None;
+    }
+    
+    config.repository.update_url.as_ref()?;
+    let repository = config.repository.clone();
+    
+    Some(tokio::spawn(async move {
+        match remote::fetch_definitions(&repository).await {
+            Ok(_) => tracing::debug!("Background definitions refresh complete"),
+            Err(e) => tracing::warn!("Background definitions refresh failed: {}", e),
+        }
+    }))
+}
+
+async fn check_for_updates(app: &App, ignored: &[String], exclude: &super::install::ExcludeArgs) -> Result<()> {
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("🔍").cyan(), t!("update-check-start"));
+    }
+    
+    // This is a simplified implementation
+    // In practice, you'd compare local and remote package versions
+    
+    let updates_available: Vec<(String, String, String)> = outdated_packages(app, ignored)
+        .await?
+        .into_iter()
+        .filter(|(package, _)| !exclude.matches(&package.name))
+        .map(|(package, current)| (package.name, current, package.version))
+        .collect();
+    
+    for (name, current, available) in &updates_available {
+        crate::cli::print_porcelain(&["outdated", name, current, available]);
+    }
+    
+    let replaced = replaced_packages(app, ignored).await?;
+    for (old, new) in &replaced {
+        crate::cli::print_porcelain(&["replaceable", &old.name, &new.name]);
+    }
+    
+    if updates_available.is_empty() && replaced.is_empty() {
+        crate::cli::print_success(&t!("update-up-to-date"));
+    } else if crate::cli::is_normal_output() {
+        if !updates_available.is_empty() {
+            println!("\n{}", t!("update-available", count = updates_available.len()));
+        }
         for (name, current, available) in updates_available {
             println!("  {} {} → {}",
                 style(&name).bold(),
@@ -64,81 +222,123 @@ async fn check_for_updates(app: &App) -> Result<()> {
                 style(&available).green()
             );
         }
+        for (old, new) in &replaced {
+            println!("  {}", t!("update-replaceable", old = style(&old.name).bold().to_string(), new = style(&new.name).green().to_string()));
+        }
+    }
+    
+    let behind = definitions_behind_upstream(app, ignored).await?;
+    for (package, latest) in &behind {
+        crate::cli::print_porcelain(&["upstream", &package.name, &package.version, latest]);
+    }
+    if !behind.is_empty() && crate::cli::is_normal_output() {
+        println!("\n{}", t!("update-upstream-newer"));
+        for (package, latest) in &behind {
+            println!("  {} {} → {}",
+                style(&package.name).bold(),
+                style(&package.version).dim(),
+                style(latest).yellow()
+            );
+        }
     }
     
     Ok(())
 }
 
-async fn update_package_definitions(app: &mut App) -> Result<()> {
-    println!("{} Updating package definitions...", style("📥").blue());
-    
-    if let Some(update_url) = &app.config.repository.update_url {
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
-            .unwrap());
-        pb.set_message("Downloading latest package definitions...");
-        pb.enable_steady_tick(std::time::Duration::from_millis(100));
+/// Installed packages whose upstream (AUR, Flathub, GitHub releases) has a newer version
+/// than their definition, so the definitions need refreshing before they can be updated
+async fn definitions_behind_upstream(app: &App, ignored: &[String]) -> Result<Vec<(Package, String)>> {
+    let mut installed = Vec::new();
+    for (name, _) in get_installed_packages(app).await? {
+        if ignored.contains(&name) {
+            continue;
+        }
+        if let Some(package) = app.repository.loader.get_package(&name) {
+            installed.push(package);
+        }
+    }
+    
+    let latest = upstream::latest_versions(&installed).await;
+    let mut behind: Vec<(Package, String)> = installed
+        .into_iter()
+        .filter_map(|package| {
+            let version = latest.get(&package.name)?;
+            vercmp(version, &package.version).is_gt().then(|| (package.clone(), version.clone()))
+        })
+        .collect();
+    behind.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    
+    Ok(behind)
+}
+
+pub async fn update_package_definitions(app: &mut App) -> Result<()> {
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("📥").blue(), t!("definitions-start"));
+    }
+    
+    if let (true, Some(url)) = (crate::simulate::is_enabled(), &app.config.repository.update_url) {
+        // Simulated runs work from the definitions already on disk
+        crate::simulate::action("download", url);
+    } else if app.config.repository.update_url.is_some() {
+        let pb = crate::progress::spinner(t!("definitions-downloading"));
         
         // Download updated package definitions
-        let client = reqwest::Client::new();
-        let response = client.get(update_url).send().await?;
-        
-        if response.status().is_success() {
-            let content = response.text().await?;
-            
-            // Save to local cache
-            let cache_dir = crate::config::get_config_dir().join("cache");
-            tokio::fs::create_dir_all(&cache_dir).await?;
-            let cache_file = cache_dir.join("remote_packages.yaml");
-            tokio::fs::write(&cache_file, content).await?;
-            
-            // Reload package definitions
-            app.repository.loader.load_definition_file(&cache_file).await?;
-            
-            pb.finish_with_message("Package definitions updated");
-            crate::cli::print_success("Package definitions updated successfully");
-        } else {
-            pb.finish_with_message("Update failed");
-            crate::cli::print_error("Failed to download package definitions");
+        match remote::fetch_definitions(&app.config.repository).await {
+            Ok(cache_file) => {
+                // Reload package definitions
+                app.repository.loader.load_definition_file(&cache_file).await?;
+                
+                pb.finish_with_message(t!("definitions-updated"));
+                crate::cli::print_success(&t!("definitions-updated-success"));
+            }
+            Err(e) => {
+                pb.finish_with_message(t!("definitions-failed-short"));
+                crate::cli::report_failure("definitions_update_failed", None, &t!("definitions-failed", error = e.to_string()));
+            }
         }
     } else {
-        crate::cli::print_warning("No update URL configured");
+        crate::cli::print_warning(&t!("definitions-no-url"));
     }
     
     Ok(())
 }
 
-async fn update_installed_packages(app: &mut App, skip_confirm: bool) -> Result<()> {
-    println!("{} Updating installed packages...", style("⬆️").green());
+async fn update_installed_packages(
+    app: &mut App,
+    ignored: &[String],
+    exclude: &super::install::ExcludeArgs,
+    skip_confirm: bool,
+) -> Result<Vec<Package>> {
+    if crate::cli::is_normal_output() {
+        println!("{} {}", style("⬆️").green(), t!("update-start"));
+    }
     
-    let installed_packages = get_installed_packages(app).await?;
-    let mut packages_to_update = Vec::new();
+    migrate_replaced_packages(app, ignored, skip_confirm).await?;
     
-    for (name, current_version) in installed_packages {
-        if let Some(package) = app.repository.loader.get_package(&name) {
-            if package.version != current_version {
-                packages_to_update.push(package.clone());
-            }
-        }
+    let mut packages_to_update: Vec<Package> = outdated_packages(app, ignored)
+        .await?
+        .into_iter()
+        .map(|(package, _)| package)
+        .collect();
+    exclude.apply(&mut packages_to_update);
+    
+    for package in &packages_to_update {
+        crate::cli::warn_if_deprecated(package);
     }
     
     if packages_to_update.is_empty() {
-        crate::cli::print_success("All packages are up to date");
-        return Ok(());
+        crate::cli::print_success(&t!("update-up-to-date"));
+        return Ok(Vec::new());
     }
     
-    println!("\nFound {} package(s) to update:", packages_to_update.len());
-    for package in &packages_to_update {
-        println!("  {} {}", 
-            style(&package.name).bold(),
-            style(&package.version).green()
-        );
+    if crate::cli::is_normal_output() {
+        println!("\n{}\n", t!("update-found", count = packages_to_update.len()));
+        crate::cli::print_size_summary(app, &packages_to_update).await;
     }
     
     if !skip_confirm {
         use std::io::{self, Write};
-        print!("\nContinue with update? [Y/n]: ");
+        print!("\n{} [Y/n]: ", t!("update-confirm"));
         io::stdout().flush()?;
         
         let mut input = String::new();
@@ -146,21 +346,100 @@ async fn update_installed_packages(app: &mut App, skip_confirm: bool) -> Result<
         
         let input = input.trim().to_lowercase();
         if input == "n" || input == "no" {
-            crate::cli::print_info("Update cancelled");
-            return Ok(());
+            crate::cli::print_info(&t!("update-cancelled"));
+            return Ok(Vec::new());
         }
     }
     
     // Update packages
-    let installer = crate::package::installer::Installer::new(&app.config);
-    
+    let mut updated = Vec::new();
     for package in packages_to_update {
-        match installer.install(&package).await {
+        match app.repository.install_package(&package, true, false).await {
+            Ok(_) => {
+                crate::cli::print_success(&t!("update-done", package = package.name.as_str()));
+                crate::cli::print_porcelain(&["updated", &package.name, &package.version]);
+                updated.push(package);
+            }
+            Err(e) => {
+                crate::cli::report_failure("update_failed", Some(&package.name), &t!("update-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+            }
+        }
+    }
+    
+    Ok(updated)
+}
+
+/// Installed packages that are deprecated in favor of, or replaced by, another package that
+/// isn't installed yet, each with its replacement
+pub async fn replaced_packages(app: &App, ignored: &[String]) -> Result<Vec<(Package, Package)>> {
+    let mut replaced = Vec::new();
+    
+    for (name, _) in get_installed_packages(app).await? {
+        if ignored.contains(&name) {
+            continue;
+        }
+        
+        let (Some(package), Some(replacement)) = (app.repository.loader.get_package(&name), app.repository.replacement(&name)) else {
+            continue;
+        };
+        if !app.repository.is_installed(&replacement.name).await? {
+            replaced.push((package.clone(), replacement.clone()));
+        }
+    }
+    
+    replaced.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    Ok(replaced)
+}
+
+/// Offer to swap each replaced package for its replacement, like pacman's Replaces: the
+/// replacement is installed first, then the old package is removed unless something still
+/// requires it by name
+async fn migrate_replaced_packages(app: &mut App, ignored: &[String], skip_confirm: bool) -> Result<()> {
+    for (old, new) in replaced_packages(app, ignored).await? {
+        crate::cli::warn_if_deprecated(&old);
+        
+        if !skip_confirm && !confirm_replacement(&old.name, &new.name)? {
+            continue;
+        }
+        
+        let explicit = app.repository.is_explicit(&old.name);
+        let mut installed = true;
+        for package in app.repository.resolve_packages(std::slice::from_ref(&new.name)).await? {
+            if app.repository.is_installed(&package.name).await? {
+                continue;
+            }
+            
+            let explicit = explicit && package.name == new.name;
+            if let Err(e) = app.repository.install_package(&package, false, explicit).await {
+                crate::cli::report_failure("install_failed", Some(&package.name), &t!("install-failed", package = package.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &package.name, &package.version]);
+                installed = false;
+                break;
+            }
+        }
+        if !installed {
+            continue;
+        }
+        
+        let dependents: Vec<String> = app.repository
+            .installed_dependents(&old.name)
+            .into_iter()
+            .map(|package| package.name.clone())
+            .collect();
+        if !dependents.is_empty() {
+            crate::cli::print_warning(&t!("update-replace-kept", old = old.name.as_str(), dependents = dependents.join(", ")));
+            continue;
+        }
+        
+        match super::remove::uninstall(app, &old, false, false).await {
             Ok(_) => {
-                crate::cli::print_success(&format!("Updated {}", package.name));
+                crate::cli::print_success(&t!("update-replaced", old = old.name.as_str(), new = new.name.as_str()));
+                crate::cli::print_porcelain(&["replaced", &old.name, &new.name]);
             }
             Err(e) => {
-                crate::cli::print_error(&format!("Failed to update {}: {}", package.name, e));
+                crate::cli::report_failure("remove_failed", Some(&old.name), &t!("remove-failed", package = old.name.as_str(), error = e.to_string()));
+                crate::cli::print_porcelain(&["failed", &old.name, &old.version]);
             }
         }
     }
@@ -168,15 +447,70 @@ async fn update_installed_packages(app: &mut App, skip_confirm: bool) -> Result<
     Ok(())
 }
 
+fn confirm_replacement(old: &str, new: &str) -> Result<bool> {
+    use std::io::{self, Write};
+    
+    print!("{} [Y/n]: ", t!("update-replace-confirm", old = old, new = new));
+    io::stdout().flush()?;
+    
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    
+    let input = input.trim().to_lowercase();
+    Ok(input.is_empty() || input == "y" || input == "yes")
+}
+
+/// Installed packages whose definition has a newer version, with the installed version.
+/// A definition older than what is installed is not a downgrade `update` makes.
+pub async fn outdated_packages(app: &App, ignored: &[String]) -> Result<Vec<(Package, String)>> {
+    let mut outdated = Vec::new();
+    
+    for (name, current_version) in get_installed_packages(app).await? {
+        if ignored.contains(&name) {
+            continue;
+        }
+        
+        if let Some(package) = app.repository.loader.get_package(&name) {
+            match vercmp(&package.version, &current_version) {
+                Ordering::Greater => outdated.push((package.clone(), current_version)),
+                Ordering::Less => tracing::debug!(
+                    "{} {} is newer than its definition ({}), not downgrading",
+                    name, current_version, package.version
+                ),
+                Ordering::Equal => {}
+            }
+        }
+    }
+    
+    outdated.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    
+    // Keep what was found for `archbox status`, which has to answer without checking
+    let pending = outdated
+        .iter()
+        .map(|(package, current)| PendingUpdate {
+            name: package.name.clone(),
+            installed: current.clone(),
+            available: package.version.clone(),
+        })
+        .collect();
+    if let Err(e) = pending_updates::record(pending) {
+        tracing::warn!("Cannot save the update check for archbox status: {}", e);
+    }
+    
+    Ok(outdated)
+}
+
 async fn get_installed_packages(app: &App) -> Result<Vec<(String, String)>> {
-    // This is a simplified implementation
-    // In practice, you'd check the actual installation status of all packages
     let mut installed = Vec::new();
     
     for package in app.repository.loader.packages().values() {
         if app.repository.is_installed(&package.name).await? {
-            // For now, assume we don't know the exact installed version
-            installed.push((package.name.clone(), "unknown".to_string()));
+            // Packages ArchBox did not install have no recorded version
+            let version = app.repository
+                .installed_record(&package.name)
+                .map(|record| record.version.clone())
+                .unwrap_or_else(|| "unknown".to_string());
+            installed.push((package.name.clone(), version));
         }
     }
     
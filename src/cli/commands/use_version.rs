@@ -0,0 +1,157 @@
+use crate::{package::layout, t, App, Result};
+use clap::Args;
+use console::style;
+
+#[derive(Args)]
+pub struct UseArgs {
+    /// Binary package to switch, as <package>@<version>; without a version, show which
+    /// version is in use here
+    #[arg(value_name = "PACKAGE[@VERSION]")]
+    pub package: String,
+    
+    /// Pin the version for the current directory and its subdirectories instead of
+    /// switching it everywhere
+    #[arg(short, long)]
+    pub local: bool,
+}
+
+pub async fn execute(app: &mut App, args: UseArgs) -> Result<()> {
+    let (name, requested) = match args.package.split_once('@') {
+        Some((name, version)) if !version.is_empty() => (name, Some(version)),
+        Some((name, _)) => (name, None),
+        None => (args.package.as_str(), None),
+    };
+    
+    let package = app.repository.loader.get_package(name)
+        .cloned()
+        .ok_or_else(|| crate::Error::PackageNotFound(name.to_string()))?;
+    
+    if !matches!(package.installation, crate::package::Installation::Binary { .. }) {
+        return Err(crate::Error::Config(t!("rollback-not-binary", package = package.name.as_str())));
+    }
+    let scope = app.repository.installed_scope(&package.name);
+    let install_path = package.install_path(scope).unwrap_or_default();
+    
+    let versions = layout::installed_versions(&package.name, scope)?;
+    let active = layout::active_version(&package.name, scope, &install_path);
+    let current_dir = std::env::current_dir()?;
+    
+    let Some(requested) = requested else {
+        show_versions(&package.name, &versions, active.as_deref(), layout::pinned_version(&package.name, &current_dir));
+        return Ok(());
+    };
+    
+    let version = requested.replace('/', "_");
+    if !versions.contains(&version) {
+        return Err(crate::Error::Config(t!(
+            "use-not-installed",
+            version = requested,
+            package = package.name.as_str()
+        )));
+    }
+    
+    // Installs from before shims existed don't have one yet
+    layout::write_shim(&package.name, scope, &install_path).await?;
+    
+    if args.local {
+        let file = layout::pin_version(&current_dir, &package.name, &version)?;
+        crate::cli::print_success(&t!(
+            "use-pinned",
+            package = package.name.as_str(),
+            version = version.as_str(),
+            file = file.display().to_string()
+        ));
+        warn_if_shims_missing(scope);
+        return Ok(());
+    }
+    
+    if active.as_deref() == Some(version.as_str()) {
+        crate::cli::print_info(&t!("rollback-already-active", package = package.name.as_str(), version = version.as_str()));
+        return Ok(());
+    }
+    
+    let file_name = install_path.file_name().unwrap_or_default();
+    let target = layout::version_dir(&package.name, scope, &version).join(file_name);
+    let started = std::time::Instant::now();
+    let result = layout::activate(&install_path, &target).await;
+    crate::history::record_step("use", Some(&package.name), Some(&version), started.elapsed(), &result);
+    result?;
+    
+    app.repository.record_version(&package.name, &version)?;
+    crate::cli::print_success(&t!("rollback-done", package = package.name.as_str(), version = version.as_str()));
+    
+    // A pin still wins over the active version inside its directory
+    if let Some((pinned, file)) = layout::pinned_version(&package.name, &current_dir) {
+        if pinned != version {
+            crate::cli::print_warning(&t!(
+                "use-overridden",
+                package = package.name.as_str(),
+                version = pinned.as_str(),
+                file = file.display().to_string()
+            ));
+        }
+    }
+    
+    Ok(())
+}
+
+fn show_versions(package: &str, versions: &[String], active: Option<&str>, pinned: Option<(String, std::path::PathBuf)>) {
+    if versions.is_empty() {
+        crate::cli::print_warning(&t!("rollback-no-versions", package = package));
+        return;
+    }
+    
+    let in_use = pinned.as_ref().map(|(version, _)| version.as_str()).or(active);
+    for version in versions {
+        crate::cli::print_porcelain(&[
+            if Some(version.as_str()) == in_use { "in-use" } else { "installed" },
+            package,
+            version,
+        ]);
+    }
+    if !crate::cli::is_normal_output() {
+        return;
+    }
+    
+    println!("{}", t!("rollback-list-header", package = style(package).bold().to_string()));
+    for version in versions {
+        let mut notes = Vec::new();
+        if Some(version.as_str()) == active {
+            notes.push(t!("rollback-active"));
+        }
+        if let Some((pinned, file)) = &pinned {
+            if pinned == version {
+                notes.push(t!("use-pinned-by", file = file.display().to_string()));
+            }
+        }
+    
+        if Some(version.as_str()) == in_use {
+            println!("  {} {}", style(version).green(), style(format!("({})", notes.join(", "))).dim());
+        } else if notes.is_empty() {
+            println!("  {}", version);
+        } else {
+            println!("  {} {}", version, style(format!("({})", notes.join(", "))).dim());
+        }
+    }
+    
+    if let Some((pinned, file)) = &pinned {
+        if !versions.contains(pinned) {
+            crate::cli::print_warning(&t!(
+                "use-pin-missing",
+                package = package,
+                version = pinned.as_str(),
+                file = file.display().to_string()
+            ));
+        }
+    }
+}
+
+/// Pins only take effect through the shims, which `archbox init` puts on PATH
+fn warn_if_shims_missing(scope: crate::package::InstallScope) {
+    let shims = layout::shims_dir(scope);
+    let on_path = std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir == shims));
+    if !on_path {
+        crate::cli::print_warning(&t!("use-shims-not-on-path", dir = shims.display().to_string()));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{t, App, Result};
+use clap::Args;
+use console::style;
+
+#[derive(Args)]
+pub struct WhyArgs {
+    /// Installed package to explain
+    #[arg(required = true)]
+    pub package: String,
+}
+
+pub async fn execute(app: &App, args: WhyArgs) -> Result<()> {
+    let package = app.repository.loader.get_package(&args.package)
+        .ok_or_else(|| crate::Error::PackageNotFound(args.package.clone()))?;
+    
+    if !app.repository.is_installed(&package.name).await? {
+        crate::cli::print_warning(&t!("why-not-installed", package = package.name.as_str()));
+        return Ok(());
+    }
+    
+    let explicit = app.repository.is_explicit(&package.name);
+    let chains = app.repository.dependency_chains(&package.name);
+    
+    if explicit {
+        crate::cli::print_info(&t!("why-explicit", package = package.name.as_str()));
+    } else if chains.is_empty() {
+        crate::cli::print_warning(&t!("why-orphan", package = package.name.as_str()));
+    } else {
+        crate::cli::print_info(&t!("why-dependency", package = package.name.as_str()));
+    }
+    
+    if chains.is_empty() || !crate::cli::is_normal_output() {
+        return Ok(());
+    }
+    
+    if explicit {
+        println!("{}", t!("why-also-required"));
+    }
+    
+    for chain in chains {
+        let last = chain.len() - 1;
+        let rendered: Vec<String> = chain
+            .iter()
+            .enumerate()
+            .map(|(i, name)| match i {
+                0 => style(name).bold().to_string(),
+                i if i == last => style(name).cyan().to_string(),
+                _ => name.clone(),
+            })
+            .collect();
+        
+        println!("  {}", rendered.join(&format!(" {} ", style("→").blue())));
+    }
+    
+    Ok(())
+}
@@ -1,8 +1,10 @@
 pub mod commands;
+pub mod table;
 
-use crate::{App, Result};
+use crate::{history, package::InstallStatus, t, App, Result};
 use clap::{Parser, Subcommand};
 use console::style;
+use std::sync::OnceLock;
 
 #[derive(Parser)]
 #[command(name = "archbox")]
@@ -20,6 +22,48 @@ pub struct Cli {
     /// Configuration file path
     #[arg(short, long, global = true)]
     pub config: Option<std::path::PathBuf>,
+    
+    /// Skip the automatic package definitions refresh for this run
+    #[arg(long, global = true)]
+    pub no_auto_update: bool,
+    
+    /// Check what is installed again instead of reusing results from the last few minutes
+    #[arg(long, global = true)]
+    pub refresh: bool,
+    
+    /// Pick group drivers for these GPUs instead of the detected ones (comma-separated)
+    #[arg(long, global = true, value_enum, value_delimiter = ',', value_name = "VENDOR")]
+    pub gpu: Vec<crate::hardware::GpuVendor>,
+    
+    /// Also write logs to this file, rotated by size (see ui.log_file)
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<std::path::PathBuf>,
+    
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "porcelain")]
+    pub quiet: bool,
+    
+    /// Stable tab-separated output for scripts, one record per line (same as `--output porcelain`)
+    #[arg(long, global = true, conflicts_with = "output")]
+    pub porcelain: bool,
+    
+    /// Output for scripts: `porcelain` prints tab-separated records, `json` prints them as
+    /// JSON arrays and reports errors as JSON objects on stderr
+    #[arg(long, global = true, value_enum, value_name = "FORMAT", conflicts_with = "quiet")]
+    pub output: Option<OutputFormat>,
+    
+    /// Report what pacman, AUR helpers, flatpak, downloads and file changes would do instead
+    /// of doing it, recording the results in a separate simulated state database
+    #[arg(long, global = true)]
+    pub simulate: bool,
+    
+    /// When another archbox is changing the system, wait for it to finish instead of failing
+    #[arg(long, global = true)]
+    pub wait: bool,
+    
+    /// Don't add the user to groups packages ask for (see installation.modify_user_groups)
+    #[arg(long, global = true)]
+    pub no_usermod: bool,
 
     #[command(subcommand)]
     pub command: Commands,
@@ -45,11 +89,179 @@ pub enum Commands {
     /// Remove packages
     Remove(commands::remove::RemoveArgs),
     
+    /// Reinstall packages from scratch, to recover a broken install
+    Reinstall(commands::reinstall::ReinstallArgs),
+    
     /// Configure application settings
     Config(commands::config::ConfigArgs),
+    
+    /// Restore files backed up when a package overwrote or removed them
+    Restore(commands::restore::RestoreArgs),
+    
+    /// Switch a binary package back to a previously installed version
+    Rollback(commands::rollback::RollbackArgs),
+    
+    /// Switch the active version of a binary package, or pin one for a directory
+    Use(commands::use_version::UseArgs),
+    
+    /// Generate or install shell completions
+    Completions(commands::completions::CompletionsArgs),
+    
+    /// Walk through first-time configuration: AUR helper, binary directory, definition
+    /// sources, default profile and shell completions
+    Setup(commands::setup::SetupArgs),
+    
+    /// Print shell setup (PATH, package environment, completions) for a shell rc file
+    Init(commands::init::InitArgs),
+    
+    /// Explain why a package is installed by showing what requires it
+    Why(commands::why::WhyArgs),
+    
+    /// Show how a package's config files differ from what ArchBox wrote and from what its
+    /// definition declares now
+    Diff(commands::diff::DiffArgs),
+    
+    /// Show disk usage of installed packages, largest first
+    Size(commands::size::SizeArgs),
+    
+    /// Inspect the transaction log
+    History(commands::history::HistoryArgs),
+    
+    /// Remove work directories kept from failed builds once they are old
+    Clean(commands::clean::CleanArgs),
+    
+    /// Show the updates found by the last check, quickly enough for a shell prompt
+    Status(commands::status::StatusArgs),
+    
+    /// Make this machine match its declaration: refresh definitions, apply the configured
+    /// profile, optionally prune what it doesn't include, and upgrade outdated packages
+    Sync(commands::sync::SyncArgs),
+    
+    /// Install or check the tools a project's archbox.yaml declares
+    Env(commands::env::EnvArgs),
+    
+    /// Install packages into a newly installed system mounted from the live ISO
+    Provision(commands::provision::ProvisionArgs),
+    
+    /// Write a Containerfile that builds a profile into an image, and optionally build it
+    Containerize(commands::containerize::ContainerizeArgs),
+    
+    /// Export a profile as cloud-init user-data for bootstrapping cloud VMs
+    CloudInit(commands::cloud_init::CloudInitArgs),
+    
+    /// Choose which package provides a capability such as `editor`
+    Alternatives(commands::alternatives::AlternativesArgs),
+    
+    /// List installation profiles, create your own or apply one
+    Profile(commands::profile::ProfileArgs),
+    
+    /// Show where package definitions come from
+    Repo(commands::repo::RepoArgs),
+    
+    /// List package groups, show what they contain, or install one
+    Group(commands::group::GroupArgs),
+    
+    /// Measure latency and throughput of definition and artifact mirrors, and optionally
+    /// save the ranking used to pick between them
+    Benchmark(commands::benchmark::BenchmarkArgs),
+    
+    /// Serve install, search and update requests from frontends over a Unix socket
+    Daemon(commands::daemon::DaemonArgs),
+    
+    /// Serve an HTTP API with package, status and history endpoints for dashboards
+    #[cfg(feature = "server")]
+    Serve(commands::serve::ServeArgs),
+    
+    /// Show what installing a definition would do (commands, downloads, files, services
+    /// and privileges) without doing any of it
+    Explain(commands::explain::ExplainArgs),
 }
 
 impl Commands {
+    /// Subcommand name as typed on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Install(_) => "install",
+            Commands::Search(_) => "search",
+            Commands::List(_) => "list",
+            Commands::Update(_) => "update",
+            Commands::Info(_) => "info",
+            Commands::Remove(_) => "remove",
+            Commands::Reinstall(_) => "reinstall",
+            Commands::Config(_) => "config",
+            Commands::Restore(_) => "restore",
+            Commands::Rollback(_) => "rollback",
+            Commands::Use(_) => "use",
+            Commands::Completions(_) => "completions",
+            Commands::Setup(_) => "setup",
+            Commands::Init(_) => "init",
+            Commands::Why(_) => "why",
+            Commands::Diff(_) => "diff",
+            Commands::Size(_) => "size",
+            Commands::History(_) => "history",
+            Commands::Clean(_) => "clean",
+            Commands::Status(_) => "status",
+            Commands::Sync(_) => "sync",
+            Commands::Provision(_) => "provision",
+            Commands::Containerize(_) => "containerize",
+            Commands::CloudInit(_) => "cloud-init",
+            Commands::Alternatives(_) => "alternatives",
+            Commands::Profile(_) => "profile",
+            Commands::Repo(_) => "repo",
+            Commands::Group(_) => "group",
+            Commands::Benchmark(_) => "benchmark",
+            Commands::Daemon(_) => "daemon",
+            #[cfg(feature = "server")]
+            Commands::Serve(_) => "serve",
+            Commands::Explain(_) => "explain",
+            Commands::Env(_) => "env",
+        }
+    }
+    
+    /// Whether the command can run under `--simulate`. The others change the system
+    /// outside the installer (restoring backups, switching links, building images, serving
+    /// requests) or need the network.
+    pub fn can_simulate(&self) -> bool {
+        #[cfg(feature = "server")]
+        if matches!(self, Commands::Serve(_)) {
+            return false;
+        }
+        
+        !matches!(
+            self,
+            Commands::Restore(_)
+                | Commands::Use(_)
+                | Commands::Provision(_)
+                | Commands::Containerize(_)
+                | Commands::Benchmark(_)
+                | Commands::Daemon(_)
+                | Commands::Clean(_)
+        )
+    }
+    
+    /// Whether the command changes packages or their files, and so takes the transaction
+    /// lock. Previews (`--dry-run`, `--check`, `--list`) don't.
+    pub fn changes_system(&self) -> bool {
+        match self {
+            Commands::Install(args) => !args.dry_run,
+            Commands::Remove(args) => !args.dry_run,
+            Commands::Reinstall(args) => !args.dry_run,
+            Commands::Update(args) => !args.check,
+            Commands::Sync(args) => !args.dry_run,
+            Commands::Provision(args) => !args.dry_run,
+            Commands::Restore(args) => !args.list,
+            Commands::Rollback(args) => !args.list,
+            Commands::Use(args) => args.package.contains('@'),
+            Commands::Env(args) => matches!(args.command, commands::env::EnvCommand::Install { dry_run: false, .. }),
+            Commands::Profile(args) => matches!(args.command, commands::profile::ProfileCommand::Apply { dry_run: false, .. }),
+            Commands::Group(args) => matches!(args.command, commands::group::GroupCommand::Install { dry_run: false, .. }),
+            Commands::Alternatives(args) => !matches!(args.command, commands::alternatives::AlternativesCommand::List { .. }),
+            // Waiting for running transactions keeps it from removing their work directories
+            Commands::Clean(_) => true,
+            _ => false,
+        }
+    }
+    
     pub async fn execute(self, app: &mut App) -> Result<()> {
         match self {
             Commands::Install(args) => commands::install::execute(app, args).await,
@@ -58,7 +270,34 @@ impl Commands {
             Commands::Update(args) => commands::update::execute(app, args).await,
             Commands::Info(args) => commands::info::execute(app, args).await,
             Commands::Remove(args) => commands::remove::execute(app, args).await,
+            Commands::Reinstall(args) => commands::reinstall::execute(app, args).await,
             Commands::Config(args) => commands::config::execute(app, args).await,
+            Commands::Restore(args) => commands::restore::execute(app, args).await,
+            Commands::Rollback(args) => commands::rollback::execute(app, args).await,
+            Commands::Use(args) => commands::use_version::execute(app, args).await,
+            Commands::Completions(args) => commands::completions::execute(&args),
+            Commands::Setup(args) => commands::setup::execute(app.config.clone(), &args),
+            Commands::Init(args) => commands::init::execute(&app.config, &args),
+            Commands::Why(args) => commands::why::execute(app, args).await,
+            Commands::Diff(args) => commands::diff::execute(app, args).await,
+            Commands::Size(args) => commands::size::execute(app, args).await,
+            Commands::History(args) => commands::history::execute(app, args).await,
+            Commands::Clean(args) => commands::clean::execute(app, args).await,
+            Commands::Status(args) => commands::status::execute(&args),
+            Commands::Sync(args) => commands::sync::execute(app, args).await,
+            Commands::Provision(args) => commands::provision::execute(app, args).await,
+            Commands::Containerize(args) => commands::containerize::execute(app, args).await,
+            Commands::CloudInit(args) => commands::cloud_init::execute(app, args).await,
+            Commands::Alternatives(args) => commands::alternatives::execute(app, args).await,
+            Commands::Profile(args) => commands::profile::execute(app, args).await,
+            Commands::Repo(args) => commands::repo::execute(app, args).await,
+            Commands::Group(args) => commands::group::execute(app, args).await,
+            Commands::Benchmark(args) => commands::benchmark::execute(app, args).await,
+            Commands::Daemon(args) => commands::daemon::execute(app.config.clone(), &args).await,
+            #[cfg(feature = "server")]
+            Commands::Serve(args) => commands::serve::execute(app.config.clone(), &args).await,
+            Commands::Explain(args) => commands::explain::execute(app, args).await,
+            Commands::Env(args) => commands::env::execute(app, args).await,
         }
     }
 }
@@ -66,11 +305,70 @@ impl Commands {
 pub async fn run() -> Result<()> {
     let cli = Cli::parse();
     
-    let mut app = App::new().await?;
+    // Completions only need the command definition
+    if let Commands::Completions(args) = &cli.command {
+        return commands::completions::execute(args);
+    }
+    
+    let mode = if cli.porcelain || cli.output == Some(OutputFormat::Porcelain) {
+        OutputMode::Porcelain
+    } else if cli.output == Some(OutputFormat::Json) {
+        OutputMode::Json
+    } else if cli.quiet {
+        OutputMode::Quiet
+    } else {
+        OutputMode::Normal
+    };
+    let _ = OUTPUT_MODE.set(mode);
     
-    // Set verbosity
-    if cli.verbose {
-        std::env::set_var("RUST_LOG", "archbox=debug");
+    if cli.simulate {
+        if !cli.command.can_simulate() {
+            return Err(crate::Error::Config(t!("simulate-unsupported", command = cli.command.name())));
+        }
+        crate::simulate::enable();
+    }
+    if cli.refresh {
+        crate::repository::status_cache::force_refresh();
+    }
+    if !cli.gpu.is_empty() {
+        crate::hardware::override_gpus(cli.gpu.clone());
+    }
+    
+    let mut config = crate::config::Config::load()?;
+    crate::http::configure(&config.network, config.installation.download_limit());
+    crate::logging::init(cli.verbose, mode != OutputMode::Normal, cli.log_file.as_deref(), &config.ui)?;
+    crate::i18n::init(config.ui.language.as_deref());
+    crate::progress::set_enabled(config.ui.show_progress && mode == OutputMode::Normal);
+    if cli.no_usermod || !config.installation.modify_user_groups {
+        crate::privilege::forbid_usermod();
+    }
+    
+    // Runs from shell startup and doesn't need the package repository
+    if let Commands::Init(args) = &cli.command {
+        return commands::init::execute(&config, args);
+    }
+    
+    // Runs from shell prompts, so it only reads what the last update check saved
+    if let Commands::Status(args) = &cli.command {
+        return commands::status::execute(args);
+    }
+    
+    // Setup writes the config file, so it doesn't need the package repository either
+    if let Commands::Setup(args) = &cli.command {
+        return commands::setup::execute(config, args);
+    }
+    if !crate::config::Config::exists() {
+        config = commands::setup::first_run(config)?;
+    }
+    
+    // The daemon and server record a transaction per request rather than one for their
+    // whole run
+    if let Commands::Daemon(args) = &cli.command {
+        return commands::daemon::execute(config, args).await;
+    }
+    #[cfg(feature = "server")]
+    if let Commands::Serve(args) = &cli.command {
+        return commands::serve::execute(config, args).await;
     }
     
     // Handle color output
@@ -78,21 +376,439 @@ pub async fn run() -> Result<()> {
         console::set_colors_enabled(false);
     }
     
-    cli.command.execute(&mut app).await
+    // Simulated runs keep their own state, so they don't need to wait for real ones. The
+    // lock is taken before the state is loaded so a run that waited sees what the one
+    // holding it installed.
+    let _lock = if cli.command.changes_system() && !cli.simulate {
+        let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+        let on_wait = |holder: &str| print_info(&t!("lock-waiting", holder = holder));
+        Some(crate::lock::acquire(&command, cli.wait, on_wait).await?)
+    } else {
+        None
+    };
+    
+    let mut app = App::with_config(config).await?;
+    warn_skipped_definitions(&app);
+    
+    let skip_auto_update = cli.no_auto_update
+        || cli.simulate
+        || matches!(cli.command, Commands::Update(_) | Commands::Sync(_) | Commands::Config(_));
+    
+    let auto_update = if skip_auto_update {
+        None
+    } else {
+        commands::update::spawn_auto_update(&app.config)
+    };
+    
+    // Read-only commands like list and search are left out of the history
+    if cli.command.changes_system() {
+        history::begin(cli.command.name(), std::env::args().skip(1).collect());
+    }
+    
+    let result = cli.command.execute(&mut app).await;
+    
+    if let Some(transaction) = history::finish(&result) {
+        crate::notify::send(&app.config.notifications, &transaction).await;
+    }
+    
+    // Let a background refresh finish writing the cache before exiting
+    if let Some(handle) = auto_update {
+        let _ = handle.await;
+    }
+    
+    result
+}
+
+/// List the definition files that failed to load, after the ones that did
+fn warn_skipped_definitions(app: &App) {
+    let failures = app.repository.loader.failures();
+    if failures.is_empty() {
+        return;
+    }
+    
+    print_warning(&t!("definitions-skipped", count = failures.len()));
+    if is_normal_output() {
+        for failure in failures {
+            println!("  {} {}", style(format!("{}:", failure.path.display())).bold(), failure.reason);
+        }
+    }
+}
+
+/// How much the CLI prints to the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    Normal,
+    /// Only errors
+    Quiet,
+    /// Tab-separated records on stdout, errors on stderr, nothing else
+    Porcelain,
+    /// Porcelain records as JSON arrays on stdout, errors as JSON objects on stderr
+    Json,
+}
+
+/// Values of `--output`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Porcelain,
+    Json,
+}
+
+static OUTPUT_MODE: OnceLock<OutputMode> = OnceLock::new();
+
+/// Output mode selected by `--quiet` / `--porcelain` / `--output`
+pub fn output_mode() -> OutputMode {
+    OUTPUT_MODE.get().copied().unwrap_or(OutputMode::Normal)
+}
+
+/// Whether human-oriented output (headings, plans, status messages) should be printed
+pub fn is_normal_output() -> bool {
+    output_mode() == OutputMode::Normal
+}
+
+/// Print a `--porcelain` record: fields joined by tabs, e.g. `installed\tripgrep\t14.1.0`,
+/// or with `--output json` a JSON array, e.g. `["installed","ripgrep","14.1.0"]`
+pub fn print_porcelain(fields: &[&str]) {
+    match output_mode() {
+        OutputMode::Porcelain => crate::progress::suspend(|| println!("{}", fields.join("\t"))),
+        OutputMode::Json => crate::progress::suspend(|| println!("{}", serde_json::json!(fields))),
+        _ => {}
+    }
 }
 
 pub fn print_success(message: &str) {
-    println!("{} {}", style("✓").green().bold(), message);
+    if !is_normal_output() {
+        return;
+    }
+    
+    crate::progress::suspend(|| println!("{} {}", style("✓").green().bold(), message));
 }
 
 pub fn print_error(message: &str) {
-    eprintln!("{} {}", style("✗").red().bold(), message);
+    crate::progress::suspend(|| eprintln!("{} {}", style("✗").red().bold(), message));
+}
+
+/// Report a failure a command carries on past, such as one package failing while the
+/// others install: styled text, or with `--output json` an object like
+/// `{"code": "install_failed", "message": "...", "package": "foo"}`
+pub fn report_failure(code: &str, package: Option<&str>, message: &str) {
+    if output_mode() == OutputMode::Json {
+        let report = serde_json::json!({
+            "code": code,
+            "message": message,
+            "package": package,
+        });
+        crate::progress::suspend(|| eprintln!("{}", report));
+        return;
+    }
+    
+    print_error(message);
+}
+
+/// Report the error a command failed with: styled text, or with `--output json` an object
+/// like `{"code": "package_not_found", "message": "...", "package": "foo", "suggestion": "..."}`
+pub fn report_error(error: &crate::Error) {
+    let suggestion = error_suggestion(error);
+    
+    if output_mode() == OutputMode::Json {
+        let report = serde_json::json!({
+            "code": error.code(),
+            "message": error.to_string(),
+            "package": error.package(),
+            "suggestion": suggestion,
+        });
+        eprintln!("{}", report);
+        return;
+    }
+    
+    print_error(&error.to_string());
+    if let Some(suggestion) = suggestion {
+        if is_normal_output() {
+            eprintln!("  {}", style(suggestion).dim());
+        }
+    }
+}
+
+/// What to try next after an error, if there is anything useful to say
+fn error_suggestion(error: &crate::Error) -> Option<String> {
+    use crate::Error;
+    
+    match error {
+        Error::PackageNotFound(name) => Some(t!("error-hint-not-found", package = name.as_str())),
+        Error::PermissionDenied { .. } => Some(t!("error-hint-permission")),
+        Error::Network(_) => Some(t!("error-hint-network")),
+        Error::UnsupportedPlatform { package, .. } => Some(t!("error-hint-platform", package = package.as_str())),
+        Error::Dependency(_) => Some(t!("error-hint-dependency")),
+        Error::Yaml(_) => Some(t!("error-hint-yaml")),
+        Error::Locked { .. } => Some(t!("error-hint-locked")),
+        Error::Timeout { .. } => Some(t!("error-hint-timeout")),
+        Error::WorkKept { source, .. } | Error::Logged { source, .. } => error_suggestion(source),
+        _ => None,
+    }
 }
 
 pub fn print_warning(message: &str) {
-    println!("{} {}", style("⚠").yellow().bold(), message);
+    if !is_normal_output() {
+        return;
+    }
+    
+    crate::progress::suspend(|| println!("{} {}", style("⚠").yellow().bold(), message));
 }
 
 pub fn print_info(message: &str) {
-    println!("{} {}", style("ℹ").blue().bold(), message);
+    if !is_normal_output() {
+        return;
+    }
+    
+    crate::progress::suspend(|| println!("{} {}", style("ℹ").blue().bold(), message));
+}
+
+/// Expand a `-` among package arguments into the names read from stdin, one per line.
+/// Blank lines and `#` comments are skipped, and porcelain records such as
+/// `available\tripgrep\t14.1.0` contribute their name field. Reading stdin leaves nothing to
+/// answer a confirmation prompt with, so it needs `--yes` or `--dry-run`.
+pub fn expand_stdin_packages(packages: &[String], confirmed: bool) -> crate::Result<Vec<String>> {
+    if !packages.iter().any(|package| package == "-") {
+        return Ok(packages.to_vec());
+    }
+    if !confirmed {
+        return Err(crate::Error::Config(t!("stdin-needs-yes")));
+    }
+    
+    let mut input = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut input)?;
+    
+    let mut expanded = Vec::new();
+    for package in packages {
+        if package != "-" {
+            expanded.push(package.clone());
+            continue;
+        }
+        for line in input.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let name = match line.split('\t').nth(1) {
+                Some(name) => name,
+                None => line,
+            };
+            if !expanded.iter().any(|existing| existing == name) {
+                expanded.push(name.to_string());
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+/// Warn that a package is deprecated, with the reason and successor when the definition
+/// gives them
+pub fn warn_if_deprecated(package: &crate::package::Package) {
+    let Some(deprecation) = &package.deprecated else {
+        return;
+    };
+    
+    print_porcelain(&["deprecated", &package.name, deprecation.successor.as_deref().unwrap_or("")]);
+    match &deprecation.reason {
+        Some(reason) => print_warning(&t!("package-deprecated-reason", package = package.name.as_str(), reason = reason.as_str())),
+        None => print_warning(&t!("package-deprecated", package = package.name.as_str())),
+    }
+    if let Some(successor) = &deprecation.successor {
+        print_info(&t!("package-deprecated-successor", successor = successor.as_str()));
+    }
+}
+
+/// Print packages as a table of name, version, method, status and categories, adding the
+/// description when `detailed`. `installed` holds the install status of each package.
+/// Occurrences of `highlight` in names and descriptions are emphasized.
+pub fn print_package_table(packages: &[(crate::package::Package, crate::package::InstallStatus)], detailed: bool, header: bool, highlight: Option<&str>) {
+    let mut headers = vec![
+        t!("table-name"),
+        t!("table-version"),
+        t!("table-method"),
+        t!("table-status"),
+        t!("table-categories"),
+    ];
+    if detailed {
+        headers.push(t!("table-description"));
+    }
+    
+    let mut table = table::Table::new(headers);
+    
+    for (package, status) in packages {
+        let status = match status {
+            InstallStatus::Installed { .. } => style(t!("status-installed")).green().to_string(),
+            InstallStatus::UpdateAvailable { current, .. } => {
+                style(t!("status-update-available", current = current.as_str())).yellow().to_string()
+            }
+            InstallStatus::NotInstalled => style(t!("status-available")).dim().to_string(),
+            InstallStatus::Error { .. } => style(t!("status-error")).red().to_string(),
+        };
+        
+        let mut row = vec![
+            highlight_matches(&package.name, highlight, &console::Style::new().bold()),
+            package.version.clone(),
+            package.installation.method().to_string(),
+            status,
+            style(package.categories.join(", ")).cyan().to_string(),
+        ];
+        if detailed {
+            row.push(highlight_matches(&package.description, highlight, &console::Style::new()));
+        }
+        
+        table.add_row(row);
+    }
+    
+    table.print(header);
+}
+
+/// Render `text` in `base` style, with every case-insensitive occurrence of `query` also in
+/// bold yellow. Without colors (`--no-color` or no terminal) the text is returned unchanged.
+pub fn highlight_matches(text: &str, query: Option<&str>, base: &console::Style) -> String {
+    let query: Vec<char> = query.unwrap_or_default().to_lowercase().chars().collect();
+    if query.is_empty() {
+        return base.apply_to(text).to_string();
+    }
+    
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some((start, end)) = find_ignoring_case(rest, &query) {
+        if start > 0 {
+            rendered.push_str(&base.apply_to(&rest[..start]).to_string());
+        }
+        rendered.push_str(&base.clone().yellow().bold().apply_to(&rest[start..end]).to_string());
+        rest = &rest[end..];
+    }
+    rendered.push_str(&base.apply_to(rest).to_string());
+    rendered
+}
+
+/// Byte range of the first occurrence of the lowercased `query` in `text`, comparing
+/// lowercased characters so the range stays on character boundaries of the original text
+fn find_ignoring_case(text: &str, query: &[char]) -> Option<(usize, usize)> {
+    text.char_indices().find_map(|(start, _)| {
+        let mut lowered = text[start..]
+            .char_indices()
+            .flat_map(|(offset, c)| c.to_lowercase().map(move |l| (start + offset + c.len_utf8(), l)));
+        let mut end = start;
+        for expected in query {
+            match lowered.next() {
+                Some((next_end, c)) if c == *expected => end = next_end,
+                _ => return None,
+            }
+        }
+        Some((start, end))
+    })
+}
+
+/// After installing or upgrading packages with kernel modules, tell the user how to get
+/// them loaded: a reboot when the running kernel was replaced, otherwise offer to have DKMS
+/// build them for the running kernel now. `--yes` accepts the offer.
+pub async fn kernel_module_notice(app: &App, packages: &[&crate::package::Package], assume_yes: bool) -> Result<()> {
+    use crate::package::requirements;
+    use std::io::{IsTerminal, Write};
+    
+    let names: Vec<&str> = packages.iter().filter(|package| package.kernel_module).map(|package| package.name.as_str()).collect();
+    if names.is_empty() || crate::simulate::is_enabled() {
+        return Ok(());
+    }
+    let names = names.join(", ");
+    
+    if requirements::running_kernel_installed() == Some(false) {
+        print_warning(&t!("kernel-reboot-needed", packages = names.as_str()));
+        print_porcelain(&["reboot-required", &names]);
+        return Ok(());
+    }
+    
+    if !requirements::on_path("dkms", None) {
+        return Ok(());
+    }
+    
+    let accepted = if assume_yes {
+        true
+    } else if std::io::stdin().is_terminal() && is_normal_output() {
+        print!("{} [y/N]: ", t!("kernel-dkms-confirm", packages = names.as_str()));
+        std::io::stdout().flush()?;
+        
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+    } else {
+        false
+    };
+    
+    if !accepted {
+        print_info(&t!("kernel-dkms-hint"));
+        return Ok(());
+    }
+    
+    let mut command = crate::privilege::privileged("dkms");
+    command.arg("autoinstall").interactive();
+    let started = std::time::Instant::now();
+    let output = app.repository.runner().output(&command).await?;
+    history::record_command(&command.command_line(), started.elapsed(), &output);
+    let status = output.status;
+    if status.success() {
+        print_success(&t!("kernel-dkms-done"));
+    } else {
+        print_warning(&t!("kernel-dkms-failed", status = status.to_string()));
+    }
+    
+    Ok(())
+}
+
+/// Remind the user to log out and back in when post-install added them to groups this
+/// session doesn't have yet
+pub async fn relogin_notice(app: &App) -> Result<()> {
+    if crate::simulate::is_enabled() {
+        return Ok(());
+    }
+    
+    let groups = app.repository.pending_relogin_groups().await?;
+    if groups.is_empty() {
+        return Ok(());
+    }
+    
+    let groups = groups.join(", ");
+    print_warning(&t!("relogin-needed", groups = groups.as_str()));
+    print_porcelain(&["relogin-required", &groups]);
+    Ok(())
+}
+
+/// Print a pacman-style transaction summary with per-package and total sizes
+pub async fn print_size_summary(app: &App, packages: &[crate::package::Package]) {
+    use crate::package::size::{estimate_sizes, format_delta, format_size};
+    
+    let estimates = estimate_sizes(app.repository.runner().as_ref(), packages).await;
+    let unknown = "?".to_string();
+    
+    println!("{}", style(format!(
+        "{:<24} {:<14} {:>14} {:>14}",
+        t!("size-column-package"), t!("size-column-version"), t!("size-column-download"), t!("size-column-net-change")
+    )).bold());
+    
+    let mut total_download = 0u64;
+    let mut total_delta = 0i64;
+    let mut incomplete = false;
+    
+    for package in packages {
+        let estimate = estimates.get(&package.name).cloned().unwrap_or_default();
+        
+        let download = estimate.download.map(format_size).unwrap_or_else(|| unknown.clone());
+        let delta = estimate.delta().map(format_delta).unwrap_or_else(|| unknown.clone());
+        
+        total_download += estimate.download.unwrap_or(0);
+        total_delta += estimate.delta().unwrap_or(0);
+        incomplete |= estimate.download.is_none() || estimate.delta().is_none();
+        
+        println!("{:<24} {:<14} {:>14} {:>14}", package.name, package.version, download, delta);
+    }
+    
+    println!();
+    println!("{:<22}{:>14}", t!("size-total-download"), format_size(total_download));
+    println!("{:<22}{:>14}", t!("size-net-upgrade"), format_delta(total_delta));
+    
+    if incomplete {
+        println!("{}", style(t!("size-incomplete")).dim());
+    }
+    println!();
 }
\ No newline at end of file
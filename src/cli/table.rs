@@ -0,0 +1,98 @@
+//! Aligned table output that fits the terminal width
+
+use console::{measure_text_width, style, truncate_str, Term};
+
+/// Gap between columns
+const SEPARATOR: &str = "  ";
+
+/// Narrowest a column is shrunk to when the table is too wide for the terminal
+const MIN_WIDTH: usize = 8;
+
+/// A table of pre-styled cells. Widths are measured without ANSI codes, so cells may be colored.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: Vec<String>) -> Self {
+        Self { headers, rows: Vec::new() }
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// Print the table, shrinking the widest columns to fit the terminal
+    pub fn print(&self, header: bool) {
+        let max_width = Term::stdout().size_checked().map(|(_, cols)| cols as usize);
+        let widths = self.column_widths(header, max_width);
+
+        if header {
+            let cells: Vec<String> = self.headers.iter().map(|h| style(h).bold().to_string()).collect();
+            println!("{}", render_row(&cells, &widths));
+        }
+
+        for row in &self.rows {
+            println!("{}", render_row(row, &widths));
+        }
+    }
+
+    fn column_widths(&self, header: bool, max_width: Option<usize>) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .headers
+            .iter()
+            .map(|h| if header { measure_text_width(h) } else { 0 })
+            .collect();
+
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(measure_text_width(cell));
+            }
+        }
+
+        let Some(max_width) = max_width else {
+            return widths;
+        };
+
+        // Take space from the widest column until the table fits (or nothing can shrink)
+        let separators = SEPARATOR.len() * widths.len().saturating_sub(1);
+        while widths.iter().sum::<usize>() + separators > max_width {
+            let Some((index, &widest)) = widths.iter().enumerate().max_by_key(|(_, w)| **w) else {
+                break;
+            };
+            if widest <= MIN_WIDTH {
+                break;
+            }
+            widths[index] = widest - 1;
+        }
+
+        widths
+    }
+}
+
+fn render_row(cells: &[String], widths: &[usize]) -> String {
+    let last = widths.len().saturating_sub(1);
+
+    cells
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(i, (cell, &width))| {
+            let cell_width = measure_text_width(cell);
+            let cell = if cell_width > width {
+                truncate_str(cell, width, "…")
+            } else {
+                cell.into()
+            };
+
+            if i == last {
+                cell.to_string()
+            } else {
+                let padding = width.saturating_sub(measure_text_width(&cell));
+                format!("{}{}", cell, " ".repeat(padding))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(SEPARATOR)
+}
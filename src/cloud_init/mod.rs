@@ -0,0 +1,225 @@
+//! cloud-init user-data generated from package definitions
+//!
+//! A fresh cloud VM has no ArchBox, so each definition is translated into what cloud-init
+//! does natively: pacman packages go into `packages`, scripts and drop-ins into
+//! `write_files`, and downloads, Flatpaks, post-install commands and services into
+//! `runcmd`. Everything runs as root, so installs are system-wide. AUR and source packages,
+//! and per-user settings, have no equivalent and are reported as skipped.
+
+use crate::package::{
+    drop_in_header, installer::{verify_script, FLATHUB_REMOTE, FLATHUB_REPO_URL}, parse_service, platform::{BinaryAsset, Platform}, DependencyType,
+    InstallScope, Installation, Package,
+};
+use crate::shell::quote;
+use crate::Result;
+use serde::Serialize;
+
+/// Where generated scripts are written on the VM
+const SCRIPT_DIR: &str = "/var/lib/archbox/cloud-init";
+
+/// The parts of a `#cloud-config` document ArchBox fills in
+#[derive(Debug, Default, Serialize)]
+pub struct UserData {
+    pub package_update: bool,
+    pub package_upgrade: bool,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub write_files: Vec<WriteFile>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub runcmd: Vec<String>,
+}
+
+/// A `write_files` entry
+#[derive(Debug, Serialize)]
+pub struct WriteFile {
+    pub path: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+}
+
+/// Something from a definition that user-data can't express
+#[derive(Debug, Clone)]
+pub struct Skipped {
+    pub package: String,
+    pub reason: String,
+}
+
+impl UserData {
+    /// Translate `packages`, in install order, for a VM running on `platform`. Returns the
+    /// user-data and what had to be left out.
+    pub fn from_packages(packages: &[Package], platform: &Platform) -> (UserData, Vec<Skipped>) {
+        let mut user_data = UserData {
+            package_update: true,
+            package_upgrade: true,
+            ..Default::default()
+        };
+        let mut skipped = Vec::new();
+
+        for package in packages {
+            let mut skip = |reason: &str| {
+                skipped.push(Skipped { package: package.name.clone(), reason: reason.to_string() });
+            };
+
+            for dep in package.get_dependencies(DependencyType::System, platform) {
+                user_data.add_package(&dep.name);
+            }
+
+            match &package.installation {
+                Installation::Pacman { packages, .. } => {
+                    for name in packages {
+                        user_data.add_package(name);
+                    }
+                }
+                Installation::Aur { .. } => {
+                    skip("AUR packages need an unprivileged user to build");
+                    continue;
+                }
+                Installation::Source { .. } => {
+                    skip("source builds aren't supported");
+                    continue;
+                }
+                Installation::Binary { url, checksum, urls, checksums, executable, fallbacks, .. } => {
+                    let (url, checksum) = match platform.resolve_binary(url, checksum.as_ref(), urls.as_ref(), checksums.as_ref(), fallbacks) {
+                        Ok(BinaryAsset::Download { url, checksum, .. }) => (url, checksum),
+                        Ok(BinaryAsset::Source { .. }) => {
+                            skip(&format!("there is no {} build and source builds aren't supported", platform.arch));
+                            continue;
+                        }
+                        Err(e) => {
+                            skip(&e.to_string());
+                            continue;
+                        }
+                    };
+                    let path = package.install_path(InstallScope::System).unwrap_or_default();
+                    user_data.runcmd.push(download_command(&url, checksum.as_deref(), &path.display().to_string(), *executable));
+                }
+                Installation::AppImage { url, checksum, urls, checksums, .. } => {
+                    let url = match platform.resolve_url(url, urls.as_ref()) {
+                        Ok(url) => url,
+                        Err(e) => {
+                            skip(&e.to_string());
+                            continue;
+                        }
+                    };
+                    let checksum = platform.resolve_checksum(checksum.as_ref(), checksums.as_ref());
+                    let path = package.install_path(InstallScope::System).unwrap_or_default();
+                    user_data.runcmd.push(download_command(&url, checksum.as_deref(), &path.display().to_string(), true));
+                }
+                Installation::Script { script, interpreter, script_sha256 } => {
+                    if let Err(e) = verify_script(script, script_sha256.as_deref()) {
+                        skip(&e.to_string());
+                        continue;
+                    }
+                    let path = format!("{}/{}.sh", SCRIPT_DIR, package.name);
+                    user_data.write_files.push(WriteFile {
+                        path: path.clone(),
+                        content: script.clone(),
+                        permissions: Some("0755".to_string()),
+                    });
+                    user_data.runcmd.push(format!("{} {}", interpreter, quote(&path)));
+                }
+                Installation::Flatpak { id, remote, remote_url, .. } => {
+                    user_data.add_package("flatpak");
+                    let remote = remote.as_deref().unwrap_or(FLATHUB_REMOTE);
+                    let Some(url) = remote_url.as_deref().or((remote == FLATHUB_REMOTE).then_some(FLATHUB_REPO_URL)) else {
+                        skip(&format!("Flatpak remote '{}' has no remote_url", remote));
+                        continue;
+                    };
+                    user_data.runcmd.push(format!(
+                        "flatpak remote-add --system --if-not-exists {} {}",
+                        quote(remote),
+                        quote(url)
+                    ));
+                    user_data.runcmd.push(format!(
+                        "flatpak install -y --noninteractive --system {} {}",
+                        quote(remote),
+                        quote(id)
+                    ));
+                }
+            }
+
+            if let Some(post_install) = &package.post_install {
+                user_data.runcmd.extend(post_install.commands.iter().flatten().cloned());
+
+                for drop_in in post_install.drop_ins(&package.name) {
+                    user_data.write_files.push(WriteFile {
+                        path: drop_in.path.display().to_string(),
+                        content: format!("{}{}", drop_in_header(&package.name), drop_in.content),
+                        permissions: None,
+                    });
+                }
+
+                // User units are enabled for every user, since there is no one user to pick
+                let scope = package.scope.unwrap_or(InstallScope::System);
+                for spec in post_install.enable_services.iter().flatten() {
+                    let (service, user) = parse_service(spec, scope);
+                    user_data.runcmd.push(if user {
+                        format!("systemctl --global enable {}", quote(service))
+                    } else {
+                        format!("systemctl enable --now {}", quote(service))
+                    });
+                }
+
+                if post_install.config_files.is_some() || post_install.environment.is_some() || post_install.user_groups.is_some() {
+                    skip("per-user config files, environment variables and groups");
+                }
+                if post_install.kernel_params.is_some() {
+                    skip("kernel parameters");
+                }
+                if post_install.firewall.is_some() {
+                    skip("firewall rules, since the firewall in use isn't known");
+                }
+            }
+        }
+
+        (user_data, skipped)
+    }
+
+    /// Run `script` once everything is installed, e.g. a profile's post-install script
+    pub fn add_script(&mut self, name: &str, script: &str) {
+        let path = format!("{}/{}.sh", SCRIPT_DIR, name);
+        self.write_files.push(WriteFile {
+            path: path.clone(),
+            content: script.to_string(),
+            permissions: Some("0755".to_string()),
+        });
+        self.runcmd.push(format!("sh {}", quote(&path)));
+    }
+
+    /// The `#cloud-config` document, with `header` lines as leading comments
+    pub fn render(&self, header: &[String]) -> Result<String> {
+        let mut output = String::from("#cloud-config\n");
+        for line in header {
+            output.push_str(&format!("# {}\n", line));
+        }
+        output.push_str(&serde_yaml::to_string(self)?);
+        Ok(output)
+    }
+
+    fn add_package(&mut self, name: &str) {
+        if !self.packages.iter().any(|package| package == name) {
+            self.packages.push(name.to_string());
+        }
+    }
+}
+
+/// Download `url` to `path`, verifying `checksum` when there is one
+fn download_command(url: &str, checksum: Option<&str>, path: &str, executable: bool) -> String {
+    let parent = std::path::Path::new(path).parent().map(|dir| dir.display().to_string()).unwrap_or_default();
+    let mut steps = vec![
+        format!("mkdir -p {}", quote(&parent)),
+        format!("curl -fsSL -o {} {}", quote(path), quote(url)),
+    ];
+    if let Some(checksum) = checksum {
+        steps.push(format!("echo {} | sha256sum -c -", quote(&format!("{}  {}", checksum, path))));
+    }
+    if executable {
+        steps.push(format!("chmod 755 {}", quote(path)));
+    }
+    steps.join(" && ")
+}
@@ -1,49 +1,66 @@
-use clap::Command;
-use clap_complete::{generate, Generator, Shell};
-use std::io;
+//! Shell completion scripts generated from the clap command definition
 
-pub fn generate_completions<G: Generator>(gen: G, app: &mut Command) {
-    generate(gen, app, app.get_name().to_string(), &mut io::stdout());
-}
+use crate::{Error, Result};
+use clap::CommandFactory;
+use clap_complete::{generate, Shell};
+use std::path::PathBuf;
 
-pub fn generate_shell_completions(shell: Shell) -> Result<String, Box<dyn std::error::Error>> {
+pub fn generate_shell_completions(shell: Shell) -> Result<String> {
     let mut app = crate::cli::Cli::command();
     let mut output = Vec::new();
     
+    generate(shell, &mut app, "archbox", &mut output);
+    
+    String::from_utf8(output)
+        .map_err(|e| Error::Config(format!("Generated completions are not valid UTF-8: {}", e)))
+}
+
+/// File name the shell's completion loader expects
+fn completion_file_name(shell: Shell) -> Option<&'static str> {
     match shell {
-        Shell::Bash => generate(clap_complete::shells::Bash, &mut app, "archbox", &mut output),
-        Shell::Zsh => generate(clap_complete::shells::Zsh, &mut app, "archbox", &mut output),
-        Shell::Fish => generate(clap_complete::shells::Fish, &mut app, "archbox", &mut output),
-        Shell::PowerShell => generate(clap_complete::shells::PowerShell, &mut app, "archbox", &mut output),
-        Shell::Elvish => generate(clap_complete::shells::Elvish, &mut app, "archbox", &mut output),
-        _ => return Err("Unsupported shell".into()),
+        Shell::Bash => Some("archbox"),
+        Shell::Zsh => Some("_archbox"),
+        Shell::Fish => Some("archbox.fish"),
+        _ => None,
+    }
+}
+
+/// Directory completions are loaded from, either system-wide or for the current user
+pub fn completion_dir(shell: Shell, system: bool) -> Option<PathBuf> {
+    if system {
+        return match shell {
+            Shell::Bash => Some(PathBuf::from("/usr/share/bash-completion/completions")),
+            Shell::Zsh => Some(PathBuf::from("/usr/share/zsh/site-functions")),
+            Shell::Fish => Some(PathBuf::from("/usr/share/fish/vendor_completions.d")),
+            _ => None,
+        };
     }
     
-    Ok(String::from_utf8(output)?)
+    match shell {
+        Shell::Bash => dirs::data_dir().map(|d| d.join("bash-completion/completions")),
+        Shell::Zsh => dirs::data_dir().map(|d| d.join("zsh/site-functions")),
+        Shell::Fish => dirs::config_dir().map(|d| d.join("fish/completions")),
+        _ => None,
+    }
 }
 
-pub fn install_completions(shell: Shell) -> Result<(), Box<dyn std::error::Error>> {
+/// Write completions for `shell` into its completion directory, returning the file written
+pub fn install_completions(shell: Shell, system: bool) -> Result<PathBuf> {
     let completions = generate_shell_completions(shell)?;
     
-    let completion_dir = match shell {
-        Shell::Bash => dirs::home_dir()
-            .map(|d| d.join(".local/share/bash-completion/completions")),
-        Shell::Zsh => dirs::home_dir()
-            .map(|d| d.join(".local/share/zsh/site-functions")),
-        Shell::Fish => dirs::home_dir()
-            .map(|d| d.join(".config/fish/completions")),
-        _ => None,
+    let (dir, file_name) = match (completion_dir(shell, system), completion_file_name(shell)) {
+        (Some(dir), Some(file_name)) => (dir, file_name),
+        _ => {
+            return Err(Error::Config(format!(
+                "No standard completion directory for {}; redirect `archbox completions {}` to a file instead",
+                shell, shell
+            )));
+        }
     };
     
-    if let Some(dir) = completion_dir {
-        std::fs::create_dir_all(&dir)?;
-        let file_path = dir.join("archbox");
-        std::fs::write(file_path, completions)?;
-        println!("Completions installed for {:?}", shell);
-    } else {
-        println!("Manual installation required for {:?}:", shell);
-        println!("{}", completions);
-    }
+    std::fs::create_dir_all(&dir)?;
+    let file_path = dir.join(file_name);
+    std::fs::write(&file_path, completions)?;
     
-    Ok(())
-}
\ No newline at end of file
+    Ok(file_path)
+}
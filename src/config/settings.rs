@@ -1,17 +1,30 @@
-use crate::{Error, Result};
+use crate::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use tokio::fs;
+use std::time::Duration;
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub package_paths: Vec<PathBuf>,
     pub aur_helper: Option<String>,
+    /// Profile that `archbox sync` makes this machine match
+    #[serde(default)]
+    pub profile: Option<String>,
     pub installation: InstallationConfig,
     pub repository: RepositoryConfig,
     pub ui: UiConfig,
+    /// Custom variables available to config file templates
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
 }
 
 /// Installation configuration
@@ -21,20 +34,89 @@ pub struct InstallationConfig {
     pub temp_dir: Option<PathBuf>,
     #[serde(default = "default_true")]
     pub verify_checksums: bool,
+    /// Back up config files before post-install overwrites them; purged files are always backed up
     #[serde(default = "default_true")]
     pub create_backups: bool,
+    /// Seconds an HTTP request may take, or a download may wait to connect or for more data;
+    /// 0 for no limit
     #[serde(default = "default_download_timeout")]
     pub download_timeout: u64,
+    /// Seconds each build or install command of a source or AUR build may run; 0 for no limit
+    #[serde(default = "default_build_timeout")]
+    pub build_timeout: u64,
+    /// Seconds an installation script or post-install command may run; 0 for no limit
+    #[serde(default = "default_script_timeout")]
+    pub script_timeout: u64,
+    /// Seconds to wait for another package manager to release pacman's database lock
+    /// before an install fails; 0 fails at once
+    #[serde(default = "default_lock_wait")]
+    pub lock_wait: u64,
+    /// Show the PKGBUILD and ask for confirmation before building AUR packages
+    #[serde(default = "default_true")]
+    pub review_pkgbuild: bool,
+    /// Versions of each binary kept in `~/.local/opt` for rollback, including the active one
+    #[serde(default = "default_keep_versions")]
+    pub keep_versions: usize,
+    /// Remove build dependencies installed for a source build once it finishes
+    #[serde(default)]
+    pub remove_build_deps: bool,
+    /// Reuse cached source builds of the same commit and build commands
+    #[serde(default = "default_true")]
+    pub build_cache: bool,
+    /// Route source builds through ccache/sccache when they are installed
+    #[serde(default = "default_true")]
+    pub compiler_cache: bool,
+    /// Mirror prefixes for download URLs, keyed by the upstream prefix they replace
+    #[serde(default)]
+    pub artifact_mirrors: HashMap<String, Vec<String>>,
+    /// Add the user to the groups packages ask for; off where accounts are managed elsewhere
+    #[serde(default = "default_true")]
+    pub modify_user_groups: bool,
+    /// Whether installs add optional dependencies, whatever the run; unset asks in
+    /// interactive runs and skips them otherwise
+    #[serde(default)]
+    pub install_optional: Option<OptionalPolicy>,
+}
+
+impl InstallationConfig {
+    pub fn download_limit(&self) -> Option<Duration> {
+        limit(self.download_timeout)
+    }
+    
+    pub fn build_limit(&self) -> Option<Duration> {
+        limit(self.build_timeout)
+    }
+    
+    pub fn script_limit(&self) -> Option<Duration> {
+        limit(self.script_timeout)
+    }
+}
+
+/// A timeout in seconds, where 0 means none
+fn limit(seconds: u64) -> Option<Duration> {
+    (seconds > 0).then(|| Duration::from_secs(seconds))
 }
 
 /// Repository configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryConfig {
     pub update_url: Option<String>,
+    /// Other URLs serving the same definitions, tried when `update_url` fails
+    #[serde(default)]
+    pub mirrors: Vec<String>,
     #[serde(default = "default_update_interval")]
     pub update_interval: u64,
     #[serde(default)]
     pub auto_update: bool,
+    /// Run a full `pacman -Syu` as part of `archbox update`
+    #[serde(default)]
+    pub system_upgrade: bool,
+    /// Packages held at their current version during updates
+    #[serde(default)]
+    pub pinned: Vec<String>,
+    /// Packages excluded from update checks and upgrades
+    #[serde(default)]
+    pub ignore_packages: Vec<String>,
 }
 
 /// UI configuration
@@ -46,6 +128,141 @@ pub struct UiConfig {
     pub show_progress: bool,
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    /// Also write logs to this file (overridden by `--log-file`)
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// Level written to the log file, independent of terminal verbosity
+    #[serde(default = "default_log_file_level")]
+    pub log_file_level: String,
+    /// Size at which the log file is rotated
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Rotated log files kept alongside the current one
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: usize,
+    /// Language for CLI messages, e.g. `de-DE` (defaults to the `LANG` locale)
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/// Notifications sent after transactions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    /// URL that receives a JSON summary of each transaction; may be a `secret://` reference
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Which transactions to notify about
+    #[serde(default)]
+    pub on: NotifyOn,
+}
+
+/// TLS and proxy settings for every download and API request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// PEM file of certificate authorities trusted on top of the system's, e.g. the one a
+    /// TLS-intercepting proxy signs with
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM client certificate presented to servers that require one
+    #[serde(default)]
+    pub client_certificate: Option<PathBuf>,
+    /// PKCS#8 PEM private key of `client_certificate`
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+    /// Ignore `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` and connect directly
+    #[serde(default)]
+    pub ignore_system_proxy: bool,
+}
+
+/// HTTP API served by `archbox serve`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Address and port to listen on
+    #[serde(default = "default_server_listen")]
+    pub listen: String,
+    /// Bearer token required by endpoints that change the system; may be a `secret://`
+    /// reference. Without one, those endpoints are disabled.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: default_server_listen(),
+            token: None,
+        }
+    }
+}
+
+fn default_server_listen() -> String {
+    "127.0.0.1:7878".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    /// Every transaction that changed something, and every failure
+    #[default]
+    Always,
+    /// Only transactions that failed
+    Failure,
+}
+
+impl std::str::FromStr for NotifyOn {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "always" => Ok(NotifyOn::Always),
+            "failure" => Ok(NotifyOn::Failure),
+            _ => Err(format!("'{}' is not one of: always, failure", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for NotifyOn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifyOn::Always => write!(f, "always"),
+            NotifyOn::Failure => write!(f, "failure"),
+        }
+    }
+}
+
+/// What installs do with the optional dependencies of the packages they install
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionalPolicy {
+    /// Install them along with the package
+    Always,
+    /// Leave them out
+    Never,
+    /// Ask which ones to install
+    Ask,
+}
+
+impl std::str::FromStr for OptionalPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "always" => Ok(OptionalPolicy::Always),
+            "never" => Ok(OptionalPolicy::Never),
+            "ask" => Ok(OptionalPolicy::Ask),
+            _ => Err(format!("'{}' is not one of: always, never, ask", value)),
+        }
+    }
+}
+
+impl std::fmt::Display for OptionalPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionalPolicy::Always => write!(f, "always"),
+            OptionalPolicy::Never => write!(f, "never"),
+            OptionalPolicy::Ask => write!(f, "ask"),
+        }
+    }
 }
 
 impl Default for Config {
@@ -61,28 +278,55 @@ impl Default for Config {
                 PathBuf::from("/etc/archbox/packages"),
             ],
             aur_helper: None,
+            profile: None,
             installation: InstallationConfig {
                 binary_dir,
                 temp_dir: None,
                 verify_checksums: true,
                 create_backups: true,
                 download_timeout: 300,
+                build_timeout: 7200,
+                script_timeout: 1800,
+                lock_wait: 300,
+                review_pkgbuild: true,
+                keep_versions: 3,
+                remove_build_deps: false,
+                build_cache: true,
+                compiler_cache: true,
+                artifact_mirrors: HashMap::new(),
+                modify_user_groups: true,
+                install_optional: None,
             },
             repository: RepositoryConfig {
-                update_url: Some("https://raw.githubusercontent.com/example/archbox-packages/main/packages.yaml".to_string()),
+                update_url: None,
+                mirrors: Vec::new(),
                 update_interval: 24,
                 auto_update: false,
+                system_upgrade: false,
+                pinned: Vec::new(),
+                ignore_packages: Vec::new(),
             },
             ui: UiConfig {
                 use_colors: true,
                 show_progress: true,
                 log_level: "info".to_string(),
+                log_file: None,
+                log_file_level: default_log_file_level(),
+                log_max_size_mb: default_log_max_size_mb(),
+                log_max_files: default_log_max_files(),
+                language: None,
             },
+            variables: HashMap::new(),
+            notifications: NotificationsConfig::default(),
+            server: ServerConfig::default(),
+            network: NetworkConfig::default(),
         }
     }
 }
 
 impl Config {
+    /// Load the config file, or the defaults when there is none yet. Defaults are not
+    /// written back; `archbox setup` creates the file.
     pub fn load() -> Result<Self> {
         let config_path = get_config_path();
         
@@ -91,12 +335,15 @@ impl Config {
             let config: Config = serde_yaml::from_str(&content)?;
             Ok(config)
         } else {
-            let config = Config::default();
-            config.save()?;
-            Ok(config)
+            Ok(Config::default())
         }
     }
     
+    /// Whether a config file has been written, i.e. this isn't the first run
+    pub fn exists() -> bool {
+        get_config_path().exists()
+    }
+    
     pub fn save(&self) -> Result<()> {
         let config_path = get_config_path();
         
@@ -140,11 +387,42 @@ pub fn get_config_dir() -> PathBuf {
         })
 }
 
+/// Directory for persistent state such as the installed package database
+pub fn get_state_dir() -> PathBuf {
+    dirs::state_dir()
+        .map(|dir| dir.join("archbox"))
+        .unwrap_or_else(|| {
+            let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push(".local/state");
+            path.push("archbox");
+            path
+        })
+}
+
+/// Directory for data that can be regenerated, such as build caches
+pub fn get_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .map(|dir| dir.join("archbox"))
+        .unwrap_or_else(|| {
+            let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+            path.push(".cache");
+            path.push("archbox");
+            path
+        })
+}
+
 fn get_config_path() -> PathBuf {
     get_config_dir().join("config.yaml")
 }
 
 fn default_true() -> bool { true }
 fn default_download_timeout() -> u64 { 300 }
+fn default_build_timeout() -> u64 { 7200 }
+fn default_script_timeout() -> u64 { 1800 }
+fn default_lock_wait() -> u64 { 300 }
+fn default_keep_versions() -> usize { 3 }
 fn default_update_interval() -> u64 { 24 }
-fn default_log_level() -> String { "info".to_string() }
\ No newline at end of file
+fn default_log_level() -> String { "info".to_string() }
+fn default_log_file_level() -> String { "debug".to_string() }
+fn default_log_max_size_mb() -> u64 { 10 }
+fn default_log_max_files() -> usize { 3 }
\ No newline at end of file
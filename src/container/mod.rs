@@ -0,0 +1,155 @@
+//! Container images built from the same definitions as bare-metal installs
+//!
+//! A build context holds a Containerfile, a copy of the running `archbox` binary and the
+//! definitions of every package the image needs, so the image installs exactly what
+//! `archbox sync` would install on a machine.
+
+use crate::{package::Package, Error, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::process::Command;
+
+pub const CONTAINERFILE: &str = "Containerfile";
+pub const DEFAULT_BASE: &str = "archlinux:latest";
+
+const DEFINITIONS_FILE: &str = "packages.yaml";
+const SCRIPT_FILE: &str = "post-install.sh";
+
+/// What an image is built from
+#[derive(Debug, Clone, Default)]
+pub struct ImageSpec {
+    /// Base image
+    pub base: String,
+    /// Profile to apply with `archbox sync`
+    pub profile: Option<String>,
+    /// Packages to install on top of the profile
+    pub packages: Vec<String>,
+    /// The profile's post-install script, run last
+    pub post_install_script: Option<String>,
+}
+
+/// Tools that can build a Containerfile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Engine {
+    Podman,
+    Docker,
+    Buildah,
+}
+
+impl Engine {
+    pub fn program(&self) -> &'static str {
+        match self {
+            Engine::Podman => "podman",
+            Engine::Docker => "docker",
+            Engine::Buildah => "buildah",
+        }
+    }
+
+    /// The first engine on `PATH`, preferring podman, then docker, then buildah
+    pub fn detect() -> Option<Engine> {
+        [Engine::Podman, Engine::Docker, Engine::Buildah]
+            .into_iter()
+            .find(|engine| on_path(engine.program()))
+    }
+
+    /// Build the context in `context` and tag the result as `tag`. Build output goes
+    /// straight to the terminal.
+    pub async fn build(&self, context: &Path, tag: &str) -> Result<()> {
+        let subcommand = match self {
+            Engine::Buildah => "bud",
+            _ => "build",
+        };
+
+        let status = Command::new(self.program())
+            .arg(subcommand)
+            .args(["-t", tag, "-f"])
+            .arg(context.join(CONTAINERFILE))
+            .arg(context)
+            .status()
+            .await?;
+
+        if !status.success() {
+            return Err(Error::CommandFailed {
+                message: format!("{} {} exited with {}", self.program(), subcommand, status),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Render the Containerfile for `spec`
+pub fn containerfile(spec: &ImageSpec) -> String {
+    let mut lines = vec![
+        "# Generated by archbox containerize".to_string(),
+        format!("FROM {}", spec.base),
+        String::new(),
+        "RUN pacman -Syu --noconfirm".to_string(),
+        "COPY archbox /usr/local/bin/archbox".to_string(),
+        format!("COPY {} /etc/archbox/packages/{}", DEFINITIONS_FILE, DEFINITIONS_FILE),
+        String::new(),
+    ];
+
+    // Installs are system-wide so the tools are on PATH for whoever runs the image
+    if let Some(profile) = &spec.profile {
+        lines.push(format!(
+            "RUN archbox --no-auto-update sync --profile {} --no-refresh --yes --system",
+            profile
+        ));
+    }
+    if !spec.packages.is_empty() {
+        lines.push(format!(
+            "RUN archbox --no-auto-update install --yes --system {}",
+            spec.packages.join(" ")
+        ));
+    }
+    if spec.post_install_script.is_some() {
+        lines.push(format!("COPY {} /tmp/{}", SCRIPT_FILE, SCRIPT_FILE));
+        lines.push(format!("RUN sh /tmp/{0} && rm /tmp/{0}", SCRIPT_FILE));
+    }
+
+    lines.push(String::new());
+    lines.push("RUN pacman -Scc --noconfirm".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Write a build context for `spec` to `dir`: the Containerfile, this `archbox` binary,
+/// the `definitions` the image installs from, and the post-install script
+pub async fn write_context(dir: &Path, spec: &ImageSpec, definitions: &[Package]) -> Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    tokio::fs::write(dir.join(CONTAINERFILE), containerfile(spec)).await?;
+
+    let binary = std::env::current_exe()?;
+    tokio::fs::copy(&binary, dir.join("archbox")).await?;
+
+    let definitions: BTreeMap<&str, &Package> = definitions
+        .iter()
+        .map(|package| (package.name.as_str(), package))
+        .collect();
+    tokio::fs::write(dir.join(DEFINITIONS_FILE), serde_yaml::to_string(&definitions)?).await?;
+
+    if let Some(script) = &spec.post_install_script {
+        tokio::fs::write(dir.join(SCRIPT_FILE), script).await?;
+    }
+
+    Ok(())
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
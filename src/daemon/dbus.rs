@@ -0,0 +1,136 @@
+//! Session D-Bus service `org.archbox.Manager` at `/org/archbox/Manager`
+//!
+//! Desktop applets and frontends call the same operations as over the socket, typed for
+//! D-Bus, and follow long installs through the `Progress` and `Finished` signals:
+//!
+//! - `Search(s query) → a(ssss)`: name, version, description, installed version
+//! - `Install(as packages, b force) → a(ssbs)`: package, version, ok, error
+//! - `CheckUpdates() → a(sss)`: package, installed version, available version
+//! - `Progress(s operation, s package, u current, u total)`, before each package
+//! - `Finished(s operation, b ok, s message)`, once an install ends
+//!
+//! An installed version of `""` means not installed. Errors are returned as
+//! `org.freedesktop.DBus.Error.Failed` with archbox's message.
+
+use super::methods::{self, Outcome, Progress};
+use crate::App;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
+use zbus::{fdo, object_server::SignalContext};
+
+pub const BUS_NAME: &str = "org.archbox.Manager";
+pub const OBJECT_PATH: &str = "/org/archbox/Manager";
+
+struct Manager {
+    app: Arc<Mutex<App>>,
+}
+
+#[zbus::interface(name = "org.archbox.Manager")]
+impl Manager {
+    async fn search(&self, query: String) -> fdo::Result<Vec<(String, String, String, String)>> {
+        let app = self.app.lock().await;
+        let results = methods::search(&app, &query, None, false).await.map_err(failed)?;
+
+        Ok(results
+            .into_iter()
+            .map(|summary| (summary.name, summary.version, summary.description, summary.installed.unwrap_or_default()))
+            .collect())
+    }
+
+    async fn install(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        packages: Vec<String>,
+        force: bool,
+    ) -> fdo::Result<Vec<(String, String, bool, String)>> {
+        let mut app = self.app.lock().await;
+        let notifications = app.config.notifications.clone();
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Progress>();
+
+        // Forward progress as signals while the install runs; the sender is dropped with
+        // the install future, which ends the forwarding
+        let install = async move {
+            let args = packages.clone();
+            let operation = async {
+                app.repository.reload_state().await?;
+                methods::install(&mut app, &packages, force, Some(&sender)).await
+            };
+            super::transaction("daemon install", args, &notifications, operation).await
+        };
+        let forward = async {
+            while let Some(progress) = receiver.recv().await {
+                let _ = Self::progress(&ctxt, progress.operation, &progress.package, progress.current as u32, progress.total as u32).await;
+            }
+        };
+        let (outcome, _) = tokio::join!(install, forward);
+
+        let message = match &outcome {
+            Ok(outcomes) => finished_message(outcomes),
+            Err(e) => e.to_string(),
+        };
+        let ok = outcome.as_ref().is_ok_and(|outcomes| outcomes.iter().all(|outcome| outcome.ok));
+        let _ = Self::finished(&ctxt, "install", ok, &message).await;
+
+        Ok(outcome
+            .map_err(failed)?
+            .into_iter()
+            .map(|outcome| (outcome.package, outcome.version, outcome.ok, outcome.error.unwrap_or_default()))
+            .collect())
+    }
+
+    async fn check_updates(&self) -> fdo::Result<Vec<(String, String, String)>> {
+        let mut app = self.app.lock().await;
+        let outdated = methods::check_updates(&mut app, false).await.map_err(failed)?;
+
+        Ok(outdated
+            .into_iter()
+            .map(|outdated| (outdated.package, outdated.installed, outdated.available))
+            .collect())
+    }
+
+    #[zbus(property)]
+    async fn version(&self) -> String {
+        env!("CARGO_PKG_VERSION").to_string()
+    }
+
+    #[zbus(signal)]
+    async fn progress(ctxt: &SignalContext<'_>, operation: &str, package: &str, current: u32, total: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn finished(ctxt: &SignalContext<'_>, operation: &str, ok: bool, message: &str) -> zbus::Result<()>;
+}
+
+/// Claim the bus name and serve the interface until the returned connection is dropped
+pub async fn connect(app: Arc<Mutex<App>>) -> crate::Result<zbus::Connection> {
+    let connection = zbus::connection::Builder::session()
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, Manager { app }))
+        .map_err(bus_error)?
+        .build()
+        .await
+        .map_err(bus_error)?;
+
+    Ok(connection)
+}
+
+fn failed(error: crate::Error) -> fdo::Error {
+    fdo::Error::Failed(error.to_string())
+}
+
+fn bus_error(error: zbus::Error) -> crate::Error {
+    crate::Error::Config(format!("Could not register {} on the session bus: {}", BUS_NAME, error))
+}
+
+fn finished_message(outcomes: &[Outcome]) -> String {
+    let failed: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.ok)
+        .map(|outcome| outcome.package.as_str())
+        .collect();
+
+    if failed.is_empty() {
+        format!("Installed {} package(s)", outcomes.len())
+    } else {
+        format!("Failed to install {}", failed.join(", "))
+    }
+}
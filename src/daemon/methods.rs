@@ -0,0 +1,312 @@
+//! The operations the daemon exposes
+
+use super::protocol::{RpcError, INVALID_PARAMS, METHOD_NOT_FOUND};
+use crate::cli::commands::{list::ListArgs, search::SearchArgs, update};
+use crate::package::{ConfigConflict, Package};
+use crate::repository::remote;
+use crate::{config::Config, App};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::IsTerminal;
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    installed: bool,
+}
+
+#[derive(Deserialize)]
+struct InfoParams {
+    package: String,
+}
+
+#[derive(Default, Deserialize)]
+struct ListParams {
+    #[serde(default)]
+    installed: bool,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InstallParams {
+    packages: Vec<String>,
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Default, Deserialize)]
+struct UpdateParams {
+    /// Only report outdated packages
+    #[serde(default)]
+    check: bool,
+    /// Refresh the package definitions first
+    #[serde(default)]
+    definitions: bool,
+}
+
+#[derive(Serialize)]
+pub struct PackageSummary {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub categories: Vec<String>,
+    /// Recorded version when installed; `unknown` for packages installed outside archbox
+    pub installed: Option<String>,
+}
+
+/// Result of installing or updating one package
+#[derive(Serialize)]
+pub struct Outcome {
+    pub package: String,
+    pub version: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Outdated {
+    pub package: String,
+    pub installed: String,
+    pub available: String,
+}
+
+/// Sent before each package of an install or update is processed
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub operation: &'static str,
+    pub package: String,
+    /// 1-based position of the package
+    pub current: usize,
+    pub total: usize,
+}
+
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<Progress>;
+
+/// Whether a method changes the system, and so is recorded as a transaction
+pub fn is_mutating(method: &str, params: &Value) -> bool {
+    match method {
+        "install" => true,
+        "update" => params["check"] != json!(true),
+        _ => false,
+    }
+}
+
+/// Prepare an application instance for serving requests: nothing can prompt, so changed
+/// config files are kept with a backup of the packaged version and, without a terminal for
+/// sudo to ask on, root is requested through polkit
+pub fn configure(app: &mut App) {
+    app.repository.set_config_conflict(ConfigConflict::Backup);
+    if !std::io::stdin().is_terminal() {
+        crate::privilege::use_pkexec(true);
+    }
+}
+
+pub async fn dispatch(app: &mut App, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "ping" => Ok(json!("pong")),
+        "version" => Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "packages": app.repository.loader.packages().len(),
+        })),
+        "search" => {
+            let params: SearchParams = parse(params)?;
+            to_json(search(app, &params.query, params.category, params.installed).await?)
+        }
+        "info" => {
+            let params: InfoParams = parse(params)?;
+            Ok(info(app, &params.package).await?)
+        }
+        "list" => {
+            let params: ListParams = parse_or_default(params)?;
+            to_json(list(app, params.category, params.installed).await?)
+        }
+        "install" => {
+            let params: InstallParams = parse(params)?;
+            to_json(install(app, &params.packages, params.force, None).await?)
+        }
+        "update" => {
+            let params: UpdateParams = parse_or_default(params)?;
+            if params.check {
+                to_json(check_updates(app, params.definitions).await?)
+            } else {
+                to_json(update(app, params.definitions, None).await?)
+            }
+        }
+        "reload" => Ok(json!({ "packages": reload(app).await? })),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+    }
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(value).map_err(crate::Error::from)?)
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn parse_or_default<T: DeserializeOwned + Default>(params: Value) -> Result<T, RpcError> {
+    if params.is_null() {
+        Ok(T::default())
+    } else {
+        parse(params)
+    }
+}
+
+async fn summary(app: &App, package: &Package) -> crate::Result<PackageSummary> {
+    let installed = if app.repository.is_installed(&package.name).await? {
+        Some(app.repository
+            .installed_record(&package.name)
+            .map(|record| record.version.clone())
+            .unwrap_or_else(|| "unknown".to_string()))
+    } else {
+        None
+    };
+
+    Ok(PackageSummary {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        description: package.description.clone(),
+        categories: package.categories.clone(),
+        installed,
+    })
+}
+
+async fn summaries(app: &App, packages: &[Package]) -> crate::Result<Vec<PackageSummary>> {
+    let mut summaries = Vec::new();
+    for package in packages {
+        summaries.push(summary(app, package).await?);
+    }
+    Ok(summaries)
+}
+
+pub async fn search(app: &App, query: &str, category: Option<String>, installed: bool) -> crate::Result<Vec<PackageSummary>> {
+    let args = SearchArgs {
+        query: query.to_string(),
+        description: false,
+        detailed: false,
+        category,
+        installed,
+        no_header: false,
+    };
+    let packages = app.repository.search_packages(query, &args).await?;
+    summaries(app, &packages).await
+}
+
+/// A definition with the installed version, or `None` when not installed
+pub async fn info(app: &App, name: &str) -> crate::Result<Value> {
+    let resolved = app.repository.resolve_name(name)?;
+    let package = app.repository
+        .loader
+        .get_package(&resolved)
+        .ok_or_else(|| crate::Error::PackageNotFound(name.to_string()))?;
+
+    let mut value = serde_json::to_value(package)?;
+    value["installed"] = json!(summary(app, package).await?.installed);
+    Ok(value)
+}
+
+pub async fn list(app: &App, category: Option<String>, installed: bool) -> crate::Result<Vec<PackageSummary>> {
+    let args = ListArgs {
+        installed,
+        available: false,
+        category,
+        detailed: false,
+        no_header: false,
+        tree: false,
+    };
+    let mut packages = Vec::new();
+    for package in app.repository.list_packages(&args).await? {
+        if !installed || app.repository.is_installed(&package.name).await? {
+            packages.push(package);
+        }
+    }
+    summaries(app, &packages).await
+}
+
+/// Install packages with their dependencies. Failures of single packages are reported in
+/// their outcome rather than failing the whole request.
+pub async fn install(app: &mut App, names: &[String], force: bool, progress: Option<&ProgressSender>) -> crate::Result<Vec<Outcome>> {
+    let requested = names
+        .iter()
+        .map(|name| app.repository.resolve_name(name))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let packages = app.repository.resolve_packages(&requested).await?;
+
+    let mut outcomes = Vec::new();
+    for (i, package) in packages.iter().enumerate() {
+        report(progress, "install", package, i, packages.len());
+        let explicit = requested.contains(&package.name);
+        let result = app.repository.install_package(package, force, explicit).await;
+        outcomes.push(outcome(package, result));
+    }
+    Ok(outcomes)
+}
+
+/// Installed packages with a newer definition, optionally refreshing the definitions first
+pub async fn check_updates(app: &mut App, refresh: bool) -> crate::Result<Vec<Outdated>> {
+    Ok(outdated(app, refresh)
+        .await?
+        .into_iter()
+        .map(|(package, installed)| Outdated {
+            package: package.name,
+            installed,
+            available: package.version,
+        })
+        .collect())
+}
+
+pub async fn update(app: &mut App, refresh: bool, progress: Option<&ProgressSender>) -> crate::Result<Vec<Outcome>> {
+    let outdated = outdated(app, refresh).await?;
+
+    let mut outcomes = Vec::new();
+    for (i, (package, _)) in outdated.iter().enumerate() {
+        report(progress, "update", package, i, outdated.len());
+        let result = app.repository.install_package(package, true, false).await;
+        outcomes.push(outcome(package, result));
+    }
+    Ok(outcomes)
+}
+
+async fn outdated(app: &mut App, refresh: bool) -> crate::Result<Vec<(Package, String)>> {
+    if refresh && app.config.repository.update_url.is_some() {
+        let cache_file = remote::fetch_definitions(&app.config.repository).await?;
+        app.repository.loader.load_definition_file(&cache_file).await?;
+    }
+
+    let ignored = update::ignored_packages(&app.config, &[]);
+    update::outdated_packages(app, &ignored).await
+}
+
+fn report(progress: Option<&ProgressSender>, operation: &'static str, package: &Package, index: usize, total: usize) {
+    if let Some(progress) = progress {
+        let _ = progress.send(Progress {
+            operation,
+            package: package.name.clone(),
+            current: index + 1,
+            total,
+        });
+    }
+}
+
+/// Re-read the configuration and package definitions, e.g. after editing them. Returns the
+/// number of definitions loaded.
+pub async fn reload(app: &mut App) -> crate::Result<usize> {
+    *app = App::with_config(Config::load()?).await?;
+    configure(app);
+    Ok(app.repository.loader.packages().len())
+}
+
+fn outcome(package: &Package, result: crate::Result<()>) -> Outcome {
+    Outcome {
+        package: package.name.clone(),
+        version: package.version.clone(),
+        ok: result.is_ok(),
+        error: result.err().map(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,176 @@
+//! Long-running daemon serving JSON-RPC over a Unix socket
+//!
+//! `archbox daemon` loads the configuration and package definitions once and then answers
+//! requests from frontends such as a GUI or a status applet, which would otherwise spawn
+//! archbox (and reload every definition) per operation. Each line a client writes is one
+//! JSON-RPC 2.0 request and each line it reads back is the matching response:
+//!
+//! ```text
+//! → {"jsonrpc": "2.0", "id": 1, "method": "search", "params": {"query": "editor"}}
+//! ← {"jsonrpc": "2.0", "id": 1, "result": [{"name": "neovim", ...}]}
+//! ```
+//!
+//! Methods: `ping`, `version`, `search`, `info`, `list`, `install`, `update` and `reload`.
+//! Requests are handled one at a time; `install` and `update` are recorded in the history
+//! and reported to the webhook like the matching commands. The same operations can also be
+//! offered on the session bus, see [`dbus`].
+
+pub mod dbus;
+pub mod methods;
+pub mod protocol;
+
+use crate::{config::NotificationsConfig, App, Error, Result};
+use protocol::{Request, Response, RpcError, INVALID_REQUEST, PARSE_ERROR};
+use serde_json::Value;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// `$XDG_RUNTIME_DIR/archbox.sock`, or `archbox.sock` in the state directory when there is
+/// no runtime directory
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(crate::config::get_state_dir)
+        .join("archbox.sock")
+}
+
+/// Accept connections on the socket bound by [`bind`] until interrupted, then remove it.
+/// With `dbus`, `org.archbox.Manager` is served on the session bus as well.
+pub async fn serve(mut app: App, listener: UnixListener, socket: &Path, dbus: bool) -> Result<()> {
+    methods::configure(&mut app);
+    info!("Listening on {}", socket.display());
+
+    let app = Arc::new(Mutex::new(app));
+    let _bus = if dbus {
+        let connection = dbus::connect(Arc::clone(&app)).await;
+        if connection.is_err() {
+            let _ = std::fs::remove_file(socket);
+        }
+        Some(connection?)
+    } else {
+        None
+    };
+    let result = loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let app = Arc::clone(&app);
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(app, stream).await {
+                            debug!("Daemon connection closed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => break Err(Error::from(e)),
+            },
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+
+    let _ = std::fs::remove_file(socket);
+    result
+}
+
+/// Bind the socket, replacing a stale one left by a daemon that did not shut down cleanly.
+/// Only the current user may connect.
+pub async fn bind(socket: &Path) -> Result<UnixListener> {
+    if socket.exists() {
+        if UnixStream::connect(socket).await.is_ok() {
+            return Err(Error::Config(format!(
+                "Another daemon is already listening on {}",
+                socket.display()
+            )));
+        }
+        std::fs::remove_file(socket)?;
+    }
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let listener = UnixListener::bind(socket)?;
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+async fn handle_connection(app: Arc<Mutex<App>>, stream: UnixStream) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some(response) = handle_line(&app, &line).await else {
+            continue;
+        };
+        let mut body = serde_json::to_string(&response)?;
+        body.push('\n');
+        writer.write_all(body.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// Answer one request line; notifications (requests without an id) get no response
+async fn handle_line(app: &Mutex<App>, line: &str) -> Option<Response> {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            let code = if serde_json::from_str::<Value>(line).is_ok() { INVALID_REQUEST } else { PARSE_ERROR };
+            return Some(Response::new(Value::Null, Err(RpcError::new(code, e.to_string()))));
+        }
+    };
+    if request.jsonrpc.as_deref().is_some_and(|version| version != "2.0") {
+        let error = RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported");
+        return Some(Response::new(request.id.unwrap_or(Value::Null), Err(error)));
+    }
+
+    debug!("Daemon request: {}", request.method);
+    let mut app = app.lock().await;
+    let outcome = if methods::is_mutating(&request.method, &request.params) {
+        let notifications = app.config.notifications.clone();
+        let args = vec![request.params.to_string()];
+        let command = format!("daemon {}", request.method);
+        let operation = async {
+            app.repository.reload_state().await?;
+            methods::dispatch(&mut app, &request.method, request.params).await
+        };
+        transaction(&command, args, &notifications, operation).await
+    } else {
+        methods::dispatch(&mut app, &request.method, request.params).await
+    };
+
+    if let Err(error) = &outcome {
+        warn!("Daemon request {} failed: {}", request.method, error);
+    }
+    request.id.map(|id| Response::new(id, outcome))
+}
+
+/// Run a request that changes the system as its own transaction, e.g. `daemon install`,
+/// recorded in the history and reported to the webhook like the matching command. It
+/// queues behind commands holding the transaction lock, so `operation` should start by
+/// reloading the state they may have changed with [`Manager::reload_state`].
+///
+/// [`Manager::reload_state`]: crate::repository::Manager::reload_state
+pub(crate) async fn transaction<T, E: std::fmt::Display + From<crate::Error>>(
+    command: &str,
+    args: Vec<String>,
+    notifications: &NotificationsConfig,
+    operation: impl std::future::Future<Output = std::result::Result<T, E>>,
+) -> std::result::Result<T, E> {
+    let on_wait = |holder: &str| info!("Waiting for the transaction lock, held by {}", holder);
+    let _lock = crate::lock::acquire(command, true, on_wait).await?;
+
+    crate::history::begin(command, args);
+    let outcome = operation.await;
+
+    if let Some(summary) = crate::history::finish(&outcome) {
+        crate::notify::send(notifications, &summary).await;
+    }
+    outcome
+}
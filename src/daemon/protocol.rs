@@ -0,0 +1,81 @@
+//! JSON-RPC 2.0 messages, one per line
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// An archbox operation failed; `data` carries the error kind
+pub const OPERATION_FAILED: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub jsonrpc: Option<String>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Absent for notifications, which get no response
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl Response {
+    pub fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        match outcome {
+            Ok(result) => Self { jsonrpc: "2.0", result: Some(result), error: None, id },
+            Err(error) => Self { jsonrpc: "2.0", result: None, error: Some(error), id },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<crate::Error> for RpcError {
+    fn from(error: crate::Error) -> Self {
+        let kind = match error.cause() {
+            crate::Error::PackageNotFound(_) => "package_not_found",
+            crate::Error::PermissionDenied { .. } => "permission_denied",
+            crate::Error::Dependency(_) => "dependency",
+            crate::Error::UnsupportedPlatform { .. } => "unsupported_platform",
+            crate::Error::Network(_) => "network",
+            crate::Error::Timeout { .. } => "timeout",
+            _ => "failed",
+        };
+        Self {
+            code: OPERATION_FAILED,
+            message: error.to_string(),
+            data: Some(serde_json::json!({ "kind": kind })),
+        }
+    }
+}
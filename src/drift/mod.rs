@@ -0,0 +1,167 @@
+//! Config drift: how deployed config files differ from what ArchBox wrote and what the
+//! definition declares now
+//!
+//! Every config file and drop-in written during post-install is also saved under
+//! [`snapshot_dir`], with resolved secrets masked. `archbox diff` compares the file on disk
+//! with that snapshot, to show local modifications, and with the definition's current
+//! content, to show what the next install or update would write. For files where ArchBox
+//! only owns a managed block, only that block is compared.
+
+use crate::config::{get_state_dir, Config};
+use crate::package::{drop_in_header, managed_block, template::TemplateContext, Package, PostInstallAction};
+use crate::state::InstalledPackage;
+use crate::{secrets, Result};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::warn;
+
+/// Directory holding the content ArchBox last wrote to each config file, per package
+pub fn snapshot_dir() -> PathBuf {
+    let dir = if crate::simulate::is_enabled() { crate::simulate::state_dir() } else { get_state_dir() };
+    dir.join("deployed")
+}
+
+/// Where the snapshot of `path` for `package` is kept. The original absolute path is
+/// preserved below the package directory, like backups.
+fn snapshot_path(package: &str, path: &Path) -> PathBuf {
+    snapshot_dir().join(package).join(path.strip_prefix("/").unwrap_or(path))
+}
+
+/// Remember `content` as what ArchBox wrote to `path` (only the managed block for block
+/// files), with secrets resolved in this run masked
+pub async fn record(package: &str, path: &Path, content: &str) -> Result<()> {
+    let snapshot = snapshot_path(package, path);
+    if let Some(parent) = snapshot.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    fs::write(&snapshot, secrets::redact(content)).await?;
+    Ok(())
+}
+
+/// Drop every snapshot of a removed package
+pub fn forget(package: &str) -> Result<()> {
+    let dir = snapshot_dir().join(package);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    Ok(())
+}
+
+/// One config file of a package, as written, as it is now and as declared
+#[derive(Debug, Clone)]
+pub struct FileDrift {
+    pub path: PathBuf,
+    /// Whether ArchBox owns only a managed block in the file
+    pub block: bool,
+    /// Content on disk (the package's block for block files), `None` if it is gone
+    pub current: Option<String>,
+    /// Content ArchBox wrote, if it was recorded
+    pub deployed: Option<String>,
+    /// Content the definition declares now, `None` if it no longer declares the file
+    pub declared: Option<String>,
+}
+
+impl FileDrift {
+    /// Whether the file was changed since ArchBox wrote it
+    pub fn locally_modified(&self) -> bool {
+        self.deployed.is_some() && self.current != self.deployed
+    }
+
+    /// Whether installing the current definition would change the file
+    pub fn differs_from_definition(&self) -> bool {
+        self.declared.is_some() && self.current != self.declared
+    }
+}
+
+/// Compare every config file and drop-in of an installed package. Files the definition
+/// declares come first (config files by path, then drop-ins), followed by files that were
+/// deployed but are no longer declared.
+pub async fn inspect(package: &Package, record: &InstalledPackage, config: &Config) -> Result<Vec<FileDrift>> {
+    let mut files: Vec<FileDrift> = Vec::new();
+
+    for (path, block, content) in declared_files(package, config).await? {
+        files.push(FileDrift {
+            current: current_content(&path, block, &package.name).await,
+            deployed: deployed_content(&package.name, &path, block).await,
+            declared: Some(normalize(&secrets::redact(&content), block)),
+            path,
+            block,
+        });
+    }
+
+    for action in &record.post_install {
+        let (path, block) = match action {
+            PostInstallAction::ConfigFile { path, .. } | PostInstallAction::DropIn { path, .. } => (path, false),
+            // Blocks in shell startup files are recorded too, but have no snapshot
+            PostInstallAction::ManagedBlock { path, .. } if snapshot_path(&package.name, path).exists() => (path, true),
+            _ => continue,
+        };
+        if files.iter().any(|file| file.path == *path) {
+            continue;
+        }
+        files.push(FileDrift {
+            path: path.clone(),
+            block,
+            current: current_content(path, block, &package.name).await,
+            deployed: deployed_content(&package.name, path, block).await,
+            declared: None,
+        });
+    }
+
+    Ok(files)
+}
+
+/// Config files and drop-ins the definition declares: path, whether it is a managed block,
+/// and content with templates rendered and secrets resolved
+async fn declared_files(package: &Package, config: &Config) -> Result<Vec<(PathBuf, bool, String)>> {
+    let Some(post_install) = &package.post_install else {
+        return Ok(Vec::new());
+    };
+    let mut files = Vec::new();
+
+    let templates = TemplateContext::from_system().with_variables(&config.variables);
+    let mut config_files: Vec<(&String, &String)> = post_install.config_files.iter().flatten().collect();
+    config_files.sort();
+    for (path, content) in config_files {
+        let path = PathBuf::from(shellexpand::tilde(&templates.render(path)).to_string());
+        let content = templates.render(content);
+        let content = match secrets::resolve(&content).await {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                warn!("Comparing {} with unresolved secrets: {}", path.display(), e);
+                content
+            }
+        };
+        let block = managed_block::comment_prefix(&path).is_some();
+        files.push((path, block, content));
+    }
+
+    for drop_in in post_install.drop_ins(&package.name) {
+        files.push((drop_in.path, false, format!("{}{}", drop_in_header(&package.name), drop_in.content)));
+    }
+
+    Ok(files)
+}
+
+async fn current_content(path: &Path, block: bool, package: &str) -> Option<String> {
+    let content = fs::read_to_string(path).await.ok()?;
+    let content = match block {
+        true => managed_block::block_body(&content, managed_block::comment_prefix(path)?, package)?,
+        false => content,
+    };
+    Some(normalize(&secrets::redact(&content), block))
+}
+
+async fn deployed_content(package: &str, path: &Path, block: bool) -> Option<String> {
+    let content = fs::read_to_string(snapshot_path(package, path)).await.ok()?;
+    Some(normalize(&content, block))
+}
+
+/// Block bodies are stored line by line, so compare them that way
+fn normalize(content: &str, block: bool) -> String {
+    match block {
+        true => content.lines().map(|line| format!("{}\n", line)).collect(),
+        false => content.to_string(),
+    }
+}
@@ -17,7 +17,7 @@ pub enum Error {
     InstallationFailed(String),
 
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(reqwest::Error),
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -33,4 +33,80 @@ pub enum Error {
 
     #[error("Dependency error: {0}")]
     Dependency(String),
-}
\ No newline at end of file
+
+    #[error("Prompt failed: {0}")]
+    Prompt(#[from] dialoguer::Error),
+
+    #[error("{package} is not available for {arch} (supported: {supported})")]
+    UnsupportedPlatform { package: String, arch: String, supported: String },
+
+    #[error("Another archbox is changing the system: {holder}")]
+    Locked { holder: String },
+
+    #[error("{operation} timed out after {seconds}s")]
+    Timeout { operation: String, seconds: u64 },
+
+    #[error("{source} (work files kept in {})", .path.display())]
+    WorkKept { source: Box<Error>, path: std::path::PathBuf },
+
+    #[error("{source} (full output in {})", .log.display())]
+    Logged { source: Box<Error>, log: std::path::PathBuf },
+}
+
+impl Error {
+    /// The error itself, without the kept work directory or install log added to it
+    pub fn cause(&self) -> &Error {
+        match self {
+            Error::WorkKept { source, .. } | Error::Logged { source, .. } => source.cause(),
+            error => error,
+        }
+    }
+
+    /// Stable identifier for the kind of error, used in `--output json` error reports
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io(_) => "io",
+            Error::Config(_) => "config",
+            Error::PackageNotFound(_) => "package_not_found",
+            Error::InstallationFailed(_) => "installation_failed",
+            Error::Network(_) => "network",
+            Error::Serialization(_) => "serialization",
+            Error::Yaml(_) => "yaml",
+            Error::CommandFailed { .. } => "command_failed",
+            Error::PermissionDenied { .. } => "permission_denied",
+            Error::Dependency(_) => "dependency",
+            Error::Prompt(_) => "prompt",
+            Error::UnsupportedPlatform { .. } => "unsupported_platform",
+            Error::Locked { .. } => "locked",
+            Error::Timeout { .. } => "timeout",
+            Error::WorkKept { source, .. } | Error::Logged { source, .. } => source.code(),
+        }
+    }
+
+    /// The package the error is about, when it names one
+    pub fn package(&self) -> Option<&str> {
+        match self {
+            Error::PackageNotFound(name) => Some(name),
+            Error::UnsupportedPlatform { package, .. } => Some(package),
+            Error::WorkKept { source, .. } | Error::Logged { source, .. } => source.package(),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    /// Requests that run out of time become [`Error::Timeout`]
+    fn from(error: reqwest::Error) -> Self {
+        if !error.is_timeout() {
+            return Error::Network(error);
+        }
+
+        Error::Timeout {
+            operation: match error.url() {
+                Some(url) => format!("Request to {}", url),
+                None => "Request".to_string(),
+            },
+            seconds: crate::http::timeout().map_or(0, |timeout| timeout.as_secs()),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! What installing a package would do, worked out without doing any of it
+//!
+//! `archbox explain` renders a [`Plan`] so a third-party definition can be reviewed before
+//! it is trusted: the commands the installer would run, what it downloads, the files it
+//! writes, the services and groups it changes, and which of those steps need root. The plan
+//! follows the installer step by step, with templates rendered but secrets left as their
+//! `secret://` references.
+
+use crate::config::Config;
+use crate::package::{
+    drop_in_header, is_git_source, layout, managed_block, parse_service, platform::{BinaryAsset, Platform}, template::TemplateContext, verify_script,
+    DependencyType, DropIn, DropInKind, FlatpakScope, InstallScope, Installation, Package, AUR_GIT_BASE, FLATHUB_REMOTE,
+    FLATHUB_REPO_URL, DETECTED_HELPERS, NATIVE_HELPER,
+};
+use crate::shell::{self, EnvValue, ShellKind};
+use crate::{secrets, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What a step does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    /// A command line run on this machine
+    Run,
+    /// Something fetched over the network
+    Download,
+    /// A file created or changed
+    Write,
+    /// A systemd unit enabled and started
+    Service,
+    /// The current user added to a group
+    Group,
+    /// A question asked on the terminal before going on
+    Prompt,
+    /// Something left for the user to do by hand
+    Manual,
+}
+
+impl StepKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StepKind::Run => "run",
+            StepKind::Download => "download",
+            StepKind::Write => "write",
+            StepKind::Service => "service",
+            StepKind::Group => "group",
+            StepKind::Prompt => "prompt",
+            StepKind::Manual => "manual",
+        }
+    }
+}
+
+/// One thing the installer would do
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub kind: StepKind,
+    /// The command line, URL, path, unit or group
+    pub detail: String,
+    /// Whether the step needs root
+    pub root: bool,
+    /// Script or file contents, shown for review
+    pub content: Option<String>,
+}
+
+/// Everything installing one package would do, in order
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub package: String,
+    pub version: String,
+    pub method: &'static str,
+    pub scope: InstallScope,
+    /// Packages from the repository installed before this one
+    pub dependencies: Vec<String>,
+    pub steps: Vec<Step>,
+    /// `secret://` references resolved while writing config files and the environment
+    pub secrets: Vec<String>,
+}
+
+impl Plan {
+    /// Work out the plan for installing `package` on `platform` with `config`, in the
+    /// `requested` scope (from `--user`/`--system`) if any. Fails where the install itself
+    /// would fail before doing anything, e.g. without a download URL for this architecture.
+    pub fn new(package: &Package, config: &Config, platform: &Platform, requested: Option<InstallScope>) -> Result<Plan> {
+        let scope = package.install_scope(requested);
+        let mut plan = Plan {
+            package: package.name.clone(),
+            version: package.version.clone(),
+            method: package.installation.method(),
+            scope,
+            dependencies: package
+                .get_dependencies(DependencyType::Package, platform)
+                .iter()
+                .filter(|dep| !dep.optional)
+                .map(|dep| dep.name.clone())
+                .collect(),
+            steps: Vec::new(),
+            secrets: Vec::new(),
+        };
+
+        let system_deps: Vec<&str> = package
+            .get_dependencies(DependencyType::System, platform)
+            .iter()
+            .map(|dep| dep.name.as_str())
+            .collect();
+        if !system_deps.is_empty() {
+            plan.run(format!("pacman -S --needed --noconfirm {}", system_deps.join(" ")), true);
+        }
+
+        plan.add_installation(package, config, platform)?;
+        if let Some(post_install) = &package.post_install {
+            let service_scope = requested.or(package.scope).unwrap_or(InstallScope::System);
+            plan.add_post_install(package, post_install, config, service_scope)?;
+        }
+
+        Ok(plan)
+    }
+
+    /// Whether any step needs root
+    pub fn needs_root(&self) -> bool {
+        self.steps.iter().any(|step| step.root)
+    }
+
+    fn push(&mut self, kind: StepKind, detail: String, root: bool, content: Option<String>) {
+        self.steps.push(Step { kind, detail, root, content });
+    }
+
+    fn run(&mut self, command: String, root: bool) {
+        self.push(StepKind::Run, command, root, None);
+    }
+
+    fn add_installation(&mut self, package: &Package, config: &Config, platform: &Platform) -> Result<()> {
+        let system = self.scope == InstallScope::System;
+
+        match &package.installation {
+            Installation::Pacman { packages, flags } => {
+                let mut words = vec!["pacman", "-S", "--needed", "--noconfirm"];
+                words.extend(flags.iter().flatten().map(String::as_str));
+                words.extend(packages.iter().map(String::as_str));
+                self.run(words.join(" "), true);
+            }
+            Installation::Aur { package: name, helper } => {
+                self.add_aur(name, helper.as_deref().or(config.aur_helper.as_deref()), config);
+            }
+            Installation::Binary { url, checksum, urls, checksums, executable, fallbacks, .. } => {
+                let (url, checksum) = match platform.resolve_binary(url, checksum.as_ref(), urls.as_ref(), checksums.as_ref(), fallbacks)? {
+                    BinaryAsset::Download { url, checksum, .. } => (url, checksum),
+                    BinaryAsset::Source { url, build_commands, install_commands } => {
+                        self.add_source(package, url, build_commands, install_commands, platform);
+                        return Ok(());
+                    }
+                };
+                self.add_download(&url, checksum.as_deref(), config);
+
+                let link = package.install_path(self.scope).unwrap_or_default();
+                let file_name = link.file_name().unwrap_or_default();
+                let target = layout::version_dir(&package.name, self.scope, &package.version).join(file_name);
+                let mode = if *executable { " (mode 755)" } else { "" };
+                self.push(StepKind::Write, format!("{}{}", target.display(), mode), system, None);
+
+                let replaces = if link.exists() && !layout::is_managed_link(&package.name, self.scope, &link) {
+                    " (the existing file is backed up first)"
+                } else {
+                    ""
+                };
+                self.push(StepKind::Write, format!("{} → {}{}", link.display(), target.display(), replaces), system, None);
+                self.push(StepKind::Write, layout::shims_dir(self.scope).join(file_name).display().to_string(), system, None);
+            }
+            Installation::Source { url, build_commands, install_commands } => {
+                self.add_source(package, url, build_commands, install_commands, platform);
+            }
+            Installation::Script { script, interpreter, script_sha256 } => {
+                verify_script(script, script_sha256.as_deref())?;
+                let detail = match script_sha256 {
+                    Some(sha256) => format!("{} <script> (sha256 {})", interpreter, sha256),
+                    None => format!("{} <script>", interpreter),
+                };
+                self.push(StepKind::Run, detail, escalates(script), Some(script.clone()));
+            }
+            Installation::AppImage { url, checksum, urls, checksums, integrate } => {
+                let url = platform.resolve_url(url, urls.as_ref())?;
+                let checksum = platform.resolve_checksum(checksum.as_ref(), checksums.as_ref());
+                self.add_download(&url, checksum.as_deref(), config);
+
+                let path = package.install_path(self.scope).unwrap_or_default();
+                self.push(StepKind::Write, format!("{} (mode 755)", path.display()), system, None);
+                if *integrate {
+                    self.run(format!("{} --appimage-extract-and-run --appimage-extract", path.display()), false);
+                }
+            }
+            Installation::Flatpak { id, remote, remote_url, .. } => {
+                let flag = FlatpakScope::from(self.scope).flag();
+                let remote = remote.as_deref().unwrap_or(FLATHUB_REMOTE);
+                match remote_url.as_deref().or((remote == FLATHUB_REMOTE).then_some(FLATHUB_REPO_URL)) {
+                    Some(url) => {
+                        self.push(StepKind::Prompt, format!("add the Flatpak remote {} if it isn't configured", remote), false, None);
+                        self.run(format!("flatpak remote-add --if-not-exists {} {} {}", flag, remote, url), system);
+                    }
+                    None => {
+                        self.push(StepKind::Manual, format!("configure the Flatpak remote {} first", remote), false, None);
+                    }
+                }
+                self.run(format!("flatpak install -y --noninteractive {} {} {}", flag, remote, id), system);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A helper builds the package when one is configured or installed, otherwise the
+    /// built-in backend clones and builds it with makepkg
+    fn add_aur(&mut self, name: &str, helper: Option<&str>, config: &Config) {
+        // A reviewed PKGBUILD is built by the built-in backend, from the checkout that was reviewed
+        let review = config.installation.review_pkgbuild;
+        let helper = match helper {
+            _ if review => None,
+            Some(NATIVE_HELPER) => None,
+            Some(helper) => on_path(helper).then_some(helper),
+            None => DETECTED_HELPERS.into_iter().find(|candidate| on_path(candidate)),
+        };
+
+        let Some(helper) = helper else {
+            self.push(StepKind::Download, format!("git clone --depth 1 {}/{}.git", AUR_GIT_BASE, name), false, None);
+            if review {
+                self.push(StepKind::Prompt, format!("review the PKGBUILD of {}", name), false, None);
+            }
+            self.run("pacman -S --needed --noconfirm --asdeps <missing dependencies from .SRCINFO>".to_string(), true);
+            self.run("makepkg -si --noconfirm --needed".to_string(), true);
+            return;
+        };
+
+        let menus = match helper {
+            "yay" => " --noeditmenu --nodiffmenu",
+            "paru" => " --skipreview",
+            _ => "",
+        };
+        self.run(format!("{} -S --needed --noconfirm{} {}", helper, menus, name), true);
+    }
+
+    fn add_source(&mut self, package: &Package, url: &str, build_commands: &[String], install_commands: &[String], platform: &Platform) {
+        let build_deps: Vec<&str> = package
+            .get_dependencies(DependencyType::Build, platform)
+            .iter()
+            .filter(|dep| !dep.optional)
+            .map(|dep| dep.name.as_str())
+            .collect();
+        if !build_deps.is_empty() {
+            self.run(format!("pacman -S --needed --noconfirm --asdeps {}", build_deps.join(" ")), true);
+        }
+
+        if !is_git_source(url) {
+            self.push(StepKind::Manual, format!("{} is an archive, which source installs don't support yet", url), false, None);
+            return;
+        }
+        self.push(StepKind::Download, format!("git clone {}", url), false, None);
+        for command in build_commands.iter().chain(install_commands) {
+            self.run(command.clone(), escalates(command));
+        }
+    }
+
+    fn add_download(&mut self, url: &str, checksum: Option<&str>, config: &Config) {
+        let mirrors = crate::repository::mirrors::artifact_candidates(url, config).len() - 1;
+        let mut detail = match checksum {
+            Some(checksum) => format!("{} (sha256 {})", url, checksum),
+            None => format!("{} (no checksum to verify)", url),
+        };
+        if mirrors > 0 {
+            detail.push_str(&format!(", or one of {} mirror(s)", mirrors));
+        }
+        self.push(StepKind::Download, detail, false, None);
+    }
+
+    fn add_post_install(
+        &mut self,
+        package: &Package,
+        post_install: &crate::package::PostInstall,
+        config: &Config,
+        service_scope: InstallScope,
+    ) -> Result<()> {
+        for command in post_install.commands.iter().flatten() {
+            self.run(command.clone(), escalates(command));
+        }
+
+        let templates = TemplateContext::from_system().with_variables(&config.variables);
+        for (path, content) in sorted(post_install.config_files.as_ref()) {
+            let path = templates.render(path);
+            let path = shellexpand::tilde(&path).to_string();
+            let content = templates.render(content);
+            self.add_secrets(&content)?;
+
+            let home = dirs::home_dir().unwrap_or_default();
+            let block = match managed_block::comment_prefix(Path::new(&path)) {
+                Some(_) => " (managed block)",
+                None => "",
+            };
+            self.push(StepKind::Write, format!("{}{}", path, block), !Path::new(&path).starts_with(home), Some(content));
+        }
+
+        for DropIn { kind, path, content } in post_install.drop_ins(&package.name) {
+            let content = format!("{}{}", drop_in_header(&package.name), content);
+            self.push(StepKind::Write, path.display().to_string(), true, Some(content));
+            match kind {
+                DropInKind::Sysctl => self.run(format!("sysctl --load {}", path.display()), true),
+                DropInKind::Udev => {
+                    self.run("udevadm control --reload-rules".to_string(), true);
+                    self.run("udevadm trigger".to_string(), true);
+                }
+                DropInKind::Tmpfiles => self.run(format!("systemd-tmpfiles --create {}", path.display()), true),
+                DropInKind::Modprobe | DropInKind::PacmanHook => {}
+            }
+        }
+
+        for rule in post_install.firewall.iter().flatten() {
+            for protocol in rule.protocols() {
+                let detail = format!("allow {}/{} in the running firewall (firewalld, ufw or nftables)", rule.ports(), protocol);
+                self.push(StepKind::Run, detail, true, None);
+            }
+        }
+        
+        if let Some(params) = &post_install.kernel_params {
+            self.push(StepKind::Manual, format!("add to the kernel command line: {}", params.join(" ")), true, None);
+        }
+
+        for spec in post_install.enable_services.iter().flatten() {
+            let (service, user) = parse_service(spec, service_scope);
+            let detail = if user { format!("{} (user unit)", service) } else { service.to_string() };
+            self.push(StepKind::Service, detail, !user, None);
+        }
+
+        for group in post_install.user_groups.iter().flatten() {
+            self.push(StepKind::Group, group.clone(), true, None);
+        }
+
+        if let Some(env_vars) = &post_install.environment {
+            for value in env_vars.values() {
+                self.add_secrets(value)?;
+            }
+
+            // Secrets are shown as references, which is how they appear until installed
+            let env_vars = env_vars
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), EnvValue::from_definition(value)?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+            let env_vars = &env_vars;
+
+            // Fish reads its own file from conf.d; the other shells source the POSIX one
+            let shells = shell::detect_shells();
+            let env_file = format!("{} (managed block)", shell::env_file_path().display());
+            self.push(StepKind::Write, env_file, false, Some(shell::posix_exports(env_vars)));
+            if shells.contains(&ShellKind::Fish) {
+                let fish_file = format!("{} (managed block)", shell::fish_env_file_path().display());
+                self.push(StepKind::Write, fish_file, false, Some(shell::fish_exports(env_vars)));
+            }
+            for startup_file in shells.iter().filter_map(|shell| shell.startup_file()) {
+                self.push(StepKind::Write, format!("{} (sources the environment file)", startup_file.display()), false, None);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_secrets(&mut self, text: &str) -> Result<()> {
+        for reference in secrets::references(text)? {
+            if !self.secrets.contains(&reference.raw) {
+                self.secrets.push(reference.raw);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Entries of an optional map, sorted by key so plans come out the same every time
+fn sorted(map: Option<&HashMap<String, String>>) -> Vec<(&String, &String)> {
+    let mut entries: Vec<_> = map.into_iter().flatten().collect();
+    entries.sort();
+    entries
+}
+
+/// Whether a command line asks for root on its own, e.g. `sudo make install`
+fn escalates(command: &str) -> bool {
+    command
+        .split_whitespace()
+        .any(|word| matches!(word, "sudo" | "doas" | "pkexec" | "su"))
+}
+
+fn on_path(command: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join(command).is_file()))
+        .unwrap_or(false)
+}
@@ -1,30 +1,160 @@
-use crate::{package::Package, Error, Result};
+use crate::{hardware::{GpuVendor, Hardware, MachineClass}, t, Error, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackageGroup {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub packages: Vec<String>,
+    #[serde(default)]
     pub optional_packages: Vec<String>,
+    #[serde(default)]
     pub conflicts: Vec<String>,
+    #[serde(default)]
     pub category: Option<String>,
+    /// Packages added only on machines with the matching hardware, such as GPU drivers
+    #[serde(default)]
+    pub hardware_packages: Vec<HardwarePackages>,
+    /// Kind of machine the group is suggested for, and included in profiles with
+    /// `machine_groups` set
+    #[serde(default)]
+    pub machine: Option<MachineClass>,
+}
+
+/// Packages a group installs only when the machine matches every condition given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardwarePackages {
+    #[serde(default)]
+    pub gpu: Option<GpuVendor>,
+    /// Hypervisor as named by `systemd-detect-virt`
+    #[serde(default)]
+    pub hypervisor: Option<String>,
+    pub packages: Vec<String>,
+}
+
+impl HardwarePackages {
+    pub fn matches(&self, hardware: &Hardware) -> bool {
+        self.gpu.is_none_or(|gpu| hardware.gpus.contains(&gpu))
+            && self.hypervisor.as_ref().is_none_or(|hypervisor| hardware.hypervisor.as_ref() == Some(hypervisor))
+    }
+}
+
+impl PackageGroup {
+    /// The group's packages, plus the hardware packages matching `hardware`
+    pub fn packages_for(&self, hardware: &Hardware) -> Vec<String> {
+        let mut packages = self.packages.clone();
+        for conditional in self.hardware_packages.iter().filter(|conditional| conditional.matches(hardware)) {
+            packages.extend(conditional.packages.iter().cloned());
+        }
+        packages
+    }
+    
+    /// Names in this group's `conflicts` that `other` is, or installs on `hardware`
+    pub fn conflicts_with(&self, other: &PackageGroup, hardware: &Hardware) -> Vec<String> {
+        let installs = other.packages_for(hardware);
+        self.conflicts
+            .iter()
+            .filter(|name| **name == other.name || installs.contains(name))
+            .cloned()
+            .collect()
+    }
+    
+    /// Whether the group names `package` in any of its lists
+    pub fn contains(&self, package: &str) -> bool {
+        let named = |packages: &Vec<String>| packages.iter().any(|name| name == package);
+        named(&self.packages)
+            || named(&self.optional_packages)
+            || self.hardware_packages.iter().any(|hardware| named(&hardware.packages))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallationProfile {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub groups: Vec<String>,
+    #[serde(default)]
     pub additional_packages: Vec<String>,
+    #[serde(default)]
     pub excluded_packages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub post_install_script: Option<String>,
+    /// SHA-256 of `post_install_script`; the script is refused unless it matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install_script_sha256: Option<String>,
+    /// Template variables for config files installed as part of this profile
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Also include the groups suggested for this kind of machine, e.g. `laptop` on laptops
+    #[serde(default)]
+    pub machine_groups: bool,
+    /// Groups that win when two of the profile's groups conflict, e.g. `pipewire` over
+    /// `pulseaudio`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefer: Vec<String>,
+}
+
+/// Two groups of a profile that can't be installed together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupConflict {
+    pub first: String,
+    pub second: String,
+    /// What one group's `conflicts` names from the other
+    pub packages: Vec<String>,
+}
+
+impl GroupConflict {
+    /// The error reported when nothing decides which of the groups to keep
+    pub fn error(&self) -> Error {
+        Error::Config(t!(
+            "group-conflict",
+            first = self.first.as_str(),
+            second = self.second.as_str(),
+            packages = self.packages.join(", ")
+        ))
+    }
+}
+
+impl InstallationProfile {
+    /// The post-install script, once it matches `post_install_script_sha256` if one is set
+    pub fn verified_post_install_script(&self) -> Result<Option<&str>> {
+        let Some(script) = &self.post_install_script else {
+            return Ok(None);
+        };
+        
+        crate::package::installer::verify_script(script, self.post_install_script_sha256.as_deref())?;
+        Ok(Some(script))
+    }
+}
+
+/// A file of user-defined groups and profiles in the groups directory
+#[derive(Debug, Default, Deserialize)]
+struct GroupFile {
+    #[serde(default)]
+    groups: Vec<PackageGroup>,
+    #[serde(default)]
+    profiles: Vec<InstallationProfile>,
 }
 
 pub struct GroupManager {
     groups: HashMap<String, PackageGroup>,
     profiles: HashMap<String, InstallationProfile>,
+    /// Names of the groups and profiles defined in the config directory
+    user_groups: HashSet<String>,
+    user_profiles: HashSet<String>,
+}
+
+impl Default for GroupManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GroupManager {
@@ -32,13 +162,68 @@ impl GroupManager {
         let mut manager = Self {
             groups: HashMap::new(),
             profiles: HashMap::new(),
+            user_groups: HashSet::new(),
+            user_profiles: HashSet::new(),
         };
         
         manager.load_default_groups();
         manager.load_default_profiles();
+        manager.load_user_groups();
+        manager.load_user_profiles();
         manager
     }
     
+    /// Group files in the config directory, in name order. Groups and profiles defined
+    /// there replace built-in ones of the same name.
+    fn load_user_groups(&mut self) {
+        for path in yaml_files(&groups_dir()) {
+            if let Err(e) = self.load_group_file(&path) {
+                warn!("Skipping group file {}: {}", path.display(), e);
+            }
+        }
+    }
+    
+    /// Add the `groups` and `profiles` defined in a YAML file, replacing any of the same name
+    pub fn load_group_file(&mut self, path: &Path) -> Result<()> {
+        let file: GroupFile = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+        for group in file.groups {
+            self.user_groups.insert(group.name.clone());
+            self.groups.insert(group.name.clone(), group);
+        }
+        for profile in file.profiles {
+            self.user_profiles.insert(profile.name.clone());
+            self.profiles.insert(profile.name.clone(), profile);
+        }
+        Ok(())
+    }
+    
+    /// Whether a group comes from the config directory rather than ArchBox itself
+    pub fn is_user_group(&self, name: &str) -> bool {
+        self.user_groups.contains(name)
+    }
+    
+    /// Whether a profile comes from the config directory rather than ArchBox itself
+    pub fn is_user_profile(&self, name: &str) -> bool {
+        self.user_profiles.contains(name)
+    }
+    
+    /// Profiles in the config directory, one YAML file each. A user profile with the
+    /// same name as a built-in one replaces it.
+    fn load_user_profiles(&mut self) {
+        for path in yaml_files(&profiles_dir()) {
+            let profile = std::fs::read_to_string(&path)
+                .map_err(Error::from)
+                .and_then(|content| Ok(serde_yaml::from_str::<InstallationProfile>(&content)?));
+            match profile {
+                Ok(profile) => {
+                    self.user_profiles.insert(profile.name.clone());
+                    self.profiles.insert(profile.name.clone(), profile);
+                }
+                Err(e) => warn!("Skipping profile {}: {}", path.display(), e),
+            }
+        }
+    }
+    
     fn load_default_groups(&mut self) {
         // Development group
         self.groups.insert("development".to_string(), PackageGroup {
@@ -56,6 +241,8 @@ impl GroupManager {
             ],
             conflicts: vec![],
             category: Some("development".to_string()),
+            hardware_packages: vec![],
+            machine: None,
         });
         
         // Media group
@@ -73,6 +260,8 @@ impl GroupManager {
             ],
             conflicts: vec![],
             category: Some("media".to_string()),
+            hardware_packages: vec![],
+            machine: None,
         });
         
         // Gaming group
@@ -89,6 +278,48 @@ impl GroupManager {
             ],
             conflicts: vec![],
             category: Some("gaming".to_string()),
+            // Vulkan drivers, with 32-bit variants for Steam and Wine
+            hardware_packages: vec![
+                gpu_packages(GpuVendor::Nvidia, "nvidia-driver"),
+                gpu_packages(GpuVendor::Amd, "mesa-vulkan-radeon"),
+                gpu_packages(GpuVendor::Intel, "mesa-vulkan-intel"),
+            ],
+            machine: None,
+        });
+        
+        // Laptop group
+        self.groups.insert("laptop".to_string(), PackageGroup {
+            name: "laptop".to_string(),
+            description: "Power management for laptops".to_string(),
+            packages: vec![
+                "power-profiles-daemon".to_string(),
+            ],
+            optional_packages: vec![
+                "powertop".to_string(),
+            ],
+            // TLP manages the same settings as power-profiles-daemon
+            conflicts: vec!["tlp".to_string()],
+            category: Some("system".to_string()),
+            hardware_packages: vec![],
+            machine: Some(MachineClass::Laptop),
+        });
+        
+        // Virtual machine group
+        self.groups.insert("vm-guest".to_string(), PackageGroup {
+            name: "vm-guest".to_string(),
+            description: "Guest agents and tools for the hypervisor running this machine".to_string(),
+            packages: vec![],
+            optional_packages: vec![],
+            conflicts: vec![],
+            category: Some("system".to_string()),
+            hardware_packages: vec![
+                hypervisor_packages("kvm", "qemu-guest-agent"),
+                hypervisor_packages("qemu", "qemu-guest-agent"),
+                hypervisor_packages("oracle", "virtualbox-guest-utils"),
+                hypervisor_packages("vmware", "open-vm-tools"),
+                hypervisor_packages("microsoft", "hyperv"),
+            ],
+            machine: Some(MachineClass::VirtualMachine),
         });
     }
     
@@ -108,6 +339,10 @@ impl GroupManager {
                 git config --global init.defaultBranch main
                 echo "Development environment configured!"
             "#.to_string()),
+            post_install_script_sha256: None,
+            variables: HashMap::new(),
+            machine_groups: true,
+            prefer: vec![],
         });
         
         // Content creator profile
@@ -120,6 +355,10 @@ impl GroupManager {
             ],
             excluded_packages: vec![],
             post_install_script: None,
+            post_install_script_sha256: None,
+            variables: HashMap::new(),
+            machine_groups: true,
+            prefer: vec![],
         });
         
         // Gamer profile
@@ -134,6 +373,10 @@ impl GroupManager {
                 sudo systemctl enable --now gamemode
                 echo "Gaming optimizations applied!"
             "#.to_string()),
+            post_install_script_sha256: None,
+            variables: HashMap::new(),
+            machine_groups: true,
+            prefer: vec![],
         });
     }
     
@@ -153,17 +396,63 @@ impl GroupManager {
         self.profiles.values().collect()
     }
     
+    /// Write a profile to the config directory, returning the file written
+    pub fn save_profile(&mut self, profile: InstallationProfile) -> Result<PathBuf> {
+        let path = profile_path(&profile.name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_yaml::to_string(&profile)?)?;
+        
+        self.user_profiles.insert(profile.name.clone());
+        self.profiles.insert(profile.name.clone(), profile);
+        Ok(path)
+    }
+    
+    /// Groups suggested for a kind of machine
+    pub fn machine_groups(&self, class: MachineClass) -> Vec<&PackageGroup> {
+        let mut groups: Vec<&PackageGroup> = self.groups.values().filter(|group| group.machine == Some(class)).collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        groups
+    }
+    
+    /// The groups a profile installs on a kind of machine: its own, plus the ones suggested
+    /// for the machine when it asks for them
+    pub fn profile_groups(&self, profile: &InstallationProfile, class: MachineClass) -> Vec<&PackageGroup> {
+        let mut groups: Vec<&PackageGroup> = profile.groups.iter().filter_map(|name| self.get_group(name)).collect();
+        if profile.machine_groups {
+            for group in self.machine_groups(class) {
+                if !groups.iter().any(|included| included.name == group.name) {
+                    groups.push(group);
+                }
+            }
+        }
+        groups
+    }
+    
+    /// A profile's packages; conflicting groups are settled by the profile's `prefer` list
+    /// and are an error otherwise
     pub fn resolve_profile_packages(&self, profile_name: &str) -> Result<Vec<String>> {
+        self.resolve_profile_packages_with(profile_name, |conflict| Err(conflict.error()))
+    }
+    
+    /// A profile's packages, asking `choose` which group to keep when two of its groups
+    /// conflict and `prefer` names neither or both
+    pub fn resolve_profile_packages_with(
+        &self,
+        profile_name: &str,
+        choose: impl FnMut(&GroupConflict) -> Result<String>,
+    ) -> Result<Vec<String>> {
         let profile = self.get_profile(profile_name)
             .ok_or_else(|| Error::Config(format!("Profile not found: {}", profile_name)))?;
         
         let mut packages = Vec::new();
         
-        // Add packages from groups
-        for group_name in &profile.groups {
-            if let Some(group) = self.get_group(group_name) {
-                packages.extend(group.packages.iter().cloned());
-            }
+        // Add packages from groups, with drivers for this machine's hardware
+        let hardware = Hardware::current();
+        let groups = self.profile_groups(profile, crate::hardware::machine_class());
+        for group in settle_conflicts(groups, &profile.prefer, &hardware, choose)? {
+            packages.extend(group.packages_for(&hardware));
         }
         
         // Add additional packages
@@ -178,4 +467,94 @@ impl GroupManager {
         
         Ok(packages)
     }
-}
\ No newline at end of file
+}
+
+/// The first pair of `groups` where either group conflicts with the other
+pub fn find_conflict(groups: &[&PackageGroup], hardware: &Hardware) -> Option<GroupConflict> {
+    for (index, first) in groups.iter().enumerate() {
+        for second in &groups[index + 1..] {
+            let mut packages = first.conflicts_with(second, hardware);
+            for name in second.conflicts_with(first, hardware) {
+                if !packages.contains(&name) {
+                    packages.push(name);
+                }
+            }
+            
+            if !packages.is_empty() {
+                return Some(GroupConflict {
+                    first: first.name.clone(),
+                    second: second.name.clone(),
+                    packages,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Drop groups until none of them conflict. Of each conflicting pair the group in `prefer`
+/// is kept, and `choose` names the one to keep when `prefer` has neither or both.
+pub fn settle_conflicts<'a>(
+    mut groups: Vec<&'a PackageGroup>,
+    prefer: &[String],
+    hardware: &Hardware,
+    mut choose: impl FnMut(&GroupConflict) -> Result<String>,
+) -> Result<Vec<&'a PackageGroup>> {
+    while let Some(conflict) = find_conflict(&groups, hardware) {
+        let keep = match (prefer.contains(&conflict.first), prefer.contains(&conflict.second)) {
+            (true, false) => conflict.first.clone(),
+            (false, true) => conflict.second.clone(),
+            _ => choose(&conflict)?,
+        };
+        let dropped = if keep == conflict.first { &conflict.second } else { &conflict.first };
+        tracing::debug!("Leaving out group {}, which conflicts with {}", dropped, keep);
+        groups.retain(|group| group.name != *dropped);
+    }
+    Ok(groups)
+}
+
+fn gpu_packages(gpu: GpuVendor, package: &str) -> HardwarePackages {
+    HardwarePackages {
+        gpu: Some(gpu),
+        hypervisor: None,
+        packages: vec![package.to_string()],
+    }
+}
+
+fn hypervisor_packages(hypervisor: &str, package: &str) -> HardwarePackages {
+    HardwarePackages {
+        gpu: None,
+        hypervisor: Some(hypervisor.to_string()),
+        packages: vec![package.to_string()],
+    }
+}
+
+/// The `.yaml` and `.yml` files in `dir`, sorted by name
+fn yaml_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml" | "yml")))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Directory holding user-defined groups, and profiles defined alongside them
+pub fn groups_dir() -> PathBuf {
+    crate::config::get_config_dir().join("groups")
+}
+
+/// Directory holding user-defined profiles
+pub fn profiles_dir() -> PathBuf {
+    crate::config::get_config_dir().join("profiles")
+}
+
+/// File a user profile named `name` is stored in
+pub fn profile_path(name: &str) -> PathBuf {
+    profiles_dir().join(format!("{}.yaml", name))
+}
@@ -0,0 +1,188 @@
+//! Detection of the machine's hardware and kind, used to pick drivers and suggest groups
+
+use crate::package::requirements;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::OnceLock;
+use tracing::debug;
+
+pub use requirements::GpuVendor;
+
+/// SMBIOS chassis types of portable machines: portable, laptop, notebook, hand held,
+/// sub notebook, tablet, convertible and detachable
+const PORTABLE_CHASSIS: [&str; 8] = ["8", "9", "10", "11", "14", "30", "31", "32"];
+
+/// Kinds of machine that call for different groups, e.g. power management on laptops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MachineClass {
+    Laptop,
+    Desktop,
+    #[serde(rename = "vm")]
+    VirtualMachine,
+}
+
+impl MachineClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MachineClass::Laptop => "laptop",
+            MachineClass::Desktop => "desktop",
+            MachineClass::VirtualMachine => "vm",
+        }
+    }
+}
+
+/// What group packages can be conditional on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hardware {
+    pub gpus: Vec<GpuVendor>,
+    /// Hypervisor the machine runs under, as named by `systemd-detect-virt` (`kvm`,
+    /// `oracle`, `vmware`, `microsoft`, ...)
+    pub hypervisor: Option<String>,
+}
+
+impl Hardware {
+    /// This machine, with the GPUs given with `--gpu` if any
+    pub fn current() -> Self {
+        Self {
+            gpus: gpus(),
+            hypervisor: hypervisor(),
+        }
+    }
+}
+
+static OVERRIDE: OnceLock<Vec<GpuVendor>> = OnceLock::new();
+static DETECTED: OnceLock<Vec<GpuVendor>> = OnceLock::new();
+static HYPERVISOR: OnceLock<Option<String>> = OnceLock::new();
+
+/// Use these GPUs instead of the detected ones for the rest of the process
+pub fn override_gpus(gpus: Vec<GpuVendor>) {
+    let _ = OVERRIDE.set(gpus);
+}
+
+/// The GPUs of this machine, or the ones given with `--gpu`. Hybrid laptops have two.
+pub fn gpus() -> Vec<GpuVendor> {
+    if let Some(gpus) = OVERRIDE.get() {
+        return gpus.clone();
+    }
+
+    DETECTED.get_or_init(detect_gpus).clone()
+}
+
+/// Ask lspci for display controllers, falling back to vulkaninfo when it isn't installed
+/// and to the kernel's DRM devices when neither is
+fn detect_gpus() -> Vec<GpuVendor> {
+    let gpus = match command_output("lspci", &["-n"]) {
+        Some(output) => parse_lspci(&output),
+        None => match command_output("vulkaninfo", &["--summary"]) {
+            Some(output) => parse_vulkaninfo(&output),
+            None => requirements::gpu_vendors(),
+        },
+    };
+
+    debug!("Detected GPUs: {:?}", gpus);
+    gpus
+}
+
+/// Whether this is a laptop, desktop or virtual machine
+pub fn machine_class() -> MachineClass {
+    let chassis = std::fs::read_to_string("/sys/class/dmi/id/chassis_type").ok();
+    classify(chassis.as_deref(), has_battery(), hypervisor().as_deref())
+}
+
+/// A virtual machine when running under a hypervisor, a laptop when the chassis is
+/// portable or there is a system battery, a desktop otherwise
+pub fn classify(chassis_type: Option<&str>, has_battery: bool, hypervisor: Option<&str>) -> MachineClass {
+    if hypervisor.is_some() {
+        MachineClass::VirtualMachine
+    } else if has_battery || chassis_type.is_some_and(|chassis| PORTABLE_CHASSIS.contains(&chassis.trim())) {
+        MachineClass::Laptop
+    } else {
+        MachineClass::Desktop
+    }
+}
+
+/// The hypervisor this machine runs under, from `systemd-detect-virt --vm`, or `unknown`
+/// when only the CPU's hypervisor flag gives it away. Containers don't count.
+pub fn hypervisor() -> Option<String> {
+    HYPERVISOR.get_or_init(detect_hypervisor).clone()
+}
+
+fn detect_hypervisor() -> Option<String> {
+    // systemd-detect-virt fails on bare metal, and where it isn't installed
+    if let Some(output) = command_output("systemd-detect-virt", &["--vm"]) {
+        let name = output.trim();
+        return (!name.is_empty() && name != "none").then(|| name.to_string());
+    }
+
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    let flagged = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"));
+    flagged.then(|| "unknown".to_string())
+}
+
+/// A battery powering the system, not one in a mouse or headset
+fn has_battery() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let read = |file: &str| std::fs::read_to_string(entry.path().join(file)).unwrap_or_default();
+        read("type").trim() == "Battery" && read("scope").trim() != "Device"
+    })
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// GPU vendors in `lspci -n` output, whose lines look like `01:00.0 0300: 10de:2484 (rev a1)`.
+/// Classes 03xx are display controllers (VGA, 3D and others).
+pub fn parse_lspci(output: &str) -> Vec<GpuVendor> {
+    let mut gpus = Vec::new();
+
+    for line in output.lines() {
+        let mut fields = line.split_whitespace().skip(1);
+        let (Some(class), Some(id)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if !class.starts_with("03") {
+            continue;
+        }
+
+        if let Some(vendor) = id.split(':').next().and_then(GpuVendor::from_pci_id) {
+            if !gpus.contains(&vendor) {
+                gpus.push(vendor);
+            }
+        }
+    }
+
+    gpus
+}
+
+/// GPU vendors in `vulkaninfo --summary` output, from its `vendorID = 0x10de` lines.
+/// Software renderers such as llvmpipe use IDs that match no vendor.
+pub fn parse_vulkaninfo(output: &str) -> Vec<GpuVendor> {
+    let mut gpus = Vec::new();
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() != "vendorID" {
+            continue;
+        }
+
+        if let Some(vendor) = GpuVendor::from_pci_id(value) {
+            if !gpus.contains(&vendor) {
+                gpus.push(vendor);
+            }
+        }
+    }
+
+    gpus
+}
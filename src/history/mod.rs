@@ -0,0 +1,299 @@
+//! Structured transaction log
+//!
+//! Every command that changes the system is a transaction: a `begin` record with the full
+//! command line, one `step` record per package operation, a `command` record per shell
+//! command the installer runs and an `end` record, appended as JSON lines to `archbox.log`
+//! in the state directory regardless of terminal verbosity. The log rolls over to
+//! `archbox.log.1` and `archbox.log.2` once it reaches [`MAX_LOG_BYTES`].
+
+use crate::config::get_state_dir;
+use crate::logging::{rotated_path, RotatingFile};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// What a log record describes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogEvent {
+    Begin {
+        command: String,
+        args: Vec<String>,
+    },
+    Step {
+        /// Operation, e.g. `install`, `remove`, `system_upgrade`
+        step: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        ok: bool,
+        duration_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// A shell command run by the installer (scripts, build and post-install commands)
+    Command {
+        command: String,
+        ok: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+        duration_ms: u64,
+        /// Last lines of the command's output
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        output: Vec<String>,
+    },
+    End {
+        ok: bool,
+        duration_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+/// One line of the transaction log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// Transaction the record belongs to
+    pub id: String,
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub event: LogEvent,
+}
+
+struct Transaction {
+    id: String,
+    started: Instant,
+    /// Kept in memory for the summary returned by [`finish`]
+    summary: TransactionSummary,
+}
+
+static CURRENT: Mutex<Option<Transaction>> = Mutex::new(None);
+
+/// Transactions started by this process so far, so a daemon's ids stay unique
+static STARTED: AtomicU32 = AtomicU32::new(0);
+
+/// Output lines kept per command record
+const OUTPUT_TAIL_LINES: usize = 5;
+
+/// Size at which the log rolls over
+pub const MAX_LOG_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Rolled over logs kept besides the current one
+const MAX_OLD_LOGS: usize = 2;
+
+/// All records of one transaction, folded together
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransactionSummary {
+    pub id: String,
+    pub started: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// `None` if the transaction never finished, e.g. it was interrupted
+    pub ok: Option<bool>,
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Step and command records in the order they happened
+    pub events: Vec<LogEvent>,
+}
+
+pub fn log_path() -> PathBuf {
+    let dir = if crate::simulate::is_enabled() { crate::simulate::state_dir() } else { get_state_dir() };
+    dir.join("archbox.log")
+}
+
+/// Start a transaction for a command, returning its id
+pub fn begin(command: &str, args: Vec<String>) -> String {
+    let mut id = format!("{}-{}", chrono::Local::now().format("%Y%m%d%H%M%S"), std::process::id());
+    let sequence = STARTED.fetch_add(1, Ordering::Relaxed);
+    if sequence > 0 {
+        id.push_str(&format!("-{}", sequence));
+    }
+
+    if let Ok(mut current) = CURRENT.lock() {
+        *current = Some(Transaction {
+            id: id.clone(),
+            started: Instant::now(),
+            summary: TransactionSummary {
+                id: id.clone(),
+                started: chrono::Utc::now().to_rfc3339(),
+                command: command.to_string(),
+                args: args.clone(),
+                ..Default::default()
+            },
+        });
+    }
+
+    append(&id, LogEvent::Begin {
+        command: command.to_string(),
+        args,
+    });
+    id
+}
+
+/// Record the outcome of one step of the current transaction
+pub fn record_step<T, E: std::fmt::Display>(
+    step: &str,
+    package: Option<&str>,
+    version: Option<&str>,
+    duration: Duration,
+    result: &std::result::Result<T, E>,
+) {
+    record(LogEvent::Step {
+        step: step.to_string(),
+        package: package.map(str::to_string),
+        version: version.map(str::to_string),
+        ok: result.is_ok(),
+        duration_ms: duration.as_millis() as u64,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+}
+
+/// Record a shell command run as part of the current transaction
+pub fn record_command(command: &str, duration: Duration, output: &std::process::Output) {
+    // Errors usually end up on stderr; fall back to stdout for quiet failures
+    let stream = if output.stderr.iter().any(|b| !b.is_ascii_whitespace()) {
+        &output.stderr
+    } else {
+        &output.stdout
+    };
+    let text = String::from_utf8_lossy(stream);
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+    record(LogEvent::Command {
+        command: command.to_string(),
+        ok: output.status.success(),
+        exit_code: output.status.code(),
+        duration_ms: duration.as_millis() as u64,
+        output: tail,
+    });
+}
+
+/// Close the current transaction, returning its summary
+pub fn finish<T, E: std::fmt::Display>(result: &std::result::Result<T, E>) -> Option<TransactionSummary> {
+    let transaction = match CURRENT.lock() {
+        Ok(mut current) => current.take(),
+        Err(_) => None,
+    };
+
+    let mut transaction = transaction?;
+    let duration_ms = transaction.started.elapsed().as_millis() as u64;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    append(&transaction.id, LogEvent::End {
+        ok: result.is_ok(),
+        duration_ms,
+        error: error.clone(),
+    });
+
+    transaction.summary.ok = Some(result.is_ok());
+    transaction.summary.duration_ms = Some(duration_ms);
+    transaction.summary.error = error;
+    Some(transaction.summary)
+}
+
+/// Append a step or command record to the current transaction
+fn record(event: LogEvent) {
+    let id = match CURRENT.lock() {
+        Ok(mut current) => match current.as_mut() {
+            Some(transaction) => {
+                transaction.summary.events.push(event.clone());
+                transaction.id.clone()
+            }
+            None => return,
+        },
+        Err(_) => return,
+    };
+
+    append(&id, event);
+}
+
+/// Id of the transaction in progress, if any
+pub fn current_id() -> Option<String> {
+    CURRENT.lock().ok()?.as_ref().map(|transaction| transaction.id.clone())
+}
+
+/// Append a record; logging must never make a command fail, so errors are only traced
+fn append(id: &str, event: LogEvent) {
+    let record = LogRecord {
+        id: id.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        event,
+    };
+
+    if let Err(e) = write_record(&record) {
+        debug!("Failed to write transaction log: {}", e);
+    }
+}
+
+fn write_record(record: &LogRecord) -> crate::Result<()> {
+    // Secrets resolved during the run can show up in command output and errors
+    let mut line = crate::secrets::redact(&serde_json::to_string(record)?);
+    line.push('\n');
+
+    let mut file = RotatingFile::open(log_path(), MAX_LOG_BYTES, MAX_OLD_LOGS)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Read every record from the rolled over logs and the current one, skipping lines that
+/// fail to parse
+pub fn read_records() -> crate::Result<Vec<LogRecord>> {
+    let path = log_path();
+    let mut files: Vec<_> = (1..=MAX_OLD_LOGS).rev().map(|index| rotated_path(&path, index)).collect();
+    files.push(path);
+
+    let mut records = Vec::new();
+    for file in files.iter().filter(|file| file.exists()) {
+        let content = std::fs::read_to_string(file)?;
+        records.extend(content.lines().filter_map(|line| serde_json::from_str::<LogRecord>(line).ok()));
+    }
+    Ok(records)
+}
+
+/// Every transaction in the log, oldest first
+pub fn transactions() -> crate::Result<Vec<TransactionSummary>> {
+    let mut transactions: Vec<TransactionSummary> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+
+    for record in read_records()? {
+        let index = match indices.get(&record.id) {
+            Some(index) => *index,
+            None => {
+                transactions.push(TransactionSummary {
+                    id: record.id.clone(),
+                    started: record.timestamp.clone(),
+                    ..Default::default()
+                });
+                indices.insert(record.id.clone(), transactions.len() - 1);
+                transactions.len() - 1
+            }
+        };
+        let transaction = &mut transactions[index];
+
+        match record.event {
+            LogEvent::Begin { command, args } => {
+                transaction.started = record.timestamp;
+                transaction.command = command;
+                transaction.args = args;
+            }
+            LogEvent::End { ok, duration_ms, error } => {
+                transaction.ok = Some(ok);
+                transaction.duration_ms = Some(duration_ms);
+                transaction.error = error;
+            }
+            event => transaction.events.push(event),
+        }
+    }
+
+    Ok(transactions)
+}
@@ -0,0 +1,106 @@
+//! HTTP clients for downloads and API requests
+//!
+//! Every client is built here, so the `network` settings from the config (extra CA
+//! certificates, a client certificate and whether to use the environment's proxy) and
+//! `installation.download_timeout` apply to all of them alike. API requests may take that
+//! long in total; downloads may take as long as they need while data keeps arriving.
+
+use crate::config::NetworkConfig;
+use crate::{Error, Result};
+use reqwest::{Certificate, ClientBuilder, Identity};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("archbox/", env!("CARGO_PKG_VERSION"));
+
+static SETTINGS: OnceLock<NetworkConfig> = OnceLock::new();
+static TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+
+/// Use these settings and this request timeout for every client built for the rest of the
+/// process
+pub fn configure(settings: &NetworkConfig, timeout: Option<Duration>) {
+    let _ = SETTINGS.set(settings.clone());
+    let _ = TIMEOUT.set(timeout);
+}
+
+/// How long a request may take, unless a client sets its own limit. For downloads this is
+/// how long connecting, and then waiting for each chunk, may take.
+pub fn timeout() -> Option<Duration> {
+    TIMEOUT.get().copied().flatten()
+}
+
+/// A client builder with ArchBox's user agent, the request timeout and the configured TLS
+/// and proxy settings
+pub fn client_builder() -> Result<ClientBuilder> {
+    let builder = download_client_builder()?;
+    Ok(match timeout() {
+        Some(timeout) => builder.timeout(timeout),
+        None => builder,
+    })
+}
+
+pub fn client() -> Result<reqwest::Client> {
+    Ok(client_builder()?.build()?)
+}
+
+/// A client for downloads of any size: only connecting is limited by the timeout, and
+/// readers wait for each chunk [`within`] it
+pub fn download_client() -> Result<reqwest::Client> {
+    Ok(download_client_builder()?.build()?)
+}
+
+fn download_client_builder() -> Result<ClientBuilder> {
+    let mut builder = reqwest::Client::builder().user_agent(USER_AGENT);
+    if let Some(timeout) = timeout() {
+        builder = builder.connect_timeout(timeout);
+    }
+    match SETTINGS.get() {
+        Some(settings) => apply(builder, settings),
+        None => Ok(builder),
+    }
+}
+
+/// Wait for one step of a request, such as the response headers or the next chunk of a
+/// download, failing once the timeout passes without it
+pub async fn within<T>(operation: &str, step: impl std::future::Future<Output = T>) -> Result<T> {
+    match timeout() {
+        Some(limit) => tokio::time::timeout(limit, step).await.map_err(|_| Error::Timeout {
+            operation: operation.to_string(),
+            seconds: limit.as_secs(),
+        }),
+        None => Ok(step.await),
+    }
+}
+
+/// Add the CA bundle, client certificate and proxy setting to `builder`
+fn apply(mut builder: ClientBuilder, settings: &NetworkConfig) -> Result<ClientBuilder> {
+    if let Some(path) = &settings.ca_bundle {
+        for certificate in Certificate::from_pem_bundle(&read(path, "CA bundle")?)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    match (&settings.client_certificate, &settings.client_key) {
+        (Some(certificate), Some(key)) => {
+            let identity = Identity::from_pkcs8_pem(&read(certificate, "client certificate")?, &read(key, "client key")?)?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err(Error::Config(
+                "network.client_certificate and network.client_key must be set together".to_string(),
+            ));
+        }
+    }
+
+    if settings.ignore_system_proxy {
+        builder = builder.no_proxy();
+    }
+
+    Ok(builder)
+}
+
+fn read(path: &Path, what: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| Error::Config(format!("Cannot read {} {}: {}", what, path.display(), e)))
+}
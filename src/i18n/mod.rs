@@ -0,0 +1,131 @@
+//! Localized CLI messages using Fluent. Translations live in `locales/<lang>/archbox.ftl`
+//! and are compiled into the binary; messages missing from a translation fall back to en-US.
+
+use fluent_bundle::{concurrent::FluentBundle, FluentResource};
+use std::sync::OnceLock;
+use tracing::warn;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::{FluentArgs, FluentValue};
+
+const DEFAULT_LOCALE: &str = "en-US";
+
+/// Bundled translations. Add an entry here when contributing a new locale.
+const LOCALES: &[(&str, &str)] = &[
+    ("en-US", include_str!("../../locales/en-US/archbox.ftl")),
+];
+
+type Bundle = FluentBundle<FluentResource>;
+
+/// Bundles in lookup order: the selected locale, then the en-US fallback
+static BUNDLES: OnceLock<Vec<Bundle>> = OnceLock::new();
+
+/// Select the message language. `language` (from `ui.language`) takes precedence over the
+/// environment. Messages looked up before this is called use the environment locale.
+pub fn init(language: Option<&str>) {
+    let _ = BUNDLES.set(load(language));
+}
+
+/// Look up a message, formatting it with `args`. Prefer the [`t!`](crate::t) macro.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let bundles = BUNDLES.get_or_init(|| load(None));
+
+    for bundle in bundles {
+        if let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) {
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, args, &mut errors);
+
+            if !errors.is_empty() {
+                warn!("Errors formatting message '{}': {:?}", id, errors);
+            }
+            return text.into_owned();
+        }
+    }
+
+    warn!("Missing translation for message '{}'", id);
+    id.to_string()
+}
+
+/// Locale from `LC_ALL`, `LC_MESSAGES` or `LANG` as a language tag (`de_DE.UTF-8` -> `de-DE`)
+pub fn system_locale() -> Option<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())?;
+
+    let tag = value.split(['.', '@']).next()?;
+    if tag.is_empty() || tag == "C" || tag == "POSIX" {
+        return None;
+    }
+
+    Some(tag.replace('_', "-"))
+}
+
+/// Names of the bundled locales
+pub fn available_locales() -> impl Iterator<Item = &'static str> {
+    LOCALES.iter().map(|(name, _)| *name)
+}
+
+fn load(language: Option<&str>) -> Vec<Bundle> {
+    let requested = language
+        .map(str::to_string)
+        .or_else(system_locale)
+        .and_then(|tag| tag.parse::<LanguageIdentifier>().ok());
+
+    let mut locales = Vec::new();
+    if let Some(locale) = requested.as_ref().and_then(negotiate) {
+        locales.push(locale);
+    }
+    if !locales.contains(&DEFAULT_LOCALE) {
+        locales.push(DEFAULT_LOCALE);
+    }
+
+    locales.into_iter().filter_map(bundle).collect()
+}
+
+/// Pick the bundled locale for `requested`: an exact match, else the same language
+fn negotiate(requested: &LanguageIdentifier) -> Option<&'static str> {
+    let parsed = || {
+        available_locales()
+            .filter_map(|name| name.parse::<LanguageIdentifier>().ok().map(|id| (name, id)))
+    };
+
+    parsed()
+        .find(|(_, id)| id == requested)
+        .or_else(|| parsed().find(|(_, id)| id.language == requested.language))
+        .map(|(name, _)| name)
+}
+
+fn bundle(name: &str) -> Option<Bundle> {
+    let source = LOCALES.iter().find(|(locale, _)| *locale == name)?.1;
+    let resource = match FluentResource::try_new(source.to_string()) {
+        Ok(resource) => resource,
+        Err((resource, errors)) => {
+            warn!("Errors parsing {} translations: {:?}", name, errors);
+            resource
+        }
+    };
+
+    let mut bundle = FluentBundle::new_concurrent(vec![name.parse().ok()?]);
+    // Unicode isolation marks only get in the way in a terminal
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        warn!("Errors loading {} translations: {:?}", name, errors);
+    }
+
+    Some(bundle)
+}
+
+/// Look up a localized CLI message: `t!("install-done")` or
+/// `t!("install-failed", package = name, error = e.to_string())`
+#[macro_export]
+macro_rules! t {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $crate::i18n::FluentValue::from($value));)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
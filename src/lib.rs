@@ -1,8 +1,34 @@
+pub mod backup;
+pub mod cache;
 pub mod cli;
+pub mod cloud_init;
+pub mod completions;
 pub mod config;
+pub mod container;
+pub mod daemon;
+pub mod drift;
 pub mod error;
+pub mod explain;
+pub mod groups;
+pub mod hardware;
+pub mod history;
+pub mod http;
+pub mod i18n;
+pub mod lock;
+pub mod logging;
+pub mod notify;
 pub mod package;
+pub mod privilege;
+pub mod progress;
+pub mod project;
 pub mod repository;
+pub mod secrets;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shell;
+pub mod simulate;
+pub mod state;
+pub mod workdir;
 
 pub use error::{Error, Result};
 
@@ -16,8 +42,17 @@ pub struct App {
 impl App {
     /// Initialize a new application instance
     pub async fn new() -> Result<Self> {
-        let config = config::Config::load()?;
-        let repository = repository::Manager::new(&config).await?;
+        Self::with_config(config::Config::load()?).await
+    }
+    
+    /// Initialize an application instance from an already loaded configuration. With
+    /// [`simulate`] enabled, commands are reported instead of run.
+    pub async fn with_config(config: config::Config) -> Result<Self> {
+        let repository = if simulate::is_enabled() {
+            repository::Manager::with_runner(&config, std::sync::Arc::new(simulate::SimulatedRunner)).await?
+        } else {
+            repository::Manager::new(&config).await?
+        };
         
         Ok(Self { config, repository })
     }
@@ -0,0 +1,76 @@
+//! The transaction lock, held by every command that changes the system
+//!
+//! Two ArchBox runs installing or removing at the same time would interleave pacman calls,
+//! file writes and state database updates. Commands that change something take an exclusive
+//! lock on `$XDG_RUNTIME_DIR/archbox.lock` first, and write who holds it into the file so a
+//! second run can say what it is waiting for. The kernel releases the lock when the holder
+//! exits, so a crashed run never leaves it stuck.
+
+use crate::{Error, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+use tracing::debug;
+
+/// Held for as long as it lives
+#[derive(Debug)]
+pub struct TransactionLock {
+    _file: File,
+}
+
+/// `$XDG_RUNTIME_DIR/archbox.lock`, or `archbox.lock` in the state directory when there is
+/// no runtime directory
+pub fn lock_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(crate::config::get_state_dir)
+        .join("archbox.lock")
+}
+
+/// Take the lock for `command`. When another run holds it, fail naming the holder, or with
+/// `wait` call `on_wait` with the holder and block until it is released.
+pub async fn acquire(command: &str, wait: bool, on_wait: impl FnOnce(&str)) -> Result<TransactionLock> {
+    let path = lock_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let holder = holder(&mut file);
+            if !wait {
+                return Err(Error::Locked { holder });
+            }
+
+            on_wait(&holder);
+            file = tokio::task::spawn_blocking(move || file.lock().map(|()| file))
+                .await
+                .map_err(|e| Error::Io(std::io::Error::other(e)))??;
+        }
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    file.set_len(0)?;
+    file.rewind()?;
+    writeln!(file, "{}\t{}\t{}", std::process::id(), chrono::Local::now().to_rfc3339(), command)?;
+    debug!("Took the transaction lock {}", path.display());
+
+    Ok(TransactionLock { _file: file })
+}
+
+/// Who holds the lock, as written by [`acquire`]: `PID 4242 (install ripgrep) since 2026-10-18 14:02:11`
+fn holder(file: &mut File) -> String {
+    let mut content = String::new();
+    let _ = file.read_to_string(&mut content);
+
+    let mut fields = content.trim_end().splitn(3, '\t');
+    let (Some(pid), Some(since), Some(command)) = (fields.next(), fields.next(), fields.next()) else {
+        return "unknown process".to_string();
+    };
+
+    let since = chrono::DateTime::parse_from_rfc3339(since)
+        .map(|since| since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| since.to_string());
+    format!("PID {} ({}) since {}", pid, command, since)
+}
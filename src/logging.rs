@@ -0,0 +1,113 @@
+//! Tracing setup: terminal output plus an optional size-rotated log file
+
+use crate::{config::UiConfig, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+/// Initialize tracing. `log_file` (from `--log-file`) takes precedence over `ui.log_file`.
+/// With `quiet` only errors reach the terminal; the log file keeps its own level.
+pub fn init(verbose: bool, quiet: bool, log_file: Option<&Path>, ui: &UiConfig) -> Result<()> {
+    let terminal_level = if quiet {
+        "error"
+    } else if verbose {
+        "debug"
+    } else {
+        ui.log_level.as_str()
+    };
+    let terminal_filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("archbox={}", terminal_level)));
+
+    let file_layer = match log_file.or(ui.log_file.as_deref()) {
+        Some(path) => {
+            let path = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref());
+            let writer = RotatingFile::open(path, ui.log_max_size_mb * 1024 * 1024, ui.log_max_files)?;
+
+            Some(
+                fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(writer))
+                    .with_filter(EnvFilter::new(format!("archbox={}", ui.log_file_level))),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(|| crate::progress::SuspendingStderr).with_filter(terminal_filter))
+        .with(file_layer)
+        .init();
+
+    Ok(())
+}
+
+/// Append-only log file that rolls over to `<name>.1`, `<name>.2`, ... once it reaches
+/// `max_bytes`, keeping at most `max_files` old files
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub(crate) fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self { path, max_bytes, max_files, file, size })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        rotated_path(&self.path, index)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated_path(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Path of the `index`th old file of a rotated log, `<path>.<index>`
+pub(crate) fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.max_bytes > 0 && self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
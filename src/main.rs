@@ -1,17 +1,10 @@
-use ArchBox::cli;
-use ArchBox::Result;
-use tracing_subscriber;
+use archbox::cli;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("archbox=info".parse().unwrap()),
-        )
-        .init();
-
+async fn main() {
     // Run the CLI
-    cli::run().await
+    if let Err(e) = cli::run().await {
+        cli::report_error(&e);
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
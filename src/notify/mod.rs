@@ -0,0 +1,109 @@
+//! Webhook notifications after transactions
+//!
+//! When `notifications.webhook_url` is set, a JSON summary of the transaction is POSTed to
+//! it once the command finishes. The payload carries a ready-made `text` line, which Slack
+//! and Mattermost incoming webhooks (and bridges like ntfy or Matrix hookshot) display as-is,
+//! alongside the full transaction for tools that want the details.
+
+use crate::config::{NotificationsConfig, NotifyOn};
+use crate::history::{LogEvent, TransactionSummary};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    text: String,
+    host: String,
+    #[serde(flatten)]
+    transaction: &'a TransactionSummary,
+}
+
+/// Whether a transaction is worth a notification. Commands that only read (search, list,
+/// info, ...) record no steps, so they are only reported when they fail.
+pub fn should_notify(config: &NotificationsConfig, transaction: &TransactionSummary) -> bool {
+    if config.webhook_url.is_none() {
+        return false;
+    }
+
+    let failed = transaction.ok == Some(false);
+    match config.on {
+        NotifyOn::Failure => failed,
+        NotifyOn::Always => failed || transaction.events.iter().any(|event| matches!(event, LogEvent::Step { .. })),
+    }
+}
+
+/// Send the notification for a finished transaction. Failures are only logged: a broken
+/// webhook must never make the command itself fail.
+pub async fn send(config: &NotificationsConfig, transaction: &TransactionSummary) {
+    if !should_notify(config, transaction) {
+        return;
+    }
+    let Some(url) = &config.webhook_url else {
+        return;
+    };
+
+    if let Err(e) = post(url, transaction).await {
+        warn!("Failed to send the webhook notification: {}", e);
+    }
+}
+
+async fn post(url: &str, transaction: &TransactionSummary) -> crate::Result<()> {
+    let url = crate::secrets::resolve(url).await?;
+    let host = crate::package::template::hostname();
+    let payload = Payload {
+        text: summary_line(&host, transaction),
+        host,
+        transaction,
+    };
+    // Command output and errors can contain secrets resolved during the run
+    let body = crate::secrets::redact(&serde_json::to_string(&payload)?);
+
+    debug!("Sending webhook notification for transaction {}", transaction.id);
+    crate::http::client_builder()?
+        .timeout(TIMEOUT)
+        .build()?
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// One line for chat messages, e.g. `archbox update on nas: failed (2 of 5 steps succeeded): ...`
+fn summary_line(host: &str, transaction: &TransactionSummary) -> String {
+    let steps: Vec<(bool, Option<&str>)> = transaction
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            LogEvent::Step { ok, package, .. } => Some((*ok, package.as_deref())),
+            _ => None,
+        })
+        .collect();
+    let succeeded = steps.iter().filter(|(ok, _)| *ok).count();
+    let mut packages: Vec<&str> = steps.iter().filter_map(|(_, package)| *package).collect();
+    packages.dedup();
+
+    let plural = |count: usize| if count == 1 { "step" } else { "steps" };
+    let mut line = format!("archbox {} on {}: ", transaction.command, host);
+    if transaction.ok == Some(false) {
+        line.push_str("failed");
+        if !steps.is_empty() {
+            line.push_str(&format!(" ({} of {} {} succeeded)", succeeded, steps.len(), plural(steps.len())));
+        }
+        if let Some(error) = &transaction.error {
+            line.push_str(&format!(": {}", error));
+        }
+    } else {
+        line.push_str(&format!("succeeded, {} {}", steps.len(), plural(steps.len())));
+    }
+    if !packages.is_empty() {
+        line.push_str(&format!(" ({})", packages.join(", ")));
+    }
+    line
+}
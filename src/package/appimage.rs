@@ -0,0 +1,217 @@
+//! AppImage update information (the `.upd_info` ELF section used by AppImageUpdate)
+
+use super::runner::{CommandRunner, CommandSpec};
+use crate::{Error, Result};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::debug;
+
+/// Parsed AppImage update information
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateInfo {
+    /// `zsync|<url to .zsync file>`
+    Zsync { url: String },
+
+    /// `gh-releases-zsync|<owner>|<repo>|<tag or latest>|<.zsync asset pattern>`
+    GithubReleases {
+        owner: String,
+        repo: String,
+        tag: String,
+        pattern: String,
+    },
+}
+
+impl UpdateInfo {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let fields: Vec<&str> = raw.trim().split('|').collect();
+
+        match fields.as_slice() {
+            ["zsync", url] => Some(UpdateInfo::Zsync { url: url.to_string() }),
+            ["gh-releases-zsync", owner, repo, tag, pattern] => Some(UpdateInfo::GithubReleases {
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                tag: tag.to_string(),
+                pattern: pattern.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Resolve the URL of the `.zsync` control file for this update source
+    pub async fn zsync_url(&self) -> Result<Option<String>> {
+        match self {
+            UpdateInfo::Zsync { url } => Ok(Some(url.clone())),
+            UpdateInfo::GithubReleases { owner, repo, tag, pattern } => {
+                let endpoint = if tag == "latest" {
+                    format!("https://api.github.com/repos/{}/{}/releases/latest", owner, repo)
+                } else {
+                    format!("https://api.github.com/repos/{}/{}/releases/tags/{}", owner, repo, tag)
+                };
+
+                let client = crate::http::client()?;
+                let release = super::http_cache::get_json(&client, &endpoint, super::http_cache::GITHUB_TTL).await?;
+
+                let url = release["assets"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|asset| {
+                        asset["name"]
+                            .as_str()
+                            .is_some_and(|name| wildcard_match(pattern, name))
+                    })
+                    .and_then(|asset| asset["browser_download_url"].as_str())
+                    .map(str::to_string);
+
+                Ok(url)
+            }
+        }
+    }
+}
+
+/// Largest section table or section read from an AppImage; real ones are a few kilobytes
+const MAX_READ: u64 = 16 * 1024 * 1024;
+
+/// Read the update information embedded in an AppImage, if any. Only the ELF header,
+/// section table and the sections needed are read, not the whole image.
+pub async fn read_update_info(path: &Path) -> Result<Option<UpdateInfo>> {
+    let image = path.to_path_buf();
+    let raw = tokio::task::spawn_blocking(move || -> Result<Option<Vec<u8>>> {
+        let mut file = File::open(&image)?;
+        Ok(find_elf_section(&mut file, ".upd_info"))
+    })
+    .await
+    .map_err(|e| Error::Io(std::io::Error::other(e)))??;
+
+    let raw = match raw {
+        Some(section) => section,
+        None => return Ok(None),
+    };
+
+    let text = String::from_utf8_lossy(&raw);
+    let text = text.trim_end_matches('\0').trim();
+    debug!("AppImage update information for {}: {:?}", path.display(), text);
+
+    Ok(UpdateInfo::parse(text))
+}
+
+/// Read `len` bytes at `offset`, or `None` if the file is too short or `len` is implausible
+fn read_at(file: &mut File, offset: u64, len: u64) -> Option<Vec<u8>> {
+    if len > MAX_READ {
+        return None;
+    }
+
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buffer = vec![0; usize::try_from(len).ok()?];
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+/// Locate a named section in an ELF image and return its contents
+fn find_elf_section(file: &mut File, name: &str) -> Option<Vec<u8>> {
+    let header = read_at(file, 0, 0x40)?;
+    if &header[..4] != b"\x7fELF" {
+        return None;
+    }
+
+    let is_64 = header[4] == 2;
+    let big_endian = header[5] == 2;
+
+    let read = |data: &[u8], offset: u64, size: usize| -> Option<u64> {
+        let start = usize::try_from(offset).ok()?;
+        let bytes = data.get(start..start.checked_add(size)?)?;
+        let mut value = 0u64;
+        for i in 0..size {
+            let byte = if big_endian { bytes[i] } else { bytes[size - 1 - i] };
+            value = (value << 8) | byte as u64;
+        }
+        Some(value)
+    };
+
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (read(&header, 0x28, 8)?, read(&header, 0x3A, 2)?, read(&header, 0x3C, 2)?, read(&header, 0x3E, 2)?)
+    } else {
+        (read(&header, 0x20, 4)?, read(&header, 0x2E, 2)?, read(&header, 0x30, 2)?, read(&header, 0x32, 2)?)
+    };
+
+    let table = read_at(file, shoff, shentsize.checked_mul(shnum)?)?;
+
+    // (name offset, data offset, data size) of section `index`
+    let section = |index: u64| -> Option<(u64, u64, u64)> {
+        let base = index.checked_mul(shentsize)?;
+        if is_64 {
+            Some((read(&table, base, 4)?, read(&table, base.checked_add(0x18)?, 8)?, read(&table, base.checked_add(0x20)?, 8)?))
+        } else {
+            Some((read(&table, base, 4)?, read(&table, base.checked_add(0x10)?, 4)?, read(&table, base.checked_add(0x14)?, 4)?))
+        }
+    };
+
+    let (_, strtab_offset, strtab_size) = section(shstrndx)?;
+    let strtab = read_at(file, strtab_offset, strtab_size)?;
+
+    for index in 0..shnum {
+        let (name_offset, offset, size) = section(index)?;
+        let name_bytes = strtab.get(usize::try_from(name_offset).ok()?..)?;
+        let name_end = name_bytes.iter().position(|&b| b == 0)?;
+
+        if &name_bytes[..name_end] == name.as_bytes() {
+            return read_at(file, offset, size);
+        }
+    }
+
+    None
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+
+    if !text.starts_with(first) || !text.ends_with(last) || text.len() < first.len() + last.len() {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+/// Delta-update an AppImage in place with `zsync`, using the existing file as the seed
+pub async fn zsync_update(runner: &dyn CommandRunner, path: &Path, zsync_url: &str) -> Result<()> {
+    let temp_path = path.with_extension("AppImage.part");
+
+    let output = runner
+        .output(CommandSpec::new("zsync").arg("-i").arg(path).arg("-o").arg(&temp_path).arg(zsync_url))
+        .await?;
+
+    if !output.status.success() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(Error::InstallationFailed(format!(
+            "zsync update failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+
+    tokio::fs::rename(&temp_path, path).await?;
+
+    Ok(())
+}
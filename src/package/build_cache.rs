@@ -0,0 +1,149 @@
+//! Cache of built source trees, so reinstalling the same commit skips recompilation
+//!
+//! Entries live in `~/.cache/archbox/builds/<pkg>/<key>/`, where the key hashes the source
+//! URL, the commit and the build commands. Only the newest entry per package is kept.
+
+use super::runner::{CommandRunner, CommandSpec};
+use crate::{config::get_cache_dir, Error, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+fn package_cache_dir(package: &str) -> PathBuf {
+    get_cache_dir().join("builds").join(package)
+}
+
+/// Cache key for a build of `commit` from `url` with the given build commands
+pub fn cache_key(url: &str, commit: &str, build_commands: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    hasher.update([0]);
+    hasher.update(commit.as_bytes());
+    for command in build_commands {
+        hasher.update([0]);
+        hasher.update(command.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Commit a remote git repository's HEAD points at, without cloning it
+pub async fn remote_head(runner: &dyn CommandRunner, url: &str) -> Option<String> {
+    let output = runner
+        .output(CommandSpec::new("git").args(["ls-remote", url, "HEAD"]))
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+}
+
+/// Cached build tree for a key, if one exists
+pub fn lookup(package: &str, key: &str) -> Option<PathBuf> {
+    let dir = package_cache_dir(package).join(key);
+    dir.is_dir().then_some(dir)
+}
+
+/// Copy a freshly built tree into the cache, replacing older builds of the package
+pub async fn store(runner: &dyn CommandRunner, package: &str, key: &str, work_dir: &Path) -> Result<()> {
+    let package_dir = package_cache_dir(package);
+    let staging = package_dir.join(format!(".{}.partial", key));
+
+    if staging.exists() {
+        tokio::fs::remove_dir_all(&staging).await?;
+    }
+    tokio::fs::create_dir_all(&staging).await?;
+    copy_tree(runner, work_dir, &staging).await?;
+
+    let mut entries = tokio::fs::read_dir(&package_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path != staging && entry.file_type().await?.is_dir() {
+            debug!("Evicting cached build {}", path.display());
+            tokio::fs::remove_dir_all(&path).await?;
+        }
+    }
+
+    tokio::fs::rename(&staging, package_dir.join(key)).await?;
+    Ok(())
+}
+
+/// Drop every cached build of a package
+pub async fn clear(package: &str) -> Result<()> {
+    let package_dir = package_cache_dir(package);
+    if package_dir.exists() {
+        debug!("Clearing cached builds in {}", package_dir.display());
+        tokio::fs::remove_dir_all(&package_dir).await?;
+    }
+    Ok(())
+}
+
+/// Populate a work directory from a cached build tree
+pub async fn restore(runner: &dyn CommandRunner, cached: &Path, work_dir: &Path) -> Result<()> {
+    copy_tree(runner, cached, work_dir).await
+}
+
+/// Copy a directory's contents, preserving permissions, timestamps and symlinks
+async fn copy_tree(runner: &dyn CommandRunner, from: &Path, to: &Path) -> Result<()> {
+    let output = runner
+        .output(CommandSpec::new("cp").arg("-a").arg(format!("{}/.", from.display())).arg(to))
+        .await?;
+
+    if !output.status.success() {
+        return Err(Error::CommandFailed {
+            message: format!(
+                "Failed to copy {} to {}: {}",
+                from.display(),
+                to.display(),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Environment for build commands that routes compilers through ccache and/or sccache
+/// when they are installed
+pub fn compiler_cache_env() -> Vec<(String, String)> {
+    let mut env = Vec::new();
+
+    let ccache_bin = Path::new("/usr/lib/ccache/bin");
+    let has_ccache = ccache_bin.is_dir();
+    let has_sccache = which("sccache");
+
+    if has_ccache {
+        let path = std::env::var("PATH").unwrap_or_default();
+        env.push(("PATH".to_string(), format!("{}:{}", ccache_bin.display(), path)));
+    }
+
+    if has_sccache {
+        env.push(("RUSTC_WRAPPER".to_string(), "sccache".to_string()));
+    }
+
+    let launcher = if has_ccache {
+        Some("ccache")
+    } else if has_sccache {
+        Some("sccache")
+    } else {
+        None
+    };
+
+    if let Some(launcher) = launcher {
+        env.push(("CMAKE_C_COMPILER_LAUNCHER".to_string(), launcher.to_string()));
+        env.push(("CMAKE_CXX_COMPILER_LAUNCHER".to_string(), launcher.to_string()));
+    }
+
+    env
+}
+
+fn which(command: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|dir| dir.join(command).is_file()))
+        .unwrap_or(false)
+}
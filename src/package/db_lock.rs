@@ -0,0 +1,120 @@
+//! Waiting out another package manager that holds pacman's database lock
+//!
+//! pacman, and AUR helpers through it, fail at once with "unable to lock database" while
+//! another transaction runs, such as an upgrade timer or a software center. pacman and AUR
+//! helper commands run through [`LockWaitRunner`] are retried once `db.lck` is gone, for up
+//! to `installation.lock_wait` seconds, instead of failing the whole transaction.
+
+use super::installer::{DETECTED_HELPERS, NATIVE_HELPER};
+use super::runner::{CommandRunner, CommandSpec};
+use crate::progress;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// How often the lock file is checked while waiting
+const POLL: Duration = Duration::from_secs(1);
+
+/// Retries commands that failed because the pacman database was locked
+#[derive(Debug)]
+pub struct LockWaitRunner {
+    inner: Arc<dyn CommandRunner>,
+    max_wait: Duration,
+    aur_helper: Option<String>,
+}
+
+impl LockWaitRunner {
+    /// Retry through `inner` for up to `max_wait` in total; zero doesn't retry at all
+    pub fn new(inner: Arc<dyn CommandRunner>, max_wait: Duration) -> Self {
+        Self { inner, max_wait, aur_helper: None }
+    }
+    
+    /// Also retry `helper`, the configured AUR helper; without one, the helpers the
+    /// installer looks for are retried
+    pub fn with_aur_helper(mut self, helper: Option<String>) -> Self {
+        self.aur_helper = helper;
+        self
+    }
+    
+    /// Whether `command` runs pacman or an AUR helper, directly or through sudo or pkexec.
+    /// Anything else may print the same message without holding the lock, so it isn't retried.
+    fn takes_lock(&self, command: &CommandSpec) -> bool {
+        let program = match command.program.to_str() {
+            Some("sudo" | "pkexec") => command.args.first().and_then(|arg| arg.to_str()),
+            program => program,
+        };
+        let Some(program) = program.map(|program| program.rsplit('/').next().unwrap_or(program)) else {
+            return false;
+        };
+        
+        program == "pacman"
+            || match self.aur_helper.as_deref() {
+                Some(NATIVE_HELPER) => false,
+                Some(helper) => program == helper,
+                None => DETECTED_HELPERS.contains(&program),
+            }
+    }
+}
+
+#[async_trait]
+impl CommandRunner for LockWaitRunner {
+    async fn output(&self, command: &CommandSpec) -> std::io::Result<Output> {
+        let started = Instant::now();
+        loop {
+            let output = self.inner.output(command).await?;
+            let waited = started.elapsed();
+            if !is_lock_error(&output) || waited >= self.max_wait || !self.takes_lock(command) {
+                return Ok(output);
+            }
+            
+            info!("The pacman database is locked; retrying {} once it is released", command);
+            wait_until_unlocked(&lock_file(sysroot(command).as_deref()), self.max_wait - waited).await;
+        }
+    }
+}
+
+/// Whether a failed command said it couldn't lock the pacman database
+pub fn is_lock_error(output: &Output) -> bool {
+    !output.status.success()
+        && [&output.stderr, &output.stdout].into_iter().any(|text| {
+            let text = String::from_utf8_lossy(text);
+            text.contains("unable to lock database") || text.contains("db.lck")
+        })
+}
+
+/// pacman's lock file for the system at `root`, or for this one
+pub fn lock_file(root: Option<&Path>) -> PathBuf {
+    root.unwrap_or(Path::new("/")).join("var/lib/pacman/db.lck")
+}
+
+/// The `--sysroot` a pacman command works on, if it was given one
+fn sysroot(command: &CommandSpec) -> Option<PathBuf> {
+    let index = command.args.iter().position(|arg| arg == "--sysroot")?;
+    command.args.get(index + 1).map(PathBuf::from)
+}
+
+/// Wait with a countdown until `lock_file` is gone or `limit` has passed, returning
+/// whether it is gone. This always waits a moment first, since a lock held elsewhere,
+/// e.g. by a helper's own pacman, doesn't show up in the file.
+pub async fn wait_until_unlocked(lock_file: &Path, limit: Duration) -> bool {
+    let deadline = Instant::now() + limit;
+    let pb = progress::spinner("");
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        pb.set_message(format!(
+            "Waiting for another package manager to release {} ({}s left)...",
+            lock_file.display(),
+            left.as_secs()
+        ));
+        tokio::time::sleep(POLL.min(left)).await;
+        
+        if !lock_file.exists() || Instant::now() >= deadline {
+            break;
+        }
+    }
+    pb.finish_and_clear();
+    !lock_file.exists()
+}
@@ -1,21 +1,55 @@
-use crate::{package::Package, Error, Result};
-use serde_yaml;
-use std::collections::HashMap;
-use std::path::Path;
+use crate::{package::{template::TemplateContext, version, BinaryFallback, Package, PacmanHook, PacmanHookWhen}, Error, Result};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_yaml::{self, Mapping, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::warn;
 use walkdir::WalkDir;
 
+/// Version of this ArchBox, compared against `requires_archbox`
+pub const ARCHBOX_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A definition file skipped while loading a directory, and why
+#[derive(Debug, Clone)]
+pub struct LoadFailure {
+    pub path: PathBuf,
+    /// What is wrong, e.g. `fd.version: invalid type: floating point `9.1`, expected a
+    /// string at line 3 column 12`
+    pub reason: String,
+}
+
 #[derive(Debug)]
 pub struct DefinitionLoader {
     packages: HashMap<String, Package>,
+    /// Shared definition fields by name, from `templates` directories
+    templates: HashMap<String, Mapping>,
+    /// Definitions skipped because they need a newer ArchBox, with the requirement
+    incompatible: HashMap<String, String>,
+    failures: Vec<LoadFailure>,
+    /// SHA-256 of each definition, taken as it is loaded or overridden
+    digests: HashMap<String, [u8; 32]>,
+    /// [`definitions_hash`](Self::definitions_hash), worked out once it is asked for
+    definitions_hash: OnceLock<String>,
 }
 
 impl DefinitionLoader {
     pub fn new() -> Self {
         Self {
             packages: HashMap::new(),
+            templates: HashMap::new(),
+            incompatible: HashMap::new(),
+            failures: Vec::new(),
+            digests: HashMap::new(),
+            definitions_hash: OnceLock::new(),
         }
     }
     
+    /// Load every definition file under `path`, after the templates in its `templates`
+    /// directories. A file that can't be loaded is skipped and recorded in
+    /// [`failures`](Self::failures), so one broken file doesn't hide the rest.
     pub async fn load_from_directory<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let path = path.as_ref();
         
@@ -23,40 +57,309 @@ impl DefinitionLoader {
             return Err(Error::Config(format!("Package directory not found: {}", path.display())));
         }
         
-        for entry in WalkDir::new(path)
+        let mut files: Vec<PathBuf> = WalkDir::new(path)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "yaml" || ext == "yml"))
-        {
-            self.load_definition_file(entry.path()).await?;
+            .filter(|e| is_yaml(e.path()))
+            .map(|e| e.into_path())
+            .collect();
+        files.sort();
+        
+        let (templates, files): (Vec<PathBuf>, Vec<PathBuf>) = files.into_iter().partition(|file| is_template(file));
+        self.load_templates(templates).await;
+        
+        for file in files {
+            let result = match tokio::fs::read_to_string(&file).await {
+                Ok(content) => self.parse_definitions(&content),
+                Err(e) => Err(e.to_string()),
+            };
+            
+            match result {
+                Ok(packages) => self.insert(packages),
+                Err(reason) => {
+                    warn!("Skipping {}: {}", file.display(), reason);
+                    self.failures.push(LoadFailure { path: file, reason });
+                }
+            }
         }
         
         Ok(())
     }
     
+    /// Files skipped by [`load_from_directory`](Self::load_from_directory), in the order
+    /// they were loaded
+    pub fn failures(&self) -> &[LoadFailure] {
+        &self.failures
+    }
+    
+    /// Load the definitions in one file, with the templates in a `templates` directory next
+    /// to it
     pub async fn load_definition_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        if let Some(Ok(entries)) = path.as_ref().parent().map(|dir| std::fs::read_dir(dir.join("templates"))) {
+            let mut templates: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|path| path.is_file() && is_yaml(path))
+                .collect();
+            templates.sort();
+            self.load_templates(templates).await;
+        }
+        
         let content = tokio::fs::read_to_string(path.as_ref()).await?;
+        self.load_definitions(&content, path.as_ref())
+    }
+    
+    /// Load the definitions `source` contains: a single definition, or a map of package
+    /// names to definitions. If any of them is invalid nothing is loaded, and the error names
+    /// the file, the package and what is wrong with it.
+    pub fn load_definitions(&mut self, content: &str, source: &Path) -> Result<()> {
+        let packages = self.parse_definitions(content).map_err(|reason| {
+            Error::Config(format!("Invalid package definition in {}: {}", source.display(), reason))
+        })?;
+        self.insert(packages);
         
-        if let Ok(package) = serde_yaml::from_str::<Package>(&content) {
-            self.validate_package(&package)?;
+        Ok(())
+    }
+    
+    fn insert(&mut self, packages: Vec<Package>) {
+        for package in packages {
+            // A JSON value orders its keys, unlike the maps inside a definition
+            let definition = serde_json::to_value(&package).map(|value| value.to_string()).unwrap_or_default();
+            self.digests.insert(package.name.clone(), Sha256::digest(definition).into());
             self.packages.insert(package.name.clone(), package);
-            return Ok(());
         }
+        self.definitions_hash = OnceLock::new();
+    }
+    
+    /// Hash of every loaded definition, which changes whenever one is loaded or overridden
+    pub fn definitions_hash(&self) -> &str {
+        self.definitions_hash.get_or_init(|| {
+            let mut names: Vec<&String> = self.digests.keys().collect();
+            names.sort();
+            
+            let mut hasher = Sha256::new();
+            for name in names {
+                hasher.update(name);
+                hasher.update([0]);
+                hasher.update(self.digests[name]);
+            }
+            format!("{:x}", hasher.finalize())
+        })
+    }
+    
+    /// Load template files, each named after its file. A template that can't be loaded is
+    /// recorded in [`failures`](Self::failures), and definitions using it fail.
+    async fn load_templates(&mut self, files: Vec<PathBuf>) {
+        for file in files {
+            let name = file.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+            let result = match tokio::fs::read_to_string(&file).await {
+                Ok(content) => parse_template(&content),
+                Err(e) => Err(e.to_string()),
+            };
+            
+            match result {
+                Ok(fields) => {
+                    self.templates.insert(name, fields);
+                }
+                Err(reason) => {
+                    warn!("Skipping template {}: {}", file.display(), reason);
+                    self.failures.push(LoadFailure { path: file, reason });
+                }
+            }
+        }
+    }
+    
+    /// The definitions in `content`, or why they can't be loaded. Definitions are
+    /// deserialized from the text rather than a parsed `Value` so that serde_yaml's errors
+    /// carry the field path and the line and column.
+    fn parse_definitions(&mut self, content: &str) -> std::result::Result<Vec<Package>, String> {
+        let value: Value = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        
+        // A single definition has its fields at the top level, though with a template its
+        // `installation` may come from there
+        if value.get("installation").is_some() || value.get("template").is_some_and(Value::is_string) {
+            let Value::Mapping(fields) = &value else {
+                unreachable!("only maps have fields");
+            };
+            if !fields.contains_key("template") {
+                return self.parse_definition(&value, content);
+            }
+            
+            let name = fields.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            let merged = Value::Mapping(self.with_template(&Mapping::new(), fields, &name)?);
+            let content = serde_yaml::to_string(&merged).map_err(|e| e.to_string())?;
+            return self.parse_definition(&merged, &content).map_err(without_line);
+        }
+        
+        let Value::Mapping(entries) = &value else {
+            return Err(format!(
+                "expected a package definition or a map of package names to definitions, found {}",
+                describe(&value)
+            ));
+        };
+        
+        // A `defaults` entry holds fields shared by every definition in the file, and a
+        // `template` names shared fields kept with the definitions. The merged definitions are
+        // deserialized from text again, but their line numbers would point into that text
+        // rather than the file, so errors leave them out.
+        let templated = entries.values().any(|entry| entry.get("template").is_some());
+        if entries.contains_key("defaults") || templated {
+            let merged = Value::Mapping(self.with_defaults(entries)?);
+            let content = serde_yaml::to_string(&merged).map_err(|e| e.to_string())?;
+            return self.parse_definition_map(&merged, &content).map_err(without_line);
+        }
+        
+        self.parse_definition_map(&value, content)
+    }
+    
+    /// A single definition, parsed as `value` and deserialized from `content`
+    fn parse_definition(&mut self, value: &Value, content: &str) -> std::result::Result<Vec<Package>, String> {
+        // Checked before deserializing, since a definition written for a newer ArchBox may
+        // use fields this one can't parse
+        if let Some(required) = value.get("requires_archbox") {
+            let name = value.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            if !self.check_requirement(&name, required).map_err(reason)? {
+                return Ok(Vec::new());
+            }
+        }
+        
+        let package: Package = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        self.validate_package(&package).map_err(reason)?;
+        Ok(vec![package])
+    }
+    
+    /// The entries of a definition map with its `defaults` and their templates merged into
+    /// each of them, and the `defaults` entry itself left out. Fields of an entry replace the
+    /// default ones, except that maps such as `metadata` are merged key by key. `installation`
+    /// defaults only apply to entries using the same method, or not naming one. Entries take
+    /// their name from their key.
+    fn with_defaults(&self, entries: &Mapping) -> std::result::Result<Mapping, String> {
+        let defaults = match entries.get("defaults") {
+            Some(Value::Mapping(defaults)) => defaults.clone(),
+            Some(defaults) => {
+                return Err(format!("defaults must be a map of definition fields, found {}", describe(defaults)));
+            }
+            None => Mapping::new(),
+        };
+        if defaults.contains_key("name") {
+            return Err("defaults can't set a name, each package takes it from its key".to_string());
+        }
+        
+        let mut merged = Mapping::new();
+        for (key, entry) in entries {
+            if key.as_str() == Some("defaults") {
+                continue;
+            }
+            
+            // Anything else is reported when the entry is deserialized
+            let Value::Mapping(fields) = entry else {
+                merged.insert(key.clone(), entry.clone());
+                continue;
+            };
+            let name = key.as_str().unwrap_or_default();
+            let mut fields = self.with_template(&defaults, fields, name)?;
+            if !fields.contains_key("name") {
+                fields.insert(Value::from("name"), key.clone());
+            }
+            merged.insert(key.clone(), Value::Mapping(fields));
+        }
+        
+        Ok(merged)
+    }
+    
+    /// `fields` on top of their template on top of `defaults`, the template being named by
+    /// `fields` or else by `defaults`. `{name}`, `{version}` and `{repository}` in the
+    /// template are filled from the definition, whose `template` key is dropped.
+    fn with_template(&self, defaults: &Mapping, fields: &Mapping, package: &str) -> std::result::Result<Mapping, String> {
+        let Some(named) = fields.get("template").or_else(|| defaults.get("template")) else {
+            return Ok(merge(defaults, fields));
+        };
+        let mut base = defaults.clone();
+        base.remove("template");
+        
+        let Some(template_name) = named.as_str() else {
+            return Err(format!("{}.template: expected a template name, found {}", package, describe(named)));
+        };
+        let Some(template) = self.templates.get(template_name) else {
+            let mut known: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+            known.sort();
+            let known = if known.is_empty() { "none".to_string() } else { known.join(", ") };
+            return Err(format!("{}.template: unknown template {} (known: {})", package, template_name, known));
+        };
+        
+        let merged = merge(&merge(&base, template), fields);
+        let mut variables = HashMap::from([("name".to_string(), package.to_string())]);
+        if let Some(version) = merged.get("version").and_then(Value::as_str) {
+            variables.insert("version".to_string(), version.to_string());
+        }
+        if let Some(repository) = merged.get("metadata").and_then(|metadata| metadata.get("repository")).and_then(Value::as_str) {
+            variables.insert("repository".to_string(), repository.trim_end_matches('/').to_string());
+        }
+        let context = TemplateContext::default().with_variables(&variables);
+        
+        // Only the template's own values are filled, the definition's are taken as written
+        let template = render(&Value::Mapping(template.clone()), &context);
+        for (placeholder, field) in [("{version}", "version"), ("{repository}", "metadata.repository")] {
+            if uses(&template, placeholder) {
+                return Err(format!("{}: template {} uses {} but the definition has no {}", package, template_name, placeholder, field));
+            }
+        }
+        let Value::Mapping(template) = template else {
+            unreachable!("rendering keeps a map a map");
+        };
         
-        if let Ok(packages) = serde_yaml::from_str::<HashMap<String, Package>>(&content) {
-            for (name, mut package) in packages {
-                package.name = name.clone();
-                self.validate_package(&package)?;
-                self.packages.insert(name, package);
+        let mut filled = merge(&merge(&base, &template), fields);
+        filled.remove("template");
+        Ok(filled)
+    }
+    
+    /// The definitions in a map of package names to definitions, parsed as `value` and
+    /// deserialized from `content`
+    fn parse_definition_map(&mut self, value: &Value, content: &str) -> std::result::Result<Vec<Package>, String> {
+        let Value::Mapping(entries) = value else {
+            return Err(format!("expected a map of package names to definitions, found {}", describe(value)));
+        };
+        
+        let mut skipped = HashSet::new();
+        for (key, entry) in entries {
+            let Some(name) = key.as_str() else {
+                return Err(format!("package names must be strings, found {}", describe(key)));
+            };
+            
+            if let Some(required) = entry.get("requires_archbox") {
+                if !self.check_requirement(name, required).map_err(reason)? {
+                    skipped.insert(name);
+                }
             }
-            return Ok(());
         }
         
-        Err(Error::Config(format!(
-            "Invalid package definition format in file: {}",
-            path.as_ref().display()
-        )))
+        let packages = DefinitionMap { skipped: &skipped }
+            .deserialize(serde_yaml::Deserializer::from_str(content))
+            .map_err(|e| e.to_string())?;
+        for package in &packages {
+            self.validate_package(package).map_err(reason)?;
+        }
+        
+        Ok(packages)
+    }
+    
+    /// Whether this ArchBox satisfies a definition's `requires_archbox`. Definitions that
+    /// need a newer version are remembered so asking for them explains why they're missing.
+    fn check_requirement(&mut self, name: &str, required: &Value) -> Result<bool> {
+        let required = required.as_str().ok_or_else(|| {
+            Error::Config(format!("Package {} has a requires_archbox that is not a string", name))
+        })?;
+        
+        if version::satisfies(required, ARCHBOX_VERSION)
+            .map_err(|e| Error::Config(format!("Package {} has an invalid requires_archbox: {}", name, e)))?
+        {
+            return Ok(true);
+        }
+        
+        warn!("Skipping {}: requires archbox {}, this is {}", name, required, ARCHBOX_VERSION);
+        self.incompatible.insert(name.to_string(), required.to_string());
+        Ok(false)
     }
     
     fn validate_package(&self, package: &Package) -> Result<()> {
@@ -68,6 +371,14 @@ impl DefinitionLoader {
             return Err(Error::Config(format!("Package {} missing version", package.name)));
         }
         
+        // The version names the directory a versioned install goes into
+        if matches!(package.version.as_str(), "." | "..") || package.version.contains('\0') {
+            return Err(Error::Config(format!(
+                "Package {} has a version that can't name a directory: {:?}",
+                package.name, package.version
+            )));
+        }
+        
         if package.description.is_empty() {
             return Err(Error::Config(format!("Package {} missing description", package.name)));
         }
@@ -83,6 +394,95 @@ impl DefinitionLoader {
             }
         }
         
+        if package.provides.iter().any(|capability| capability.is_empty() || *capability == package.name) {
+            return Err(Error::Config(format!(
+                "Package {} has an empty or self-referencing provides entry",
+                package.name
+            )));
+        }
+        
+        // Names are passed to the shell as variables, so they have to be valid ones
+        let valid_variable = |key: &str| {
+            key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        if let Some(key) = package.env.keys().find(|key| !valid_variable(key)) {
+            return Err(Error::Config(format!(
+                "Package {} has an env entry that is not a valid variable name: {:?}",
+                package.name, key
+            )));
+        }
+        
+        if let Some(post_install) = &package.post_install {
+            self.validate_pacman_hooks(post_install.pacman_hooks.iter().flatten(), &package.name)?;
+            
+            if post_install.tmpfiles.iter().flatten().any(|line| line.trim().is_empty() || line.contains('\n')) {
+                return Err(Error::Config(format!(
+                    "Package {} has a tmpfiles entry that is not a single line",
+                    package.name
+                )));
+            }
+            
+            if post_install.firewall.iter().flatten().any(|rule| rule.port == 0 || rule.to.is_some_and(|to| to < rule.port)) {
+                return Err(Error::Config(format!(
+                    "Package {} has a firewall rule with port 0 or a range ending before it starts",
+                    package.name
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn validate_pacman_hooks<'a>(&self, hooks: impl Iterator<Item = &'a PacmanHook>, package_name: &str) -> Result<()> {
+        let mut names = HashSet::new();
+        
+        for hook in hooks {
+            // The name becomes part of the file name
+            if hook.name.is_empty() || !hook.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+                return Err(Error::Config(format!(
+                    "Package {} has a pacman hook name that is empty or not made of letters, digits, '-', '_' and '.': {:?}",
+                    package_name, hook.name
+                )));
+            }
+            if !names.insert(hook.name.as_str()) {
+                return Err(Error::Config(format!(
+                    "Package {} has more than one pacman hook named {}",
+                    package_name, hook.name
+                )));
+            }
+            if !hook.exec.starts_with('/') {
+                return Err(Error::Config(format!(
+                    "Package {} pacman hook {} needs an exec starting with an absolute path",
+                    package_name, hook.name
+                )));
+            }
+            if hook.triggers.is_empty() || hook.triggers.iter().any(|trigger| trigger.operations.is_empty() || trigger.targets.is_empty()) {
+                return Err(Error::Config(format!(
+                    "Package {} pacman hook {} needs triggers, each with operations and targets",
+                    package_name, hook.name
+                )));
+            }
+            // Each value is written as one line of the hook file
+            let multiline = |value: &str| value.contains(['\n', '\r']);
+            if multiline(&hook.exec)
+                || hook.description.as_deref().is_some_and(multiline)
+                || hook.depends.iter().any(|depend| multiline(depend))
+                || hook.triggers.iter().any(|trigger| trigger.targets.iter().any(|target| multiline(target)))
+            {
+                return Err(Error::Config(format!(
+                    "Package {} pacman hook {} has an exec, description, target or dependency spanning more than one line",
+                    package_name, hook.name
+                )));
+            }
+            if hook.abort_on_fail && hook.when != PacmanHookWhen::PreTransaction {
+                return Err(Error::Config(format!(
+                    "Package {} pacman hook {} can only abort_on_fail when it runs pre_transaction",
+                    package_name, hook.name
+                )));
+            }
+        }
+        
         Ok(())
     }
     
@@ -106,13 +506,27 @@ impl DefinitionLoader {
                     )));
                 }
             }
-            Installation::Binary { url, install_path, .. } => {
-                if url.is_empty() || install_path.is_empty() {
+            Installation::Binary { url, urls, install_path, fallbacks, .. } => {
+                if (url.is_empty() && urls.as_ref().is_none_or(|urls| urls.is_empty())) || install_path.is_empty() {
                     return Err(Error::Config(format!(
                         "Package {} has invalid binary installation config",
                         package_name
                     )));
                 }
+                for fallback in fallbacks {
+                    let incomplete = match fallback {
+                        BinaryFallback::Binary { url, .. } => url.is_empty(),
+                        BinaryFallback::Source { url, build_commands, install_commands } => {
+                            url.is_empty() || build_commands.is_empty() || install_commands.is_empty()
+                        }
+                    };
+                    if incomplete {
+                        return Err(Error::Config(format!(
+                            "Package {} has a fallback without a URL or build commands",
+                            package_name
+                        )));
+                    }
+                }
             }
             Installation::Source { url, build_commands, install_commands, .. } => {
                 if url.is_empty() {
@@ -134,16 +548,22 @@ impl DefinitionLoader {
                     )));
                 }
             }
-            Installation::Script { script, .. } => {
+            Installation::Script { script, script_sha256, .. } => {
                 if script.is_empty() {
                     return Err(Error::Config(format!(
                         "Package {} has empty installation script",
                         package_name
                     )));
                 }
+                if script_sha256.as_ref().is_some_and(|sha256| sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit())) {
+                    return Err(Error::Config(format!(
+                        "Package {} has a script_sha256 that is not 64 hex digits",
+                        package_name
+                    )));
+                }
             }
-            Installation::AppImage { url, .. } => {
-                if url.is_empty() {
+            Installation::AppImage { url, urls, .. } => {
+                if url.is_empty() && urls.as_ref().is_none_or(|urls| urls.is_empty()) {
                     return Err(Error::Config(format!(
                         "Package {} has empty AppImage URL",
                         package_name
@@ -163,6 +583,22 @@ impl DefinitionLoader {
         Ok(())
     }
     
+    /// Change fields of a loaded definition for this run only, from `path=value` overrides
+    /// such as `installation.url=https://...` or `version=1.2.3`. The result is validated like
+    /// a loaded definition, and a path the definition has no field for is an error.
+    pub fn override_fields(&mut self, name: &str, overrides: &[String]) -> Result<()> {
+        let mut package = self.get_package(name).ok_or_else(|| self.not_found(name))?.clone();
+        
+        for field in overrides {
+            package = override_field(&package, field)
+                .map_err(|reason| Error::Config(format!("Cannot set {} on {}: {}", field, name, reason)))?;
+        }
+        self.validate_package(&package)?;
+        
+        self.insert(vec![package]);
+        Ok(())
+    }
+    
     pub fn packages(&self) -> &HashMap<String, Package> {
         &self.packages
     }
@@ -171,6 +607,18 @@ impl DefinitionLoader {
         self.packages.get(name)
     }
     
+    /// Error for a package that isn't loaded, saying to upgrade when its definition needs
+    /// a newer ArchBox
+    pub fn not_found(&self, name: &str) -> Error {
+        match self.incompatible.get(name) {
+            Some(required) => Error::Config(format!(
+                "{} requires archbox {}, but this is {}; upgrade archbox to install it",
+                name, required, ARCHBOX_VERSION
+            )),
+            None => Error::PackageNotFound(name.to_string()),
+        }
+    }
+    
     pub fn search_packages(&self, query: &str) -> Vec<&Package> {
         let query_lower = query.to_lowercase();
         
@@ -179,7 +627,7 @@ impl DefinitionLoader {
             .filter(|package| {
                 package.name.to_lowercase().contains(&query_lower)
                     || package.description.to_lowercase().contains(&query_lower)
-                    || package.metadata.tags.as_ref().map_or(false, |tags| {
+                    || package.metadata.tags.as_ref().is_some_and(|tags| {
                         tags.iter().any(|tag| tag.to_lowercase().contains(&query_lower))
                     })
             })
@@ -210,4 +658,183 @@ impl Default for DefinitionLoader {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Deserializes a map of package names to definitions, passing over the `skipped` ones
+/// without looking inside them
+struct DefinitionMap<'a> {
+    skipped: &'a HashSet<&'a str>,
+}
+
+impl<'de> DeserializeSeed<'de> for DefinitionMap<'_> {
+    type Value = Vec<Package>;
+    
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DefinitionMap<'_> {
+    type Value = Vec<Package>;
+    
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map of package names to definitions")
+    }
+    
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Self::Value, A::Error> {
+        let mut packages = Vec::new();
+        
+        while let Some(name) = map.next_key::<String>()? {
+            if self.skipped.contains(name.as_str()) {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+            
+            let mut package: Package = map.next_value()?;
+            package.name = name;
+            packages.push(package);
+        }
+        
+        Ok(packages)
+    }
+}
+
+/// `package` with one `path=value` override applied. The value is taken as a string first,
+/// then as YAML, so `version=1.10` stays a string while `kernel_module=true` or
+/// `categories=[dev, cli]` work too.
+fn override_field(package: &Package, field: &str) -> std::result::Result<Package, String> {
+    let (path, value) = field.split_once('=').ok_or("expected FIELD=VALUE")?;
+    let keys: Vec<&str> = path.split('.').collect();
+    if keys.iter().any(|key| key.is_empty()) {
+        return Err(format!("{} is not a field path", path));
+    }
+    if path == "name" {
+        return Err("the name can't be changed".to_string());
+    }
+    
+    let as_string = with_field(package, &keys, Value::String(value.to_string()));
+    match serde_yaml::from_str::<Value>(value) {
+        Ok(parsed) if as_string.is_err() && !parsed.is_string() => with_field(package, &keys, parsed).or(as_string),
+        _ => as_string,
+    }
+}
+
+fn with_field(package: &Package, keys: &[&str], value: Value) -> std::result::Result<Package, String> {
+    let mut document = serde_yaml::to_value(package).map_err(|e| e.to_string())?;
+    
+    let mut node = &mut document;
+    for (depth, key) in keys.iter().enumerate() {
+        if node.is_null() {
+            *node = Value::Mapping(Default::default());
+        }
+        let Value::Mapping(fields) = node else {
+            return Err(format!("{} has no fields", keys[..depth].join(".")));
+        };
+        node = fields.entry(Value::from(*key)).or_insert(Value::Null);
+    }
+    *node = value.clone();
+    
+    let package: Package = serde_yaml::from_value(document).map_err(|e| e.to_string())?;
+    
+    // Fields the definition doesn't have are dropped when deserializing, so look for the value
+    // in what came out
+    let written = serde_yaml::to_value(&package).map_err(|e| e.to_string())?;
+    if keys.iter().try_fold(&written, |node, key| node.get(key)) != Some(&value) {
+        return Err(format!("the definition has no field {}", keys.join(".")));
+    }
+    
+    Ok(package)
+}
+
+/// The message of a validation error, without the "Configuration error" prefix
+fn reason(error: Error) -> String {
+    match error {
+        Error::Config(detail) => detail,
+        error => error.to_string(),
+    }
+}
+
+/// A template's fields, or why they can't be used
+fn parse_template(content: &str) -> std::result::Result<Mapping, String> {
+    let value: Value = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+    let Value::Mapping(fields) = value else {
+        return Err(format!("a template must be a map of definition fields, found {}", describe(&value)));
+    };
+    if fields.contains_key("name") {
+        return Err("a template can't set a name, each package has its own".to_string());
+    }
+    if fields.contains_key("template") {
+        return Err("a template can't use another template".to_string());
+    }
+    Ok(fields)
+}
+
+/// `fields` on top of `base`, merging maps both have. An `installation` is only merged
+/// with one using the same method, or when it doesn't name one.
+fn merge(base: &Mapping, fields: &Mapping) -> Mapping {
+    let mut merged = base.clone();
+    
+    for (key, value) in fields {
+        let combined = match (merged.get(key), value) {
+            (Some(Value::Mapping(default)), Value::Mapping(value)) if key.as_str() != Some("installation") || same_method(default, value) => {
+                Value::Mapping(merge(default, value))
+            }
+            _ => value.clone(),
+        };
+        merged.insert(key.clone(), combined);
+    }
+    
+    merged
+}
+
+/// Whether an entry's `installation` uses the method of the default one, or doesn't say
+fn same_method(default: &Mapping, installation: &Mapping) -> bool {
+    installation.get("method").is_none_or(|method| default.get("method") == Some(method))
+}
+
+/// `value` with the known placeholders in its strings filled from `context`
+fn render(value: &Value, context: &TemplateContext) -> Value {
+    match value {
+        Value::String(text) => Value::String(context.render(text)),
+        Value::Sequence(items) => Value::Sequence(items.iter().map(|item| render(item, context)).collect()),
+        Value::Mapping(fields) => Value::Mapping(fields.iter().map(|(key, field)| (key.clone(), render(field, context))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Whether any string in `value` still contains `placeholder`
+fn uses(value: &Value, placeholder: &str) -> bool {
+    match value {
+        Value::String(text) => text.contains(placeholder),
+        Value::Sequence(items) => items.iter().any(|item| uses(item, placeholder)),
+        Value::Mapping(fields) => fields.values().any(|field| uses(field, placeholder)),
+        _ => false,
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml")
+}
+
+/// Whether `path` is in a `templates` directory, holding a template rather than definitions
+fn is_template(path: &Path) -> bool {
+    path.parent().and_then(Path::file_name).is_some_and(|dir| dir == "templates")
+}
+
+/// `reason` without serde_yaml's line and column, for definitions deserialized from merged text
+fn without_line(reason: String) -> String {
+    reason.split(" at line ").next().unwrap_or_default().to_string()
+}
+
+/// What kind of YAML value `value` is, for error messages
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "nothing",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Sequence(_) => "a list",
+        Value::Mapping(_) => "a map",
+        Value::Tagged(_) => "a tagged value",
+    }
+}
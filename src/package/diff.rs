@@ -0,0 +1,36 @@
+//! Colored unified diffs for previewing file changes
+
+use console::style;
+use similar::{ChangeTag, TextDiff};
+use std::path::Path;
+
+/// Render a unified diff of `current` against `new` for `path`, with three lines of context
+pub fn unified_diff(path: &Path, current: &str, new: &str) -> String {
+    labeled_diff(path, ("current", current), ("new", new))
+}
+
+/// Render a unified diff between two versions of `path`, each named by its label
+pub fn labeled_diff(path: &Path, (old_label, old): (&str, &str), (new_label, new): (&str, &str)) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut out = String::new();
+
+    out.push_str(&format!("{}\n", style(format!("--- {} ({})", path.display(), old_label)).red().bold()));
+    out.push_str(&format!("{}\n", style(format!("+++ {} ({})", path.display(), new_label)).green().bold()));
+
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        out.push_str(&format!("{}\n", style(hunk.header()).cyan()));
+
+        for change in hunk.iter_changes() {
+            let line = change.value().trim_end_matches('\n');
+            let rendered = match change.tag() {
+                ChangeTag::Delete => style(format!("-{}", line)).red().to_string(),
+                ChangeTag::Insert => style(format!("+{}", line)).green().to_string(),
+                ChangeTag::Equal => format!(" {}", line),
+            };
+            out.push_str(&rendered);
+            out.push('\n');
+        }
+    }
+
+    out
+}
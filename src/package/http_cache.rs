@@ -0,0 +1,181 @@
+//! On-disk cache for metadata API responses (AUR RPC, GitHub API, Flathub API)
+//!
+//! Entries live in `~/.cache/archbox/http/<key>.json`, where the key hashes the URL. A fresh
+//! entry is returned without a request; a stale one is revalidated with its ETag or
+//! Last-Modified date. When a server reports its rate limit as exhausted, nothing is sent
+//! to it until the limit resets and the last known response is used instead.
+
+use crate::{config::get_cache_dir, Error, Result};
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// AUR package metadata changes with every push, so it is only kept briefly
+pub const AUR_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Unauthenticated GitHub API access is limited to 60 requests an hour
+pub const GITHUB_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub const FLATHUB_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    /// Unix time the response was fetched or last revalidated
+    fetched_at: u64,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// Unix time until which a host must not be asked again
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RateLimit {
+    until: u64,
+}
+
+fn cache_dir() -> PathBuf {
+    get_cache_dir().join("http")
+}
+
+fn entry_path(url: &str) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(url.as_bytes());
+    cache_dir().join(format!("{:x}.json", hasher.finalize()))
+}
+
+fn rate_limit_path(host: &str) -> PathBuf {
+    cache_dir().join(format!("rate-limit-{}.json", host))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &PathBuf) -> Option<T> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+fn write_json<T: Serialize>(path: &PathBuf, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+/// GET `url`, answering from the cache while the entry is younger than `ttl`
+pub async fn get(client: &reqwest::Client, url: &str, ttl: Duration) -> Result<String> {
+    let path = entry_path(url);
+    let cached: Option<Entry> = read_json(&path).filter(|entry: &Entry| entry.url == url);
+
+    if let Some(entry) = &cached {
+        if now().saturating_sub(entry.fetched_at) < ttl.as_secs() {
+            debug!("Using cached response for {}", url);
+            return Ok(entry.body.clone());
+        }
+    }
+
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let limit_path = rate_limit_path(&host);
+    if let Some(limit) = read_json::<RateLimit>(&limit_path).filter(|limit| limit.until > now()) {
+        return match cached {
+            Some(entry) => {
+                debug!("{} is rate limited, using the cached response for {}", host, url);
+                Ok(entry.body)
+            }
+            None => Err(rate_limited(&host, limit.until)),
+        };
+    }
+
+    let mut request = client.get(url);
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await?;
+    let headers = response.headers().clone();
+    let status = response.status();
+
+    if let Some(until) = exhausted_until(&headers, status) {
+        write_json(&limit_path, &RateLimit { until })?;
+        if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+            return match cached {
+                Some(entry) => Ok(entry.body),
+                None => Err(rate_limited(&host, until)),
+            };
+        }
+    }
+
+    if status == StatusCode::NOT_MODIFIED {
+        if let Some(mut entry) = cached {
+            entry.fetched_at = now();
+            write_json(&path, &entry)?;
+            return Ok(entry.body);
+        }
+    }
+
+    let response = response.error_for_status()?;
+    let header_value = |name| headers.get(name).and_then(|value: &header::HeaderValue| value.to_str().ok()).map(str::to_string);
+    let entry = Entry {
+        url: url.to_string(),
+        fetched_at: now(),
+        etag: header_value(header::ETAG),
+        last_modified: header_value(header::LAST_MODIFIED),
+        body: response.text().await?,
+    };
+    write_json(&path, &entry)?;
+
+    Ok(entry.body)
+}
+
+/// GET `url` through the cache and parse it as JSON
+pub async fn get_json(client: &reqwest::Client, url: &str, ttl: Duration) -> Result<serde_json::Value> {
+    Ok(serde_json::from_str(&get(client, url, ttl).await?)?)
+}
+
+/// When the response says no requests are left, the time they become available again.
+/// Understands GitHub's `x-ratelimit-*` headers and `Retry-After`.
+fn exhausted_until(headers: &header::HeaderMap, status: StatusCode) -> Option<u64> {
+    let number = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    if let Some(seconds) = number(header::RETRY_AFTER.as_str()) {
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::FORBIDDEN || status == StatusCode::SERVICE_UNAVAILABLE {
+            return Some(now() + seconds);
+        }
+    }
+
+    if number("x-ratelimit-remaining") == Some(0) {
+        return Some(number("x-ratelimit-reset").unwrap_or_else(|| now() + 60));
+    }
+
+    None
+}
+
+fn rate_limited(host: &str, until: u64) -> Error {
+    let minutes = until.saturating_sub(now()).div_ceil(60);
+    Error::Config(format!(
+        "{} rate limit reached and no cached response is available; try again in {} minute(s)",
+        host, minutes
+    ))
+}
@@ -0,0 +1,158 @@
+//! Full output of the commands run while installing a package
+//!
+//! Error messages only quote what a failing command printed to stderr, and the transaction
+//! log keeps its last few lines. Every command an install runs is also written in full to
+//! `~/.local/state/archbox/logs/<package>/<timestamp>.log`, and a failed install names that
+//! file in its error.
+
+use super::runner::{CommandRunner, CommandSpec};
+use crate::{config::get_state_dir, Error};
+use async_trait::async_trait;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// Logs kept per package; older ones are removed when a new install starts
+const KEEP_LOGS: usize = 10;
+
+/// Where the install logs of `package_name` are kept
+pub fn log_dir(package_name: &str) -> PathBuf {
+    get_state_dir().join("logs").join(package_name)
+}
+
+/// The log of one install, created when the first command runs
+#[derive(Debug)]
+pub struct InstallLog {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl InstallLog {
+    /// A log for installing `package_name` now. Nothing is written until a command runs.
+    pub fn new(package_name: &str) -> Self {
+        let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        Self {
+            path: log_dir(package_name).join(format!("{}.log", timestamp)),
+            file: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether any command was logged
+    pub fn is_written(&self) -> bool {
+        self.file.lock().is_ok_and(|file| file.is_some())
+    }
+
+    /// Name the log in `error` if anything was written to it
+    pub fn attach(&self, error: Error) -> Error {
+        if !self.is_written() {
+            return error;
+        }
+
+        Error::Logged {
+            source: Box::new(error),
+            log: self.path.clone(),
+        }
+    }
+
+    /// Append a command with what it printed and how it ended. Failing to log never fails
+    /// the install.
+    fn record(&self, command: &CommandSpec, result: &std::io::Result<Output>) {
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        if file.is_none() {
+            match self.create() {
+                Ok(created) => *file = Some(created),
+                Err(e) => {
+                    warn!("Cannot write the install log {}: {}", self.path.display(), e);
+                    return;
+                }
+            }
+        }
+        let Some(file) = file.as_mut() else {
+            return;
+        };
+
+        let mut entry = format!("[{}] $ {}\n", chrono::Local::now().to_rfc3339(), command);
+        if let Some(dir) = &command.current_dir {
+            entry.push_str(&format!("(in {})\n", dir.display()));
+        }
+        match result {
+            Ok(output) => {
+                for (stream, content) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+                    if content.is_empty() {
+                        continue;
+                    }
+                    entry.push_str(&format!("--- {} ---\n{}", stream, String::from_utf8_lossy(content)));
+                    if !content.ends_with(b"\n") {
+                        entry.push('\n');
+                    }
+                }
+                entry.push_str(&format!("--- {} ---\n\n", output.status));
+            }
+            Err(e) => entry.push_str(&format!("--- could not run: {} ---\n\n", e)),
+        }
+
+        if let Err(e) = file.write_all(entry.as_bytes()) {
+            warn!("Cannot write the install log {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn create(&self) -> std::io::Result<File> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        prune(dir);
+
+        debug!("Logging install output to {}", self.path.display());
+        std::fs::OpenOptions::new().create(true).append(true).open(&self.path)
+    }
+}
+
+/// Remove all but the newest logs in `dir`, leaving room for the one about to be written
+fn prune(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    // Timestamped names sort by age
+    let mut logs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    logs.sort();
+
+    let excess = (logs.len() + 1).saturating_sub(KEEP_LOGS);
+    for old in &logs[..excess] {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+/// Passes commands on to another runner and writes each one with its output to a log
+#[derive(Debug)]
+pub struct LoggingRunner {
+    inner: Arc<dyn CommandRunner>,
+    log: Arc<InstallLog>,
+}
+
+impl LoggingRunner {
+    pub fn new(inner: Arc<dyn CommandRunner>, log: Arc<InstallLog>) -> Self {
+        Self { inner, log }
+    }
+}
+
+#[async_trait]
+impl CommandRunner for LoggingRunner {
+    async fn output(&self, command: &CommandSpec) -> std::io::Result<Output> {
+        let result = self.inner.output(command).await;
+        self.log.record(command, &result);
+        result
+    }
+}
@@ -1,20 +1,68 @@
 //! Package installation logic for different methods
 
+mod aur;
+mod firewall;
+mod post_install;
+mod provenance;
+
+pub use aur::{SrcInfo, AUR_GIT_BASE, DETECTED_HELPERS, NATIVE_HELPER};
+pub use firewall::FirewallBackend;
+pub use post_install::{current_username, drop_in_header, parse_service, ConfigConflict, DropIn, DropInKind, PostInstallAction};
+pub use provenance::Provenance;
+
 use crate::{
   config::Config,
-  package::{Installation, Package, PostInstall},
-  Error, Result,
+  package::{
+      build_cache, install_log::{InstallLog, LoggingRunner}, layout, platform::{BinaryAsset, Platform}, runner::{CommandRunner, CommandSpec, SystemRunner}, template::TemplateContext,
+      DependencyType, FlatpakScope, InstallScope, Installation, Package,
+  },
+  privilege, progress, workdir::WorkDir, Error, Result,
 };
-use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashMap;
+use indicatif::ProgressBar;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use tokio::process::Command;
+use std::process::Output;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
-use tracing::{debug, info, warn, error};
+use tracing::{debug, info, warn};
+
+pub(crate) const FLATHUB_REMOTE: &str = "flathub";
+pub(crate) const FLATHUB_REPO_URL: &str = "https://dl.flathub.org/repo/flathub.flatpakrepo";
+
+/// Changes made to the system by a successful installation
+#[derive(Debug, Clone, Default)]
+pub struct InstallOutcome {
+  /// Scope the package was installed in
+  pub scope: InstallScope,
+  /// Config files written during post-install
+  pub config_files: Vec<PathBuf>,
+  /// Post-install actions applied, in order
+  pub actions: Vec<PostInstallAction>,
+  /// Where the installed artifacts came from
+  pub provenance: Option<Provenance>,
+}
+
+/// Options for the Source method, usually set from the command line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildOptions {
+  /// Keep the build directory instead of deleting it
+  pub keep_build: bool,
+  /// Remove build dependencies that were not installed before the build (like `makepkg -r`)
+  pub remove_build_deps: bool,
+}
 
 /// Package installer handles different installation methods
+#[derive(Clone)]
 pub struct Installer {
   config: Config,
+  templates: TemplateContext,
+  build_options: BuildOptions,
+  config_conflict: ConfigConflict,
+  scope: Option<InstallScope>,
+  root: Option<PathBuf>,
+  runner: Arc<dyn CommandRunner>,
+  reinstall: bool,
 }
 
 impl Installer {
@@ -22,49 +70,191 @@ impl Installer {
   pub fn new(config: &Config) -> Self {
       Self {
           config: config.clone(),
+          templates: TemplateContext::from_system().with_variables(&config.variables),
+          build_options: BuildOptions {
+              keep_build: false,
+              remove_build_deps: config.installation.remove_build_deps,
+          },
+          config_conflict: ConfigConflict::default(),
+          scope: None,
+          root: None,
+          runner: Arc::new(SystemRunner),
+          reinstall: false,
       }
   }
   
-  /// Install a package using the appropriate method
-  pub async fn install(&self, package: &Package) -> Result<()> {
+  pub fn with_build_options(mut self, build_options: BuildOptions) -> Self {
+      self.build_options = build_options;
+      self
+  }
+  
+  /// Set how post-install handles changes to existing config files
+  pub fn with_config_conflict(mut self, config_conflict: ConfigConflict) -> Self {
+      self.config_conflict = config_conflict;
+      self
+  }
+  
+  /// Add template variables (e.g. from a profile) on top of the configured ones
+  pub fn with_variables(mut self, variables: &std::collections::HashMap<String, String>) -> Self {
+      self.templates = self.templates.with_variables(variables);
+      self
+  }
+  
+  /// Install for the current user or system-wide, overriding the definition's scope
+  pub fn with_scope(mut self, scope: Option<InstallScope>) -> Self {
+      self.scope = scope;
+      self
+  }
+  
+  /// Install into the system mounted at `root` (e.g. `/mnt` from the live ISO): pacman runs
+  /// with `--sysroot`, scripts through `arch-chroot`, and files land under `root`
+  pub fn with_root(mut self, root: &Path) -> Self {
+      self.root = Some(root.to_path_buf());
+      self
+  }
+  
+  /// Install afresh even what looks up to date: pacman, AUR helpers and makepkg reinstall
+  /// instead of skipping (no `--needed`), Flatpak redeploys, source packages are rebuilt
+  /// instead of restored from the build cache and AppImages are downloaded in full
+  pub fn with_reinstall(mut self, reinstall: bool) -> Self {
+      self.reinstall = reinstall;
+      self
+  }
+  
+  /// Run commands through `runner` instead of on this machine
+  pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+      self.runner = runner;
+      self
+  }
+  
+  /// Install a package using the appropriate method. The output of every command it runs
+  /// goes to an [install log](crate::package::install_log), which a failure names.
+  pub async fn install(&self, package: &Package) -> Result<InstallOutcome> {
+      let log = Arc::new(InstallLog::new(&package.name));
+      let installer = Installer {
+          runner: Arc::new(LoggingRunner::new(Arc::clone(&self.runner), Arc::clone(&log))),
+          ..self.clone()
+      };
+      
+      installer.install_logged(package).await.map_err(|error| log.attach(error))
+  }
+  
+  async fn install_logged(&self, package: &Package) -> Result<InstallOutcome> {
       info!("Installing {} via {:?}", package.name, package.installation);
       
-      match &package.installation {
+      if let Some(root) = &self.root {
+          // These need the new system running (a user to build as, a session bus)
+          if matches!(package.installation, Installation::Aur { .. } | Installation::Source { .. } | Installation::Flatpak { .. }) {
+              return Err(Error::InstallationFailed(format!(
+                  "{} can't be installed into {}; install it after booting the new system",
+                  package.name,
+                  root.display()
+              )));
+          }
+      }
+      
+      // A new system has no users yet, so everything installed into it is system-wide
+      let scope = match self.root {
+          Some(_) => InstallScope::System,
+          None => package.install_scope(self.scope),
+      };
+      let install_path = package.install_path(scope).map(|path| self.target_path(&path));
+      if install_path.is_some() && scope == InstallScope::System {
+          privilege::require_root(&format!("Installing {} system-wide", package.name))?;
+      }
+      
+      let provenance = match &package.installation {
           Installation::Pacman { packages, flags } => {
-              self.install_pacman(packages, flags.as_ref()).await?;
+              self.install_pacman(packages, flags.as_ref()).await?
           }
           Installation::Aur { package: pkg, helper } => {
-              self.install_aur(pkg, helper.as_ref()).await?;
+              let commit = self.install_aur(pkg, helper.as_ref()).await?;
+              Provenance {
+                  source: format!("{}/{}.git", AUR_GIT_BASE, pkg),
+                  commit,
+                  ..Default::default()
+              }
           }
-          Installation::Binary { url, checksum, install_path, executable } => {
-              self.install_binary(url, checksum.as_ref(), install_path, *executable).await?;
+          Installation::Binary { url, checksum, urls, checksums, executable, fallbacks, .. } => {
+              let platform = Platform::current();
+              match platform.resolve_binary(url, checksum.as_ref(), urls.as_ref(), checksums.as_ref(), fallbacks)? {
+                  BinaryAsset::Download { url, checksum, fallback } => {
+                      if fallback {
+                          warn!("{} has no {} build; downloading the fallback {} instead", package.name, platform.arch, url);
+                      }
+                      let install_path = install_path.unwrap_or_default();
+                      // Versioned links are absolute, so a target root gets the file in place
+                      let version = self.root.is_none().then_some((scope, package.version.as_str()));
+                      self.install_binary(&url, checksum.as_ref(), &install_path, *executable, &package.name, version).await?
+                  }
+                  BinaryAsset::Source { url, build_commands, install_commands } => {
+                      warn!("{} has no {} build; building it from {} instead", package.name, platform.arch, url);
+                      let commit = self.install_source(url, build_commands, install_commands, package).await?;
+                      Provenance {
+                          source: url.to_string(),
+                          commit,
+                          ..Default::default()
+                      }
+                  }
+              }
           }
           Installation::Source { url, build_commands, install_commands } => {
-              self.install_source(url, build_commands, install_commands).await?;
+              let commit = self.install_source(url, build_commands, install_commands, package).await?;
+              Provenance {
+                  source: url.clone(),
+                  commit,
+                  ..Default::default()
+              }
           }
-          Installation::Script { script, interpreter } => {
-              self.install_script(script, interpreter).await?;
+          Installation::Script { script, interpreter, script_sha256 } => {
+              verify_script(script, script_sha256.as_deref())?;
+              self.install_script(script, interpreter, &package.command_env()).await?;
+              Provenance {
+                  source: format!("script: {}", interpreter),
+                  sha256: Some(self.calculate_sha256(script.as_bytes())),
+                  ..Default::default()
+              }
           }
-          Installation::AppImage { url, checksum, integrate } => {
-              self.install_appimage(url, checksum.as_ref(), *integrate, &package.name).await?;
+          Installation::AppImage { url, checksum, urls, checksums, integrate } => {
+              let platform = Platform::current();
+              let url = platform.resolve_url(url, urls.as_ref())?;
+              let checksum = platform.resolve_checksum(checksum.as_ref(), checksums.as_ref());
+              let appimage_path = install_path.unwrap_or_default();
+              let integrate = *integrate && self.root.is_none();
+              self.install_appimage(&url, checksum.as_ref(), integrate, &package.name, &appimage_path).await?
           }
-          Installation::Flatpak { id, remote } => {
-              self.install_flatpak(id, remote.as_ref()).await?;
+          Installation::Flatpak { id, remote, remote_url, .. } => {
+              self.install_flatpak(id, remote.as_deref(), remote_url.as_deref(), scope.into()).await?
           }
-      }
+      };
       
-      // Run post-installation configuration
+      let mut outcome = InstallOutcome {
+          scope,
+          provenance: Some(provenance),
+          ..Default::default()
+      };
+      
+      // Run post-installation configuration. Unprefixed services follow an explicitly chosen
+      // scope and are system services otherwise.
       if let Some(post_install) = &package.post_install {
-          self.run_post_install(post_install, &package.name).await?;
+          let service_scope = self.scope.or(package.scope).unwrap_or(InstallScope::System);
+          self.run_post_install(post_install, package, service_scope, &mut outcome).await?;
       }
       
-      Ok(())
+      Ok(outcome)
   }
   
-  /// Install packages via pacman
-  async fn install_pacman(&self, packages: &[String], flags: Option<&Vec<String>>) -> Result<()> {
-      let mut cmd = Command::new("pacman");
-      cmd.args(&["-S", "--needed", "--noconfirm"]);
+  /// Install packages via pacman, then ask pacman which versions it installed and who signed them
+  async fn install_pacman(&self, packages: &[String], flags: Option<&Vec<String>>) -> Result<Provenance> {
+      let mut cmd = privilege::privileged("pacman");
+      if let Some(root) = &self.root {
+          cmd.arg("--sysroot").arg(root);
+      }
+      cmd.arg("-S");
+      if !self.reinstall {
+          cmd.arg("--needed");
+      }
+      cmd.arg("--noconfirm");
       
       if let Some(flags) = flags {
           cmd.args(flags);
@@ -72,9 +262,9 @@ impl Installer {
       
       cmd.args(packages);
       
-      debug!("Running: pacman {:?}", cmd.as_std().get_args().collect::<Vec<_>>());
+      debug!("Running: {}", cmd);
       
-      let output = cmd.output().await?;
+      let output = self.run(&cmd).await?;
       
       if !output.status.success() {
           let stderr = String::from_utf8_lossy(&output.stderr);
@@ -85,30 +275,51 @@ impl Installer {
       }
       
       info!("Successfully installed pacman packages: {:?}", packages);
-      Ok(())
+      
+      let mut query = CommandSpec::new("pacman");
+      if let Some(root) = &self.root {
+          query.arg("--sysroot").arg(root);
+      }
+      query.arg("-Qi").args(packages);
+      let info = match self.run(&query).await {
+          Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).to_string(),
+          _ => String::new(),
+      };
+      
+      Ok(Provenance::pacman(packages, &info))
   }
   
-  /// Install package from AUR
-  async fn install_aur(&self, package: &str, helper: Option<&String>) -> Result<()> {
-      let aur_helper = helper
-          .map(|h| h.as_str())
-          .or(self.config.aur_helper.as_deref())
-          .unwrap_or("yay");
-      
-      // Check if AUR helper is available
-      if !self.command_exists(aur_helper).await? {
-          return Err(Error::InstallationFailed(format!(
-              "AUR helper '{}' not found. Please install it first.",
-              aur_helper
-          )));
+  /// Install package from AUR, through a helper if one is available or the built-in backend otherwise.
+  /// Returns the commit of the AUR repository that was built, when the built-in backend built it.
+  async fn install_aur(&self, package: &str, helper: Option<&String>) -> Result<Option<String>> {
+      // A helper fetches and builds its own copy, and AUR dependencies it pulls in are never
+      // shown, so a PKGBUILD that is reviewed is built by the built-in backend from the very
+      // checkout that was reviewed
+      if self.config.installation.review_pkgbuild {
+          return self.install_aur_native(package).await;
       }
       
-      let mut cmd = Command::new(aur_helper);
-      cmd.args(&["-S", "--needed", "--noconfirm", package]);
+      let aur_helper = match self.select_aur_helper(helper).await? {
+          Some(helper) => helper,
+          None => return self.install_aur_native(package).await,
+      };
       
-      debug!("Running: {} {:?}", aur_helper, cmd.as_std().get_args().collect::<Vec<_>>());
+      let mut cmd = CommandSpec::new(&aur_helper);
+      cmd.arg("-S");
+      if !self.reinstall {
+          cmd.arg("--needed");
+      }
+      cmd.arg("--noconfirm");
+      match aur_helper.as_str() {
+          "yay" => { cmd.args(["--noeditmenu", "--nodiffmenu"]); }
+          "paru" => { cmd.arg("--skipreview"); }
+          _ => {}
+      }
+      cmd.arg(package);
       
-      let output = cmd.output().await?;
+      debug!("Running: {}", cmd);
+      
+      let output = self.run(&cmd).await?;
       
       if !output.status.success() {
           let stderr = String::from_utf8_lossy(&output.stderr);
@@ -119,36 +330,110 @@ impl Installer {
       }
       
       info!("Successfully installed AUR package: {}", package);
-      Ok(())
+      Ok(None)
   }
   
-  /// Install binary from URL
-  async fn install_binary(&self, url: &str, checksum: Option<&String>, install_path: &str, executable: bool) -> Result<()> {
-      let pb = ProgressBar::new_spinner();
-      pb.set_style(ProgressStyle::default_spinner()
-          .template("{spinner:.green} {msg}")
-          .unwrap());
-      pb.set_message("Downloading binary...");
-      pb.enable_steady_tick(std::time::Duration::from_millis(100));
-      
-      // Download the binary
-      let client = reqwest::Client::builder()
-          .user_agent("archbox/0.1.0")
-          .build()?;
-      
-      let response = client.get(url).send().await?;
+  /// Pick the AUR helper to use. `None` means the built-in backend: either requested
+  /// explicitly with `native`, or because no helper is installed.
+  async fn select_aur_helper(&self, helper: Option<&String>) -> Result<Option<String>> {
+      let requested = helper
+          .map(|h| h.as_str())
+          .or(self.config.aur_helper.as_deref());
+      
+      match requested {
+          Some(aur::NATIVE_HELPER) => Ok(None),
+          Some(helper) => {
+              if self.command_exists(helper).await? {
+                  Ok(Some(helper.to_string()))
+              } else {
+                  warn!("AUR helper '{}' not found, using the built-in AUR backend", helper);
+                  Ok(None)
+              }
+          }
+          None => {
+              for candidate in aur::DETECTED_HELPERS {
+                  if self.command_exists(candidate).await? {
+                      return Ok(Some(candidate.to_string()));
+                  }
+              }
+              debug!("No AUR helper found, using the built-in AUR backend");
+              Ok(None)
+          }
+      }
+  }
+  
+  /// Download a file from the best of its mirrors, falling back to the next one when a
+  /// download fails. Returns the content and the URL it came from.
+  async fn download_with_progress(&self, url: &str, label: &str) -> Result<(Vec<u8>, String)> {
+      let client = crate::http::download_client()?;
+      
+      // Always holds at least the URL itself
+      let candidates = crate::repository::mirrors::artifact_candidates(url, &self.config);
+      let (last, earlier) = candidates.split_last().expect("artifact candidates include the URL");
+      for candidate in earlier {
+          match self.download_from(&client, &candidate.url, label).await {
+              Ok(content) => return Ok((content, candidate.url.clone())),
+              Err(e) => warn!("Download from {} failed, trying the next mirror: {}", candidate.url, e),
+          }
+      }
       
-      if !response.status().is_success() {
-          pb.finish_with_message("Download failed");
-          return Err(Error::Network(reqwest::Error::from(response.error_for_status().unwrap_err())));
+      let content = self.download_from(&client, &last.url, label).await?;
+      Ok((content, last.url.clone()))
+  }
+  
+  /// Download a file, showing transferred bytes, rate and ETA. Falls back to a byte
+  /// counter when the server does not send a Content-Length. Fails if the server stalls
+  /// for longer than the download timeout, however long the whole download takes.
+  async fn download_from(&self, client: &reqwest::Client, url: &str, label: &str) -> Result<Vec<u8>> {
+      let operation = format!("Downloading {}", url);
+      let mut response = crate::http::within(&operation, client.get(url).send()).await??.error_for_status()?;
+      let total = response.content_length();
+      
+      let pb = progress::download(total, format!("Downloading {}", label));
+      
+      let mut content = Vec::with_capacity(total.unwrap_or(0) as usize);
+      loop {
+          match crate::http::within(&operation, response.chunk()).await {
+              Ok(Ok(Some(chunk))) => {
+                  content.extend_from_slice(&chunk);
+                  pb.inc(chunk.len() as u64);
+              }
+              Ok(Ok(None)) => break,
+              Ok(Err(e)) => {
+                  pb.abandon_with_message(format!("Download of {} failed", label));
+                  return Err(e.into());
+              }
+              Err(e) => {
+                  pb.abandon_with_message(format!("Download of {} failed", label));
+                  return Err(e);
+              }
+          }
       }
       
-      let content = response.bytes().await?;
+      pb.finish_and_clear();
+      Ok(content)
+  }
+  
+  /// Install binary from URL. With a version, the file goes into that scope's versioned layout
+  /// and `install_path` becomes a symlink to it; without one it is written in place.
+  async fn install_binary(
+      &self,
+      url: &str,
+      checksum: Option<&String>,
+      install_path: &Path,
+      executable: bool,
+      package_name: &str,
+      version: Option<(InstallScope, &str)>,
+  ) -> Result<Provenance> {
+      // Download the binary
+      let (content, source) = self.download_with_progress(url, package_name).await?;
+      
+      let pb = progress::spinner("");
       
       // Verify checksum if provided
+      let actual_checksum = self.calculate_sha256(&content);
       if let Some(expected_checksum) = checksum {
           pb.set_message("Verifying checksum...");
-          let actual_checksum = self.calculate_sha256(&content);
           if actual_checksum != *expected_checksum {
               pb.finish_with_message("Checksum verification failed");
               return Err(Error::InstallationFailed(format!(
@@ -158,52 +443,192 @@ impl Installer {
           }
       }
       
+      let target = match version {
+          Some((scope, version)) => {
+              let file_name = install_path.file_name().ok_or_else(|| {
+                  Error::InstallationFailed(format!("Invalid install path: {}", install_path.display()))
+              })?;
+              layout::version_dir(package_name, scope, version).join(file_name)
+          }
+          None => install_path.to_path_buf(),
+      };
+      
       // Ensure install directory exists
-      let install_path = PathBuf::from(install_path);
-      if let Some(parent) = install_path.parent() {
+      if let Some(parent) = target.parent() {
           fs::create_dir_all(parent).await?;
       }
       
       pb.set_message("Installing binary...");
       
-      // Write the binary
-      fs::write(&install_path, content).await?;
+      // A file not managed through the layout is the user's own, so keep a copy
+      let managed = version.is_some_and(|(scope, _)| layout::is_managed_link(package_name, scope, install_path));
+      if !managed {
+          self.backup_existing(package_name, install_path).await?;
+      }
+      
+      // Write the binary next to its destination and move it into place, so a
+      // running copy is never truncated
+      let file_name = target.file_name().unwrap_or_default().to_string_lossy().to_string();
+      let partial = target.with_file_name(format!(".{}.archbox-part", file_name));
+      fs::write(&partial, content).await?;
       
       // Make executable if required
       if executable {
           #[cfg(unix)]
           {
               use std::os::unix::fs::PermissionsExt;
-              let mut perms = fs::metadata(&install_path).await?.permissions();
+              let mut perms = fs::metadata(&partial).await?.permissions();
               perms.set_mode(0o755);
-              fs::set_permissions(&install_path, perms).await?;
+              fs::set_permissions(&partial, perms).await?;
+          }
+      }
+      
+      fs::rename(&partial, &target).await?;
+      
+      if let Some((scope, _)) = version {
+          layout::activate(install_path, &target).await?;
+          layout::write_shim(package_name, scope, install_path).await?;
+          
+          let active = layout::active_version(package_name, scope, install_path).unwrap_or_default();
+          let keep = self.config.installation.keep_versions;
+          let pruned = layout::prune_versions(package_name, scope, keep, &active).await?;
+          if !pruned.is_empty() {
+              debug!("Removed old versions of {}: {:?}", package_name, pruned);
           }
       }
       
       pb.finish_with_message("Binary installed successfully");
       info!("Installed binary to: {}", install_path.display());
+      Ok(Provenance::download(&source, actual_checksum))
+  }
+  
+  /// Install from source, returning the commit that was built for git sources
+  async fn install_source(&self, url: &str, build_commands: &[String], install_commands: &[String], package: &Package) -> Result<Option<String>> {
+      let work_dir = WorkDir::create(&self.config.installation, &format!("build-{}", package.name))?;
+      
+      let installed_deps = self.install_build_dependencies(package).await?;
+      
+      let result = self.build_source(url, build_commands, install_commands, work_dir.path(), package).await;
+      
+      if self.build_options.remove_build_deps && !installed_deps.is_empty() {
+          if let Err(e) = self.remove_build_dependencies(&installed_deps).await {
+              warn!("{}", e);
+          }
+      }
+      
+      if self.build_options.keep_build && result.is_ok() {
+          let kept = work_dir.keep();
+          info!("Build directory kept at {}", kept.display());
+          return result;
+      }
+      
+      work_dir.finish(result)
+  }
+  
+  /// Install the package's Build dependencies that are missing, returning the ones installed
+  async fn install_build_dependencies(&self, package: &Package) -> Result<Vec<String>> {
+      let declared: Vec<String> = package
+          .get_dependencies(DependencyType::Build, &Platform::current())
+          .iter()
+          .filter(|dep| !dep.optional)
+          .map(|dep| dep.name.clone())
+          .collect();
+      
+      let missing = aur::missing_dependencies(self.runner.as_ref(), &declared).await?;
+      if missing.is_empty() {
+          return Ok(missing);
+      }
+      
+      info!("Installing build dependencies for {}: {:?}", package.name, missing);
+      let output = self.run(privilege::privileged("pacman")
+          .args(["-S", "--needed", "--noconfirm", "--asdeps"])
+          .args(&missing))
+          .await?;
+      
+      if !output.status.success() {
+          return Err(Error::Dependency(format!(
+              "Failed to install build dependencies for {}: {}",
+              package.name,
+              String::from_utf8_lossy(&output.stderr)
+          )));
+      }
+      
+      Ok(missing)
+  }
+  
+  async fn remove_build_dependencies(&self, packages: &[String]) -> Result<()> {
+      info!("Removing build dependencies: {:?}", packages);
+      let output = self.run(privilege::privileged("pacman")
+          .args(["-Rns", "--noconfirm"])
+          .args(packages))
+          .await?;
+      
+      if !output.status.success() {
+          return Err(Error::CommandFailed {
+              message: format!(
+                  "Failed to remove build dependencies: {}",
+                  String::from_utf8_lossy(&output.stderr)
+              ),
+          });
+      }
+      
       Ok(())
   }
   
-  /// Install from source
-  async fn install_source(&self, url: &str, build_commands: &[String], install_commands: &[String]) -> Result<()> {
-      let temp_dir = tempfile::tempdir()?;
-      let work_dir = temp_dir.path();
+  /// Fetch, build and install a source package in `work_dir`, returning the commit that
+  /// was built for git sources
+  async fn build_source(
+      &self,
+      url: &str,
+      build_commands: &[String],
+      install_commands: &[String],
+      work_dir: &Path,
+      package: &Package,
+  ) -> Result<Option<String>> {
+      let pb = progress::spinner("");
+      let package_name = package.name.as_str();
+      let package_env = package.command_env();
+      
+      let is_git = is_git_source(url);
+      
+      // Builds are cached per commit, which needs a git source. A reinstall doesn't trust
+      // the cached tree but still stores the new build.
+      let remote_commit = if self.config.installation.build_cache && is_git {
+          build_cache::remote_head(self.runner.as_ref(), url).await
+      } else {
+          None
+      };
+      // The environment can change what gets built, e.g. through PREFIX
+      let keyed_commands: Vec<String> = package_env
+          .iter()
+          .map(|(key, value)| format!("{}={}", key, value))
+          .chain(build_commands.iter().cloned())
+          .collect();
+      let mut cache_key = remote_commit
+          .as_ref()
+          .map(|commit| build_cache::cache_key(url, commit, &keyed_commands));
+      
+      let cached = cache_key.as_deref().filter(|_| !self.reinstall).and_then(|key| build_cache::lookup(package_name, key));
+      if let Some(cached) = cached {
+          pb.set_message("Restoring cached build...");
+          info!("Reusing cached build of {} from {}", package_name, cached.display());
+          build_cache::restore(self.runner.as_ref(), &cached, work_dir).await?;
+          
+          pb.set_message("Installing...");
+          self.run_install_commands(install_commands, work_dir, &package_env, &pb).await?;
+          
+          pb.finish_with_message("Source installation complete");
+          return Ok(remote_commit);
+      }
       
       // Clone/download source
-      let pb = ProgressBar::new_spinner();
-      pb.set_style(ProgressStyle::default_spinner()
-          .template("{spinner:.green} {msg}")
-          .unwrap());
       pb.set_message("Downloading source...");
-      pb.enable_steady_tick(std::time::Duration::from_millis(100));
       
-      if url.ends_with(".git") || url.contains("github.com") || url.contains("gitlab.com") {
+      let commit = if is_git {
           // Git repository
-          let output = Command::new("git")
-              .args(&["clone", url, "."])
-              .current_dir(work_dir)
-              .output()
+          let output = self.run(CommandSpec::new("git")
+              .args(["clone", url, "."])
+              .current_dir(work_dir))
               .await?;
           
           if !output.status.success() {
@@ -213,21 +638,35 @@ impl Installer {
                   String::from_utf8_lossy(&output.stderr)
               )));
           }
+          
+          // Key the stored build on what was actually cloned, in case HEAD moved meanwhile
+          let commit = self.head_commit(work_dir).await;
+          if cache_key.is_some() {
+              cache_key = Some(build_cache::cache_key(url, commit.as_deref().unwrap_or_default(), &keyed_commands));
+          }
+          commit
       } else {
           // Download and extract archive
-          let client = reqwest::Client::new();
+          let client = crate::http::client()?;
           let response = client.get(url).send().await?;
-          let content = response.bytes().await?;
+          let _content = response.bytes().await?;
           
           // This is simplified - in practice you'd detect archive type and extract accordingly
           return Err(Error::InstallationFailed("Archive extraction not implemented yet".to_string()));
-      }
+      };
       
       pb.set_message("Building from source...");
       
+      let mut env = if self.config.installation.compiler_cache {
+          build_cache::compiler_cache_env()
+      } else {
+          Vec::new()
+      };
+      env.extend(package_env.iter().cloned());
+      
       // Run build commands
       for command in build_commands {
-          let output = self.run_shell_command(command, work_dir).await?;
+          let output = self.run_shell_command(command, work_dir, &env, self.config.installation.build_limit()).await?;
           if !output.status.success() {
               pb.finish_with_message("Build failed");
               return Err(Error::InstallationFailed(format!(
@@ -238,11 +677,22 @@ impl Installer {
           }
       }
       
+      if let Some(key) = &cache_key {
+          if let Err(e) = build_cache::store(self.runner.as_ref(), package_name, key, work_dir).await {
+              warn!("Failed to cache build of {}: {}", package_name, e);
+          }
+      }
+      
       pb.set_message("Installing...");
+      self.run_install_commands(install_commands, work_dir, &package_env, &pb).await?;
       
-      // Run install commands
+      pb.finish_with_message("Source installation complete");
+      Ok(commit)
+  }
+  
+  async fn run_install_commands(&self, install_commands: &[String], work_dir: &Path, env: &[(String, String)], pb: &ProgressBar) -> Result<()> {
       for command in install_commands {
-          let output = self.run_shell_command(command, work_dir).await?;
+          let output = self.run_shell_command(command, work_dir, env, self.config.installation.build_limit()).await?;
           if !output.status.success() {
               pb.finish_with_message("Installation failed");
               return Err(Error::InstallationFailed(format!(
@@ -253,13 +703,16 @@ impl Installer {
           }
       }
       
-      pb.finish_with_message("Source installation complete");
       Ok(())
   }
   
   /// Install via script
-  async fn install_script(&self, script: &str, interpreter: &str) -> Result<()> {
-      let temp_file = tempfile::NamedTempFile::new()?;
+  async fn install_script(&self, script: &str, interpreter: &str, env: &[(String, String)]) -> Result<()> {
+      // Inside a target root the script has to be somewhere the chroot can see it
+      let temp_file = match &self.root {
+          Some(root) => tempfile::NamedTempFile::new_in(root.join("tmp"))?,
+          None => tempfile::NamedTempFile::new()?,
+      };
       let script_path = temp_file.path();
       
       // Write script to temporary file
@@ -275,10 +728,21 @@ impl Installer {
       }
       
       // Execute script
-      let output = Command::new(interpreter)
-          .arg(script_path)
-          .output()
-          .await?;
+      let mut cmd = match &self.root {
+          Some(root) => {
+              let chroot_path = Path::new("/").join(script_path.strip_prefix(root).unwrap_or(script_path));
+              let mut cmd = CommandSpec::new("arch-chroot");
+              cmd.arg(root).arg(interpreter).arg(chroot_path);
+              cmd
+          }
+          None => {
+              let mut cmd = CommandSpec::new(interpreter);
+              cmd.arg(script_path);
+              cmd
+          }
+      };
+      cmd.envs(expand_env(env)?).timeout(self.config.installation.script_limit());
+      let output = self.run(&cmd).await?;
       
       if !output.status.success() {
           return Err(Error::InstallationFailed(format!(
@@ -292,25 +756,26 @@ impl Installer {
   }
   
   /// Install AppImage
-  async fn install_appimage(&self, url: &str, checksum: Option<&String>, integrate: bool, name: &str) -> Result<()> {
-      let appimage_dir = dirs::home_dir()
-          .ok_or_else(|| Error::InstallationFailed("Could not find home directory".to_string()))?
-          .join(".local/share/applications");
+  async fn install_appimage(&self, url: &str, checksum: Option<&String>, integrate: bool, name: &str, appimage_path: &Path) -> Result<Provenance> {
+      let appimage_dir = appimage_path.parent().unwrap_or(Path::new("/"));
       
-      fs::create_dir_all(&appimage_dir).await?;
+      fs::create_dir_all(appimage_dir).await?;
       
-      let appimage_path = appimage_dir.join(format!("{}.AppImage", name));
-      
-      // Download AppImage (reuse binary installation logic)
-      self.install_binary(url, checksum, &appimage_path.to_string_lossy(), true).await?;
+      // Prefer a zsync delta update when an existing AppImage carries update information,
+      // otherwise download it in full (reuse binary installation logic)
+      let provenance = if !self.reinstall && appimage_path.exists() && self.delta_update_appimage(appimage_path, checksum, name).await {
+          info!("Delta-updated AppImage: {}", appimage_path.display());
+          Provenance::download(url, self.calculate_sha256(&fs::read(appimage_path).await?))
+      } else {
+          self.install_binary(url, checksum, appimage_path, true, name, None).await?
+      };
       
       if integrate {
           // Extract desktop file and icon for integration
-          let output = Command::new(&appimage_path)
+          let output = self.run(CommandSpec::new(appimage_path)
               .arg("--appimage-extract-and-run")
               .arg("--appimage-extract")
-              .current_dir(&appimage_dir)
-              .output()
+              .current_dir(appimage_dir))
               .await;
           
           if let Err(e) = output {
@@ -320,11 +785,69 @@ impl Installer {
           }
       }
       
-      Ok(())
+      Ok(provenance)
   }
   
-  /// Install Flatpak
-  async fn install_flatpak(&self, id: &str, remote: Option<&String>) -> Result<()> {
+  /// Try to update an installed AppImage with zsync. Returns false when a full download is needed.
+  async fn delta_update_appimage(&self, path: &Path, checksum: Option<&String>, name: &str) -> bool {
+      let update_info = match crate::package::appimage::read_update_info(path).await {
+          Ok(Some(info)) => info,
+          Ok(None) => {
+              debug!("{} has no update information", path.display());
+              return false;
+          }
+          Err(e) => {
+              debug!("Could not read update information from {}: {}", path.display(), e);
+              return false;
+          }
+      };
+      
+      if !self.command_exists("zsync").await.unwrap_or(false) {
+          debug!("zsync not available, falling back to full download");
+          return false;
+      }
+      
+      let zsync_url = match update_info.zsync_url().await {
+          Ok(Some(url)) => url,
+          Ok(None) => return false,
+          Err(e) => {
+              warn!("Failed to resolve AppImage update source: {}", e);
+              return false;
+          }
+      };
+      
+      if let Err(e) = self.backup_existing(name, path).await {
+          warn!("Failed to back up {}: {}", path.display(), e);
+          return false;
+      }
+      
+      if let Err(e) = crate::package::appimage::zsync_update(self.runner.as_ref(), path, &zsync_url).await {
+          warn!("{}; falling back to full download", e);
+          return false;
+      }
+      
+      if let Some(expected) = checksum {
+          let actual = match fs::read(path).await {
+              Ok(data) => self.calculate_sha256(&data),
+              Err(_) => return false,
+          };
+          if actual != *expected {
+              warn!("Delta-updated AppImage failed checksum verification; falling back to full download");
+              return false;
+          }
+      }
+      
+      true
+  }
+  
+  /// Install Flatpak, recording the commit that was deployed
+  async fn install_flatpak(
+      &self,
+      id: &str,
+      remote: Option<&str>,
+      remote_url: Option<&str>,
+      scope: FlatpakScope,
+  ) -> Result<Provenance> {
       // Check if flatpak is available
       if !self.command_exists("flatpak").await? {
           return Err(Error::InstallationFailed(
@@ -332,18 +855,17 @@ impl Installer {
           ));
       }
       
-      let mut cmd = Command::new("flatpak");
-      cmd.args(&["install", "-y"]);
+      let remote = remote.unwrap_or(FLATHUB_REMOTE);
+      self.ensure_flatpak_remote(remote, remote_url, scope).await?;
       
-      if let Some(remote) = remote {
-          cmd.arg(remote);
-      } else {
-          cmd.arg("flathub");
+      let mut cmd = CommandSpec::new("flatpak");
+      cmd.args(["install", "-y", "--noninteractive"]);
+      if self.reinstall {
+          cmd.arg("--reinstall");
       }
+      cmd.args([scope.flag(), remote, id]);
       
-      cmd.arg(id);
-      
-      let output = cmd.output().await?;
+      let output = self.run(&cmd).await?;
       
       if !output.status.success() {
           return Err(Error::InstallationFailed(format!(
@@ -353,75 +875,142 @@ impl Installer {
       }
       
       info!("Successfully installed Flatpak: {}", id);
-      Ok(())
-  }
-  
-  /// Run post-installation configuration
-  async fn run_post_install(&self, post_install: &PostInstall, package_name: &str) -> Result<()> {
-      info!("Running post-installation configuration for {}", package_name);
       
-      // Run commands
-      if let Some(commands) = &post_install.commands {
-          for command in commands {
-              info!("Running post-install command: {}", command);
-              let output = self.run_shell_command(command, Path::new("/")).await?;
-              if !output.status.success() {
-                  warn!("Post-install command failed: {}", command);
-              }
+      let commit = match self.run(CommandSpec::new("flatpak").args(["info", scope.flag(), "--show-commit", id])).await {
+          Ok(output) if output.status.success() => {
+              Some(String::from_utf8_lossy(&output.stdout).trim().to_string()).filter(|commit| !commit.is_empty())
           }
+          _ => None,
+      };
+      
+      Ok(Provenance {
+          source: format!("flatpak: {} {}", remote, id),
+          commit,
+          ..Default::default()
+      })
+  }
+  
+  /// Make sure a Flatpak remote is configured for the given scope, offering to add it if not
+  async fn ensure_flatpak_remote(
+      &self,
+      remote: &str,
+      remote_url: Option<&str>,
+      scope: FlatpakScope,
+  ) -> Result<()> {
+      let output = self.run(CommandSpec::new("flatpak").args(["remotes", scope.flag(), "--columns=name"])).await?;
+      
+      let configured = String::from_utf8_lossy(&output.stdout)
+          .lines()
+          .any(|line| line.trim() == remote);
+      
+      if configured {
+          return Ok(());
       }
       
-      // Create/modify config files
-      if let Some(config_files) = &post_install.config_files {
-          for (path, content) in config_files {
-              self.create_config_file(path, content).await?;
+      let url = match remote_url {
+          Some(url) => url,
+          None if remote == FLATHUB_REMOTE => FLATHUB_REPO_URL,
+          None => {
+              return Err(Error::InstallationFailed(format!(
+                  "Flatpak remote '{}' is not configured and the package does not specify a remote_url",
+                  remote
+              )));
           }
-      }
+      };
       
-      // Enable services
-      if let Some(services) = &post_install.enable_services {
-          for service in services {
-              self.enable_service(service).await?;
+      let question = format!("Flatpak remote '{}' is not configured. Add it from {}?", remote, url);
+      match prompt_yes_no(&question, true)? {
+          Some(true) => {}
+          Some(false) => {
+              return Err(Error::InstallationFailed(format!(
+                  "Flatpak remote '{}' is required but was not added",
+                  remote
+              )));
           }
-      }
-      
-      // Add user to groups
-      if let Some(groups) = &post_install.user_groups {
-          for group in groups {
-              self.add_user_to_group(group).await?;
+          None => {
+              return Err(Error::InstallationFailed(format!(
+                  "Flatpak remote '{}' is not configured (add it with: flatpak remote-add {} {} {})",
+                  remote,
+                  scope.flag(),
+                  remote,
+                  url
+              )));
           }
       }
       
-      // Set environment variables
-      if let Some(env_vars) = &post_install.environment {
-          self.set_environment_variables(env_vars).await?;
+      info!("Adding Flatpak remote {} ({})", remote, url);
+      let output = self.run(CommandSpec::new("flatpak").args(["remote-add", "--if-not-exists", scope.flag(), remote, url])).await?;
+      
+      if !output.status.success() {
+          return Err(Error::InstallationFailed(format!(
+              "Failed to add Flatpak remote {}: {}",
+              remote,
+              String::from_utf8_lossy(&output.stderr)
+          )));
       }
       
       Ok(())
   }
   
+  /// Run a command through the installer's runner
+  async fn run(&self, command: &CommandSpec) -> Result<Output> {
+      match self.runner.output(command).await {
+          Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Err(Error::Timeout {
+              operation: command.command_line(),
+              seconds: command.timeout.unwrap_or_default().as_secs(),
+          }),
+          result => Ok(result?),
+      }
+  }
+  
+  /// The commit checked out in a git working tree, if git can tell
+  async fn head_commit(&self, dir: &Path) -> Option<String> {
+      let output = self.run(CommandSpec::new("git").args(["rev-parse", "HEAD"]).current_dir(dir)).await.ok()?;
+      let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+      (output.status.success() && !commit.is_empty()).then_some(commit)
+  }
+  
   /// Helper function to check if a command exists
   async fn command_exists(&self, command: &str) -> Result<bool> {
-      let output = Command::new("which")
-          .arg(command)
-          .output()
-          .await?;
+      let output = self.run(CommandSpec::new("which").arg(command)).await?;
       
       Ok(output.status.success())
   }
   
-  /// Helper function to run shell commands
-  async fn run_shell_command(&self, command: &str, work_dir: &Path) -> Result<std::process::Output> {
-      let output = Command::new("sh")
-          .arg("-c")
+  /// Helper function to run shell commands with `env` expanded, stopping them after `limit`
+  pub(crate) async fn run_shell_command(&self, command: &str, work_dir: &Path, env: &[(String, String)], limit: Option<Duration>) -> Result<Output> {
+      let started = std::time::Instant::now();
+      let mut cmd = match &self.root {
+          Some(root) => {
+              let mut cmd = CommandSpec::new("arch-chroot");
+              cmd.arg(root).arg("sh");
+              cmd
+          }
+          None => {
+              let mut cmd = CommandSpec::new("sh");
+              cmd.current_dir(work_dir);
+              cmd
+          }
+      };
+      cmd.arg("-c")
           .arg(command)
-          .current_dir(work_dir)
-          .output()
-          .await?;
+          .envs(expand_env(env)?)
+          .timeout(limit);
+      let output = self.run(&cmd).await?;
+      crate::history::record_command(command, started.elapsed(), &output);
       
       Ok(output)
   }
   
+  /// Where `path` on the system being installed lives from here: under the target root if
+  /// there is one
+  fn target_path(&self, path: &Path) -> PathBuf {
+      match &self.root {
+          Some(root) => root.join(path.strip_prefix("/").unwrap_or(path)),
+          None => path.to_path_buf(),
+      }
+  }
+  
   /// Calculate SHA256 checksum
   fn calculate_sha256(&self, data: &[u8]) -> String {
       use sha2::{Sha256, Digest};
@@ -430,76 +1019,75 @@ impl Installer {
       format!("{:x}", hasher.finalize())
   }
   
-  /// Create configuration file
-  async fn create_config_file(&self, path: &str, content: &str) -> Result<()> {
-      let expanded_path = shellexpand::tilde(path);
-      let path = Path::new(expanded_path.as_ref());
-      
-      if let Some(parent) = path.parent() {
-          fs::create_dir_all(parent).await?;
+  /// Back up a file that is about to be overwritten, if backups are enabled.
+  /// Returns the backup location when one was made.
+  async fn backup_existing(&self, package_name: &str, path: &Path) -> Result<Option<PathBuf>> {
+      if self.config.installation.create_backups && path.is_file() {
+          return Ok(Some(crate::backup::backup_file(package_name, path).await?));
       }
       
-      fs::write(path, content).await?;
-      info!("Created config file: {}", path.display());
-      Ok(())
+      Ok(None)
   }
+}
+
+/// `env` with `~` and `$VAR` in each value expanded from ArchBox's own environment
+fn expand_env(env: &[(String, String)]) -> Result<Vec<(String, String)>> {
+  env.iter()
+      .map(|(key, value)| {
+          let expanded = shellexpand::full(value).map_err(|e| {
+              Error::InstallationFailed(format!("Can't expand {} in the package environment: {}", key, e))
+          })?;
+          Ok((key.clone(), expanded.into_owned()))
+      })
+      .collect()
+}
+
+/// Refuse a script whose SHA-256 doesn't match the hash pinned for it, so a mirror serving
+/// altered definitions can't swap what runs
+pub fn verify_script(script: &str, sha256: Option<&str>) -> Result<()> {
+  let Some(expected) = sha256.map(str::trim) else {
+      return Ok(());
+  };
   
-  /// Enable systemd service
-  async fn enable_service(&self, service: &str) -> Result<()> {
-      let output = Command::new("systemctl")
-          .args(&["enable", "--now", service])
-          .output()
-          .await?;
-      
-      if output.status.success() {
-          info!("Enabled service: {}", service);
-      } else {
-          warn!("Failed to enable service {}: {}", service, String::from_utf8_lossy(&output.stderr));
-      }
-      
-      Ok(())
+  use sha2::{Digest, Sha256};
+  let actual = format!("{:x}", Sha256::digest(script.as_bytes()));
+  if !actual.eq_ignore_ascii_case(expected) {
+      return Err(Error::InstallationFailed(format!(
+          "Script hash mismatch: expected sha256 {}, got {}; refusing to run it",
+          expected, actual
+      )));
   }
   
-  /// Add user to group
-  async fn add_user_to_group(&self, group: &str) -> Result<()> {
-      let username = std::env::var("USER")
-          .or_else(|_| std::env::var("USERNAME"))
-          .unwrap_or_else(|_| "user".to_string());
-      
-      let output = Command::new("usermod")
-          .args(&["-a", "-G", group, &username])
-          .output()
-          .await?;
-      
-      if output.status.success() {
-          info!("Added user {} to group {}", username, group);
-      } else {
-          warn!("Failed to add user to group {}: {}", group, String::from_utf8_lossy(&output.stderr));
-      }
-      
-      Ok(())
+  Ok(())
+}
+
+/// Whether a Source package's `url` is cloned with git; anything else is an archive
+pub fn is_git_source(url: &str) -> bool {
+  url.ends_with(".git") || url.contains("github.com") || url.contains("gitlab.com")
+}
+
+/// Ask a yes/no question on the terminal. Returns `None` when there is no terminal to ask on.
+fn prompt_yes_no(question: &str, default_yes: bool) -> Result<Option<bool>> {
+  if !std::io::stdin().is_terminal() {
+      return Ok(None);
   }
   
-  /// Set environment variables
-  async fn set_environment_variables(&self, env_vars: &HashMap<String, String>) -> Result<()> {
-      let profile_path = dirs::home_dir()
-          .ok_or_else(|| Error::InstallationFailed("Could not find home directory".to_string()))?
-          .join(".profile");
-      
-      let mut content = String::new();
-      if profile_path.exists() {
-          content = fs::read_to_string(&profile_path).await?;
-      }
-      
-      for (key, value) in env_vars {
-          let env_line = format!("export {}=\"{}\"\n", key, value);
-          if !content.contains(&env_line) {
-              content.push_str(&env_line);
-          }
-      }
-      
-      fs::write(&profile_path, content).await?;
-      info!("Updated environment variables in {}", profile_path.display());
-      Ok(())
-  }
-}
\ No newline at end of file
+  let hint = if default_yes { "[Y/n]" } else { "[y/N]" };
+  
+  // Keep the progress bars from drawing over the question while waiting for an answer
+  let input = progress::suspend(|| -> Result<String> {
+      print!("{} {}: ", question, hint);
+      std::io::stdout().flush()?;
+      
+      let mut input = String::new();
+      std::io::stdin().read_line(&mut input)?;
+      Ok(input)
+  })?;
+  
+  let answer = match input.trim().to_lowercase().as_str() {
+      "" => default_yes,
+      "y" | "yes" => true,
+      _ => false,
+  };
+  Ok(Some(answer))
+}
@@ -0,0 +1,322 @@
+//! Built-in AUR backend: clone, resolve dependencies from `.SRCINFO` and build with makepkg
+
+use super::{prompt_yes_no, Installer};
+use crate::{
+  config::get_state_dir,
+  package::runner::{CommandRunner, CommandSpec},
+  privilege::{privileged, running_as_root},
+  workdir::WorkDir,
+  Error, Result,
+};
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Helper name that selects the built-in backend explicitly
+pub const NATIVE_HELPER: &str = "native";
+
+/// Helpers looked for, in order, when none is configured
+pub const DETECTED_HELPERS: [&str; 2] = ["yay", "paru"];
+
+/// Where AUR packages are cloned from, as `<base>/<package>.git`
+pub const AUR_GIT_BASE: &str = "https://aur.archlinux.org";
+
+/// Dependencies declared in a `.SRCINFO` file
+#[derive(Debug, Default, Clone)]
+pub struct SrcInfo {
+  pub depends: Vec<String>,
+  pub makedepends: Vec<String>,
+  pub checkdepends: Vec<String>,
+}
+
+impl SrcInfo {
+  /// Parse the dependency fields of a `.SRCINFO`, including architecture-specific ones
+  pub fn parse(content: &str) -> Self {
+      let mut info = SrcInfo::default();
+
+      for line in content.lines() {
+          let Some((key, value)) = line.trim().split_once(" = ") else {
+              continue;
+          };
+
+          let key = key.split('_').next().unwrap_or(key);
+          let name = strip_version_constraint(value.trim());
+
+          let list = match key {
+              "depends" => &mut info.depends,
+              "makedepends" => &mut info.makedepends,
+              "checkdepends" => &mut info.checkdepends,
+              _ => continue,
+          };
+
+          if !list.iter().any(|existing| existing == name) {
+              list.push(name.to_string());
+          }
+      }
+
+      info
+  }
+
+  /// Every dependency needed to build and run the package
+  pub fn all_dependencies(&self) -> Vec<String> {
+      let mut all: Vec<String> = Vec::new();
+      for dep in self.depends.iter().chain(&self.makedepends).chain(&self.checkdepends) {
+          if !all.contains(dep) {
+              all.push(dep.clone());
+          }
+      }
+      all
+  }
+}
+
+/// Copy of the last PKGBUILD built for a package, used as the base for review diffs
+fn reviewed_pkgbuild_path(package: &str) -> PathBuf {
+  get_state_dir().join("aur").join(package).join("PKGBUILD")
+}
+
+/// `foo>=1.2` -> `foo`
+fn strip_version_constraint(dep: &str) -> &str {
+  dep.split(['<', '>', '=']).next().unwrap_or(dep).trim()
+}
+
+impl Installer {
+  /// Build and install an AUR package without a helper, returning the commit of its AUR
+  /// repository that was built
+  pub(super) async fn install_aur_native(&self, package: &str) -> Result<Option<String>> {
+      let mut visited = HashSet::new();
+      self.build_aur_package(package, &mut visited, self.reinstall).await
+  }
+
+  /// Build and install one AUR package after its AUR dependencies. With `reinstall` it is
+  /// installed even if the same version already is.
+  async fn build_aur_package(&self, package: &str, visited: &mut HashSet<String>, reinstall: bool) -> Result<Option<String>> {
+      if !visited.insert(package.to_string()) {
+          return Ok(None);
+      }
+
+      for tool in ["git", "makepkg"] {
+          if !self.command_exists(tool).await? {
+              return Err(Error::InstallationFailed(format!(
+                  "'{}' is required to build AUR packages without a helper",
+                  tool
+              )));
+          }
+      }
+
+      let build_root = WorkDir::create(&self.config.installation, &format!("aur-{}", package))?;
+      let result = self.build_aur_package_in(package, build_root.path(), visited, reinstall).await;
+      build_root.finish(result)
+  }
+
+  /// The body of [`build_aur_package`](Self::build_aur_package), cloning into `build_root`
+  async fn build_aur_package_in(&self, package: &str, build_root: &Path, visited: &mut HashSet<String>, reinstall: bool) -> Result<Option<String>> {
+      let build_dir = clone_aur_package(self.runner.as_ref(), package, build_root).await?;
+      let commit = self.head_commit(&build_dir).await;
+      
+      self.review_pkgbuild(package, &build_dir).await?;
+
+      let srcinfo = self.read_srcinfo(&build_dir).await?;
+      let missing = missing_dependencies(self.runner.as_ref(), &srcinfo.all_dependencies()).await?;
+
+      let mut repo_deps = Vec::new();
+      for dep in missing {
+          if in_sync_repos(self.runner.as_ref(), &dep).await {
+              repo_deps.push(dep);
+          } else {
+              info!("{} depends on AUR package {}", package, dep);
+              Box::pin(self.build_aur_package(&dep, visited, false)).await?;
+          }
+      }
+
+      if !repo_deps.is_empty() {
+          info!("Installing build dependencies for {}: {:?}", package, repo_deps);
+          let output = self.run(privileged("pacman")
+              .args(["-S", "--needed", "--noconfirm", "--asdeps"])
+              .args(&repo_deps))
+              .await?;
+
+          if !output.status.success() {
+              return Err(Error::InstallationFailed(format!(
+                  "Failed to install dependencies for {}: {}",
+                  package,
+                  String::from_utf8_lossy(&output.stderr)
+              )));
+          }
+      }
+
+      info!("Building {} with makepkg", package);
+      let mut makepkg = makepkg(&build_dir)?;
+      makepkg.args(["-si", "--noconfirm"]).timeout(self.config.installation.build_limit());
+      if !reinstall {
+          makepkg.arg("--needed");
+      }
+      let output = self.run(&makepkg).await?;
+
+      if !output.status.success() {
+          return Err(Error::InstallationFailed(format!(
+              "makepkg failed for {}: {}",
+              package,
+              String::from_utf8_lossy(&output.stderr)
+          )));
+      }
+
+      remember_pkgbuild(package, &build_dir).await?;
+      
+      info!("Successfully built and installed AUR package: {}", package);
+      Ok(commit)
+  }
+  
+  /// Show the PKGBUILD (or its changes since the last build) and ask before building it.
+  /// Skipped when `installation.review_pkgbuild` is off or nothing changed since the last build.
+  async fn review_pkgbuild(&self, package: &str, build_dir: &Path) -> Result<()> {
+      if !self.config.installation.review_pkgbuild {
+          return Ok(());
+      }
+      
+      let pkgbuild = build_dir.join("PKGBUILD");
+      let current = tokio::fs::read_to_string(&pkgbuild).await?;
+      let previous_path = reviewed_pkgbuild_path(package);
+      let previous = tokio::fs::read_to_string(&previous_path).await.ok();
+      
+      if previous.as_deref() == Some(current.as_str()) {
+          info!("PKGBUILD for {} is unchanged since it was last built", package);
+          return Ok(());
+      }
+      
+      if !std::io::stdin().is_terminal() {
+          return Err(Error::InstallationFailed(format!(
+              "PKGBUILD for {} needs review but no terminal is available \
+               (set installation.review_pkgbuild to false to skip review)",
+              package
+          )));
+      }
+      
+      let diff = match previous {
+          Some(_) => {
+              let color = if console::colors_enabled() { "--color=always" } else { "--color=never" };
+              let output = self.run(CommandSpec::new("git")
+                  .args(["--no-pager", "diff", "--no-index", color])
+                  .arg(&previous_path)
+                  .arg(&pkgbuild))
+                  .await
+                  .ok()
+                  .filter(|output| output.status.code() == Some(1));
+              
+              Some(output.map(|output| String::from_utf8_lossy(&output.stdout).to_string()))
+          }
+          None => None,
+      };
+      
+      crate::progress::suspend(|| {
+          match diff {
+              Some(diff) => {
+                  println!("\n==> Changes to the PKGBUILD for {} since the last build:\n", package);
+                  println!("{}", diff.as_deref().unwrap_or(&current));
+              }
+              None => {
+                  println!("\n==> PKGBUILD for {}:\n", package);
+                  println!("{}", current);
+              }
+          }
+          println!();
+      });
+      
+      if prompt_yes_no(&format!("Build {} from this PKGBUILD?", package), false)? == Some(true) {
+          Ok(())
+      } else {
+          Err(Error::InstallationFailed(format!("PKGBUILD for {} was not approved", package)))
+      }
+  }
+
+  /// Read `.SRCINFO`, generating it from the PKGBUILD if the repository lacks one
+  async fn read_srcinfo(&self, build_dir: &Path) -> Result<SrcInfo> {
+      let path = build_dir.join(".SRCINFO");
+
+      let content = if path.exists() {
+          tokio::fs::read_to_string(&path).await?
+      } else {
+          let output = self.run(makepkg(build_dir)?.arg("--printsrcinfo")).await?;
+          String::from_utf8_lossy(&output.stdout).to_string()
+      };
+
+      Ok(SrcInfo::parse(&content))
+  }
+}
+
+/// Shallow-clone an AUR package's git repository into `parent`
+async fn clone_aur_package(runner: &dyn CommandRunner, package: &str, parent: &Path) -> Result<PathBuf> {
+  let build_dir = parent.join(package);
+
+  info!("Cloning AUR package {}", package);
+  let output = runner
+      .output(CommandSpec::new("git")
+          .args(["clone", "--depth", "1", &format!("{}/{}.git", AUR_GIT_BASE, package)])
+          .arg(&build_dir))
+      .await?;
+
+  if !output.status.success() || !build_dir.join("PKGBUILD").exists() {
+      return Err(Error::InstallationFailed(format!(
+          "Failed to clone AUR package {}: {}",
+          package,
+          String::from_utf8_lossy(&output.stderr)
+      )));
+  }
+
+  Ok(build_dir)
+}
+
+/// Keep the PKGBUILD that was just built so the next review only shows what changed
+async fn remember_pkgbuild(package: &str, build_dir: &Path) -> Result<()> {
+  let path = reviewed_pkgbuild_path(package);
+  if let Some(parent) = path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+  }
+  tokio::fs::copy(build_dir.join("PKGBUILD"), &path).await?;
+  Ok(())
+}
+
+/// Dependencies not yet satisfied on this system (`pacman -T`)
+pub(super) async fn missing_dependencies(runner: &dyn CommandRunner, deps: &[String]) -> Result<Vec<String>> {
+  if deps.is_empty() {
+      return Ok(Vec::new());
+  }
+
+  let output = runner.output(CommandSpec::new("pacman").arg("-T").args(deps)).await?;
+
+  Ok(String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .map(|line| strip_version_constraint(line).to_string())
+      .filter(|line| !line.is_empty())
+      .collect())
+}
+
+/// Whether a dependency (or something providing it) is available from the sync repositories
+async fn in_sync_repos(runner: &dyn CommandRunner, dep: &str) -> bool {
+  runner
+      .output(CommandSpec::new("pacman").args(["-Sp", "--print-format", "%n", dep]))
+      .await
+      .map(|output| output.status.success())
+      .unwrap_or(false)
+}
+
+/// makepkg refuses to run as root, so drop to the invoking sudo user when necessary
+fn makepkg(build_dir: &Path) -> Result<CommandSpec> {
+  let mut cmd = if running_as_root() {
+      let user = std::env::var("SUDO_USER").map_err(|_| {
+          Error::PermissionDenied {
+              operation: "makepkg cannot run as root; run archbox via sudo from a regular user".to_string(),
+          }
+      })?;
+      debug!("Building as {}", user);
+
+      let mut cmd = CommandSpec::new("runuser");
+      cmd.args(["-u", &user, "--", "makepkg"]);
+      cmd
+  } else {
+      CommandSpec::new("makepkg")
+  };
+
+  cmd.current_dir(build_dir);
+  Ok(cmd)
+}
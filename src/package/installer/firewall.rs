@@ -0,0 +1,157 @@
+//! Opening ports in the running firewall for post-install `firewall` rules
+
+use super::{Installer, PostInstallAction};
+use crate::{package::runner::CommandSpec, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Output;
+use tracing::info;
+
+/// Table and chain nftables rules go into, as in Arch's default `/etc/nftables.conf`
+const NFT_CHAIN: [&str; 3] = ["inet", "filter", "input"];
+
+/// Firewalls ArchBox can add rules to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallBackend {
+  Firewalld,
+  Ufw,
+  Nftables,
+}
+
+impl FirewallBackend {
+  pub fn name(&self) -> &'static str {
+      match self {
+          FirewallBackend::Firewalld => "firewalld",
+          FirewallBackend::Ufw => "ufw",
+          FirewallBackend::Nftables => "nftables",
+      }
+  }
+
+  /// Commands allowing incoming connections on `ports` (`22000` or `8000-8010`). firewalld
+  /// gets the rule both now and in its permanent configuration. nftables rules go at the top
+  /// of the chain, ahead of its final drop or reject, and only into the running ruleset.
+  pub fn open_commands(&self, ports: &str, protocol: &str, package_name: &str) -> Vec<CommandSpec> {
+      match self {
+          FirewallBackend::Firewalld => vec![
+              firewall_cmd(&[&format!("--add-port={}/{}", ports, protocol)]),
+              firewall_cmd(&["--permanent", &format!("--add-port={}/{}", ports, protocol)]),
+          ],
+          FirewallBackend::Ufw => vec![command("ufw", &["allow", &ufw_ports(ports, protocol), "comment", &rule_comment(package_name)])],
+          FirewallBackend::Nftables => {
+              let comment = format!("\"{}\"", rule_comment(package_name));
+              vec![nft(&["insert", "rule"], &[protocol, "dport", ports, "accept", "comment", &comment])]
+          }
+      }
+  }
+}
+
+impl Installer {
+  /// The firewall in use, if any. firewalld and ufw are checked first since they keep their
+  /// own rules in nftables.
+  pub(super) async fn detect_firewall(&self) -> Option<FirewallBackend> {
+      let succeeds = |output: Result<Output>| output.is_ok_and(|output| output.status.success());
+
+      if succeeds(self.run(&firewall_cmd(&["--state"])).await) {
+          return Some(FirewallBackend::Firewalld);
+      }
+
+      if let Ok(output) = self.run(&command("ufw", &["status"])).await {
+          if output.status.success() && String::from_utf8_lossy(&output.stdout).contains("Status: active") {
+              return Some(FirewallBackend::Ufw);
+          }
+      }
+
+      if succeeds(self.run(&command("systemctl", &["is-active", "--quiet", "nftables"])).await) {
+          return Some(FirewallBackend::Nftables);
+      }
+
+      None
+  }
+
+  /// Allow incoming connections on `ports` for one protocol
+  pub(super) async fn open_port(&self, backend: FirewallBackend, ports: &str, protocol: &str, package_name: &str) -> Result<PostInstallAction> {
+      for command in backend.open_commands(ports, protocol, package_name) {
+          self.run_firewall_command(&command).await?;
+      }
+
+      info!("Opened {}/{} in {} for {}", ports, protocol, backend.name(), package_name);
+      Ok(PostInstallAction::FirewallRule {
+          backend,
+          ports: ports.to_string(),
+          protocol: protocol.to_string(),
+      })
+  }
+
+  /// Remove a rule added by [`open_port`](Self::open_port)
+  pub(super) async fn close_port(&self, backend: FirewallBackend, ports: &str, protocol: &str, package_name: &str) -> Result<()> {
+      let commands = match backend {
+          FirewallBackend::Firewalld => vec![
+              firewall_cmd(&[&format!("--remove-port={}/{}", ports, protocol)]),
+              firewall_cmd(&["--permanent", &format!("--remove-port={}/{}", ports, protocol)]),
+          ],
+          FirewallBackend::Ufw => vec![command("ufw", &["delete", "allow", &ufw_ports(ports, protocol)])],
+          FirewallBackend::Nftables => {
+              // nftables deletes rules by handle, so look it up by the rule's comment
+              let output = self.run_firewall_command(&nft(&["-a", "list", "chain"], &[])).await?;
+              let rule = format!("{} dport {} accept comment \"{}\"", protocol, ports, rule_comment(package_name));
+
+              String::from_utf8_lossy(&output.stdout)
+                  .lines()
+                  .filter(|line| line.contains(&rule))
+                  .filter_map(|line| line.rsplit_once("# handle ").map(|(_, handle)| handle.trim().to_string()))
+                  .map(|handle| nft(&["delete", "rule"], &["handle", &handle]))
+                  .collect()
+          }
+      };
+
+      for command in &commands {
+          self.run_firewall_command(command).await?;
+      }
+
+      info!("Closed {}/{} in {} for {}", ports, protocol, backend.name(), package_name);
+      Ok(())
+  }
+
+  async fn run_firewall_command(&self, command: &CommandSpec) -> Result<Output> {
+      let output = self.run(command).await?;
+
+      if !output.status.success() {
+          return Err(Error::CommandFailed {
+              message: format!(
+                  "{} failed: {}",
+                  command.command_line(),
+                  String::from_utf8_lossy(&output.stderr).trim()
+              ),
+          });
+      }
+
+      Ok(output)
+  }
+}
+
+/// Comment marking the rules ArchBox added for a package
+fn rule_comment(package_name: &str) -> String {
+  format!("archbox:{}", package_name)
+}
+
+fn command(program: &str, args: &[&str]) -> CommandSpec {
+  let mut cmd = CommandSpec::new(program);
+  cmd.args(args);
+  cmd
+}
+
+fn firewall_cmd(args: &[&str]) -> CommandSpec {
+  command("firewall-cmd", args)
+}
+
+/// An nft command working on [`NFT_CHAIN`]
+fn nft(before_chain: &[&str], after_chain: &[&str]) -> CommandSpec {
+  let mut cmd = CommandSpec::new("nft");
+  cmd.args(before_chain).args(NFT_CHAIN).args(after_chain);
+  cmd
+}
+
+/// ufw writes port ranges with a colon
+fn ufw_ports(ports: &str, protocol: &str) -> String {
+  format!("{}/{}", ports.replace('-', ":"), protocol)
+}
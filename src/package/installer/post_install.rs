@@ -0,0 +1,896 @@
+//! Post-installation configuration and its rollback
+
+use super::{FirewallBackend, InstallOutcome, Installer};
+use crate::{
+  package::{
+      diff, managed_block,
+      runner::{CommandRunner, CommandSpec},
+      InstallScope, Package, PacmanHook, PacmanHookOperation, PacmanHookTarget, PacmanHookWhen, PostInstall,
+  },
+  privilege, progress, secrets,
+  shell::{self, EnvValue, ShellKind},
+  Error, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+/// Block id used for the shell startup hook that sources the environment file
+const ENV_HOOK_ID: &str = "env";
+
+/// What to do when post-install would change an existing config file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigConflict {
+  /// Show a diff and ask. Without a terminal, back up (if enabled) and overwrite.
+  #[default]
+  Ask,
+  /// Back up the current file, then overwrite it
+  Backup,
+  /// Keep the current file
+  Skip,
+  /// Overwrite without a backup
+  Overwrite,
+}
+
+/// A single change made to the system during post-install, recorded so it can be undone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PostInstallAction {
+  /// A whole config file was written; `backup` holds the previous content if it existed
+  ConfigFile {
+      path: PathBuf,
+      existed: bool,
+      backup: Option<PathBuf>,
+      /// Content the file had before, kept only in memory so a failed install can put it
+      /// back even when no backup was taken
+      #[serde(skip)]
+      previous: Option<String>,
+  },
+
+  /// A marker-delimited block was written into a file; `backup` holds the previous content
+  ManagedBlock {
+      path: PathBuf,
+      created: bool,
+      backup: Option<PathBuf>,
+      /// Content the file had before, for rollback only
+      #[serde(skip)]
+      previous: Option<String>,
+  },
+
+  /// A systemd service that was not enabled before was enabled and started, in the user
+  /// manager if `user` is set
+  Service {
+      name: String,
+      #[serde(default)]
+      user: bool,
+  },
+
+  /// The user was added to a group they were not already in
+  UserGroup { user: String, group: String },
+
+  /// A system drop-in file owned entirely by ArchBox (sysctl, udev, pacman hook, ...)
+  DropIn {
+      path: PathBuf,
+      kind: DropInKind,
+      backup: Option<PathBuf>,
+      /// Content of a drop-in written by an earlier install, for rollback only
+      #[serde(skip)]
+      previous: Option<String>,
+  },
+
+  /// Incoming connections were allowed on `ports` (`22000` or `8000-8010`) for `protocol`
+  FirewallRule {
+      backend: FirewallBackend,
+      ports: String,
+      protocol: String,
+  },
+}
+
+/// Kinds of system drop-in files, which determine where they live and how they are reloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropInKind {
+  Sysctl,
+  Udev,
+  Modprobe,
+  PacmanHook,
+  Tmpfiles,
+}
+
+/// A drop-in file to write for a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropIn {
+  pub kind: DropInKind,
+  pub path: PathBuf,
+  /// Content without the [`drop_in_header`]
+  pub content: String,
+}
+
+impl DropInKind {
+  /// Path of a drop-in file named after `name`: the package, or for pacman hooks the
+  /// package and hook names
+  pub fn path(&self, name: &str) -> PathBuf {
+      match self {
+          DropInKind::Sysctl => PathBuf::from(format!("/etc/sysctl.d/90-archbox-{}.conf", name)),
+          DropInKind::Udev => PathBuf::from(format!("/etc/udev/rules.d/90-archbox-{}.rules", name)),
+          DropInKind::Modprobe => PathBuf::from(format!("/etc/modprobe.d/archbox-{}.conf", name)),
+          DropInKind::PacmanHook => PathBuf::from(format!("/etc/pacman.d/hooks/90-archbox-{}.hook", name)),
+          DropInKind::Tmpfiles => PathBuf::from(format!("/etc/tmpfiles.d/archbox-{}.conf", name)),
+      }
+  }
+
+  /// Commands that make the system pick up a changed or removed drop-in
+  fn reload_commands(&self, path: &Path) -> Vec<Vec<String>> {
+      match self {
+          DropInKind::Sysctl if path.exists() => {
+              vec![vec!["sysctl".into(), "--load".into(), path.display().to_string()]]
+          }
+          DropInKind::Sysctl => vec![vec!["sysctl".into(), "--system".into()]],
+          DropInKind::Udev => vec![
+              vec!["udevadm".into(), "control".into(), "--reload-rules".into()],
+              vec!["udevadm".into(), "trigger".into()],
+          ],
+          // Module options apply the next time the module is loaded
+          DropInKind::Modprobe => vec![],
+          // pacman reads its hooks at the start of every transaction
+          DropInKind::PacmanHook => vec![],
+          DropInKind::Tmpfiles if path.exists() => {
+              vec![vec!["systemd-tmpfiles".into(), "--create".into(), path.display().to_string()]]
+          }
+          // Whatever the removed lines created is left alone
+          DropInKind::Tmpfiles => vec![],
+      }
+  }
+}
+
+impl PostInstall {
+  /// The sysctl, udev, modprobe, pacman hook and tmpfiles drop-ins to write for a package
+  pub fn drop_ins(&self, package_name: &str) -> Vec<DropIn> {
+      let mut drop_ins = Vec::new();
+      let mut push = |kind: DropInKind, name: &str, content: String| {
+          drop_ins.push(DropIn { kind, path: kind.path(name), content });
+      };
+
+      if let Some(sysctl) = &self.sysctl {
+          let mut keys: Vec<&String> = sysctl.keys().collect();
+          keys.sort();
+          let content = keys
+              .into_iter()
+              .map(|key| format!("{} = {}\n", key, sysctl[key]))
+              .collect::<String>();
+          push(DropInKind::Sysctl, package_name, content);
+      }
+
+      if let Some(rules) = &self.udev_rules {
+          let content = rules.iter().map(|rule| format!("{}\n", rule)).collect::<String>();
+          push(DropInKind::Udev, package_name, content);
+      }
+
+      if let Some(modules) = &self.modprobe {
+          let mut names: Vec<&String> = modules.keys().collect();
+          names.sort();
+          let content = names
+              .into_iter()
+              .map(|module| format!("options {} {}\n", module, modules[module]))
+              .collect::<String>();
+          push(DropInKind::Modprobe, package_name, content);
+      }
+
+      for hook in self.pacman_hooks.iter().flatten() {
+          push(DropInKind::PacmanHook, &format!("{}-{}", package_name, hook.name), hook.render());
+      }
+
+      if let Some(lines) = &self.tmpfiles {
+          let content = lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+          push(DropInKind::Tmpfiles, package_name, content);
+      }
+
+      drop_ins
+  }
+}
+
+impl PacmanHook {
+  /// The hook in alpm-hooks(5) format
+  pub fn render(&self) -> String {
+      let mut hook = String::new();
+
+      for trigger in &self.triggers {
+          hook.push_str("[Trigger]\n");
+          for operation in &trigger.operations {
+              let operation = match operation {
+                  PacmanHookOperation::Install => "Install",
+                  PacmanHookOperation::Upgrade => "Upgrade",
+                  PacmanHookOperation::Remove => "Remove",
+              };
+              hook.push_str(&format!("Operation = {}\n", operation));
+          }
+          let kind = match trigger.kind {
+              PacmanHookTarget::Package => "Package",
+              PacmanHookTarget::Path => "Path",
+          };
+          hook.push_str(&format!("Type = {}\n", kind));
+          for target in &trigger.targets {
+              hook.push_str(&format!("Target = {}\n", target));
+          }
+          hook.push('\n');
+      }
+
+      hook.push_str("[Action]\n");
+      if let Some(description) = &self.description {
+          hook.push_str(&format!("Description = {}\n", description));
+      }
+      let when = match self.when {
+          PacmanHookWhen::PreTransaction => "PreTransaction",
+          PacmanHookWhen::PostTransaction => "PostTransaction",
+      };
+      hook.push_str(&format!("When = {}\n", when));
+      hook.push_str(&format!("Exec = {}\n", self.exec));
+      for dependency in &self.depends {
+          hook.push_str(&format!("Depends = {}\n", dependency));
+      }
+      if self.needs_targets {
+          hook.push_str("NeedsTargets\n");
+      }
+      if self.abort_on_fail {
+          hook.push_str("AbortOnFail\n");
+      }
+
+      hook
+  }
+}
+
+/// First line of every drop-in, marking it as generated
+pub fn drop_in_header(package_name: &str) -> String {
+  format!("# Managed by ArchBox for {}; changes will be overwritten\n", package_name)
+}
+
+impl PostInstallAction {
+  /// Whether two actions change the same thing (file, service, group or profile)
+  pub fn same_target(&self, other: &PostInstallAction) -> bool {
+      match (self, other) {
+          (Self::ConfigFile { path: a, .. }, Self::ConfigFile { path: b, .. }) => a == b,
+          (Self::ManagedBlock { path: a, .. }, Self::ManagedBlock { path: b, .. }) => a == b,
+          (Self::Service { name: a, user: u }, Self::Service { name: b, user: v }) => a == b && u == v,
+          (Self::UserGroup { user: a, group: g }, Self::UserGroup { user: b, group: h }) => a == b && g == h,
+          (Self::DropIn { path: a, .. }, Self::DropIn { path: b, .. }) => a == b,
+          (
+              Self::FirewallRule { backend: a, ports: p, protocol: r },
+              Self::FirewallRule { backend: b, ports: q, protocol: s },
+          ) => a == b && p == q && r == s,
+          _ => false,
+      }
+  }
+}
+
+impl Installer {
+  /// Run post-installation configuration, undoing completed steps if any step fails
+  pub(super) async fn run_post_install(
+      &self,
+      post_install: &PostInstall,
+      package: &Package,
+      service_scope: InstallScope,
+      outcome: &mut InstallOutcome,
+  ) -> Result<()> {
+      let package_name = package.name.as_str();
+      info!("Running post-installation configuration for {}", package_name);
+
+      if let Err(e) = self.apply_post_install(post_install, package, service_scope, outcome).await {
+          warn!("Post-install for {} failed, rolling back: {}", package_name, e);
+          self.rollback_post_install(package_name, &outcome.actions).await;
+          outcome.actions.clear();
+          outcome.config_files.clear();
+
+          return Err(Error::InstallationFailed(format!(
+              "Post-install configuration for {} failed and was rolled back: {}",
+              package_name, e
+          )));
+      }
+
+      Ok(())
+  }
+
+  async fn apply_post_install(
+      &self,
+      post_install: &PostInstall,
+      package: &Package,
+      service_scope: InstallScope,
+      outcome: &mut InstallOutcome,
+  ) -> Result<()> {
+      let package_name = package.name.as_str();
+      // A target root has no users yet, so per-user settings wait until it is booted
+      let per_user = self.root.is_none();
+      if !per_user
+          && (post_install.config_files.is_some() || post_install.user_groups.is_some() || post_install.environment.is_some())
+      {
+          warn!(
+              "Skipping config files, groups and environment of {}; reinstall it after booting the new system to apply them",
+              package_name
+          );
+      }
+
+      // Run commands
+      if let Some(commands) = &post_install.commands {
+          for command in commands {
+              info!("Running post-install command: {}", command);
+              let output = self.run_shell_command(command, Path::new("/"), &package.command_env(), self.config.installation.script_limit()).await?;
+              if !output.status.success() {
+                  warn!("Post-install command failed: {}", command);
+              }
+          }
+      }
+
+      // Create/modify config files
+      if let Some(config_files) = post_install.config_files.as_ref().filter(|_| per_user) {
+          for (path, content) in config_files {
+              let path = self.templates.render(path);
+              let content = secrets::resolve(&self.templates.render(content)).await?;
+              let Some(action) = self.write_config_file(&path, &content, package_name).await? else {
+                  continue;
+              };
+              match &action {
+                  PostInstallAction::ConfigFile { path, .. } => {
+                      outcome.config_files.push(path.clone());
+                      crate::drift::record(package_name, path, &content).await?;
+                  }
+                  PostInstallAction::ManagedBlock { path, .. } => crate::drift::record(package_name, path, &content).await?,
+                  _ => {}
+              }
+              outcome.actions.push(action);
+          }
+      }
+
+      // System drop-ins
+      for drop_in in post_install.drop_ins(package_name) {
+          outcome.actions.push(self.write_drop_in(&drop_in, package_name).await?);
+      }
+
+      // Firewall rules, in whichever firewall is running
+      if let Some(rules) = &post_install.firewall {
+          if self.root.is_some() {
+              warn!("Skipping firewall rules of {}; reinstall it after booting the new system to open its ports", package_name);
+          } else if let Some(backend) = self.detect_firewall().await {
+              for rule in rules {
+                  for protocol in rule.protocols() {
+                      outcome.actions.push(self.open_port(backend, &rule.ports(), protocol, package_name).await?);
+                  }
+              }
+          } else {
+              warn!("No running firewall found (firewalld, ufw or nftables); not opening ports for {}", package_name);
+          }
+      }
+
+      if let Some(params) = &post_install.kernel_params {
+          // Editing bootloader entries automatically is too risky; ask the user to do it
+          warn!(
+              "{} needs kernel parameters that must be added to your bootloader configuration manually: {}",
+              package_name,
+              params.join(" ")
+          );
+      }
+
+      // Enable services
+      if let Some(services) = &post_install.enable_services {
+          for service in services {
+              outcome.actions.extend(self.enable_service(service, service_scope).await?);
+          }
+      }
+
+      // Add user to groups
+      if let Some(groups) = post_install.user_groups.as_ref().filter(|_| per_user) {
+          for group in groups {
+              if let Some(action) = self.add_user_to_group(group).await? {
+                  outcome.actions.push(action);
+              }
+          }
+      }
+
+      // Set environment variables
+      if let Some(env_vars) = post_install.environment.as_ref().filter(|_| per_user) {
+          self.set_environment_variables(env_vars, package_name, outcome).await?;
+      }
+
+      Ok(())
+  }
+
+  /// Undo post-install actions in reverse order. Failures are logged and skipped.
+  pub async fn rollback_post_install(&self, package_name: &str, actions: &[PostInstallAction]) {
+      for action in actions.iter().rev() {
+          if let Err(e) = self.undo_action(package_name, action).await {
+              warn!("Failed to undo {:?}: {}", action, e);
+          }
+      }
+  }
+
+  /// Revert the post-install changes that belong to a package once it is uninstalled:
+  /// managed blocks are stripped (leaving the rest of each file intact) and drop-ins deleted
+  pub async fn revert_on_removal(&self, package_name: &str, actions: &[PostInstallAction]) {
+      for action in actions {
+          let result = match action {
+              PostInstallAction::ManagedBlock { path, created, .. } => {
+                  remove_block_from_file(path, package_name, *created).await
+              }
+              PostInstallAction::DropIn { path, kind, .. } => remove_drop_in(self.runner.as_ref(), path, *kind).await,
+              PostInstallAction::FirewallRule { backend, ports, protocol } => {
+                  self.close_port(*backend, ports, protocol, package_name).await
+              }
+              _ => Ok(()),
+          };
+
+          if let Err(e) = result {
+              warn!("Failed to revert {:?}: {}", action, e);
+          }
+      }
+  }
+
+  async fn undo_action(&self, package_name: &str, action: &PostInstallAction) -> Result<()> {
+      match action {
+          PostInstallAction::ConfigFile { path, existed, backup, previous } => {
+              match (existed, backup, previous) {
+                  (_, Some(backup), _) => {
+                      fs::copy(backup, path).await?;
+                      info!("Restored {} from backup", path.display());
+                  }
+                  (_, None, Some(previous)) => {
+                      fs::write(path, previous).await?;
+                      info!("Restored {}", path.display());
+                  }
+                  (false, None, None) => {
+                      if path.exists() {
+                          fs::remove_file(path).await?;
+                          info!("Removed {}", path.display());
+                      }
+                  }
+                  (true, None, None) => {
+                      warn!("Cannot restore {}: no backup was made", path.display());
+                  }
+              }
+          }
+          PostInstallAction::ManagedBlock { path, created, backup, previous } => {
+              match (backup, previous) {
+                  (Some(backup), _) => {
+                      fs::copy(backup, path).await?;
+                      info!("Restored {} from backup", path.display());
+                  }
+                  (None, Some(previous)) => {
+                      fs::write(path, previous).await?;
+                      info!("Restored {}", path.display());
+                  }
+                  (None, None) => remove_block_from_file(path, package_name, *created).await?,
+              }
+          }
+          PostInstallAction::Service { name, user } => {
+              let output = self.run(self.service_command("disable", *user).arg(name)).await?;
+
+              if !output.status.success() {
+                  return Err(Error::CommandFailed {
+                      message: String::from_utf8_lossy(&output.stderr).to_string(),
+                  });
+              }
+              info!("Disabled service: {}", name);
+          }
+          PostInstallAction::DropIn { path, kind, backup, previous } => match (backup, previous) {
+              (Some(backup), _) => {
+                  fs::copy(backup, path).await?;
+                  reload_drop_in(self.runner.as_ref(), path, *kind).await;
+              }
+              (None, Some(previous)) => {
+                  fs::write(path, previous).await?;
+                  reload_drop_in(self.runner.as_ref(), path, *kind).await;
+              }
+              (None, None) => remove_drop_in(self.runner.as_ref(), path, *kind).await?,
+          },
+          PostInstallAction::UserGroup { user, group } => {
+              let output = self.run(CommandSpec::new("gpasswd").args(["-d", user, group])).await?;
+
+              if !output.status.success() {
+                  return Err(Error::CommandFailed {
+                      message: String::from_utf8_lossy(&output.stderr).to_string(),
+                  });
+              }
+              info!("Removed user {} from group {}", user, group);
+          }
+          PostInstallAction::FirewallRule { backend, ports, protocol } => {
+              self.close_port(*backend, ports, protocol, package_name).await?;
+          }
+      }
+
+      Ok(())
+  }
+
+  /// Write a config file, as a managed block when the format supports line comments.
+  /// Returns `None` when the user chose to keep the existing file.
+  async fn write_config_file(&self, path: &str, content: &str, package_name: &str) -> Result<Option<PostInstallAction>> {
+      let expanded_path = shellexpand::tilde(path);
+      let path = Path::new(expanded_path.as_ref());
+
+      match managed_block::comment_prefix(path) {
+          Some(prefix) => self.write_managed_block(path, prefix, content, package_name, true).await,
+          None => self.create_config_file(path, content, package_name).await,
+      }
+  }
+
+  /// Insert or update a package's managed block in a file. With `review`, a change to an
+  /// existing file goes through the config conflict policy and may be skipped (`None`).
+  async fn write_managed_block(
+      &self,
+      path: &Path,
+      prefix: &str,
+      body: &str,
+      package_name: &str,
+      review: bool,
+  ) -> Result<Option<PostInstallAction>> {
+      if let Some(parent) = path.parent() {
+          fs::create_dir_all(parent).await?;
+      }
+
+      let created = !path.exists();
+      let existing = if created {
+          String::new()
+      } else {
+          fs::read_to_string(path).await?
+      };
+
+      let previous = (!created).then(|| existing.clone());
+      let updated = managed_block::upsert_block(&existing, prefix, package_name, body);
+      if updated == existing {
+          debug!("Managed block in {} is up to date", path.display());
+          return Ok(Some(PostInstallAction::ManagedBlock { path: path.to_path_buf(), created, backup: None, previous }));
+      }
+
+      let backup = if created || !review {
+          self.backup_existing(package_name, path).await?
+      } else {
+          match self.resolve_config_conflict(path, &existing, &updated)? {
+              ConfigConflict::Skip => return Ok(None),
+              choice => self.backup_for(choice, package_name, path).await?,
+          }
+      };
+
+      fs::write(path, updated).await?;
+      info!("Updated managed block in {}", path.display());
+
+      Ok(Some(PostInstallAction::ManagedBlock {
+          path: path.to_path_buf(),
+          created,
+          backup,
+          previous,
+      }))
+  }
+
+  /// Overwrite a whole configuration file
+  async fn create_config_file(&self, path: &Path, content: &str, package_name: &str) -> Result<Option<PostInstallAction>> {
+      if let Some(parent) = path.parent() {
+          fs::create_dir_all(parent).await?;
+      }
+
+      let existed = path.exists();
+      let current = if path.is_file() {
+          fs::read_to_string(path).await.ok()
+      } else {
+          None
+      };
+
+      let backup = match &current {
+          Some(current) if current != content => {
+              match self.resolve_config_conflict(path, current, content)? {
+                  ConfigConflict::Skip => return Ok(None),
+                  choice => self.backup_for(choice, package_name, path).await?,
+              }
+          }
+          _ => self.backup_existing(package_name, path).await?,
+      };
+
+      fs::write(path, content).await?;
+      info!("Created config file: {}", path.display());
+
+      Ok(Some(PostInstallAction::ConfigFile {
+          path: path.to_path_buf(),
+          existed,
+          backup,
+          previous: current,
+      }))
+  }
+
+  /// Decide what to do with a config file whose content would change, showing a diff and
+  /// asking when the policy is `Ask`. Returns `Ask` when there is no terminal to ask on.
+  fn resolve_config_conflict(&self, path: &Path, current: &str, new: &str) -> Result<ConfigConflict> {
+      let choice = match self.config_conflict {
+          ConfigConflict::Ask if std::io::stdin().is_terminal() => {
+              // Resolved secrets stay off the screen
+              let preview = diff::unified_diff(path, &secrets::redact(current), &secrets::redact(new));
+              prompt_config_conflict(path, &preview)?
+          }
+          choice => choice,
+      };
+
+      if choice == ConfigConflict::Skip {
+          info!("Keeping existing {}", path.display());
+      }
+      Ok(choice)
+  }
+
+  /// Back up `path` as `choice` requires before it is overwritten
+  async fn backup_for(&self, choice: ConfigConflict, package_name: &str, path: &Path) -> Result<Option<PathBuf>> {
+      match choice {
+          ConfigConflict::Backup => Ok(Some(crate::backup::backup_file(package_name, path).await?)),
+          ConfigConflict::Overwrite => Ok(None),
+          _ => self.backup_existing(package_name, path).await,
+      }
+  }
+
+  /// Enable systemd service. Units prefixed with `user:` or `system:` go to that manager,
+  /// others to the one for `scope`. Returns an action only if the unit was not enabled
+  /// already, so a rollback leaves units the user enabled themselves alone.
+  async fn enable_service(&self, spec: &str, scope: InstallScope) -> Result<Option<PostInstallAction>> {
+      let (service, user) = parse_service(spec, scope);
+
+      let status = self.run(self.service_manager(user).args(["is-enabled", service])).await?;
+      let was_enabled = status.status.success() && String::from_utf8_lossy(&status.stdout).trim() == "enabled";
+
+      let output = self.run(self.service_command("enable", user).arg(service)).await?;
+
+      if !output.status.success() {
+          return Err(Error::CommandFailed {
+              message: format!(
+                  "Failed to enable service {}: {}",
+                  service,
+                  String::from_utf8_lossy(&output.stderr)
+              ),
+          });
+      }
+
+      info!("Enabled {} service: {}", if user { "user" } else { "system" }, service);
+      if was_enabled {
+          return Ok(None);
+      }
+      Ok(Some(PostInstallAction::Service { name: service.to_string(), user }))
+  }
+
+  /// `systemctl enable --now` or `disable --now` through the user or system manager. In a
+  /// target root units can only be changed offline.
+  fn service_command(&self, action: &str, user: bool) -> CommandSpec {
+      let mut cmd = self.service_manager(user);
+      cmd.arg(action);
+      if self.root.is_none() {
+          cmd.arg("--now");
+      }
+      cmd
+  }
+
+  /// `systemctl` for the user or system manager. In a target root user units are those of
+  /// every user.
+  fn service_manager(&self, user: bool) -> CommandSpec {
+      match &self.root {
+          Some(root) => {
+              let mut cmd = CommandSpec::new("systemctl");
+              cmd.arg(format!("--root={}", root.display()));
+              if user {
+                  cmd.arg("--global");
+              }
+              cmd
+          }
+          None => systemctl(user),
+      }
+  }
+
+  /// Add user to group, returning an action only if membership changed
+  async fn add_user_to_group(&self, group: &str) -> Result<Option<PostInstallAction>> {
+      let username = current_username();
+
+      let groups = self.run(CommandSpec::new("id").args(["-nG", &username])).await?;
+
+      if String::from_utf8_lossy(&groups.stdout).split_whitespace().any(|g| g == group) {
+          info!("User {} is already in group {}", username, group);
+          return Ok(None);
+      }
+
+      if !privilege::usermod_allowed() {
+          warn!("Not adding {} to group {} since user accounts are not to be modified; ask an administrator to run: usermod -aG {} {}", username, group, group, username);
+          return Ok(None);
+      }
+
+      let output = self.run(CommandSpec::new("usermod").args(["-a", "-G", group, &username])).await?;
+
+      if output.status.success() {
+          info!("Added user {} to group {}", username, group);
+          Ok(Some(PostInstallAction::UserGroup {
+              user: username,
+              group: group.to_string(),
+          }))
+      } else {
+          warn!("Failed to add user to group {}: {}", group, String::from_utf8_lossy(&output.stderr));
+          Ok(None)
+      }
+  }
+
+  /// Set environment variables in the ArchBox environment files for the detected shells
+  async fn set_environment_variables(&self, env_vars: &HashMap<String, String>, package_name: &str, outcome: &mut InstallOutcome) -> Result<()> {
+      let shells = shell::detect_shells();
+
+      let mut resolved = HashMap::new();
+      for (key, value) in env_vars {
+          let value = match EnvValue::from_definition(value)? {
+              EnvValue::Literal(value) => EnvValue::Literal(secrets::resolve(&value).await?),
+              value => value,
+          };
+          resolved.insert(key.clone(), value);
+      }
+      let env_vars = &resolved;
+
+      // The files can hold resolved secrets, so only the user may read them
+      let env_file = shell::env_file_path();
+      let action = self.write_managed_block(&env_file, "#", &shell::posix_exports(env_vars), package_name, false).await?;
+      restrict_to_owner(&env_file).await?;
+      outcome.actions.extend(action);
+
+      if shells.contains(&ShellKind::Fish) {
+          let fish_file = shell::fish_env_file_path();
+          let action = self.write_managed_block(&fish_file, "#", &shell::fish_exports(env_vars), package_name, false).await?;
+          restrict_to_owner(&fish_file).await?;
+          outcome.actions.extend(action);
+      }
+
+      for shell in shells {
+          let startup_file = match shell {
+              ShellKind::Fish => Some(shell::fish_env_file_path()),
+              _ => shell.startup_file(),
+          };
+
+          if let Some(startup_file) = startup_file {
+              ensure_shell_init(&startup_file, shell, &self.config.installation.binary_dir).await?;
+          }
+      }
+
+      Ok(())
+  }
+
+  /// Write a system drop-in file for a package and reload the affected subsystem
+  async fn write_drop_in(&self, drop_in: &DropIn, package_name: &str) -> Result<PostInstallAction> {
+      let DropIn { kind, path, content } = drop_in;
+      let kind = *kind;
+      let path = self.target_path(path);
+
+      if let Some(parent) = path.parent() {
+          fs::create_dir_all(parent).await?;
+      }
+
+      let backup = self.backup_existing(package_name, &path).await?;
+      let previous = if path.is_file() { fs::read_to_string(&path).await.ok() } else { None };
+      let content = format!("{}{}", drop_in_header(package_name), content);
+      fs::write(&path, &content).await?;
+      info!("Wrote {}", path.display());
+
+      // A target root picks its drop-ins up when it boots
+      if self.root.is_none() {
+          reload_drop_in(self.runner.as_ref(), &path, kind).await;
+          crate::drift::record(package_name, &path, &content).await?;
+      }
+
+      Ok(PostInstallAction::DropIn { path, kind, backup, previous })
+  }
+}
+
+/// Run the reload commands for a drop-in; failures only warn since the file is already in place
+async fn reload_drop_in(runner: &dyn CommandRunner, path: &Path, kind: DropInKind) {
+  for command in kind.reload_commands(path) {
+      match runner.output(CommandSpec::new(&command[0]).args(&command[1..])).await {
+          Ok(output) if output.status.success() => debug!("Ran {:?}", command),
+          Ok(output) => warn!("{:?} failed: {}", command, String::from_utf8_lossy(&output.stderr)),
+          Err(e) => warn!("Failed to run {:?}: {}", command, e),
+      }
+  }
+}
+
+async fn remove_drop_in(runner: &dyn CommandRunner, path: &Path, kind: DropInKind) -> Result<()> {
+  if path.exists() {
+      fs::remove_file(path).await?;
+      info!("Removed {}", path.display());
+      reload_drop_in(runner, path, kind).await;
+  }
+
+  Ok(())
+}
+
+/// Add the `archbox init` setup to a shell startup file. The hook is shared by all
+/// packages, so it is not recorded as a package action.
+async fn ensure_shell_init(startup_file: &Path, shell: ShellKind, binary_dir: &Path) -> Result<()> {
+  let existing = if startup_file.exists() {
+      fs::read_to_string(startup_file).await?
+  } else {
+      String::new()
+  };
+
+  let updated = managed_block::upsert_block(&existing, "#", ENV_HOOK_ID, &shell::init_script(shell, binary_dir));
+  if updated != existing {
+      if let Some(parent) = startup_file.parent() {
+          fs::create_dir_all(parent).await?;
+      }
+      fs::write(startup_file, updated).await?;
+      info!("Added ArchBox shell setup to {}", startup_file.display());
+  }
+
+  Ok(())
+}
+
+/// Make `path` readable and writable by its owner only
+async fn restrict_to_owner(path: &Path) -> Result<()> {
+  #[cfg(unix)]
+  if path.exists() {
+      use std::os::unix::fs::PermissionsExt;
+      fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+  }
+  Ok(())
+}
+
+/// Strip a package's block from a file, deleting the file if ArchBox created it and nothing else remains
+async fn remove_block_from_file(path: &Path, package_name: &str, created: bool) -> Result<()> {
+  if !path.exists() {
+      return Ok(());
+  }
+
+  let prefix = managed_block::comment_prefix(path).unwrap_or("#");
+  let content = fs::read_to_string(path).await?;
+
+  if let Some(remaining) = managed_block::remove_block(&content, prefix, package_name) {
+      if created && remaining.trim().is_empty() {
+          fs::remove_file(path).await?;
+          info!("Removed {}", path.display());
+      } else {
+          fs::write(path, remaining).await?;
+          info!("Removed managed block from {}", path.display());
+      }
+  }
+
+  Ok(())
+}
+
+/// Show the pending change to a config file and ask whether to back up, skip or overwrite
+fn prompt_config_conflict(path: &Path, preview: &str) -> Result<ConfigConflict> {
+  progress::suspend(|| loop {
+      println!("\n==> {} would be changed:\n", path.display());
+      print!("{}", preview);
+      print!("\n[b]ack up and overwrite, [s]kip, [o]verwrite [B/s/o]: ");
+      std::io::stdout().flush()?;
+
+      let mut input = String::new();
+      std::io::stdin().read_line(&mut input)?;
+
+      match input.trim().to_lowercase().as_str() {
+          "" | "b" | "backup" => return Ok(ConfigConflict::Backup),
+          "s" | "skip" => return Ok(ConfigConflict::Skip),
+          "o" | "overwrite" => return Ok(ConfigConflict::Overwrite),
+          _ => println!("Please answer b, s or o"),
+      }
+  })
+}
+
+/// Split a service spec into the unit name and whether it is a user unit. A `user:` or
+/// `system:` prefix (`user:syncthing.service`) wins over the default `scope`.
+pub fn parse_service(spec: &str, scope: InstallScope) -> (&str, bool) {
+  if let Some(unit) = spec.strip_prefix("user:") {
+      (unit, true)
+  } else if let Some(unit) = spec.strip_prefix("system:") {
+      (unit, false)
+  } else {
+      (spec, scope == InstallScope::User)
+  }
+}
+
+/// `systemctl`, talking to the user manager when `user` is set
+fn systemctl(user: bool) -> CommandSpec {
+  let mut cmd = CommandSpec::new("systemctl");
+  if user {
+      cmd.arg("--user");
+  }
+  cmd
+}
+
+/// The user ArchBox runs for, from `$USER`
+pub fn current_username() -> String {
+  std::env::var("USER")
+      .or_else(|_| std::env::var("USERNAME"))
+      .unwrap_or_else(|_| "user".to_string())
+}
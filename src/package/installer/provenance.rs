@@ -0,0 +1,117 @@
+//! Where installed artifacts came from, kept in the state database as an audit trail
+
+use serde::{Deserialize, Serialize};
+
+/// The exact source of an installed package, as far as its method can tell
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+  /// URL the artifact was downloaded or cloned from (the mirror actually used), or the
+  /// repository packages and versions for pacman and Flatpak installs
+  pub source: String,
+
+  /// Release tag, when the download URL names one
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub tag: Option<String>,
+
+  /// Git commit that was built, or the Flatpak commit that was deployed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub commit: Option<String>,
+
+  /// SHA-256 of the downloaded file or script that was installed
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sha256: Option<String>,
+
+  /// Whose signature pacman verified before installing
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signed_by: Option<String>,
+
+  /// Definition fields changed with `install --set` for this install, as `path=value`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub overrides: Vec<String>,
+}
+
+impl Provenance {
+  /// A downloaded file: where it came from and what it hashed to
+  pub fn download(url: &str, sha256: String) -> Self {
+      Provenance {
+          source: url.to_string(),
+          tag: release_tag(url),
+          sha256: Some(sha256),
+          ..Default::default()
+      }
+  }
+
+  /// Packages installed from the pacman repositories, described from `pacman -Qi` output.
+  /// Packages missing from the output are listed without a version.
+  pub fn pacman(packages: &[String], query: &str) -> Self {
+      let records = parse_pacman_info(query);
+
+      let source = packages
+          .iter()
+          .map(|package| match records.iter().find(|record| record.name == *package) {
+              Some(record) => format!("{} {}", package, record.version),
+              None => package.clone(),
+          })
+          .collect::<Vec<_>>()
+          .join(", ");
+
+      let mut signers: Vec<&str> = Vec::new();
+      for record in &records {
+          if record.signed && !signers.contains(&record.packager.as_str()) {
+              signers.push(&record.packager);
+          }
+      }
+
+      Provenance {
+          source: format!("pacman: {}", source),
+          signed_by: (!signers.is_empty()).then(|| signers.join(", ")),
+          ..Default::default()
+      }
+  }
+}
+
+/// The fields of one `pacman -Qi` entry that provenance cares about
+#[derive(Debug, Default)]
+struct PacmanRecord {
+  name: String,
+  version: String,
+  packager: String,
+  signed: bool,
+}
+
+fn parse_pacman_info(output: &str) -> Vec<PacmanRecord> {
+  let mut records = Vec::new();
+  let mut current = PacmanRecord::default();
+
+  // Entries are separated by blank lines; wrapped values continue on lines without a key
+  for line in output.lines().chain(std::iter::once("")) {
+      if line.trim().is_empty() {
+          if !current.name.is_empty() {
+              records.push(std::mem::take(&mut current));
+          }
+          continue;
+      }
+      let Some((key, value)) = line.split_once(':') else {
+          continue;
+      };
+
+      let value = value.trim().to_string();
+      match key.trim() {
+          "Name" => current.name = value,
+          "Version" => current.version = value,
+          "Packager" => current.packager = value,
+          "Validated By" => current.signed = value.contains("Signature"),
+          _ => {}
+      }
+  }
+
+  records
+}
+
+/// The tag in a release download URL, e.g. `v1.2.0` in
+/// `https://github.com/o/r/releases/download/v1.2.0/tool.tar.gz`
+fn release_tag(url: &str) -> Option<String> {
+  let (_, rest) = url.split_once("/releases/download/")?;
+  let tag = rest.split('/').next()?;
+  (!tag.is_empty()).then(|| tag.to_string())
+}
@@ -0,0 +1,213 @@
+//! Versioned install layout for binaries
+//!
+//! Each version lives in `<prefix>/opt/<pkg>/<version>/` (`~/.local/opt` for user scope,
+//! `/usr/local/opt` system-wide) and the file on `PATH` is a symlink into the active version,
+//! so upgrades and rollbacks are a single atomic symlink swap.
+//!
+//! Every versioned binary also gets a shim in [`shims_dir`], which sits ahead of the binary
+//! directory on `PATH`. The shim runs the version pinned for the current directory in an
+//! [`VERSIONS_FILE`] (like asdf's `.tool-versions`), falling back to the active version.
+
+use crate::{package::InstallScope, Error, Result};
+use std::path::{Path, PathBuf};
+
+/// Per-directory version pins, one `<package> <version>` per line
+pub const VERSIONS_FILE: &str = ".archbox-versions";
+
+/// Root of all versioned installs
+pub fn opt_dir(scope: InstallScope) -> PathBuf {
+    scope.prefix().join("opt")
+}
+
+pub fn package_dir(package: &str, scope: InstallScope) -> PathBuf {
+    opt_dir(scope).join(package)
+}
+
+pub fn version_dir(package: &str, scope: InstallScope, version: &str) -> PathBuf {
+    package_dir(package, scope).join(version.replace('/', "_"))
+}
+
+/// Versions present on disk, most recently installed first
+pub fn installed_versions(package: &str, scope: InstallScope) -> Result<Vec<String>> {
+    let dir = package_dir(package, scope);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut versions = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            let modified = entry.metadata()?.modified()?;
+            versions.push((modified, entry.file_name().to_string_lossy().to_string()));
+        }
+    }
+
+    versions.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(versions.into_iter().map(|(_, version)| version).collect())
+}
+
+/// Version a link currently points at, if it points into the package's layout
+pub fn active_version(package: &str, scope: InstallScope, link: &Path) -> Option<String> {
+    let target = std::fs::read_link(link).ok()?;
+    let relative = target.strip_prefix(package_dir(package, scope)).ok()?;
+
+    relative
+        .components()
+        .next()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+}
+
+/// Whether `link` is a symlink managed through the package's layout
+pub fn is_managed_link(package: &str, scope: InstallScope, link: &Path) -> bool {
+    active_version(package, scope, link).is_some()
+}
+
+/// Point `link` at `target`, replacing whatever is there in one rename
+pub async fn activate(link: &Path, target: &Path) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let file_name = link
+        .file_name()
+        .ok_or_else(|| Error::InstallationFailed(format!("Invalid install path: {}", link.display())))?;
+    let temp_link = link.with_file_name(format!(".{}.archbox-new", file_name.to_string_lossy()));
+
+    let _ = tokio::fs::remove_file(&temp_link).await;
+    tokio::fs::symlink(target, &temp_link).await?;
+    tokio::fs::rename(&temp_link, link).await?;
+
+    Ok(())
+}
+
+/// Delete all but the `keep` most recent versions, never touching the active one
+pub async fn prune_versions(package: &str, scope: InstallScope, keep: usize, active: &str) -> Result<Vec<String>> {
+    let mut removed = Vec::new();
+
+    let stale = installed_versions(package, scope)?
+        .into_iter()
+        .filter(|version| version != active)
+        .skip(keep.saturating_sub(1));
+
+    for version in stale {
+        tokio::fs::remove_dir_all(version_dir(package, scope, &version)).await?;
+        removed.push(version);
+    }
+
+    Ok(removed)
+}
+
+/// Remove every installed version of a package
+pub async fn remove_package(package: &str, scope: InstallScope) -> Result<()> {
+    let dir = package_dir(package, scope);
+    if dir.exists() {
+        tokio::fs::remove_dir_all(dir).await?;
+    }
+    Ok(())
+}
+
+/// Directory holding the version-selecting shims
+pub fn shims_dir(scope: InstallScope) -> PathBuf {
+    scope.prefix().join("share/archbox/shims")
+}
+
+/// Write the shim for `link`, which dispatches to a pinned version or to `link` itself
+pub async fn write_shim(package: &str, scope: InstallScope, link: &Path) -> Result<()> {
+    let file_name = link
+        .file_name()
+        .ok_or_else(|| Error::InstallationFailed(format!("Invalid install path: {}", link.display())))?
+        .to_string_lossy()
+        .to_string();
+    let dir = shims_dir(scope);
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let script = format!(
+        r#"#!/bin/sh
+# Generated by archbox for {package}; runs the version pinned in {versions_file}, if any
+dir=$PWD
+while :; do
+    if [ -f "$dir/{versions_file}" ]; then
+        version=$(awk '$1 == "{package}" {{ print $2; exit }}' "$dir/{versions_file}")
+        [ -n "$version" ] && break
+    fi
+    [ "$dir" = / ] && break
+    dir=$(dirname "$dir")
+done
+if [ -n "$version" ]; then
+    target="{package_dir}/$(printf '%s' "$version" | tr / _)/{file_name}"
+    if [ ! -x "$target" ]; then
+        echo "archbox: {package} $version is not installed (pinned in $dir/{versions_file})" >&2
+        exit 127
+    fi
+    exec "$target" "$@"
+fi
+exec "{link}" "$@"
+"#,
+        package = package,
+        versions_file = VERSIONS_FILE,
+        package_dir = package_dir(package, scope).display(),
+        file_name = file_name,
+        link = link.display(),
+    );
+
+    let shim = dir.join(&file_name);
+    let partial = dir.join(format!(".{}.archbox-new", file_name));
+    tokio::fs::write(&partial, script).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+    tokio::fs::rename(&partial, &shim).await?;
+
+    Ok(())
+}
+
+/// Remove the shim for `link`, if there is one
+pub async fn remove_shim(scope: InstallScope, link: &Path) -> Result<()> {
+    if let Some(file_name) = link.file_name() {
+        let shim = shims_dir(scope).join(file_name);
+        if shim.exists() {
+            tokio::fs::remove_file(shim).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The version of `package` pinned for `dir` and the file that pins it, searching `dir`
+/// and then each of its parents
+pub fn pinned_version(package: &str, dir: &Path) -> Option<(String, PathBuf)> {
+    dir.ancestors().find_map(|dir| {
+        let file = dir.join(VERSIONS_FILE);
+        let content = std::fs::read_to_string(&file).ok()?;
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?, fields.next()?))
+            })
+            .find(|(name, _)| *name == package)
+            .map(|(_, version)| (version.to_string(), file))
+    })
+}
+
+/// Pin `package` to `version` in `dir`'s versions file, replacing any earlier pin
+pub fn pin_version(dir: &Path, package: &str, version: &str) -> Result<PathBuf> {
+    let file = dir.join(VERSIONS_FILE);
+    let existing = match std::fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut lines: Vec<String> = existing
+        .lines()
+        .filter(|line| line.split_whitespace().next() != Some(package))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("{} {}", package, version));
+
+    std::fs::write(&file, lines.join("\n") + "\n")?;
+    Ok(file)
+}
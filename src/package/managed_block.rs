@@ -0,0 +1,114 @@
+//! Marker-delimited sections that ArchBox owns inside otherwise user-managed files
+//!
+//! ```text
+//! # >>> archbox:starship >>>
+//! eval "$(starship init bash)"
+//! # <<< archbox:starship <<<
+//! ```
+
+use std::path::Path;
+
+/// Comment prefix used for block markers in a file, or `None` if the format has no line
+/// comments or is not one ArchBox knows, in which case the file is written whole
+pub fn comment_prefix(path: &Path) -> Option<&'static str> {
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+
+    match extension {
+        Some("lua" | "sql" | "hs") => Some("--"),
+        Some("vim") => Some("\""),
+        Some("js" | "ts" | "jsonc" | "rs" | "c" | "cpp" | "h" | "kdl" | "zig") => Some("//"),
+        Some("el" | "lisp") => Some(";;"),
+        Some(
+            "sh" | "bash" | "zsh" | "fish" | "conf" | "cfg" | "toml" | "yaml" | "yml" | "py" | "rb" | "pl" | "ini"
+            | "env" | "properties" | "nix" | "tmux" | "rules",
+        ) => Some("#"),
+        // Shell startup files and the like, which have no extension
+        _ => match file_name {
+            ".bashrc" | ".bash_profile" | ".zshrc" | ".zprofile" | ".zshenv" | ".profile" | ".xprofile"
+            | ".gitconfig" | ".env" | "config" => Some("#"),
+            _ => None,
+        },
+    }
+}
+
+fn begin_marker(prefix: &str, package: &str) -> String {
+    format!("{} >>> archbox:{} >>>", prefix, package)
+}
+
+fn end_marker(prefix: &str, package: &str) -> String {
+    format!("{} <<< archbox:{} <<<", prefix, package)
+}
+
+/// Locate the line range of a package's block as (begin line, end line), inclusive
+fn find_block(lines: &[&str], prefix: &str, package: &str) -> Option<(usize, usize)> {
+    let begin = begin_marker(prefix, package);
+    let end = end_marker(prefix, package);
+
+    let start = lines.iter().position(|line| line.trim_end() == begin)?;
+    let finish = lines[start..].iter().position(|line| line.trim_end() == end)? + start;
+
+    Some((start, finish))
+}
+
+/// Insert or replace a package's block, leaving everything outside it untouched
+pub fn upsert_block(content: &str, prefix: &str, package: &str, body: &str) -> String {
+    let mut block = vec![begin_marker(prefix, package)];
+    block.extend(body.lines().map(str::to_string));
+    block.push(end_marker(prefix, package));
+
+    let lines: Vec<&str> = content.lines().collect();
+
+    let mut result: Vec<String> = match find_block(&lines, prefix, package) {
+        Some((start, finish)) => {
+            let mut result: Vec<String> = lines[..start].iter().map(|l| l.to_string()).collect();
+            result.extend(block);
+            result.extend(lines[finish + 1..].iter().map(|l| l.to_string()));
+            result
+        }
+        None => {
+            let mut result: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+            if result.last().is_some_and(|line| !line.trim().is_empty()) {
+                result.push(String::new());
+            }
+            result.extend(block);
+            result
+        }
+    };
+
+    result.push(String::new());
+    result.join("\n")
+}
+
+/// Remove a package's block, returning `None` if the content has no such block
+pub fn remove_block(content: &str, prefix: &str, package: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, finish) = find_block(&lines, prefix, package)?;
+
+    let mut result: Vec<&str> = lines[..start].to_vec();
+    let rest = &lines[finish + 1..];
+
+    // Drop the blank separator line added in front of the block
+    if result.last().is_some_and(|line| line.trim().is_empty())
+        && rest.first().is_none_or(|line| line.trim().is_empty())
+    {
+        result.pop();
+    }
+    result.extend_from_slice(rest);
+
+    if result.is_empty() {
+        return Some(String::new());
+    }
+
+    let mut content = result.join("\n");
+    content.push('\n');
+    Some(content)
+}
+
+/// Extract the body of a package's block
+pub fn block_body(content: &str, prefix: &str, package: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (start, finish) = find_block(&lines, prefix, package)?;
+
+    Some(lines[start + 1..finish].join("\n"))
+}
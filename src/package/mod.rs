@@ -1,12 +1,29 @@
+pub mod appimage;
+pub mod build_cache;
+pub mod db_lock;
 pub mod definition;
+pub mod diff;
+pub mod http_cache;
+pub mod install_log;
 pub mod installer;
+pub mod layout;
+pub mod managed_block;
+pub mod platform;
+pub mod requirements;
+pub mod runner;
+pub mod size;
+pub mod template;
+pub mod upstream;
+pub mod version;
 
 pub use definition::*;
 pub use installer::*;
 
-use crate::Result;
+use crate::Error;
+use platform::Platform;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 /// Represents a package in the repository
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +37,57 @@ pub struct Package {
     pub installation: Installation,
     pub post_install: Option<PostInstall>,
     pub metadata: PackageMetadata,
+    
+    /// Install for the current user or system-wide; see [`Package::install_scope`]
+    #[serde(default)]
+    pub scope: Option<InstallScope>,
+    
+    /// Capabilities this package provides, such as `editor`, which groups, profiles and
+    /// dependencies can name instead of a specific package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provides: Vec<String>,
+    
+    /// Architectures (or operating systems) the package can be installed on; empty means any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<String>,
+    
+    /// Oldest ArchBox that understands this definition, e.g. `>=0.3`; checked at load time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_archbox: Option<String>,
+    
+    /// Packages this one supersedes; `update` offers to migrate their installs to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaces: Vec<String>,
+    
+    /// Set when the package should no longer be used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<Deprecation>,
+    
+    /// Kernel, commands, GPU, RAM and disk space the package needs, checked before installing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires: Option<requirements::Requirements>,
+    
+    /// Installs kernel modules (often built by DKMS), which only load into the kernel they
+    /// were built for
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub kernel_module: bool,
+    
+    /// Environment for the package's build and install commands, install script and
+    /// post-install commands, e.g. `CARGO_BUILD_JOBS: "4"`. `~` and `$VAR` in values are
+    /// expanded when the commands run.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Why a package is deprecated and what to use instead
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Deprecation {
+    #[serde(default)]
+    pub reason: Option<String>,
+    
+    /// Package (or capability) that takes over; `update` offers to migrate to it
+    #[serde(default)]
+    pub successor: Option<String>,
 }
 
 /// Package dependency definition
@@ -30,12 +98,21 @@ pub struct Dependency {
     
     #[serde(default)]
     pub optional: bool,
+    
+    /// Only needed on this architecture or operating system
     pub platform: Option<String>,
     
     #[serde(default)]
     pub dep_type: DependencyType,
 }
 
+impl Dependency {
+    /// Whether the dependency is needed on `platform`
+    pub fn applies_to(&self, platform: &Platform) -> bool {
+        self.platform.as_deref().is_none_or(|constraint| platform.matches(constraint))
+    }
+}
+
 /// Types of dependencies
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
@@ -71,11 +148,20 @@ pub enum Installation {
     /// Download and install binary
     #[serde(rename = "binary")]
     Binary {
+        /// May contain `{arch}`, `{os}` and `{libc}` placeholders
+        #[serde(default)]
         url: String,
         checksum: Option<String>,
+        /// Per-architecture URLs, taking precedence over `url`
+        urls: Option<HashMap<String, String>>,
+        /// Per-architecture checksums, taking precedence over `checksum`
+        checksums: Option<HashMap<String, String>>,
         install_path: String,
         #[serde(default = "default_true")]
         executable: bool,
+        /// Tried in order when there is no URL for the machine's architecture
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fallbacks: Vec<BinaryFallback>,
     },
     
     /// Install from source
@@ -92,13 +178,23 @@ pub enum Installation {
         script: String,
         #[serde(default = "default_shell")]
         interpreter: String,
+        /// SHA-256 of `script`, pinned by the definition's author; the script is refused
+        /// unless it matches
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        script_sha256: Option<String>,
     },
     
     /// Install AppImage
     #[serde(rename = "appimage")]
     AppImage {
+        /// May contain `{arch}`, `{os}` and `{libc}` placeholders
+        #[serde(default)]
         url: String,
         checksum: Option<String>,
+        /// Per-architecture URLs, taking precedence over `url`
+        urls: Option<HashMap<String, String>>,
+        /// Per-architecture checksums, taking precedence over `checksum`
+        checksums: Option<HashMap<String, String>>,
         /// Desktop integration
         #[serde(default)]
         integrate: bool,
@@ -109,21 +205,269 @@ pub enum Installation {
     Flatpak {
         id: String,
         remote: Option<String>,
+        /// `.flatpakrepo` URL used to add the remote if it is not configured yet
+        remote_url: Option<String>,
+        #[serde(default)]
+        scope: FlatpakScope,
     },
 }
 
+impl Installation {
+    /// Method name as written in package definitions
+    pub fn method(&self) -> &'static str {
+        match self {
+            Installation::Pacman { .. } => "pacman",
+            Installation::Aur { .. } => "aur",
+            Installation::Binary { .. } => "binary",
+            Installation::Source { .. } => "source",
+            Installation::Script { .. } => "script",
+            Installation::AppImage { .. } => "appimage",
+            Installation::Flatpak { .. } => "flatpak",
+        }
+    }
+}
+
+/// What a binary package installs instead when it has no build for the machine's architecture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum BinaryFallback {
+    /// Another build to download, such as a static musl or universal binary
+    Binary {
+        /// May contain `{arch}`, `{os}` and `{libc}` placeholders
+        url: String,
+        checksum: Option<String>,
+        /// Per-architecture checksums, taking precedence over `checksum`
+        checksums: Option<HashMap<String, String>>,
+        /// Architectures the build exists for, all when empty
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        arch: Vec<String>,
+    },
+    
+    /// Build from source, with `install_commands` putting the result at the package's `install_path`
+    Source {
+        url: String,
+        build_commands: Vec<String>,
+        install_commands: Vec<String>,
+    },
+}
+
+/// Whether a package is installed for the current user (`~/.local`, user services) or
+/// system-wide (`/usr/local`, system services)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallScope {
+    #[default]
+    User,
+    
+    System,
+}
+
+impl InstallScope {
+    /// Prefix that binaries, AppImages and versioned installs live under
+    pub fn prefix(&self) -> PathBuf {
+        match self {
+            InstallScope::User => dirs::home_dir().unwrap_or_default().join(".local"),
+            InstallScope::System => PathBuf::from("/usr/local"),
+        }
+    }
+    
+    /// Move a path under the other scope's prefix to the same place under this one,
+    /// e.g. `~/.local/bin/fd` becomes `/usr/local/bin/fd` for system scope
+    pub fn rebase(&self, path: &Path) -> PathBuf {
+        let other = match self {
+            InstallScope::User => InstallScope::System,
+            InstallScope::System => InstallScope::User,
+        };
+        
+        match path.strip_prefix(other.prefix()) {
+            Ok(relative) => self.prefix().join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+impl std::fmt::Display for InstallScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InstallScope::User => "user",
+            InstallScope::System => "system",
+        })
+    }
+}
+
+impl From<InstallScope> for FlatpakScope {
+    fn from(scope: InstallScope) -> Self {
+        match scope {
+            InstallScope::User => FlatpakScope::User,
+            InstallScope::System => FlatpakScope::System,
+        }
+    }
+}
+
+/// Installation scope for Flatpaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FlatpakScope {
+    User,
+    
+    #[default]
+    System,
+}
+
+impl FlatpakScope {
+    /// Command-line flag selecting this installation
+    pub fn flag(&self) -> &'static str {
+        match self {
+            FlatpakScope::User => "--user",
+            FlatpakScope::System => "--system",
+        }
+    }
+}
+
 /// Post-installation configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PostInstall {
     pub commands: Option<Vec<String>>,
     
+    /// Files to write; paths and content may use `{user}`, `{home}`, `{hostname}`
+    /// and custom variables from the config's `variables` section
     pub config_files: Option<HashMap<String, String>>,
     
+    /// systemd units to enable; prefix with `user:` for user units
     pub enable_services: Option<Vec<String>>,
     
     pub user_groups: Option<Vec<String>>,
     
     pub environment: Option<HashMap<String, String>>,
+    
+    /// Kernel tunables written to `/etc/sysctl.d`
+    pub sysctl: Option<HashMap<String, String>>,
+    
+    /// udev rules written to `/etc/udev/rules.d`
+    pub udev_rules: Option<Vec<String>>,
+    
+    /// Kernel module options (module name to options) written to `/etc/modprobe.d`
+    pub modprobe: Option<HashMap<String, String>>,
+    
+    /// pacman hooks written to `/etc/pacman.d/hooks`, one file each
+    pub pacman_hooks: Option<Vec<PacmanHook>>,
+    
+    /// systemd-tmpfiles lines, e.g. `d /var/lib/demo 0755 root root -`, written to
+    /// `/etc/tmpfiles.d`
+    pub tmpfiles: Option<Vec<String>>,
+    
+    /// Ports to open in the active firewall (firewalld, ufw or nftables) while installed
+    pub firewall: Option<Vec<FirewallRule>>,
+    
+    /// Kernel command line parameters the user must add to their bootloader
+    pub kernel_params: Option<Vec<String>>,
+}
+
+/// A pacman hook (see alpm-hooks(5)): an action run before or after transactions that
+/// touch matching packages or files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PacmanHook {
+    /// Distinguishes the package's hooks; part of the file name
+    pub name: String,
+    
+    pub description: Option<String>,
+    
+    pub triggers: Vec<PacmanHookTrigger>,
+    
+    #[serde(default)]
+    pub when: PacmanHookWhen,
+    
+    /// Command to run, with an absolute path to the executable
+    pub exec: String,
+    
+    /// Packages that must be installed for the hook to run
+    #[serde(default)]
+    pub depends: Vec<String>,
+    
+    /// Pass the matched targets to the command on stdin
+    #[serde(default)]
+    pub needs_targets: bool,
+    
+    /// Cancel the transaction if the command fails (pre-transaction hooks only)
+    #[serde(default)]
+    pub abort_on_fail: bool,
+}
+
+/// What a pacman hook reacts to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PacmanHookTrigger {
+    #[serde(rename = "type")]
+    pub kind: PacmanHookTarget,
+    
+    pub operations: Vec<PacmanHookOperation>,
+    
+    /// Package names, or file paths without the leading `/`; globs and `!` negation work
+    pub targets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacmanHookTarget {
+    Package,
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacmanHookOperation {
+    Install,
+    Upgrade,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PacmanHookWhen {
+    PreTransaction,
+    
+    #[default]
+    PostTransaction,
+}
+
+/// A port, or range of ports, to allow incoming connections on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirewallRule {
+    /// Port, or the first port of a range
+    pub port: u16,
+    
+    /// Last port of a range
+    pub to: Option<u16>,
+    
+    #[serde(default)]
+    pub protocol: FirewallProtocol,
+}
+
+impl FirewallRule {
+    /// `22000`, or `8000-8010` for a range
+    pub fn ports(&self) -> String {
+        match self.to {
+            Some(to) if to != self.port => format!("{}-{}", self.port, to),
+            _ => self.port.to_string(),
+        }
+    }
+    
+    /// The protocols to open the ports for
+    pub fn protocols(&self) -> &'static [&'static str] {
+        match self.protocol {
+            FirewallProtocol::Tcp => &["tcp"],
+            FirewallProtocol::Udp => &["udp"],
+            FirewallProtocol::Both => &["tcp", "udp"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FirewallProtocol {
+    #[default]
+    Tcp,
+    Udp,
+    Both,
 }
 
 /// Package metadata
@@ -153,23 +497,86 @@ pub enum InstallStatus {
     Error { message: String },
 }
 
+impl InstallStatus {
+    /// Installed, whether or not an update is available
+    pub fn is_installed(&self) -> bool {
+        matches!(self, InstallStatus::Installed { .. } | InstallStatus::UpdateAvailable { .. })
+    }
+}
+
 fn default_true() -> bool { true }
 fn default_shell() -> String { "/bin/bash".to_string() }
 
 impl Package {
+    /// The package's `env` as pairs, before expansion
+    pub fn command_env(&self) -> Vec<(String, String)> {
+        self.env.iter().map(|(key, value)| (key.clone(), value.clone())).collect()
+    }
+    
     /// Check if this package is a system package
     pub fn is_system_package(&self) -> bool {
         matches!(self.installation, Installation::Pacman { .. })
     }
     
-    /// Get all dependencies of a specific type
-    pub fn get_dependencies(&self, dep_type: DependencyType) -> Vec<&Dependency> {
+    /// Scope to install in: pacman and AUR packages are always system-wide; otherwise
+    /// `requested` (from `--user`/`--system`), the definition's `scope`, then what the
+    /// definition implies (a Flatpak's own scope, a binary's `install_path`), else user scope
+    pub fn install_scope(&self, requested: Option<InstallScope>) -> InstallScope {
+        match &self.installation {
+            Installation::Pacman { .. } | Installation::Aur { .. } => InstallScope::System,
+            Installation::Flatpak { scope, .. } => requested.or(self.scope).unwrap_or(match scope {
+                FlatpakScope::User => InstallScope::User,
+                FlatpakScope::System => InstallScope::System,
+            }),
+            Installation::Binary { install_path, .. } => requested.or(self.scope).unwrap_or_else(|| {
+                // Without a scope, a binary bound for /usr/local is a system install
+                let path = shellexpand::tilde(install_path);
+                if Path::new(path.as_ref()).starts_with(InstallScope::System.prefix()) {
+                    InstallScope::System
+                } else {
+                    InstallScope::User
+                }
+            }),
+            _ => requested.or(self.scope).unwrap_or_default(),
+        }
+    }
+    
+    /// Where a binary or AppImage package puts its file in `scope`
+    pub fn install_path(&self, scope: InstallScope) -> Option<PathBuf> {
+        match &self.installation {
+            Installation::Binary { install_path, .. } => {
+                Some(scope.rebase(Path::new(shellexpand::tilde(install_path).as_ref())))
+            }
+            Installation::AppImage { .. } => Some(
+                scope.prefix().join("share/applications").join(format!("{}.AppImage", self.name)),
+            ),
+            _ => None,
+        }
+    }
+    
+    /// Get all dependencies of a specific type that apply on `platform`
+    pub fn get_dependencies(&self, dep_type: DependencyType, platform: &Platform) -> Vec<&Dependency> {
         self.dependencies
             .iter()
             .filter(|dep| std::mem::discriminant(&dep.dep_type) == std::mem::discriminant(&dep_type))
+            .filter(|dep| dep.applies_to(platform))
             .collect()
     }
     
+    /// Whether the package can be installed on `platform`
+    pub fn supports(&self, platform: &Platform) -> bool {
+        self.platforms.is_empty() || self.platforms.iter().any(|constraint| platform.matches(constraint))
+    }
+    
+    /// Error for installing the package on a platform it doesn't support
+    pub fn unsupported_error(&self, platform: &Platform) -> Error {
+        Error::UnsupportedPlatform {
+            package: self.name.clone(),
+            arch: platform.arch.clone(),
+            supported: self.platforms.join(", "),
+        }
+    }
+    
     /// Check if package has any optional dependencies
     pub fn has_optional_dependencies(&self) -> bool {
         self.dependencies.iter().any(|dep| dep.optional)
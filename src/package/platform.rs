@@ -0,0 +1,158 @@
+//! Detection of the running machine, used to pick per-architecture download URLs
+
+use super::BinaryFallback;
+use crate::{Error, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// What a binary package installs on this platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryAsset<'a> {
+    /// A build to download, `fallback` when it came from the package's fallbacks
+    Download {
+        url: String,
+        checksum: Option<String>,
+        fallback: bool,
+    },
+    /// A source build from the package's fallbacks
+    Source {
+        url: &'a str,
+        build_commands: &'a [String],
+        install_commands: &'a [String],
+    },
+}
+
+impl BinaryAsset<'_> {
+    pub fn is_fallback(&self) -> bool {
+        match self {
+            BinaryAsset::Download { fallback, .. } => *fallback,
+            BinaryAsset::Source { .. } => true,
+        }
+    }
+}
+
+/// The machine ArchBox is running on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    /// CPU architecture ArchBox was built for, as Rust names it (`x86_64`, `aarch64`, ...).
+    /// This matches `uname -m` for 64-bit machines, but 32-bit ARM is `arm` rather than `armv7l`.
+    pub arch: String,
+    pub os: String,
+    /// `gnu` or `musl`
+    pub libc: String,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        Self {
+            arch: std::env::consts::ARCH.to_string(),
+            os: std::env::consts::OS.to_string(),
+            libc: detect_libc().to_string(),
+        }
+    }
+
+    /// Pick the URL for this platform: an entry in `urls` keyed by architecture wins over
+    /// the generic `url`. `{arch}`, `{os}` and `{libc}` placeholders are then filled in.
+    pub fn resolve_url(&self, url: &str, urls: Option<&HashMap<String, String>>) -> Result<String> {
+        let template = match urls.and_then(|urls| urls.get(&self.arch)) {
+            Some(url) => url.as_str(),
+            None if !url.is_empty() => url,
+            None => {
+                return Err(Error::InstallationFailed(format!(
+                    "No download URL for architecture {}",
+                    self.arch
+                )));
+            }
+        };
+
+        Ok(self.render(template))
+    }
+
+    /// Pick what a binary package installs here: its own URL when it has one for this
+    /// architecture, else the first of `fallbacks` that applies
+    pub fn resolve_binary<'a>(
+        &self,
+        url: &str,
+        checksum: Option<&String>,
+        urls: Option<&HashMap<String, String>>,
+        checksums: Option<&HashMap<String, String>>,
+        fallbacks: &'a [BinaryFallback],
+    ) -> Result<BinaryAsset<'a>> {
+        let missing = match self.resolve_url(url, urls) {
+            Ok(url) => {
+                return Ok(BinaryAsset::Download {
+                    url,
+                    checksum: self.resolve_checksum(checksum, checksums),
+                    fallback: false,
+                });
+            }
+            Err(e) => e,
+        };
+
+        let fallback = fallbacks.iter().find_map(|fallback| match fallback {
+            BinaryFallback::Binary { url, checksum, checksums, arch } => {
+                if !arch.is_empty() && !arch.iter().any(|arch| self.matches(arch)) {
+                    return None;
+                }
+                Some(BinaryAsset::Download {
+                    url: self.render(url),
+                    checksum: self.resolve_checksum(checksum.as_ref(), checksums.as_ref()),
+                    fallback: true,
+                })
+            }
+            BinaryFallback::Source { url, build_commands, install_commands } => Some(BinaryAsset::Source {
+                url,
+                build_commands,
+                install_commands,
+            }),
+        });
+
+        fallback.ok_or(missing)
+    }
+
+    /// Pick the checksum for this platform, preferring an entry in `checksums` keyed by architecture
+    pub fn resolve_checksum(
+        &self,
+        checksum: Option<&String>,
+        checksums: Option<&HashMap<String, String>>,
+    ) -> Option<String> {
+        checksums
+            .and_then(|checksums| checksums.get(&self.arch))
+            .or(checksum)
+            .cloned()
+    }
+
+    /// Whether a platform constraint such as `aarch64` or `linux` names this machine.
+    /// Debian-style `amd64` and `arm64` are accepted for the architectures.
+    pub fn matches(&self, constraint: &str) -> bool {
+        let constraint = match constraint {
+            "amd64" => "x86_64",
+            "arm64" => "aarch64",
+            other => other,
+        };
+        constraint == self.arch || constraint == self.os
+    }
+
+    /// Replace `{arch}`, `{os}` and `{libc}` in a URL template
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{arch}", &self.arch)
+            .replace("{os}", &self.os)
+            .replace("{libc}", &self.libc)
+    }
+}
+
+/// musl systems ship their dynamic loader as `ld-musl-<arch>.so.1`
+fn detect_libc() -> &'static str {
+    let is_musl = ["/lib", "/usr/lib"].iter().any(|dir| {
+        std::fs::read_dir(Path::new(dir))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+            })
+            .unwrap_or(false)
+    });
+
+    if is_musl { "musl" } else { "gnu" }
+}
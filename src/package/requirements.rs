@@ -0,0 +1,217 @@
+//! Hardware and system requirements checked before a package is installed
+//!
+//! A definition's `requires` block lets it refuse to install where it can't work, e.g. a
+//! CUDA package on a machine without an NVIDIA GPU, instead of failing halfway through.
+
+use crate::{package::version, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The `requires` block of a definition
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Requirements {
+    /// Running kernel version, e.g. `>=6.1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel: Option<String>,
+
+    /// Commands that must be on PATH
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+
+    /// GPU vendor that must be present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu: Option<GpuVendor>,
+
+    /// Minimum installed RAM, e.g. `8 GiB`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+
+    /// Minimum free space where the package is installed, e.g. `2 GiB`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+}
+
+impl GpuVendor {
+    /// PCI vendor ids as found in sysfs (`0x10de`) or printed by lspci (`10de`)
+    pub fn from_pci_id(id: &str) -> Option<Self> {
+        match id.trim().trim_start_matches("0x").to_ascii_lowercase().as_str() {
+            "10de" => Some(GpuVendor::Nvidia),
+            "1002" => Some(GpuVendor::Amd),
+            "8086" => Some(GpuVendor::Intel),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for GpuVendor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuVendor::Nvidia => write!(f, "NVIDIA"),
+            GpuVendor::Amd => write!(f, "AMD"),
+            GpuVendor::Intel => write!(f, "Intel"),
+        }
+    }
+}
+
+impl Requirements {
+    /// The requirements this machine doesn't meet, as readable reasons. Files go to
+    /// `install_dir`, and commands are looked up inside `root` when installing into a
+    /// mounted system.
+    pub fn unmet(&self, install_dir: &Path, root: Option<&Path>) -> Result<Vec<String>> {
+        let mut unmet = Vec::new();
+
+        if let Some(constraint) = &self.kernel {
+            match kernel_version() {
+                Some(running) => {
+                    let ok = version::satisfies(constraint, &running)
+                        .map_err(|e| Error::Config(format!("Invalid kernel requirement: {}", e)))?;
+                    if !ok {
+                        unmet.push(format!("needs kernel {}, running {}", constraint, running));
+                    }
+                }
+                None => unmet.push(format!("needs kernel {}, but the running version is unknown", constraint)),
+            }
+        }
+
+        for command in &self.commands {
+            if !on_path(command, root) {
+                unmet.push(format!("needs the `{}` command", command));
+            }
+        }
+
+        if let Some(vendor) = self.gpu {
+            let found = gpu_vendors();
+            if !found.contains(&vendor) {
+                let found = if found.is_empty() {
+                    "none".to_string()
+                } else {
+                    found.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+                };
+                unmet.push(format!("needs an {} GPU (found: {})", vendor, found));
+            }
+        }
+
+        if let Some(minimum) = &self.memory {
+            let minimum = parse_requirement_size("memory", minimum)?;
+            if let Some(total) = total_memory().filter(|total| *total < minimum) {
+                unmet.push(format!(
+                    "needs {} of RAM, this machine has {}",
+                    super::size::format_size(minimum),
+                    super::size::format_size(total)
+                ));
+            }
+        }
+
+        if let Some(minimum) = &self.disk {
+            let minimum = parse_requirement_size("disk", minimum)?;
+            if let Some(free) = free_space(install_dir).filter(|free| *free < minimum) {
+                unmet.push(format!(
+                    "needs {} free in {}, only {} available",
+                    super::size::format_size(minimum),
+                    install_dir.display(),
+                    super::size::format_size(free)
+                ));
+            }
+        }
+
+        Ok(unmet)
+    }
+}
+
+/// Release of the running kernel without its local suffix, e.g. `6.8.2`
+pub fn kernel_version() -> Option<String> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    let version: String = release
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    (!version.is_empty()).then_some(version)
+}
+
+/// Whether the running kernel is still installed. Upgrading the kernel package deletes
+/// the old kernel's `/usr/lib/modules/<release>`, so modules can no longer be loaded into
+/// it until a reboot. `None` when the running release is unknown.
+pub fn running_kernel_installed() -> Option<bool> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    Some(Path::new("/usr/lib/modules").join(release.trim()).is_dir())
+}
+
+/// Vendors of the GPUs the kernel has a DRM device for
+pub fn gpu_vendors() -> Vec<GpuVendor> {
+    let mut vendors = Vec::new();
+
+    let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+        return vendors;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // card0, card1, ... but not connectors like card0-DP-1
+        if !name.starts_with("card") || name.contains('-') {
+            continue;
+        }
+
+        let vendor = std::fs::read_to_string(entry.path().join("device/vendor"))
+            .ok()
+            .and_then(|id| GpuVendor::from_pci_id(&id));
+        if let Some(vendor) = vendor.filter(|vendor| !vendors.contains(vendor)) {
+            vendors.push(vendor);
+        }
+    }
+
+    vendors
+}
+
+/// Installed RAM in bytes
+pub fn total_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kib: u64 = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// Free bytes on the filesystem holding `path`, or its closest existing parent
+pub fn free_space(path: &Path) -> Option<u64> {
+    let existing: PathBuf = path.ancestors().find(|dir| dir.exists())?.to_path_buf();
+    let output = std::process::Command::new("df")
+        .args(["--output=avail", "-B1"])
+        .arg(&existing)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout).lines().nth(1)?.trim().parse().ok()
+}
+
+fn parse_requirement_size(field: &str, value: &str) -> Result<u64> {
+    super::size::parse_size(value)
+        .ok_or_else(|| Error::Config(format!("Invalid {} requirement '{}': use a size like 8 GiB", field, value)))
+}
+
+/// Whether `command` is an executable on PATH, or in the usual directories of `root`
+pub fn on_path(command: &str, root: Option<&Path>) -> bool {
+    match root {
+        Some(root) => ["usr/bin", "usr/local/bin", "bin"]
+            .iter()
+            .any(|dir| root.join(dir).join(command).is_file()),
+        None => std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).any(|dir| dir.join(command).is_file()))
+            .unwrap_or(false),
+    }
+}
@@ -0,0 +1,272 @@
+//! How the installer runs external programs
+//!
+//! Every command the [`Installer`](super::Installer) and the repository
+//! [`Manager`](crate::repository::Manager) run (pacman, AUR helpers, git, systemctl, ...)
+//! is described as a [`CommandSpec`] and handed to a [`CommandRunner`]. [`SystemRunner`]
+//! runs them for real; [`RecordingRunner`] only records them and answers with canned
+//! output, so installs can be exercised without an Arch system.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A program to run with its arguments, working directory, extra environment and how long
+/// it may take
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    pub current_dir: Option<PathBuf>,
+    pub env: Vec<(OsString, OsString)>,
+    pub timeout: Option<Duration>,
+    pub interactive: bool,
+}
+
+impl CommandSpec {
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            current_dir: None,
+            env: Vec::new(),
+            timeout: None,
+            interactive: false,
+        }
+    }
+
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    pub fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.env.extend(vars.into_iter().map(|(key, value)| (key.as_ref().to_os_string(), value.as_ref().to_os_string())));
+        self
+    }
+
+    /// Stop the command, and everything it started, once it has run this long. Runners
+    /// report it as an error of kind [`TimedOut`](std::io::ErrorKind::TimedOut).
+    pub fn timeout(&mut self, limit: Option<Duration>) -> &mut Self {
+        self.timeout = limit;
+        self
+    }
+
+    /// Give the command the terminal, so it can show its own progress and ask questions.
+    /// Its output isn't collected: runners answer with an empty stdout and stderr.
+    pub fn interactive(&mut self) -> &mut Self {
+        self.interactive = true;
+        self
+    }
+
+    /// Program and arguments separated by spaces, e.g. `pacman -S --needed ripgrep`
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|word| word.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.command_line())
+    }
+}
+
+/// Runs commands to completion and collects their output
+#[async_trait]
+pub trait CommandRunner: fmt::Debug + Send + Sync {
+    async fn output(&self, command: &CommandSpec) -> std::io::Result<Output>;
+}
+
+/// Runs commands on this machine
+#[derive(Debug, Default)]
+pub struct SystemRunner;
+
+#[async_trait]
+impl CommandRunner for SystemRunner {
+    async fn output(&self, command: &CommandSpec) -> std::io::Result<Output> {
+        let mut cmd = tokio::process::Command::new(&command.program);
+        cmd.args(&command.args).envs(command.env.iter().map(|(key, value)| (key, value)));
+        if let Some(dir) = &command.current_dir {
+            cmd.current_dir(dir);
+        }
+        if command.interactive {
+            cmd.stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit());
+        }
+        let Some(limit) = command.timeout else {
+            return match command.interactive {
+                true => Ok(Output { status: cmd.status().await?, stdout: Vec::new(), stderr: Vec::new() }),
+                false => cmd.output().await,
+            };
+        };
+
+        // Left in the terminal's foreground process group, so sudo can still ask for a
+        // password and Ctrl-C reaches everything it started
+        if !command.interactive {
+            cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let child = cmd.kill_on_drop(true).spawn()?;
+        let pid = child.id();
+
+        // The child has to be alive while its descendants are looked up, so it is only
+        // dropped (and killed) after them
+        let output = child.wait_with_output();
+        tokio::pin!(output);
+        tokio::select! {
+            output = &mut output => output,
+            _ = tokio::time::sleep(limit) => {
+                if let Some(pid) = pid {
+                    kill_process_tree(pid);
+                }
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("{} did not finish within {}s", command, limit.as_secs()),
+                ))
+            }
+        }
+    }
+}
+
+/// A process and its descendants that are still attached to it, descendants first. Parents
+/// are read from `/proc/<pid>/stat`, which every kernel has.
+fn process_tree(pid: u32) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for entry in std::fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let Some(child) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        // The command name may contain spaces and parentheses, so fields count from the last ')'
+        let stat = std::fs::read_to_string(entry.path().join("stat")).unwrap_or_default();
+        let parent = stat.rsplit_once(')').and_then(|(_, fields)| fields.split_whitespace().nth(1)?.parse().ok());
+        if let Some(parent) = parent {
+            children.entry(parent).or_default().push(child);
+        }
+    }
+
+    fn collect(pid: u32, children: &HashMap<u32, Vec<u32>>, tree: &mut Vec<u32>) {
+        for &child in children.get(&pid).into_iter().flatten() {
+            collect(child, children, tree);
+        }
+        tree.push(pid);
+    }
+
+    let mut tree = Vec::new();
+    collect(pid, &children, &mut tree);
+    tree
+}
+
+/// Send SIGKILL to a process and everything it started. The standard library can only
+/// signal single processes, so this goes through kill(1).
+fn kill_process_tree(pid: u32) {
+    let pids: Vec<String> = process_tree(pid).iter().map(u32::to_string).collect();
+    let killed = std::process::Command::new("kill")
+        .args(["-KILL", "--"])
+        .args(&pids)
+        .stderr(Stdio::null())
+        .status();
+    if !killed.is_ok_and(|status| status.success()) {
+        tracing::warn!("Could not stop process {} and its children", pid);
+    }
+}
+
+type Handler = Box<dyn Fn(&CommandSpec) -> std::io::Result<Output> + Send + Sync>;
+
+/// Records every command instead of running it. Commands succeed with no output unless a
+/// rule added with [`respond`](Self::respond) or [`on`](Self::on) matches them.
+#[derive(Default)]
+pub struct RecordingRunner {
+    commands: Mutex<Vec<CommandSpec>>,
+    rules: Mutex<Vec<(String, Handler)>>,
+}
+
+impl RecordingRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer commands whose [command line](CommandSpec::command_line) starts with
+    /// `prefix` with an exit code and standard output. The rule added last wins.
+    pub fn respond(&self, prefix: &str, code: i32, stdout: &str) -> &Self {
+        let stdout = stdout.as_bytes().to_vec();
+        self.on(prefix, move |_| Ok(output(code, stdout.clone(), Vec::new())))
+    }
+
+    /// Handle commands whose command line starts with `prefix` with `handler`, e.g. to
+    /// create the files a real `git clone` would have
+    pub fn on<F>(&self, prefix: &str, handler: F) -> &Self
+    where
+        F: Fn(&CommandSpec) -> std::io::Result<Output> + Send + Sync + 'static,
+    {
+        self.rules.lock().unwrap().push((prefix.to_string(), Box::new(handler)));
+        self
+    }
+
+    /// Every command seen so far, in order
+    pub fn commands(&self) -> Vec<CommandSpec> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    /// The command lines of every command seen so far, in order
+    pub fn command_lines(&self) -> Vec<String> {
+        self.commands().iter().map(CommandSpec::command_line).collect()
+    }
+}
+
+impl fmt::Debug for RecordingRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingRunner")
+            .field("commands", &self.commands.lock().unwrap().len())
+            .field("rules", &self.rules.lock().unwrap().len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CommandRunner for RecordingRunner {
+    async fn output(&self, command: &CommandSpec) -> std::io::Result<Output> {
+        self.commands.lock().unwrap().push(command.clone());
+
+        let line = command.command_line();
+        let rules = self.rules.lock().unwrap();
+        match rules.iter().rev().find(|(prefix, _)| line.starts_with(prefix.as_str())) {
+            Some((_, handler)) => handler(command),
+            None => Ok(output(0, Vec::new(), Vec::new())),
+        }
+    }
+}
+
+/// Output of a finished command with exit code `code`
+pub fn output(code: i32, stdout: Vec<u8>, stderr: Vec<u8>) -> Output {
+    Output {
+        status: ExitStatus::from_raw(code << 8),
+        stdout,
+        stderr,
+    }
+}
@@ -0,0 +1,280 @@
+//! Download and installed size estimates for transaction previews, and disk usage of
+//! installed packages
+
+use crate::package::{
+    layout, platform::{BinaryAsset, Platform}, runner::{CommandRunner, CommandSpec}, FlatpakScope, InstallScope, Installation, Package,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Estimated sizes for installing or upgrading one package
+#[derive(Debug, Clone, Default)]
+pub struct SizeEstimate {
+    /// Bytes that will be downloaded
+    pub download: Option<u64>,
+    /// Bytes on disk after installation
+    pub installed: Option<u64>,
+    /// Bytes currently on disk for an existing installation
+    pub current: Option<u64>,
+}
+
+impl SizeEstimate {
+    /// Change in disk usage after the transaction
+    pub fn delta(&self) -> Option<i64> {
+        self.installed
+            .map(|installed| installed as i64 - self.current.unwrap_or(0) as i64)
+    }
+}
+
+/// Estimate sizes for a set of packages, keyed by package name
+pub async fn estimate_sizes(runner: &dyn CommandRunner, packages: &[Package]) -> HashMap<String, SizeEstimate> {
+    let mut estimates = HashMap::new();
+
+    let platform = Platform::current();
+
+    for package in packages {
+        let estimate = match &package.installation {
+            Installation::Pacman { packages: names, .. } => pacman_estimate(runner, names).await,
+            Installation::Binary { url, urls, install_path, fallbacks, .. } => {
+                let path = PathBuf::from(shellexpand::tilde(install_path).as_ref());
+                match platform.resolve_binary(url, None, urls.as_ref(), None, fallbacks) {
+                    Ok(BinaryAsset::Download { url, .. }) => remote_file_estimate(&url, path).await,
+                    _ => SizeEstimate::default(),
+                }
+            }
+            Installation::AppImage { url, urls, .. } => {
+                let path = dirs::home_dir()
+                    .unwrap_or_default()
+                    .join(".local/share/applications")
+                    .join(format!("{}.AppImage", package.name));
+                match platform.resolve_url(url, urls.as_ref()) {
+                    Ok(url) => remote_file_estimate(&url, path).await,
+                    Err(_) => SizeEstimate::default(),
+                }
+            }
+            Installation::Flatpak { id, remote, scope, .. } => {
+                flatpak_estimate(runner, id, remote.as_deref().unwrap_or("flathub"), *scope).await
+            }
+            _ => SizeEstimate::default(),
+        };
+
+        estimates.insert(package.name.clone(), estimate);
+    }
+
+    estimates
+}
+
+async fn pacman_estimate(runner: &dyn CommandRunner, names: &[String]) -> SizeEstimate {
+    let mut estimate = SizeEstimate::default();
+
+    // Download sizes of packages not yet in the local cache: up-to-date packages are left
+    // out with --needed, and pacman gives cached ones a file:// location
+    let mut query = CommandSpec::new("pacman");
+    query.args(["-Sp", "--needed", "--print-format", "%s %l"]).args(names);
+    if let Ok(output) = runner.output(&query).await {
+        if output.status.success() {
+            estimate.download = Some(
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter_map(|line| line.trim().split_once(' '))
+                    .filter(|(_, location)| !location.starts_with("file://"))
+                    .filter_map(|(size, _)| size.parse::<u64>().ok())
+                    .sum(),
+            );
+        }
+    }
+
+    let available = pacman_installed_sizes(runner, "-Si", names).await;
+    if !available.is_empty() {
+        estimate.installed = Some(available.values().sum());
+    }
+
+    let current = pacman_installed_sizes(runner, "-Qi", names).await;
+    if !current.is_empty() {
+        estimate.current = Some(current.values().sum());
+    }
+
+    estimate
+}
+
+/// Parse the "Installed Size" field of `pacman -Si`/`pacman -Qi` output, keyed by package name
+pub async fn pacman_installed_sizes(runner: &dyn CommandRunner, query: &str, names: &[String]) -> HashMap<String, u64> {
+    let mut sizes = HashMap::new();
+
+    let output = match runner.output(CommandSpec::new("pacman").arg(query).args(names)).await {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("pacman {} failed: {}", query, e);
+            return sizes;
+        }
+    };
+
+    let mut current_name = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        match key.trim() {
+            "Name" => current_name = Some(value.trim().to_string()),
+            "Installed Size" => {
+                if let (Some(name), Some(size)) = (current_name.take(), parse_size(value)) {
+                    sizes.insert(name, size);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    sizes
+}
+
+async fn remote_file_estimate(url: &str, path: PathBuf) -> SizeEstimate {
+    let mut estimate = SizeEstimate::default();
+
+    let client = crate::http::client_builder().and_then(|builder| {
+        Ok(builder.timeout(std::time::Duration::from_secs(10)).build()?)
+    });
+
+    if let Ok(client) = client {
+        if let Ok(response) = client.head(url).send().await {
+            estimate.download = response
+                .headers()
+                .get(reqwest::header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok());
+        }
+    }
+
+    estimate.installed = estimate.download;
+    estimate.current = std::fs::metadata(&path).ok().map(|meta| meta.len());
+    estimate
+}
+
+async fn flatpak_estimate(runner: &dyn CommandRunner, id: &str, remote: &str, scope: FlatpakScope) -> SizeEstimate {
+    let mut estimate = SizeEstimate::default();
+
+    if let Ok(output) = runner.output(CommandSpec::new("flatpak").args(["remote-info", scope.flag(), remote, id])).await {
+        let info = String::from_utf8_lossy(&output.stdout);
+        estimate.download = field_size(&info, "Download");
+        estimate.installed = field_size(&info, "Installed");
+    }
+
+    if let Ok(output) = runner.output(CommandSpec::new("flatpak").args(["info", scope.flag(), id])).await {
+        if output.status.success() {
+            estimate.current = field_size(&String::from_utf8_lossy(&output.stdout), "Installed");
+        }
+    }
+
+    estimate
+}
+
+/// Bytes on disk for an installed package. Pacman and AUR packages use `pacman -Qi` and
+/// Flatpaks `flatpak info`; other methods sum the files ArchBox put on disk (versioned
+/// installs, AppImages, install paths and `owned_files`) under `scope`. `None` if nothing
+/// was found.
+pub async fn installed_size(runner: &dyn CommandRunner, package: &Package, scope: InstallScope, owned_files: &[PathBuf]) -> Option<u64> {
+    match &package.installation {
+        Installation::Pacman { packages: names, .. } => {
+            let sizes = pacman_installed_sizes(runner, "-Qi", names).await;
+            (!sizes.is_empty()).then(|| sizes.values().sum())
+        }
+        Installation::Aur { package: name, .. } => {
+            pacman_installed_sizes(runner, "-Qi", std::slice::from_ref(name)).await.values().copied().next()
+        }
+        Installation::Flatpak { id, .. } => {
+            let output = runner.output(CommandSpec::new("flatpak").args(["info", FlatpakScope::from(scope).flag(), id])).await.ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            field_size(&String::from_utf8_lossy(&output.stdout), "Installed")
+        }
+        _ => manifest_size(package, scope, owned_files),
+    }
+}
+
+fn manifest_size(package: &Package, scope: InstallScope, owned_files: &[PathBuf]) -> Option<u64> {
+    let mut paths = vec![layout::package_dir(&package.name, scope)];
+
+    // A managed link points into the package dir, which is already counted
+    if let Some(path) = package.install_path(scope) {
+        if !layout::is_managed_link(&package.name, scope, &path) {
+            paths.push(path);
+        }
+    }
+
+    paths.extend(owned_files.iter().cloned());
+
+    paths
+        .iter()
+        .filter_map(|path| disk_usage(path))
+        .reduce(|total, size| total + size)
+}
+
+/// Total size of a file or directory tree, without following symlinks
+fn disk_usage(path: &Path) -> Option<u64> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return Some(metadata.len());
+    }
+
+    let total = std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| disk_usage(&entry.path()))
+        .sum();
+
+    Some(total)
+}
+
+fn field_size(info: &str, field: &str) -> Option<u64> {
+    info.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == field)
+        .and_then(|(_, value)| parse_size(value))
+}
+
+/// Parse human-readable sizes like "1.5 MiB", "12.3 MB", "8G" or "512 B"
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().replace('\u{a0}', " ");
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.replace(',', ".").parse().ok()?;
+
+    let multiplier: f64 = match unit.trim() {
+        "" | "B" | "bytes" => 1.0,
+        "KiB" | "K" => 1024.0,
+        "MiB" | "M" => 1024.0 * 1024.0,
+        "GiB" | "G" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" | "T" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "kB" | "KB" => 1000.0,
+        "MB" => 1000.0 * 1000.0,
+        "GB" => 1000.0 * 1000.0 * 1000.0,
+        _ => return None,
+    };
+
+    Some((number * multiplier) as u64)
+}
+
+/// Format a byte count like pacman does, e.g. "12.34 MiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.2} {}", size, UNITS[unit])
+}
+
+/// Format a signed size change, e.g. "+1.20 MiB" or "-300.00 KiB"
+pub fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, format_size(delta.unsigned_abs()))
+}
@@ -0,0 +1,77 @@
+//! Placeholder expansion for post-install config files
+
+use std::collections::HashMap;
+
+/// Values substituted for `{name}` placeholders
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    variables: HashMap<String, String>,
+}
+
+impl TemplateContext {
+    /// Context with the built-in `{user}`, `{home}` and `{hostname}` variables
+    pub fn from_system() -> Self {
+        let mut variables = HashMap::new();
+
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "user".to_string());
+        variables.insert("user".to_string(), user);
+
+        if let Some(home) = dirs::home_dir() {
+            variables.insert("home".to_string(), home.display().to_string());
+        }
+
+        variables.insert("hostname".to_string(), hostname());
+
+        Self { variables }
+    }
+
+    /// Add custom variables, overriding built-ins with the same name
+    pub fn with_variables(mut self, variables: &HashMap<String, String>) -> Self {
+        self.variables.extend(variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+        self
+    }
+
+    /// Replace every `{name}` whose name is a known variable. Unknown placeholders are
+    /// left untouched so braces used by the config format itself survive.
+    pub fn render(&self, input: &str) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input;
+
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            let candidate = &rest[start + 1..];
+
+            let replacement = candidate
+                .find('}')
+                .map(|end| &candidate[..end])
+                .and_then(|name| self.variables.get(name).map(|value| (name.len(), value)));
+
+            match replacement {
+                Some((name_len, value)) => {
+                    output.push_str(value);
+                    rest = &candidate[name_len + 1..];
+                }
+                None => {
+                    output.push('{');
+                    rest = candidate;
+                }
+            }
+        }
+
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Name of this machine
+pub fn hostname() -> String {
+    ["/etc/hostname", "/proc/sys/kernel/hostname"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
@@ -0,0 +1,113 @@
+//! Latest versions published upstream, used to notice definitions that have fallen behind
+//!
+//! AUR packages are looked up with one batched RPC request, Flathub apps through the Flathub
+//! API and binaries or AppImages released on GitHub through the releases API. Every
+//! response goes through [`http_cache`](super::http_cache), so repeated checks stay within
+//! the services' rate limits.
+
+use super::{http_cache, platform::Platform, Installation, Package};
+use crate::Result;
+use std::collections::HashMap;
+use tracing::debug;
+
+const AUR_RPC: &str = "https://aur.archlinux.org/rpc/v5/info";
+const FLATHUB_API: &str = "https://flathub.org/api/v2/appstream";
+const GITHUB_API: &str = "https://api.github.com/repos";
+
+/// Latest upstream version of each package that has a known upstream, by package name.
+/// Lookups that fail are logged and left out.
+pub async fn latest_versions(packages: &[&Package]) -> HashMap<String, String> {
+    let client = match crate::http::client() {
+        Ok(client) => client,
+        Err(e) => {
+            debug!("Could not create an HTTP client for upstream checks: {}", e);
+            return HashMap::new();
+        }
+    };
+    let platform = Platform::current();
+
+    let mut versions = HashMap::new();
+    let mut aur_names = HashMap::new();
+
+    for package in packages {
+        let lookup = match &package.installation {
+            Installation::Aur { package: aur_name, .. } => {
+                aur_names.insert(aur_name.clone(), package.name.clone());
+                continue;
+            }
+            Installation::Flatpak { id, remote, .. } if remote.as_deref().unwrap_or("flathub") == "flathub" => {
+                flathub_version(&client, id).await
+            }
+            Installation::Binary { url, urls, .. } | Installation::AppImage { url, urls, .. } => {
+                match platform.resolve_url(url, urls.as_ref()).ok().as_deref().and_then(github_repo) {
+                    Some((owner, repo)) => github_version(&client, &owner, &repo).await,
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        match lookup {
+            Ok(Some(version)) => {
+                versions.insert(package.name.clone(), version);
+            }
+            Ok(None) => {}
+            Err(e) => debug!("Upstream version check for {} failed: {}", package.name, e),
+        }
+    }
+
+    if !aur_names.is_empty() {
+        let mut names: Vec<&String> = aur_names.keys().collect();
+        names.sort();
+        match aur_versions(&client, &names).await {
+            Ok(found) => {
+                for (aur_name, version) in found {
+                    if let Some(package) = aur_names.get(&aur_name) {
+                        versions.insert(package.clone(), version);
+                    }
+                }
+            }
+            Err(e) => debug!("AUR version check failed: {}", e),
+        }
+    }
+
+    versions
+}
+
+async fn aur_versions(client: &reqwest::Client, names: &[&String]) -> Result<HashMap<String, String>> {
+    let query: Vec<String> = names.iter().map(|name| format!("arg[]={}", name)).collect();
+    let url = format!("{}?{}", AUR_RPC, query.join("&"));
+    let response = http_cache::get_json(client, &url, http_cache::AUR_TTL).await?;
+
+    Ok(response["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|result| Some((result["Name"].as_str()?.to_string(), result["Version"].as_str()?.to_string())))
+        .collect())
+}
+
+async fn flathub_version(client: &reqwest::Client, id: &str) -> Result<Option<String>> {
+    let url = format!("{}/{}", FLATHUB_API, id);
+    let appstream = http_cache::get_json(client, &url, http_cache::FLATHUB_TTL).await?;
+
+    Ok(appstream["releases"][0]["version"].as_str().map(str::to_string))
+}
+
+async fn github_version(client: &reqwest::Client, owner: &str, repo: &str) -> Result<Option<String>> {
+    let url = format!("{}/{}/{}/releases/latest", GITHUB_API, owner, repo);
+    let release = http_cache::get_json(client, &url, http_cache::GITHUB_TTL).await?;
+
+    Ok(release["tag_name"]
+        .as_str()
+        .map(|tag| tag.trim_start_matches('v').to_string()))
+}
+
+/// `(owner, repo)` of a GitHub release download URL
+fn github_repo(url: &str) -> Option<(String, String)> {
+    let path = url.strip_prefix("https://github.com/")?;
+    let mut parts = path.split('/');
+    let owner = parts.next()?;
+    let repo = parts.next()?;
+    (parts.next() == Some("releases")).then(|| (owner.to_string(), repo.to_string()))
+}
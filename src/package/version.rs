@@ -0,0 +1,254 @@
+//! Version ordering compatible with pacman's `vercmp`
+//!
+//! Versions are `[epoch:]version[-release]`. Epochs compare first, then versions, then
+//! releases when both sides have one. Each part is compared segment by segment the way
+//! alpm's `rpmvercmp` does, so `1.0rc < 1.0 < 1.0.1` and `1:0.9 > 2.0`.
+
+use std::cmp::Ordering;
+
+/// Compare two versions like `vercmp` from pacman
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (epoch_a, version_a, release_a) = parse_evr(a);
+    let (epoch_b, version_b, release_b) = parse_evr(b);
+
+    rpmvercmp(epoch_a, epoch_b)
+        .then_with(|| rpmvercmp(version_a, version_b))
+        .then_with(|| match (release_a, release_b) {
+            (Some(release_a), Some(release_b)) => rpmvercmp(release_a, release_b),
+            _ => Ordering::Equal,
+        })
+}
+
+/// Check `version` against a constraint such as `>=0.3` or `>=1.2, <2`. Clauses are
+/// separated by commas; a bare version means at least that version.
+pub fn satisfies(constraint: &str, version: &str) -> Result<bool, String> {
+    for clause in constraint.split(',').map(str::trim) {
+        let (op, wanted) = [">=", "<=", "==", ">", "<", "="]
+            .iter()
+            .find_map(|op| clause.strip_prefix(op).map(|rest| (*op, rest.trim())))
+            .unwrap_or((">=", clause));
+        if wanted.is_empty() {
+            return Err(format!("'{}' has no version", clause));
+        }
+
+        let ordering = vercmp(version, wanted);
+        let ok = match op {
+            ">=" => ordering.is_ge(),
+            "<=" => ordering.is_le(),
+            ">" => ordering.is_gt(),
+            "<" => ordering.is_lt(),
+            _ => ordering.is_eq(),
+        };
+        if !ok {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Split `epoch:version-release`. A missing epoch is `0`; the release follows the last `-`.
+fn parse_evr(evr: &str) -> (&str, &str, Option<&str>) {
+    let digits = evr.bytes().take_while(u8::is_ascii_digit).count();
+    let (epoch, rest) = match evr[digits..].strip_prefix(':') {
+        Some(rest) if digits > 0 => (&evr[..digits], rest),
+        Some(rest) => ("0", rest),
+        None => ("0", evr),
+    };
+
+    match rest.rsplit_once('-') {
+        Some((version, release)) => (epoch, version, Some(release)),
+        None => (epoch, rest, None),
+    }
+}
+
+/// alpm's `rpmvercmp`: compare alternating runs of digits and letters, skipping other
+/// characters as separators
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut one, mut two) = (0, 0);
+
+    while one < a.len() && two < b.len() {
+        let (start_one, start_two) = (one, two);
+        while one < a.len() && !a[one].is_ascii_alphanumeric() {
+            one += 1;
+        }
+        while two < b.len() && !b[two].is_ascii_alphanumeric() {
+            two += 1;
+        }
+
+        if one == a.len() || two == b.len() {
+            break;
+        }
+
+        // Different separator lengths decide it, e.g. `2___a` > `2_a`
+        if one - start_one != two - start_two {
+            return (one - start_one).cmp(&(two - start_two));
+        }
+
+        let numeric = a[one].is_ascii_digit();
+        let class = |c: &u8| if numeric { c.is_ascii_digit() } else { c.is_ascii_alphabetic() };
+        let end_one = one + a[one..].iter().take_while(|c| class(c)).count();
+        let end_two = two + b[two..].iter().take_while(|c| class(c)).count();
+
+        // A number against letters: the number is newer
+        if end_two == two {
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+
+        let (mut segment_one, mut segment_two) = (&a[one..end_one], &b[two..end_two]);
+        if numeric {
+            segment_one = trim_zeros(segment_one);
+            segment_two = trim_zeros(segment_two);
+
+            let by_length = segment_one.len().cmp(&segment_two.len());
+            if by_length.is_ne() {
+                return by_length;
+            }
+        }
+
+        let by_content = segment_one.cmp(segment_two);
+        if by_content.is_ne() {
+            return by_content;
+        }
+
+        one = end_one;
+        two = end_two;
+    }
+
+    if one == a.len() && two == b.len() {
+        return Ordering::Equal;
+    }
+
+    // Whatever is left over decides, but a trailing letter run never beats nothing:
+    // `1.0rc` < `1.0` < `1.0.1`
+    let rest_one = a.get(one);
+    let rest_two = b.get(two);
+    if (rest_one.is_none() && !rest_two.is_some_and(u8::is_ascii_alphabetic)) || rest_one.is_some_and(u8::is_ascii_alphabetic) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&c| c == b'0').count();
+    &digits[zeros..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(a: &str, b: &str, expected: i32) {
+        let expected = expected.cmp(&0);
+        assert_eq!(vercmp(a, b), expected, "vercmp({}, {})", a, b);
+        assert_eq!(vercmp(b, a), expected.reverse(), "vercmp({}, {})", b, a);
+    }
+
+    // Cases from pacman's test/util/vercmptest.sh
+
+    #[test]
+    fn plain_versions() {
+        check("1.5.0", "1.5.0", 0);
+        check("1.5.1", "1.5.0", 1);
+        check("1.5.1", "1.5", 1);
+    }
+
+    #[test]
+    fn with_release() {
+        check("1.5.0-1", "1.5.0-1", 0);
+        check("1.5.0-1", "1.5.0-2", -1);
+        check("1.5.0-1", "1.5.1-1", -1);
+        check("1.5.0-2", "1.5.1-1", -1);
+        check("1.5-1", "1.5.1-1", -1);
+        check("1.5-2", "1.5.1-1", -1);
+        check("1.5-2", "1.5.1-2", -1);
+    }
+
+    #[test]
+    fn release_on_one_side_only() {
+        check("1.5", "1.5-1", 0);
+        check("1.5-1", "1.5", 0);
+        check("1.1-1", "1.1", 0);
+        check("1.0-1", "1.1", -1);
+        check("1.1-1", "1.0", 1);
+    }
+
+    #[test]
+    fn alphanumeric() {
+        check("1.5b-1", "1.5-1", -1);
+        check("1.5b", "1.5", -1);
+        check("1.5b-1", "1.5", -1);
+        check("1.5b", "1.5.1", -1);
+    }
+
+    #[test]
+    fn manpage_examples() {
+        check("1.0a", "1.0alpha", -1);
+        check("1.0alpha", "1.0b", -1);
+        check("1.0b", "1.0beta", -1);
+        check("1.0beta", "1.0rc", -1);
+        check("1.0rc", "1.0", -1);
+    }
+
+    #[test]
+    fn dotted_letters() {
+        check("1.5.a", "1.5", 1);
+        check("1.5.b", "1.5.a", 1);
+        check("1.5.1", "1.5.b", 1);
+        check("1.5.b-1", "1.5.b", 0);
+        check("1.5-1", "1.5.b", -1);
+    }
+
+    #[test]
+    fn separators() {
+        check("2.0", "2_0", 0);
+        check("2.0_a", "2_0.a", 0);
+        check("2.0a", "2.0.a", -1);
+        check("2___a", "2_a", 1);
+    }
+
+    #[test]
+    fn epochs() {
+        check("0:1.0", "0:1.0", 0);
+        check("0:1.0", "0:1.1", -1);
+        check("1:1.0", "0:1.0", 1);
+        check("1:1.0", "0:1.1", 1);
+        check("1:1.0", "2:1.1", -1);
+        check("1:1.0", "0:1.0-1", 1);
+        check("1:1.0-1", "0:1.1-1", 1);
+        check("0:1.0", "1.0", 0);
+        check("0:1.0", "1.1", -1);
+        check("0:1.1", "1.0", 1);
+        check("1:1.0", "1.0", 1);
+        check("1:1.0", "1.1", 1);
+        check("1:1.1", "1.1", 1);
+    }
+
+    #[test]
+    fn leading_zeros() {
+        check("1.002", "1.2", 0);
+        check("1.010", "1.9", 1);
+    }
+
+    #[test]
+    fn constraints() {
+        assert_eq!(satisfies(">=0.3", "0.3.0"), Ok(true));
+        assert_eq!(satisfies(">=0.3", "0.2.9"), Ok(false));
+        assert_eq!(satisfies("0.3", "1.0"), Ok(true));
+        assert_eq!(satisfies(">=1.2, <2", "1.10"), Ok(true));
+        assert_eq!(satisfies(">=1.2, <2", "2.0"), Ok(false));
+        assert_eq!(satisfies("=1.0", "1.0-3"), Ok(true));
+        assert_eq!(satisfies(">1:0.5", "2.0"), Ok(false));
+        assert!(satisfies(">=", "1.0").is_err());
+    }
+}
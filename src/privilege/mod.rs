@@ -0,0 +1,79 @@
+//! Root privileges for system-wide operations
+//!
+//! Only system-scope work goes through here: every pacman transaction (installs, removals,
+//! upgrades and build dependencies) is run through sudo when needed, and writes under
+//! system prefixes require ArchBox itself to run as root.
+//! User-scope installs never ask for privileges.
+
+use crate::package::runner::CommandSpec;
+use crate::{Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PKEXEC: AtomicBool = AtomicBool::new(false);
+static NO_USERMOD: AtomicBool = AtomicBool::new(false);
+
+/// Escalate with pkexec instead of sudo, so the desktop's polkit agent asks for the password.
+/// Used by the daemon, which has no terminal for sudo to prompt on.
+pub fn use_pkexec(enabled: bool) {
+    PKEXEC.store(enabled, Ordering::Relaxed);
+}
+
+/// Leave user accounts alone: post-install `user_groups` are reported instead of applied.
+/// For machines whose accounts are managed centrally (LDAP, config management).
+pub fn forbid_usermod() {
+    NO_USERMOD.store(true, Ordering::Relaxed);
+}
+
+/// Whether post-install may add the user to groups (see [`forbid_usermod`])
+pub fn usermod_allowed() -> bool {
+    !NO_USERMOD.load(Ordering::Relaxed)
+}
+
+/// Whether ArchBox itself is running as root
+pub fn running_as_root() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata("/proc/self").map(|meta| meta.uid() == 0).unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// The program that runs commands as root for us: `None` when already root, otherwise
+/// sudo, or pkexec (see [`use_pkexec`])
+fn escalation() -> Option<&'static str> {
+    if running_as_root() {
+        None
+    } else if PKEXEC.load(Ordering::Relaxed) {
+        Some("pkexec")
+    } else {
+        Some("sudo")
+    }
+}
+
+/// A command that needs root, prefixed with sudo (or pkexec, see [`use_pkexec`]) when not
+/// already running as root
+pub fn privileged(program: &str) -> CommandSpec {
+    match escalation() {
+        Some(escalation) => {
+            let mut cmd = CommandSpec::new(escalation);
+            cmd.arg(program);
+            cmd
+        }
+        None => CommandSpec::new(program),
+    }
+}
+
+/// Fail with a hint to use sudo unless running as root
+pub fn require_root(operation: &str) -> Result<()> {
+    if running_as_root() {
+        return Ok(());
+    }
+
+    Err(Error::PermissionDenied {
+        operation: format!("{} needs root; re-run with sudo", operation),
+    })
+}
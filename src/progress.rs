@@ -0,0 +1,99 @@
+//! Shared progress reporting. Every bar and spinner is attached to one `MultiProgress`
+//! so concurrent operations render as a single dashboard, and terminal output is
+//! printed through it instead of over the bars.
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static MULTI: OnceLock<MultiProgress> = OnceLock::new();
+
+const TICK: Duration = Duration::from_millis(100);
+
+/// The process-wide progress dashboard
+pub fn multi() -> &'static MultiProgress {
+    MULTI.get_or_init(MultiProgress::new)
+}
+
+/// Enable or hide all progress rendering (`ui.show_progress`)
+pub fn set_enabled(enabled: bool) {
+    if !enabled {
+        multi().set_draw_target(ProgressDrawTarget::hidden());
+    }
+}
+
+/// Overall bar counting finished packages out of `len`
+pub fn overall(len: usize) -> ProgressBar {
+    let pb = multi().add(ProgressBar::new(len as u64));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb.enable_steady_tick(TICK);
+    pb
+}
+
+/// Per-task spinner with a status message
+pub fn spinner(message: impl Into<String>) -> ProgressBar {
+    let pb = multi().add(ProgressBar::new_spinner());
+    pb.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {msg}").unwrap());
+    pb.set_message(message.into());
+    pb.enable_steady_tick(TICK);
+    pb
+}
+
+/// Byte progress for a download. Without a known length only the transferred bytes
+/// and rate are shown.
+pub fn download(total: Option<u64>, message: impl Into<String>) -> ProgressBar {
+    let pb = match total {
+        Some(total) => {
+            let pb = ProgressBar::new(total);
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner:.green} {msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+            pb
+        }
+        None => {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.green} {msg} {bytes} ({bytes_per_sec})")
+                    .unwrap(),
+            );
+            pb
+        }
+    };
+
+    let pb = multi().add(pb);
+    pb.set_message(message.into());
+    pb.enable_steady_tick(TICK);
+    pb
+}
+
+/// Run `f` with the bars cleared so its output doesn't interleave with them
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    multi().suspend(f)
+}
+
+/// Writer for tracing's terminal output that prints to stderr above the progress bars
+pub struct SuspendingStderr;
+
+impl Write for SuspendingStderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        suspend(|| io::stderr().write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        suspend(|| io::stderr().write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
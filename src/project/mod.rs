@@ -0,0 +1,51 @@
+//! Project-local manifests
+//!
+//! An `archbox.yaml` in a project directory lists the tools working on the project needs,
+//! so onboarding comes down to `archbox env install`.
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "archbox.yaml";
+
+/// The contents of a project's `archbox.yaml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectManifest {
+    /// Project name shown in messages, defaulting to the directory name
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Packages the project needs
+    #[serde(default)]
+    pub packages: Vec<String>,
+
+    /// Template variables for the config files of these packages
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// Find `archbox.yaml` in `start` or the nearest parent directory that has one
+pub fn find_manifest(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Load a manifest, filling in the project name from its directory
+pub fn load_manifest(path: &Path) -> Result<ProjectManifest> {
+    let content = std::fs::read_to_string(path)?;
+    let mut manifest: ProjectManifest = serde_yaml::from_str(&content)
+        .map_err(|e| Error::Config(format!("Invalid project manifest {}: {}", path.display(), e)))?;
+
+    if manifest.name.is_none() {
+        manifest.name = path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string());
+    }
+
+    Ok(manifest)
+}
@@ -1,33 +1,95 @@
 use crate::{
+  cache::CacheManager,
   config::Config,
-  package::{DefinitionLoader, Package, InstallStatus, DependencyType},
+  package::{
+      db_lock::LockWaitRunner,
+      platform::Platform,
+      version::vercmp,
+      runner::{CommandRunner, CommandSpec, SystemRunner},
+      BuildOptions, ConfigConflict, DefinitionLoader, Package, InstallScope, InstallStatus, DependencyType, PostInstallAction,
+  },
   cli::commands::{search::SearchArgs, list::ListArgs},
+  state::{InstallReason, InstalledPackage, StateDb},
   Error, Result,
 };
 use std::collections::{HashMap, HashSet, VecDeque};
-use tokio::process::Command;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
+/// Size limit of the cache manager, past which the oldest resolutions are dropped
+const RESOLUTION_CACHE_MB: u64 = 10;
+/// How long a dependency resolution is reused
+const RESOLUTION_CACHE_HOURS: u64 = 24;
+
 #[derive(Debug)]
 pub struct Manager {
   pub loader: DefinitionLoader,
   config: Config,
   installed_cache: HashMap<String, InstallStatus>,
+  state: StateDb,
+  build_options: Option<BuildOptions>,
+  config_conflict: ConfigConflict,
+  variables: HashMap<String, String>,
+  scope: Option<InstallScope>,
+  root: Option<PathBuf>,
+  platform: Platform,
+  runner: Arc<dyn CommandRunner>,
+  reinstall: bool,
+  /// `path=value` overrides applied to definitions for this run, by package
+  overrides: HashMap<String, Vec<String>>,
+  /// Cached dependency resolutions, read on the first resolution
+  resolution_cache: Option<CacheManager>,
 }
 
 impl Manager {
   pub async fn new(config: &Config) -> Result<Self> {
+      Self::with_runner(config, Arc::new(SystemRunner)).await
+  }
+  
+  /// Load the definitions and check what is installed, running every command (pacman
+  /// queries as well as installs) through `runner`, retried while another pacman holds
+  /// the database lock
+  pub async fn with_runner(config: &Config, runner: Arc<dyn CommandRunner>) -> Result<Self> {
       let mut loader = DefinitionLoader::new();
       
       for path in &config.package_paths {
+          // Relative paths like ./data/packages only exist when run from a checkout, and
+          // project commands run from anywhere
+          if !path.exists() {
+              debug!("Skipping missing package directory: {}", path.display());
+              continue;
+          }
+          
           info!("Loading packages from: {}", path.display());
           loader.load_from_directory(path).await?;
       }
       
+      let remote_cache = super::remote::definitions_cache_path();
+      if remote_cache.exists() {
+          info!("Loading remote packages from: {}", remote_cache.display());
+          if let Err(e) = loader.load_definition_file(&remote_cache).await {
+              warn!("Ignoring cached remote definitions: {}", e);
+          }
+      }
+      
       let mut manager = Self {
           loader,
           config: config.clone(),
           installed_cache: HashMap::new(),
+          state: StateDb::load()?,
+          build_options: None,
+          config_conflict: ConfigConflict::default(),
+          variables: HashMap::new(),
+          scope: None,
+          root: None,
+          platform: Platform::current(),
+          runner: Arc::new(LockWaitRunner::new(runner, Duration::from_secs(config.installation.lock_wait)).with_aur_helper(config.aur_helper.clone())),
+          reinstall: false,
+          overrides: HashMap::new(),
+          resolution_cache: None,
       };
       
       manager.refresh_installed_cache().await?;
@@ -77,7 +139,26 @@ impl Manager {
       Ok(packages)
   }
   
-  pub async fn resolve_packages(&self, package_names: &[String]) -> Result<Vec<Package>> {
+  /// The packages to install for `package_names`, dependencies first. Closures are kept in
+  /// the cache manager, so resolving the same names against the same definitions again
+  /// skips the recursive walk.
+  pub async fn resolve_packages(&mut self, package_names: &[String]) -> Result<Vec<Package>> {
+      let key = self.resolution_key(package_names);
+      if self.resolution_cache.is_none() {
+          match CacheManager::new(crate::cache::default_dir(), RESOLUTION_CACHE_MB, RESOLUTION_CACHE_HOURS).await {
+              Ok(cache) => self.resolution_cache = Some(cache),
+              Err(e) => debug!("Not caching dependency resolution: {}", e),
+          }
+      }
+      
+      let cached = self.resolution_cache.as_ref().and_then(|cache| cache.get_resolution(&key));
+      if let Some(names) = cached {
+          let packages: Option<Vec<Package>> = names.iter().map(|name| self.loader.get_package(name).cloned()).collect();
+          if let Some(packages) = packages {
+              return Ok(packages);
+          }
+      }
+      
       let mut resolved = Vec::new();
       let mut visited = HashSet::new();
       let mut visiting = HashSet::new();
@@ -86,9 +167,41 @@ impl Manager {
           self.resolve_package_recursive(name, &mut resolved, &mut visited, &mut visiting)?;
       }
       
+      if let Some(cache) = &mut self.resolution_cache {
+          let names = resolved.iter().map(|package| package.name.clone()).collect();
+          if let Err(e) = cache.store_resolution(key, names).await {
+              debug!("Cannot cache dependency resolution: {}", e);
+          }
+      }
+      
       Ok(resolved)
   }
   
+  /// Hash of the requested names and everything their resolution depends on: the
+  /// definitions, the platform, chosen alternatives and which packages ArchBox installed
+  fn resolution_key(&self, package_names: &[String]) -> String {
+      let mut hasher = Sha256::new();
+      hasher.update(env!("CARGO_PKG_VERSION"));
+      for name in package_names {
+          hasher.update([0]);
+          hasher.update(name);
+      }
+      hasher.update(format!("\0{:?}\0", self.platform));
+      hasher.update(self.loader.definitions_hash());
+      
+      for (capability, provider) in self.state.alternatives() {
+          hasher.update(format!("\0{}={}", capability, provider));
+      }
+      let mut installed: Vec<&String> = self.state.packages().keys().collect();
+      installed.sort();
+      for name in installed {
+          hasher.update([0]);
+          hasher.update(name);
+      }
+      
+      format!("{:x}", hasher.finalize())
+  }
+  
   fn resolve_package_recursive(
       &self,
       name: &str,
@@ -104,19 +217,29 @@ impl Manager {
           return Err(Error::Dependency(format!("Circular dependency detected: {}", name)));
       }
       
+      let provider = self.resolve_name(name)?;
+      if provider != name {
+          return self.resolve_package_recursive(&provider, resolved, visited, visiting);
+      }
+      
       let package = self.loader.get_package(name)
-          .ok_or_else(|| Error::PackageNotFound(name.to_string()))?;
+          .ok_or_else(|| self.loader.not_found(name))?;
+      
+      if !package.supports(&self.platform) {
+          return Err(package.unsupported_error(&self.platform));
+      }
       
       visiting.insert(name.to_string());
       
       // Resolve dependencies first
       for dep in &package.dependencies {
-          if dep.optional {
-              continue; // Skip optional dependencies for now
+          if dep.optional || !dep.applies_to(&self.platform) {
+              continue; // Skip optional dependencies for now, and ones for other platforms
           }
           
           match dep.dep_type {
               DependencyType::Package => {
+                  self.check_version_constraint(package, dep)?;
                   self.resolve_package_recursive(&dep.name, resolved, visited, visiting)?;
               }
               DependencyType::System => {
@@ -137,22 +260,401 @@ impl Manager {
       Ok(())
   }
   
-  /// Install a package
-  pub async fn install_package(&mut self, package: &Package, force: bool) -> Result<()> {
+  /// Optional package dependencies of `packages` that installing them could add: defined,
+  /// available on this platform, not installed and not among `packages` already. Each comes
+  /// with the name of the first package that suggests it.
+  pub async fn optional_dependencies(&self, packages: &[Package]) -> Result<Vec<(Package, String)>> {
+      let mut optional: Vec<(Package, String)> = Vec::new();
+      
+      for package in packages {
+          for dep in &package.dependencies {
+              if !dep.optional || !matches!(dep.dep_type, DependencyType::Package) || !dep.applies_to(&self.platform) {
+                  continue;
+              }
+              let Some(dependency) = self.resolve_name(&dep.name).ok().and_then(|name| self.loader.get_package(&name)) else {
+                  debug!("Optional dependency {} of {} is not available", dep.name, package.name);
+                  continue;
+              };
+              
+              let listed = packages.iter().chain(optional.iter().map(|(listed, _)| listed)).any(|listed| listed.name == dependency.name);
+              if listed || !dependency.supports(&self.platform) || self.is_installed(&dependency.name).await? {
+                  continue;
+              }
+              optional.push((dependency.clone(), package.name.clone()));
+          }
+      }
+      
+      Ok(optional)
+  }
+  
+  /// Packages that provide `capability`, sorted by name
+  pub fn providers(&self, capability: &str) -> Vec<&Package> {
+      let mut providers: Vec<&Package> = self.loader.packages()
+          .values()
+          .filter(|package| package.provides.iter().any(|provided| provided == capability))
+          .collect();
+      providers.sort_by(|a, b| a.name.cmp(&b.name));
+      providers
+  }
+  
+  /// Every capability some package provides, sorted
+  pub fn capabilities(&self) -> Vec<String> {
+      let capabilities: std::collections::BTreeSet<&String> = self.loader.packages()
+          .values()
+          .flat_map(|package| &package.provides)
+          .collect();
+      capabilities.into_iter().cloned().collect()
+  }
+  
+  /// The package a name refers to: the package itself, or the provider of a capability.
+  /// A capability resolves to the chosen alternative, then an installed provider, then its
+  /// only provider; with several candidates the user has to choose.
+  pub fn resolve_name(&self, name: &str) -> Result<String> {
+      if self.loader.get_package(name).is_some() {
+          return Ok(name.to_string());
+      }
+      
+      let mut providers = self.providers(name);
+      if providers.is_empty() {
+          return Err(self.loader.not_found(name));
+      }
+      
+      providers.retain(|package| package.supports(&self.platform));
+      if providers.is_empty() {
+          return Err(Error::UnsupportedPlatform {
+              package: name.to_string(),
+              arch: self.platform.arch.clone(),
+              supported: self.providers(name)
+                  .iter()
+                  .flat_map(|package| package.platforms.iter().map(String::as_str))
+                  .collect::<Vec<_>>()
+                  .join(", "),
+          });
+      }
+      
+      if let Some(chosen) = self.state.alternative(name) {
+          if providers.iter().any(|package| package.name == chosen) {
+              return Ok(chosen.to_string());
+          }
+          warn!("Alternative {} for {} no longer provides it or is unavailable on {}", chosen, name, self.platform.arch);
+      }
+      
+      if let Some(installed) = providers.iter().find(|package| self.state.get(&package.name).is_some()) {
+          return Ok(installed.name.clone());
+      }
+      
+      match providers.as_slice() {
+          [only] => Ok(only.name.clone()),
+          _ => Err(Error::Dependency(format!(
+              "Several packages provide {} ({}); choose one with `archbox alternatives set {} <package>`",
+              name,
+              providers.iter().map(|package| package.name.as_str()).collect::<Vec<_>>().join(", "),
+              name
+          ))),
+      }
+  }
+  
+  /// Package to migrate an install of `package_name` to: the successor it was deprecated in
+  /// favor of, or else a package declaring that it `replaces` it
+  pub fn replacement(&self, package_name: &str) -> Option<&Package> {
+      let successor = self.loader.get_package(package_name)
+          .and_then(|package| package.deprecated.as_ref())
+          .and_then(|deprecation| deprecation.successor.as_deref())
+          .and_then(|successor| self.resolve_name(successor).ok())
+          .and_then(|successor| self.loader.get_package(&successor));
+      
+      successor
+          .or_else(|| {
+              let mut replacing: Vec<&Package> = self.loader.packages()
+                  .values()
+                  .filter(|package| package.replaces.iter().any(|replaced| replaced == package_name))
+                  .filter(|package| package.supports(&self.platform))
+                  .collect();
+              replacing.sort_by(|a, b| a.name.cmp(&b.name));
+              replacing.into_iter().next()
+          })
+          .filter(|package| package.name != package_name)
+  }
+  
+  /// Resolve packages for another machine, e.g. cloud-init user-data for an aarch64 VM
+  pub fn set_platform(&mut self, platform: Platform) {
+      self.platform = platform;
+  }
+  
+  /// Whether a package can be installed on the platform being resolved for. Unknown
+  /// packages count as supported, so the caller reports them as missing instead.
+  pub fn is_supported(&self, package_name: &str) -> bool {
+      self.loader.get_package(package_name).is_none_or(|package| package.supports(&self.platform))
+  }
+  
+  /// Provider chosen for a capability with `archbox alternatives set`
+  pub fn alternative(&self, capability: &str) -> Option<&str> {
+      self.state.alternative(capability)
+  }
+  
+  /// Record the provider to use for a capability, or forget the choice with `None`
+  pub fn set_alternative(&mut self, capability: &str, provider: Option<&str>) -> Result<()> {
+      self.update_state(|state| state.set_alternative(capability, provider))
+  }
+  
+  /// Change the installed package database and save it. It is read again first, so what
+  /// another process recorded since this one loaded it is kept rather than overwritten.
+  fn update_state<R>(&mut self, change: impl FnOnce(&mut StateDb) -> R) -> Result<R> {
+      self.state = StateDb::load()?;
+      let result = change(&mut self.state);
+      self.state.save()?;
+      Ok(result)
+  }
+  
+  /// Read the installed package database and installed versions again, picking up what
+  /// other processes changed. Long-running processes call it once they hold the
+  /// transaction lock.
+  pub async fn reload_state(&mut self) -> Result<()> {
+      self.state = StateDb::load()?;
+      self.refresh_installed_cache().await
+  }
+  
+  /// Fail when the definition a dependency resolves to doesn't satisfy its `version`
+  fn check_version_constraint(&self, package: &Package, dep: &crate::package::Dependency) -> Result<()> {
+      let Some(constraint) = &dep.version else {
+          return Ok(());
+      };
+      let Some(target) = self.resolve_name(&dep.name).ok().and_then(|name| self.loader.get_package(&name)) else {
+          // Reported as missing when the dependency itself is resolved
+          return Ok(());
+      };
+      
+      let satisfied = crate::package::version::satisfies(constraint, &target.version)
+          .map_err(|e| Error::Config(format!("Package {} has an invalid version for {}: {}", package.name, dep.name, e)))?;
+      if !satisfied {
+          return Err(Error::Dependency(format!(
+              "{} requires {} {}, but the definition is {} {}",
+              package.name, dep.name, constraint, target.name, target.version
+          )));
+      }
+      
+      Ok(())
+  }
+  
+  /// Packages in the repository that directly require `package_name`, by name or through a
+  /// capability it provides, sorted by name
+  pub fn dependents(&self, package_name: &str) -> Vec<&Package> {
+      let mut dependents: Vec<&Package> = self.loader
+          .packages()
+          .values()
+          .filter(|package| {
+              package.dependencies.iter().any(|dep| {
+                  !dep.optional
+                      && matches!(dep.dep_type, DependencyType::Package)
+                      && (dep.name == package_name || self.resolve_name(&dep.name).is_ok_and(|provider| provider == package_name))
+              })
+          })
+          .collect();
+      
+      dependents.sort_by(|a, b| a.name.cmp(&b.name));
+      dependents
+  }
+  
+  /// Installed packages that directly require `package_name`
+  pub fn installed_dependents(&self, package_name: &str) -> Vec<&Package> {
+      let mut dependents = self.dependents(package_name);
+      dependents.retain(|package| {
+          matches!(
+              self.installed_cache.get(&package.name),
+              Some(InstallStatus::Installed { .. })
+          )
+      });
+      dependents
+  }
+  
+  /// Expand a removal set with every installed package that transitively depends on it.
+  /// Dependents are ordered before the packages they require.
+  pub fn removal_cascade(&self, package_names: &[String]) -> Vec<Package> {
+      let mut ordered = Vec::new();
+      let mut visited = HashSet::new();
+      
+      for name in package_names {
+          self.collect_dependents(name, &mut ordered, &mut visited);
+      }
+      
+      ordered
+  }
+  
+  fn collect_dependents(&self, name: &str, ordered: &mut Vec<Package>, visited: &mut HashSet<String>) {
+      if !visited.insert(name.to_string()) {
+          return;
+      }
+      
+      for dependent in self.installed_dependents(name) {
+          self.collect_dependents(&dependent.name, ordered, visited);
+      }
+      
+      if let Some(package) = self.loader.get_package(name) {
+          ordered.push(package.clone());
+      }
+  }
+  
+  /// Whether an installed package was installed explicitly. Packages ArchBox has no record
+  /// of (e.g. pacman packages installed by hand) count as explicit.
+  pub fn is_explicit(&self, package_name: &str) -> bool {
+      self.state
+          .get(package_name)
+          .is_none_or(|record| record.reason == InstallReason::Explicit)
+  }
+  
+  /// Shortest dependency chains from each explicitly installed package down to
+  /// `package_name`, e.g. `[neovim, telescope, ripgrep]`. Empty for an orphaned dependency.
+  pub fn dependency_chains(&self, package_name: &str) -> Vec<Vec<String>> {
+      let mut parents: HashMap<String, String> = HashMap::new();
+      let mut queue = VecDeque::from([package_name.to_string()]);
+      let mut roots = Vec::new();
+      
+      // Walk up from the package through installed dependents, breadth first
+      while let Some(name) = queue.pop_front() {
+          for dependent in self.installed_dependents(&name) {
+              if dependent.name == package_name || parents.contains_key(&dependent.name) {
+                  continue;
+              }
+              
+              parents.insert(dependent.name.clone(), name.clone());
+              
+              // Stop at explicit packages; anything above them is not needed to explain this one
+              if self.is_explicit(&dependent.name) {
+                  roots.push(dependent.name.clone());
+              } else {
+                  queue.push_back(dependent.name.clone());
+              }
+          }
+      }
+      
+      roots
+          .into_iter()
+          .map(|root| {
+              let mut chain = vec![root.clone()];
+              let mut current = root;
+              while let Some(parent) = parents.get(&current) {
+                  chain.push(parent.clone());
+                  current = parent.clone();
+              }
+              chain
+          })
+          .collect()
+  }
+  
+  /// Override the configured source build options for subsequent installs
+  pub fn set_build_options(&mut self, build_options: BuildOptions) {
+      self.build_options = Some(build_options);
+  }
+  
+  /// Set how subsequent installs handle changes to existing config files
+  pub fn set_config_conflict(&mut self, config_conflict: ConfigConflict) {
+      self.config_conflict = config_conflict;
+  }
+  
+  /// Add template variables (e.g. from a profile) for subsequent installs
+  pub fn set_variables(&mut self, variables: &HashMap<String, String>) {
+      self.variables.extend(variables.clone());
+  }
+  
+  /// Install subsequent packages for the current user or system-wide (`--user`/`--system`)
+  pub fn set_scope(&mut self, scope: Option<InstallScope>) {
+      self.scope = scope;
+  }
+  
+  /// Install subsequent packages into the system mounted at `root` instead of this one.
+  /// Nothing is recorded in this machine's state for them.
+  pub fn set_root(&mut self, root: &Path) {
+      self.root = Some(root.to_path_buf());
+  }
+  
+  /// Make subsequent installs start from scratch instead of reusing what is already there
+  /// (see [`Installer::with_reinstall`](crate::package::installer::Installer::with_reinstall))
+  pub fn set_reinstall(&mut self, reinstall: bool) {
+      self.reinstall = reinstall;
+  }
+  
+  /// Change fields of `name`'s definition for this run (`install --set`). Installs of it
+  /// record the overrides in their provenance.
+  pub fn override_definition(&mut self, name: &str, overrides: &[String]) -> Result<()> {
+      self.loader.override_fields(name, overrides)?;
+      self.overrides.entry(name.to_string()).or_default().extend_from_slice(overrides);
+      Ok(())
+  }
+  
+  /// The runner commands go through, for work outside the installer such as removals
+  pub fn runner(&self) -> Arc<dyn CommandRunner> {
+      Arc::clone(&self.runner)
+  }
+  
+  /// Scope an installed package was installed in. Records that predate scopes fall back to
+  /// the definition's default.
+  pub fn installed_scope(&self, package_name: &str) -> InstallScope {
+      self.state
+          .get(package_name)
+          .and_then(|record| record.scope)
+          .or_else(|| self.loader.get_package(package_name).map(|package| package.install_scope(None)))
+          .unwrap_or_default()
+  }
+  
+  /// Install a package, recording the step in the transaction log. `explicit` marks it as
+  /// requested by the user rather than pulled in as a dependency.
+  pub async fn install_package(&mut self, package: &Package, force: bool, explicit: bool) -> Result<()> {
+      let started = std::time::Instant::now();
+      let result = self.install_package_inner(package, force, explicit).await;
+      
+      crate::history::record_step("install", Some(&package.name), Some(&package.version), started.elapsed(), &result);
+      result
+  }
+  
+  async fn install_package_inner(&mut self, package: &Package, force: bool, explicit: bool) -> Result<()> {
       info!("Installing package: {}", package.name);
       
-      if !force {
+      // What is installed here says nothing about the target root
+      if !force && self.root.is_none() {
           if let Some(InstallStatus::Installed { .. }) = self.installed_cache.get(&package.name) {
               warn!("Package {} is already installed", package.name);
+              if explicit {
+                  self.update_state(|state| state.mark_explicit(&package.name))?;
+              }
               return Ok(());
           }
       }
       
-      self.install_system_dependencies(package).await?;
+      self.check_requirements(package)?;
+      let scope = self.scope.or_else(|| self.state.get(&package.name).and_then(|record| record.scope));
       
-      let installer = crate::package::installer::Installer::new(&self.config);
-      installer.install(package).await?;
+      let mut outcome = if crate::simulate::is_enabled() {
+          crate::simulate::install(package, &self.config, &self.platform, scope)?
+      } else {
+          self.install_system_dependencies(package).await?;
+          
+          let mut installer = crate::package::installer::Installer::new(&self.config)
+              .with_config_conflict(self.config_conflict)
+              .with_variables(&self.variables)
+              .with_scope(scope)
+              .with_reinstall(self.reinstall)
+              .with_runner(self.runner());
+          if let Some(build_options) = self.build_options {
+              installer = installer.with_build_options(build_options);
+          }
+          if let Some(root) = &self.root {
+              installer = installer.with_root(root);
+          }
+          installer.install(package).await?
+      };
+      
+      if let Some(root) = &self.root {
+          info!("Installed package {} into {}", package.name, root.display());
+          return Ok(());
+      }
       
+      if let (Some(provenance), Some(overrides)) = (&mut outcome.provenance, self.overrides.get(&package.name)) {
+          provenance.overrides = overrides.clone();
+      }
+      self.update_state(|state| state.record_install(package, &outcome, explicit))?;
+      
+      super::status_cache::forget(&package.name)?;
+      super::pending_updates::forget(&package.name)?;
       self.installed_cache.insert(
           package.name.clone(),
           InstallStatus::Installed {
@@ -165,9 +667,33 @@ impl Manager {
       Ok(())
   }
   
+  /// Refuse to install a package whose `requires` block this machine doesn't meet
+  fn check_requirements(&self, package: &Package) -> Result<()> {
+      let Some(requires) = &package.requires else {
+          return Ok(());
+      };
+      
+      let prefix = package.install_scope(self.scope).prefix();
+      let install_dir = match &self.root {
+          Some(root) => root.join(prefix.strip_prefix("/").unwrap_or(&prefix)),
+          None => prefix,
+      };
+      
+      let unmet = requires.unmet(&install_dir, self.root.as_deref())?;
+      if !unmet.is_empty() {
+          return Err(Error::InstallationFailed(format!(
+              "{} can't be installed here: {}",
+              package.name,
+              unmet.join("; ")
+          )));
+      }
+      
+      Ok(())
+  }
+  
   async fn install_system_dependencies(&self, package: &Package) -> Result<()> {
       let system_deps: Vec<&str> = package
-          .get_dependencies(DependencyType::System)
+          .get_dependencies(DependencyType::System, &self.platform)
           .iter()
           .map(|dep| dep.name.as_str())
           .collect();
@@ -178,11 +704,14 @@ impl Manager {
       
       info!("Installing system dependencies: {:?}", system_deps);
       
-      let mut cmd = Command::new("pacman");
-      cmd.args(&["-S", "--needed", "--noconfirm"])
+      let mut cmd = crate::privilege::privileged("pacman");
+      if let Some(root) = &self.root {
+          cmd.arg("--sysroot").arg(root);
+      }
+      cmd.args(["-S", "--needed", "--noconfirm"])
           .args(&system_deps);
       
-      let output = cmd.output().await?;
+      let output = self.runner.output(&cmd).await?;
       
       if !output.status.success() {
           return Err(Error::CommandFailed {
@@ -196,6 +725,68 @@ impl Manager {
       Ok(())
   }
   
+  /// Get the recorded installation state of a package, if ArchBox installed it
+  pub fn installed_record(&self, package_name: &str) -> Option<&InstalledPackage> {
+      self.state.get(package_name)
+  }
+  
+  /// Groups post-install added the current user to that this login session doesn't have
+  /// yet. They only take effect after logging out and back in.
+  pub async fn pending_relogin_groups(&self) -> Result<Vec<String>> {
+      let user = crate::package::current_username();
+      let mut added: Vec<&str> = self.state
+          .packages()
+          .values()
+          .flat_map(|record| &record.post_install)
+          .filter_map(|action| match action {
+              PostInstallAction::UserGroup { user: member, group } if *member == user => Some(group.as_str()),
+              _ => None,
+          })
+          .collect();
+      if added.is_empty() {
+          return Ok(Vec::new());
+      }
+      added.sort_unstable();
+      added.dedup();
+      
+      // Without a user, `id` lists the groups of this process, i.e. of the login session
+      let output = self.runner.output(CommandSpec::new("id").arg("-nG")).await?;
+      let session = String::from_utf8_lossy(&output.stdout).to_string();
+      let session: HashSet<&str> = session.split_whitespace().collect();
+      
+      Ok(added.into_iter().filter(|group| !session.contains(group)).map(str::to_string).collect())
+  }
+  
+  /// Recorded state of every package ArchBox installed, sorted by name
+  pub fn installed_records(&self) -> Vec<&InstalledPackage> {
+      let mut records: Vec<&InstalledPackage> = self.state.packages().values().collect();
+      records.sort_by(|a, b| a.name.cmp(&b.name));
+      records
+  }
+  
+  /// Mark a package as removed, returning its last recorded state
+  pub fn record_removal(&mut self, package_name: &str) -> Result<Option<InstalledPackage>> {
+      self.installed_cache.insert(package_name.to_string(), InstallStatus::NotInstalled);
+      super::status_cache::forget(package_name)?;
+      super::pending_updates::forget(package_name)?;
+      
+      let record = self.update_state(|state| state.remove(package_name))?;
+      crate::drift::forget(package_name)?;
+      
+      Ok(record)
+  }
+  
+  /// Record that an installed package was switched to another version
+  pub fn record_version(&mut self, package_name: &str, version: &str) -> Result<()> {
+      self.update_state(|state| state.set_version(package_name, version))?;
+      
+      if let Some(InstallStatus::Installed { version: cached, .. }) = self.installed_cache.get_mut(package_name) {
+          *cached = version.to_string();
+      }
+      
+      Ok(())
+  }
+  
   pub async fn is_installed(&self, package_name: &str) -> Result<bool> {
       Ok(matches!(
           self.installed_cache.get(package_name),
@@ -203,17 +794,77 @@ impl Manager {
       ))
   }
   
+  /// Status of each of `names` from the checks made when the manager was created, so a
+  /// whole listing costs no further pacman calls. Packages ArchBox installed at an older
+  /// version than their definition have an update available, and packages whose check
+  /// failed carry the error.
+  pub async fn statuses(&self, names: &[&str]) -> HashMap<String, InstallStatus> {
+      names
+          .iter()
+          .map(|&name| {
+              let status = match self.installed_cache.get(name) {
+                  Some(status @ InstallStatus::Installed { .. }) => {
+                      let recorded = self.state.get(name).map(|record| record.version.as_str());
+                      match (recorded, self.loader.get_package(name)) {
+                          (Some(current), Some(package)) if vercmp(&package.version, current).is_gt() => {
+                              InstallStatus::UpdateAvailable {
+                                  current: current.to_string(),
+                                  available: package.version.clone(),
+                              }
+                          }
+                          _ => status.clone(),
+                      }
+                  }
+                  Some(status) => status.clone(),
+                  None => InstallStatus::NotInstalled,
+              };
+              (name.to_string(), status)
+          })
+          .collect()
+  }
+  
+  /// Whether a system (pacman) dependency is installed
+  pub async fn is_system_installed(&self, name: &str) -> Result<bool> {
+      let output = self.runner.output(CommandSpec::new("pacman").args(["-Q", name])).await?;
+      
+      Ok(output.status.success())
+  }
+  
   async fn refresh_installed_cache(&mut self) -> Result<()> {
       debug!("Refreshing installed package cache");
       
-      // This is a simplified implementation
-      // In practice, you'd want to check various installation methods
+      // Only pacman packages are probed; recent probes are reused between runs
+      let mut persisted = super::status_cache::StatusCache::load();
+      let mut probed = false;
       
       for package in self.loader.packages().values() {
-          let status = self.check_package_status(package).await?;
+          let pacman = matches!(package.installation, crate::package::Installation::Pacman { .. }) && !crate::simulate::is_enabled();
+          let status = match persisted.get(&package.name).filter(|_| pacman) {
+              Some(status) => status,
+              // A failed check is reported for that package and tried again next run
+              None => match self.check_package_status(package).await {
+                  Ok(status) => {
+                      if pacman {
+                          persisted.insert(&package.name, status.clone());
+                          probed = true;
+                      }
+                      status
+                  }
+                  Err(e) => {
+                      warn!("Could not check whether {} is installed: {}", package.name, e);
+                      InstallStatus::Error { message: e.to_string() }
+                  }
+              },
+          };
           self.installed_cache.insert(package.name.clone(), status);
       }
       
+      if probed {
+          if let Err(e) = persisted.save() {
+              warn!("Failed to save the installed status cache: {}", e);
+          }
+      }
+      
       Ok(())
   }
   
@@ -222,12 +873,10 @@ impl Manager {
       // Implementation depends on installation method
       // This is a simplified version
       match &package.installation {
-          crate::package::Installation::Pacman { packages, .. } => {
+          // A simulated system only has what the state database records
+          crate::package::Installation::Pacman { packages, .. } if !crate::simulate::is_enabled() => {
               for pkg in packages {
-                  let output = Command::new("pacman")
-                      .args(&["-Q", pkg])
-                      .output()
-                      .await?;
+                  let output = self.runner.output(CommandSpec::new("pacman").args(["-Q", pkg])).await?;
                   
                   if output.status.success() {
                       let version_info = String::from_utf8_lossy(&output.stdout);
@@ -246,8 +895,14 @@ impl Manager {
               Ok(InstallStatus::NotInstalled)
           }
           _ => {
-              // For other installation methods, implement specific checks
-              Ok(InstallStatus::NotInstalled)
+              // Other methods are tracked through the state database
+              Ok(match self.state.get(&package.name) {
+                  Some(record) => InstallStatus::Installed {
+                      version: record.version.clone(),
+                      installed_at: record.installed_at.clone(),
+                  },
+                  None => InstallStatus::NotInstalled,
+              })
           }
       }
   }
@@ -0,0 +1,160 @@
+//! Mirrors for package definitions and downloaded artifacts
+//!
+//! Definitions can be fetched from `repository.update_url` or any of `repository.mirrors`.
+//! Artifact downloads whose URL starts with a key of `installation.artifact_mirrors` can
+//! also come from each mirror prefix listed for it. Candidates are tried in the order
+//! saved by `archbox benchmark --save`, falling back to the next one when a download fails.
+
+use crate::config::{Config, RepositoryConfig};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// One place a file can be downloaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// What the ranking is keyed by: the definitions URL, or the artifact mirror prefix
+    pub endpoint: String,
+    pub url: String,
+}
+
+/// Endpoints ordered fastest first, as measured by `archbox benchmark`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ranking {
+    /// Unix time of the measurement
+    #[serde(default)]
+    pub measured_at: u64,
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+}
+
+impl Ranking {
+    pub fn load() -> Self {
+        std::fs::read_to_string(ranking_path())
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = ranking_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_yaml::to_string(self)?)?;
+        Ok(path)
+    }
+
+    /// Sort candidates by rank; unranked ones keep their order after the ranked ones
+    pub fn order(&self, candidates: &mut [Candidate]) {
+        candidates.sort_by_key(|candidate| {
+            self.endpoints
+                .iter()
+                .position(|endpoint| *endpoint == candidate.endpoint)
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+/// File the ranking is persisted in
+pub fn ranking_path() -> PathBuf {
+    crate::config::get_state_dir().join("mirrors.yaml")
+}
+
+/// The definitions URL followed by its mirrors, unranked
+pub fn definition_endpoints(repository: &RepositoryConfig) -> Vec<Candidate> {
+    repository
+        .update_url
+        .iter()
+        .chain(&repository.mirrors)
+        .map(|url| Candidate { endpoint: url.clone(), url: url.clone() })
+        .collect()
+}
+
+/// The artifact URL itself followed by the same file on each configured mirror, unranked
+pub fn artifact_endpoints(url: &str, mirrors: &HashMap<String, Vec<String>>) -> Vec<Candidate> {
+    let mut candidates = vec![Candidate { endpoint: origin(url), url: url.to_string() }];
+
+    for (upstream, prefixes) in mirrors {
+        let Some(path) = url.strip_prefix(upstream.as_str()) else {
+            continue;
+        };
+        for prefix in prefixes {
+            candidates.push(Candidate {
+                endpoint: prefix.clone(),
+                url: format!("{}{}", prefix, path),
+            });
+        }
+    }
+
+    candidates
+}
+
+/// Where to fetch definitions from, best first
+pub fn definition_candidates(repository: &RepositoryConfig) -> Vec<Candidate> {
+    let mut candidates = definition_endpoints(repository);
+    Ranking::load().order(&mut candidates);
+    candidates
+}
+
+/// Where to download an artifact from, best first
+pub fn artifact_candidates(url: &str, config: &Config) -> Vec<Candidate> {
+    let mut candidates = artifact_endpoints(url, &config.installation.artifact_mirrors);
+    Ranking::load().order(&mut candidates);
+    candidates
+}
+
+/// `scheme://host` of a URL, used to rank the upstream of an artifact
+fn origin(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let host = rest.split('/').next().unwrap_or(rest);
+    format!("{}://{}/", scheme, host)
+}
+
+/// Result of measuring one endpoint
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub endpoint: String,
+    /// Best time until response headers arrived
+    pub latency: Duration,
+    /// Bytes per second while reading the body
+    pub throughput: f64,
+    pub bytes: u64,
+}
+
+/// Download up to `limit` bytes of `url` `samples` times and keep the best latency and
+/// throughput
+pub async fn measure(client: &reqwest::Client, candidate: &Candidate, samples: usize, limit: u64) -> Result<Measurement> {
+    let mut latency = Duration::MAX;
+    let mut throughput: f64 = 0.0;
+    let mut bytes = 0;
+
+    for _ in 0..samples.max(1) {
+        let started = Instant::now();
+        let mut response = client.get(&candidate.url).send().await?.error_for_status()?;
+        latency = latency.min(started.elapsed());
+
+        let body_started = Instant::now();
+        let mut read = 0u64;
+        while let Some(chunk) = response.chunk().await? {
+            read += chunk.len() as u64;
+            if read >= limit {
+                break;
+            }
+        }
+        let elapsed = body_started.elapsed().as_secs_f64().max(f64::EPSILON);
+        throughput = throughput.max(read as f64 / elapsed);
+        bytes = read;
+    }
+
+    Ok(Measurement {
+        endpoint: candidate.endpoint.clone(),
+        latency,
+        throughput,
+        bytes,
+    })
+}
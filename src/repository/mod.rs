@@ -1,2 +1,7 @@
 pub mod manager;
+pub mod mirrors;
+pub mod pending_updates;
+pub mod remote;
+pub mod status_cache;
+
 pub use manager::*;
\ No newline at end of file
@@ -0,0 +1,71 @@
+//! Updates found by the last check, kept for `archbox status`
+//!
+//! Finding outdated packages loads every definition and checks what is installed, which is
+//! far too slow for a shell prompt. Every check writes what it found into the cache
+//! directory, installs and removals drop the packages they change, and `archbox status`
+//! only reads the file.
+
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// An installed package whose definition has a newer version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingUpdate {
+    pub name: String,
+    pub installed: String,
+    pub available: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PendingUpdates {
+    /// When the last check ran; none before the first one
+    pub checked_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub packages: Vec<PendingUpdate>,
+}
+
+impl PendingUpdates {
+    /// What the last check found, or nothing when no check ran or the file can't be read
+    pub fn load() -> Self {
+        std::fs::read_to_string(cache_path())
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self) -> Result<()> {
+        let path = cache_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Replace what the last check found with `packages`
+pub fn record(packages: Vec<PendingUpdate>) -> Result<()> {
+    PendingUpdates {
+        checked_at: Some(Utc::now()),
+        packages,
+    }
+    .write()
+}
+
+/// Drop a package ArchBox just installed or removed, since its update is no longer pending
+pub fn forget(package: &str) -> Result<()> {
+    let mut pending = PendingUpdates::load();
+    let count = pending.packages.len();
+    pending.packages.retain(|update| update.name != package);
+    if pending.packages.len() != count {
+        pending.write()?;
+    }
+    Ok(())
+}
+
+/// File the last check is persisted in
+pub fn cache_path() -> PathBuf {
+    crate::config::get_cache_dir().join("pending-updates.yaml")
+}
@@ -0,0 +1,69 @@
+//! Remote package definition updates
+
+use super::mirrors;
+use crate::config::RepositoryConfig;
+use crate::{Error, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Local copy of the most recently downloaded remote definitions
+pub fn definitions_cache_path() -> PathBuf {
+    crate::config::get_config_dir().join("cache").join("remote_packages.yaml")
+}
+
+/// Time since remote definitions were last downloaded, if they ever were
+pub fn definitions_age() -> Option<Duration> {
+    let modified = std::fs::metadata(definitions_cache_path())
+        .and_then(|meta| meta.modified())
+        .ok()?;
+
+    modified.elapsed().ok()
+}
+
+/// Whether remote definitions are older than `interval_hours` (or were never downloaded)
+pub fn definitions_stale(interval_hours: u64) -> bool {
+    definitions_age().is_none_or(|age| age >= Duration::from_secs(interval_hours * 3600))
+}
+
+/// Download remote definitions into the local cache from the best reachable mirror,
+/// returning the cache file path
+pub async fn fetch_definitions(repository: &RepositoryConfig) -> Result<PathBuf> {
+    let mut last_error = Error::Config("No update_url configured for package definitions".to_string());
+
+    for candidate in mirrors::definition_candidates(repository) {
+        match fetch_from(&candidate.url).await {
+            Ok(path) => return Ok(path),
+            Err(e) => {
+                warn!("Fetching definitions from {} failed: {}", candidate.url, e);
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+async fn fetch_from(update_url: &str) -> Result<PathBuf> {
+    debug!("Fetching package definitions from {}", update_url);
+
+    let client = crate::http::client()?;
+    let response = client.get(update_url).send().await?;
+
+    if !response.status().is_success() {
+        return Err(Error::Config(format!(
+            "Failed to download package definitions: HTTP {}",
+            response.status()
+        )));
+    }
+
+    let content = response.text().await?;
+
+    let cache_file = definitions_cache_path();
+    if let Some(parent) = cache_file.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&cache_file, content).await?;
+
+    Ok(cache_file)
+}
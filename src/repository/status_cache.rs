@@ -0,0 +1,112 @@
+//! Installed status of pacman packages, kept between runs
+//!
+//! Checking whether a pacman package is installed costs a `pacman -Q` per definition, which
+//! quick commands like `search` and `list` would otherwise pay on every run. Results are
+//! reused for [`TTL`], and never once pacman's local database changed after they were taken.
+//! `--refresh` ignores them for one run. Other installation methods are tracked in the state
+//! database and are not cached.
+
+use crate::package::InstallStatus;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a result is trusted when pacman's database hasn't changed
+pub const TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Directory pacman adds and removes a subdirectory in for every package it changes
+const PACMAN_LOCAL_DB: &str = "/var/lib/pacman/local";
+
+static REFRESH: AtomicBool = AtomicBool::new(false);
+
+/// Check every package again in this process instead of reusing cached results
+pub fn force_refresh() {
+    REFRESH.store(true, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    status: InstallStatus,
+    /// Unix time of the check
+    checked_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatusCache {
+    #[serde(default)]
+    packages: HashMap<String, Entry>,
+}
+
+impl StatusCache {
+    /// Cached results, or none when `--refresh` was given or the cache can't be read
+    pub fn load() -> Self {
+        if REFRESH.load(Ordering::Relaxed) {
+            return Self::default();
+        }
+        Self::read()
+    }
+
+    /// Persist these results over the ones on disk, keeping entries written by other runs
+    /// in the meantime
+    pub fn save(&self) -> Result<()> {
+        let mut merged = Self::read();
+        merged.packages.extend(self.packages.clone());
+        merged.write()
+    }
+
+    /// Status of a package if it was checked recently enough and pacman hasn't changed
+    /// anything since
+    pub fn get(&self, package: &str) -> Option<InstallStatus> {
+        let entry = self.packages.get(package)?;
+        let now = unix_now();
+        let database_changed = modified_at(Path::new(PACMAN_LOCAL_DB)).is_some_and(|changed| changed >= entry.checked_at);
+
+        (now.saturating_sub(entry.checked_at) < TTL.as_secs() && !database_changed).then(|| entry.status.clone())
+    }
+
+    pub fn insert(&mut self, package: &str, status: InstallStatus) {
+        self.packages.insert(package.to_string(), Entry { status, checked_at: unix_now() });
+    }
+
+    fn read() -> Self {
+        std::fs::read_to_string(cache_path())
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write(&self) -> Result<()> {
+        let path = cache_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Drop the cached status of a package ArchBox just installed or removed
+pub fn forget(package: &str) -> Result<()> {
+    let mut cache = StatusCache::read();
+    if cache.packages.remove(package).is_some() {
+        cache.write()?;
+    }
+    Ok(())
+}
+
+/// File the results are persisted in
+pub fn cache_path() -> PathBuf {
+    crate::config::get_cache_dir().join("installed-status.yaml")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn modified_at(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
@@ -0,0 +1,216 @@
+//! Secret references in post-install configuration
+//!
+//! Config file contents and environment values may contain `secret://<backend>/<name>`,
+//! resolved when the package is installed so definitions can be shared without credentials:
+//!
+//! - `secret://pass/github/token`: first line of `pass show github/token`
+//! - `secret://age/github-token`: `~/.config/archbox/secrets/github-token.age`, decrypted
+//!   with the identity in `~/.config/archbox/secrets/identity.txt`
+//! - `secret://env/GITHUB_TOKEN`: the `GITHUB_TOKEN` environment variable
+//!
+//! Every value resolved is remembered for the rest of the run so [`redact`] can keep it out
+//! of the transaction log and config diffs.
+
+use crate::{config::get_config_dir, Error, Result};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::process::Command;
+
+pub const SCHEME: &str = "secret://";
+
+const MASK: &str = "********";
+
+/// Values resolved so far in this run
+static REVEALED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Where a secret comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Pass,
+    Age,
+    Env,
+}
+
+impl Backend {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pass" => Some(Backend::Pass),
+            "age" => Some(Backend::Age),
+            "env" => Some(Backend::Env),
+            _ => None,
+        }
+    }
+}
+
+/// A `secret://` reference found in some text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretRef {
+    /// The reference as written
+    pub raw: String,
+    pub backend: Backend,
+    pub name: String,
+}
+
+/// Directory holding age-encrypted secrets and the identity that decrypts them
+pub fn secrets_dir() -> PathBuf {
+    get_config_dir().join("secrets")
+}
+
+/// Every `secret://` reference in `text`, in order. A name runs until whitespace, a quote
+/// or another character that can't appear in a secret path.
+pub fn references(text: &str) -> Result<Vec<SecretRef>> {
+    let mut found = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(SCHEME) {
+        let tail = &rest[start + SCHEME.len()..];
+        let len = tail
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "/._-@+".contains(c)))
+            .unwrap_or(tail.len());
+        let path = &tail[..len];
+        let raw = format!("{}{}", SCHEME, path);
+
+        let (backend, name) = path
+            .split_once('/')
+            .filter(|(_, name)| !name.is_empty())
+            .ok_or_else(|| Error::Config(format!("Invalid secret reference {}: expected {}<backend>/<name>", raw, SCHEME)))?;
+        let backend = Backend::from_name(backend)
+            .ok_or_else(|| Error::Config(format!("Unknown secret backend '{}' in {} (use pass, age or env)", backend, raw)))?;
+
+        found.push(SecretRef { raw, backend, name: name.to_string() });
+        rest = &tail[len..];
+    }
+
+    Ok(found)
+}
+
+/// Replace every `secret://` reference in `text` with its value
+pub async fn resolve(text: &str) -> Result<String> {
+    let mut resolved = text.to_string();
+
+    for reference in references(text)? {
+        let value = lookup(&reference).await?;
+        remember(&value);
+        resolved = resolved.replacen(&reference.raw, &value, 1);
+    }
+
+    Ok(resolved)
+}
+
+/// Mask every secret resolved in this run, also in its JSON-escaped form
+pub fn redact(text: &str) -> String {
+    let revealed = REVEALED.lock().unwrap_or_else(|e| e.into_inner());
+    let mut redacted = text.to_string();
+
+    for value in revealed.iter() {
+        redacted = redacted.replace(value.as_str(), MASK);
+
+        let escaped = serde_json::to_string(value).unwrap_or_default();
+        let escaped = escaped.trim_matches('"');
+        if escaped != value {
+            redacted = redacted.replace(escaped, MASK);
+        }
+    }
+
+    redacted
+}
+
+fn remember(value: &str) {
+    // Masking very short values would mangle unrelated text
+    if value.len() < 4 {
+        return;
+    }
+
+    let mut revealed = REVEALED.lock().unwrap_or_else(|e| e.into_inner());
+    if !revealed.iter().any(|known| known == value) {
+        revealed.push(value.to_string());
+    }
+}
+
+async fn lookup(reference: &SecretRef) -> Result<String> {
+    let fail = |reason: String| Error::Config(format!("Can't resolve {}: {}", reference.raw, reason));
+
+    match reference.backend {
+        Backend::Env => std::env::var(&reference.name).map_err(|_| fail("environment variable is not set".to_string())),
+        Backend::Pass => {
+            let output = Command::new("pass")
+                .args(["show", &reference.name])
+                .output()
+                .await
+                .map_err(|e| fail(format!("failed to run pass: {}", e)))?;
+            if !output.status.success() {
+                return Err(fail(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            }
+
+            // pass keeps the password on the first line and anything else below it
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(stdout.lines().next().unwrap_or_default().to_string())
+        }
+        Backend::Age => {
+            let dir = secrets_dir();
+            let file = dir.join(format!("{}.age", reference.name));
+            if !file.is_file() {
+                return Err(fail(format!("{} does not exist", file.display())));
+            }
+
+            let output = Command::new("age")
+                .arg("--decrypt")
+                .arg("-i")
+                .arg(dir.join("identity.txt"))
+                .arg(&file)
+                .output()
+                .await
+                .map_err(|e| fail(format!("failed to run age: {}", e)))?;
+            if !output.status.success() {
+                return Err(fail(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            }
+
+            Ok(String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_are_found_in_order() {
+        let text = r#"token = "secret://pass/github/token"; key=secret://env/API_KEY"#;
+        let found = references(text).unwrap();
+
+        assert_eq!(found, vec![
+            SecretRef { raw: "secret://pass/github/token".to_string(), backend: Backend::Pass, name: "github/token".to_string() },
+            SecretRef { raw: "secret://env/API_KEY".to_string(), backend: Backend::Env, name: "API_KEY".to_string() },
+        ]);
+        assert!(references("no secrets here").unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_references_are_rejected() {
+        assert!(references("secret://vault/token").is_err());
+        assert!(references("secret://pass/").is_err());
+        assert!(references("secret://pass").is_err());
+    }
+
+    #[tokio::test]
+    async fn references_resolve_to_their_values() {
+        std::env::set_var("ARCHBOX_TEST_RESOLVE", "s3cr\"et$value");
+
+        let resolved = resolve("token=secret://env/ARCHBOX_TEST_RESOLVE;").await.unwrap();
+        assert_eq!(resolved, "token=s3cr\"et$value;");
+        assert!(resolve("secret://env/ARCHBOX_TEST_UNSET").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn resolved_values_are_redacted() {
+        std::env::set_var("ARCHBOX_TEST_REDACT", "hunter\"2");
+        std::env::set_var("ARCHBOX_TEST_SHORT", "ab");
+        resolve("secret://env/ARCHBOX_TEST_REDACT secret://env/ARCHBOX_TEST_SHORT").await.unwrap();
+
+        assert_eq!(redact("password: hunter\"2"), "password: ********");
+        assert_eq!(redact(r#"{"password":"hunter\"2"}"#), r#"{"password":"********"}"#);
+        // Too short to mask without mangling other text
+        assert_eq!(redact("ab"), "ab");
+    }
+}
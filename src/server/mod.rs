@@ -0,0 +1,255 @@
+//! HTTP API for dashboards, served by `archbox serve` (built with the `server` feature)
+//!
+//! Read endpoints answer anyone who can reach the listen address, which defaults to
+//! localhost. Endpoints that change the system need `Authorization: Bearer <token>` with
+//! `server.token` from the config, and are disabled when no token is configured.
+//!
+//! | Endpoint                         | Body                                        |
+//! |----------------------------------|---------------------------------------------|
+//! | `GET /api/v1/status`             |                                             |
+//! | `GET /api/v1/packages`           | query: `category`, `installed`              |
+//! | `GET /api/v1/packages/{name}`    |                                             |
+//! | `GET /api/v1/search`             | query: `q`, `category`, `installed`         |
+//! | `GET /api/v1/history`            | query: `limit` (default 20), newest first   |
+//! | `POST /api/v1/install`           | `{"packages": [...], "force": false}`       |
+//! | `POST /api/v1/update`            | `{"check": false, "definitions": false}`    |
+//! | `POST /api/v1/reload`            |                                             |
+
+use crate::daemon::methods;
+use crate::{App, Error, Result};
+use axum::extract::{Json, Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::info;
+
+#[derive(Clone)]
+struct Server {
+    app: Arc<Mutex<App>>,
+    /// Resolved `server.token`
+    token: Option<Arc<str>>,
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    installed: bool,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    installed: bool,
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    #[serde(default = "default_history_limit")]
+    limit: usize,
+}
+
+fn default_history_limit() -> usize {
+    20
+}
+
+#[derive(Deserialize)]
+struct InstallBody {
+    packages: Vec<String>,
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Default, Deserialize)]
+struct UpdateBody {
+    #[serde(default)]
+    check: bool,
+    #[serde(default)]
+    definitions: bool,
+}
+
+/// An archbox error as an HTTP response: `{"error": "...", "kind": "..."}`
+struct ApiError(StatusCode, String, &'static str);
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let (status, kind) = match error.cause() {
+            Error::PackageNotFound(_) => (StatusCode::NOT_FOUND, "package_not_found"),
+            Error::Dependency(_) => (StatusCode::UNPROCESSABLE_ENTITY, "dependency"),
+            Error::UnsupportedPlatform { .. } => (StatusCode::UNPROCESSABLE_ENTITY, "unsupported_platform"),
+            Error::PermissionDenied { .. } => (StatusCode::FORBIDDEN, "permission_denied"),
+            Error::Network(_) => (StatusCode::BAD_GATEWAY, "network"),
+            Error::Timeout { .. } => (StatusCode::GATEWAY_TIMEOUT, "timeout"),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "failed"),
+        };
+        ApiError(status, error.to_string(), kind)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let ApiError(status, message, kind) = self;
+        (status, Json(json!({ "error": message, "kind": kind }))).into_response()
+    }
+}
+
+type ApiResult = std::result::Result<Json<Value>, ApiError>;
+
+fn to_json<T: serde::Serialize>(value: T) -> ApiResult {
+    Ok(Json(serde_json::to_value(value).map_err(Error::from)?))
+}
+
+/// Serve the API on `listen` until interrupted
+pub async fn serve(mut app: App, listen: &str) -> Result<()> {
+    methods::configure(&mut app);
+    let token = match &app.config.server.token {
+        Some(token) => Some(Arc::from(crate::secrets::resolve(token).await?)),
+        None => None,
+    };
+
+    let server = Server {
+        app: Arc::new(Mutex::new(app)),
+        token,
+    };
+    let router = Router::new()
+        .route("/api/v1/status", get(status))
+        .route("/api/v1/packages", get(packages))
+        .route("/api/v1/packages/{name}", get(package))
+        .route("/api/v1/search", get(search))
+        .route("/api/v1/history", get(history))
+        .route("/api/v1/install", post(install))
+        .route("/api/v1/update", post(update))
+        .route("/api/v1/reload", post(reload))
+        .with_state(server);
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!("Serving the HTTP API on {}", listen);
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Check the bearer token of a request to a mutating endpoint
+fn authorize(server: &Server, headers: &HeaderMap) -> std::result::Result<(), ApiError> {
+    let Some(expected) = &server.token else {
+        return Err(ApiError(
+            StatusCode::FORBIDDEN,
+            "Set server.token in the config to enable this endpoint".to_string(),
+            "disabled",
+        ));
+    };
+
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if constant_time_eq(given.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(ApiError(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token".to_string(), "unauthorized")),
+    }
+}
+
+/// Compare without returning early, so response times don't reveal how much of a guessed
+/// token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn status(State(server): State<Server>) -> ApiResult {
+    let mut app = server.app.lock().await;
+    let installed = methods::list(&app, None, true).await?.len();
+    let outdated = methods::check_updates(&mut app, false).await?;
+    let last_transaction = crate::history::transactions()?.pop();
+
+    Ok(Json(json!({
+        "host": crate::package::template::hostname(),
+        "version": env!("CARGO_PKG_VERSION"),
+        "definitions": app.repository.loader.packages().len(),
+        "definitions_age_secs": crate::repository::remote::definitions_age().map(|age| age.as_secs()),
+        "installed": installed,
+        "outdated": outdated,
+        "last_transaction": last_transaction,
+    })))
+}
+
+async fn packages(State(server): State<Server>, Query(query): Query<ListQuery>) -> ApiResult {
+    let app = server.app.lock().await;
+    to_json(methods::list(&app, query.category, query.installed).await?)
+}
+
+async fn package(State(server): State<Server>, Path(name): Path<String>) -> ApiResult {
+    let app = server.app.lock().await;
+    Ok(Json(methods::info(&app, &name).await?))
+}
+
+async fn search(State(server): State<Server>, Query(query): Query<SearchQuery>) -> ApiResult {
+    let app = server.app.lock().await;
+    to_json(methods::search(&app, &query.q, query.category, query.installed).await?)
+}
+
+async fn history(Query(query): Query<HistoryQuery>) -> ApiResult {
+    let mut transactions = crate::history::transactions()?;
+    transactions.reverse();
+    transactions.truncate(query.limit);
+    to_json(transactions)
+}
+
+async fn install(State(server): State<Server>, headers: HeaderMap, Json(body): Json<InstallBody>) -> ApiResult {
+    authorize(&server, &headers)?;
+    let mut app = server.app.lock().await;
+    let notifications = app.config.notifications.clone();
+
+    let outcomes = crate::daemon::transaction(
+        "serve install",
+        body.packages.clone(),
+        &notifications,
+        async {
+            app.repository.reload_state().await?;
+            methods::install(&mut app, &body.packages, body.force, None).await
+        },
+    )
+    .await?;
+    to_json(outcomes)
+}
+
+async fn update(State(server): State<Server>, headers: HeaderMap, body: Option<Json<UpdateBody>>) -> ApiResult {
+    authorize(&server, &headers)?;
+    let body = body.map(|Json(body)| body).unwrap_or_default();
+    let mut app = server.app.lock().await;
+
+    if body.check {
+        return to_json(methods::check_updates(&mut app, body.definitions).await?);
+    }
+
+    let notifications = app.config.notifications.clone();
+    let outcomes = crate::daemon::transaction(
+        "serve update",
+        Vec::new(),
+        &notifications,
+        async {
+            app.repository.reload_state().await?;
+            methods::update(&mut app, body.definitions, None).await
+        },
+    )
+    .await?;
+    to_json(outcomes)
+}
+
+async fn reload(State(server): State<Server>, headers: HeaderMap) -> ApiResult {
+    authorize(&server, &headers)?;
+    let mut app = server.app.lock().await;
+    Ok(Json(json!({ "packages": methods::reload(&mut app).await? })))
+}
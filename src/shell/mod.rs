@@ -0,0 +1,319 @@
+//! Shell detection and generation of shell startup snippets
+
+use crate::package::{layout, InstallScope};
+use crate::{secrets, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Shells ArchBox knows how to configure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ShellKind {
+    /// Any POSIX shell reading `~/.profile` (sh, dash, bash login shells)
+    #[value(name = "sh")]
+    Posix,
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl ShellKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.rsplit('/').next().unwrap_or(name);
+
+        match name {
+            "sh" | "dash" | "ksh" => Some(ShellKind::Posix),
+            "bash" => Some(ShellKind::Bash),
+            "zsh" => Some(ShellKind::Zsh),
+            "fish" => Some(ShellKind::Fish),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShellKind::Posix => "sh",
+            ShellKind::Bash => "bash",
+            ShellKind::Zsh => "zsh",
+            ShellKind::Fish => "fish",
+        }
+    }
+
+    /// Startup file that should source the ArchBox environment for this shell
+    pub fn startup_file(&self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+
+        match self {
+            ShellKind::Posix => Some(home.join(".profile")),
+            ShellKind::Bash => Some(home.join(".bashrc")),
+            ShellKind::Zsh => Some(home.join(".zshenv")),
+            // Fish sources everything in conf.d on its own
+            ShellKind::Fish => None,
+        }
+    }
+}
+
+/// Detect the shells in use: the login shell from `$SHELL`, plus any shell with existing
+/// configuration in the home directory. POSIX is always included for login sessions.
+pub fn detect_shells() -> Vec<ShellKind> {
+    let mut shells = vec![ShellKind::Posix];
+
+    if let Some(shell) = std::env::var("SHELL").ok().and_then(|s| ShellKind::from_name(&s)) {
+        shells.push(shell);
+    }
+
+    if let Some(home) = dirs::home_dir() {
+        if home.join(".zshrc").exists() || home.join(".zshenv").exists() {
+            shells.push(ShellKind::Zsh);
+        }
+        if home.join(".bashrc").exists() {
+            shells.push(ShellKind::Bash);
+        }
+        if home.join(".config/fish").is_dir() {
+            shells.push(ShellKind::Fish);
+        }
+    }
+
+    let mut unique = Vec::new();
+    for shell in shells {
+        if !unique.contains(&shell) {
+            unique.push(shell);
+        }
+    }
+    unique
+}
+
+/// A package environment value as it is exported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvValue {
+    /// Written in the definition: `~` and `$VAR` are expanded by the shell
+    Expand(String),
+    /// Holds resolved secrets, so it is written exactly as it is
+    Literal(String),
+}
+
+impl EnvValue {
+    /// How a definition's value is exported: expanded, unless it references secrets. A
+    /// literal value still holds the references until they are resolved.
+    pub fn from_definition(value: &str) -> Result<Self> {
+        Ok(if secrets::references(value)?.is_empty() {
+            EnvValue::Expand(value.to_string())
+        } else {
+            EnvValue::Literal(value.to_string())
+        })
+    }
+}
+
+/// Single-quote a word for `sh`
+pub fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// POSIX environment file holding every package's exports
+pub fn env_file_path() -> PathBuf {
+    crate::config::get_config_dir().join("env.sh")
+}
+
+/// Fish environment file, picked up automatically from `conf.d`
+pub fn fish_env_file_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".config"))
+        .join("fish/conf.d/archbox.fish")
+}
+
+/// Line sourcing the POSIX environment file if it exists
+pub fn source_line() -> String {
+    let path = env_file_path();
+    format!("[ -f \"{0}\" ] && . \"{0}\"", path.display())
+}
+
+/// Shell setup printed by `archbox init` and written into startup files: puts `binary_dir`
+/// on PATH with the version shims ahead of it, loads package environment variables and
+/// enables completions
+pub fn init_script(shell: ShellKind, binary_dir: &Path) -> String {
+    let bin = expand_tilde_segments(&binary_dir.display().to_string());
+    let shims = expand_tilde_segments(&layout::shims_dir(InstallScope::User).display().to_string());
+
+    match shell {
+        ShellKind::Fish => [
+            format!("fish_add_path --global \"{}\"", bin),
+            format!("fish_add_path --global \"{}\"", shims),
+            // conf.d/archbox.fish holds the exports and is sourced by fish itself
+            "if status is-interactive; and type -q archbox".to_string(),
+            "    archbox completions fish | source".to_string(),
+            "end".to_string(),
+        ]
+        .join("\n"),
+        _ => {
+            let mut lines = vec![
+                format!(
+                    "case \":$PATH:\" in *\":{0}:\"*) ;; *) export PATH=\"{0}:$PATH\" ;; esac",
+                    bin
+                ),
+                format!(
+                    "case \":$PATH:\" in *\":{0}:\"*) ;; *) export PATH=\"{0}:$PATH\" ;; esac",
+                    shims
+                ),
+                source_line(),
+            ];
+
+            match shell {
+                ShellKind::Bash => {
+                    lines.push("if [[ $- == *i* ]] && command -v archbox >/dev/null 2>&1; then".to_string());
+                    lines.push("    source <(archbox completions bash)".to_string());
+                    lines.push("fi".to_string());
+                }
+                ShellKind::Zsh => {
+                    // Picked up by compinit, which runs after .zshenv
+                    let dir = dirs::data_dir()
+                        .map(|dir| dir.join("zsh/site-functions"))
+                        .unwrap_or_default();
+                    lines.push(format!(
+                        "fpath=(\"{}\" $fpath)",
+                        expand_tilde_segments(&dir.display().to_string())
+                    ));
+                }
+                _ => {}
+            }
+
+            lines.join("\n")
+        }
+    }
+}
+
+/// Replace a leading `~/` in each colon-separated segment with `$HOME/`, since
+/// tilde is not expanded inside quotes
+fn expand_tilde_segments(value: &str) -> String {
+    value
+        .split(':')
+        .map(|segment| match segment.strip_prefix("~/") {
+            Some(rest) => format!("$HOME/{}", rest),
+            None if segment == "~" => "$HOME".to_string(),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn sorted_keys<V>(env: &HashMap<String, V>) -> Vec<&String> {
+    let mut keys: Vec<&String> = env.keys().collect();
+    keys.sort();
+    keys
+}
+
+/// `value` in double quotes with the `special` characters escaped. `$` is left alone so
+/// variables still expand.
+fn double_quote(value: &str, special: &[char]) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if special.contains(&c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Single-quote a word for fish, which also treats backslashes as escapes there
+fn fish_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// `export` lines for POSIX shells
+pub fn posix_exports(env: &HashMap<String, EnvValue>) -> String {
+    sorted_keys(env)
+        .into_iter()
+        .map(|key| {
+            let value = match &env[key] {
+                EnvValue::Expand(value) => double_quote(&expand_tilde_segments(value), &['"', '\\', '`']),
+                EnvValue::Literal(value) => quote(value),
+            };
+            format!("export {}={}", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `set -gx` lines for fish. Path-like variables are split into list elements.
+pub fn fish_exports(env: &HashMap<String, EnvValue>) -> String {
+    sorted_keys(env)
+        .into_iter()
+        .map(|key| {
+            let (value, literal) = match &env[key] {
+                EnvValue::Expand(value) => (expand_tilde_segments(value), false),
+                EnvValue::Literal(value) => (value.clone(), true),
+            };
+            let element = |part: &str| {
+                if literal {
+                    fish_quote(part)
+                } else if part.starts_with('$') && !part.contains('/') {
+                    part.to_string()
+                } else {
+                    double_quote(part, &['"', '\\'])
+                }
+            };
+
+            if key.ends_with("PATH") {
+                let parts: Vec<String> = value
+                    .split(':')
+                    .filter(|part| !part.is_empty())
+                    .map(element)
+                    .collect();
+                format!("set -gx {} {}", key, parts.join(" "))
+            } else if literal {
+                format!("set -gx {} {}", key, fish_quote(&value))
+            } else {
+                format!("set -gx {} {}", key, double_quote(&value, &['"', '\\']))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(values: &[(&str, EnvValue)]) -> HashMap<String, EnvValue> {
+        values.iter().map(|(key, value)| (key.to_string(), value.clone())).collect()
+    }
+
+    #[test]
+    fn definition_values_expand_unless_they_reference_secrets() {
+        assert_eq!(EnvValue::from_definition("$PATH:~/bin").unwrap(), EnvValue::Expand("$PATH:~/bin".to_string()));
+        assert_eq!(
+            EnvValue::from_definition("secret://env/TOKEN").unwrap(),
+            EnvValue::Literal("secret://env/TOKEN".to_string())
+        );
+    }
+
+    #[test]
+    fn posix_exports_escape_quotes() {
+        let env = env(&[
+            ("PATH", EnvValue::Expand("$PATH:~/.cargo/bin".to_string())),
+            ("QUOTED", EnvValue::Expand(r#"say "hi" `now` \o/"#.to_string())),
+            ("TOKEN", EnvValue::Literal(r#"a'b"c$d`e\f:~/g"#.to_string())),
+        ]);
+
+        assert_eq!(posix_exports(&env), [
+            r#"export PATH="$PATH:$HOME/.cargo/bin""#,
+            r#"export QUOTED="say \"hi\" \`now\` \\o/""#,
+            r#"export TOKEN='a'\''b"c$d`e\f:~/g'"#,
+        ].join("\n"));
+    }
+
+    #[test]
+    fn fish_exports_escape_quotes() {
+        let env = env(&[
+            ("PATH", EnvValue::Expand("$PATH:~/.cargo/bin".to_string())),
+            ("QUOTED", EnvValue::Expand(r#"say "hi" \o/"#.to_string())),
+            ("TOKEN", EnvValue::Literal(r#"a'b"c$d\f:~/g"#.to_string())),
+        ]);
+
+        assert_eq!(fish_exports(&env), [
+            r#"set -gx PATH $PATH "$HOME/.cargo/bin""#,
+            r#"set -gx QUOTED "say \"hi\" \\o/""#,
+            r#"set -gx TOKEN 'a\'b"c$d\\f:~/g'"#,
+        ].join("\n"));
+    }
+}
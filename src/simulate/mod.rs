@@ -0,0 +1,114 @@
+//! `--simulate`: exercise workflows without touching the system
+//!
+//! Installs, removals, upgrades and rollbacks go through their usual steps, but every
+//! backend action (pacman, AUR helpers, flatpak, downloads, file writes, services) is only
+//! reported. The state database and transaction log are updated as if the actions had
+//! succeeded. They live under [`state_dir`] rather than next to the real ones, so a
+//! simulated run never changes what archbox knows about the machine it runs on.
+
+use crate::{
+    config::Config,
+    explain::Plan,
+    history::LogEvent,
+    package::{
+        installer::InstallOutcome,
+        platform::Platform,
+        runner::{self, CommandRunner, CommandSpec},
+        InstallScope, Package,
+    },
+    t, Result,
+};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Simulate every backend action for the rest of this process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Where simulated runs keep their state database and transaction log
+pub fn state_dir() -> PathBuf {
+    crate::config::get_state_dir().join("simulation")
+}
+
+/// Report an action a real run would have taken, e.g. `run` `pacman -S ripgrep`
+pub fn action(kind: &str, detail: &str) {
+    info!("Simulated {}: {}", kind, detail);
+    crate::cli::print_info(&t!("simulate-action", kind = kind, detail = detail));
+    crate::cli::print_porcelain(&["simulated", kind, detail]);
+}
+
+/// Stand in for installing `package`: report each step of its plan, system dependencies
+/// included, and return the outcome of an install that made no post-install changes
+pub fn install(package: &Package, config: &Config, platform: &Platform, scope: Option<InstallScope>) -> Result<InstallOutcome> {
+    let plan = Plan::new(package, config, platform, scope)?;
+
+    for step in &plan.steps {
+        action(step.kind.name(), &step.detail);
+    }
+
+    Ok(InstallOutcome {
+        scope: plan.scope,
+        ..Default::default()
+    })
+}
+
+/// Versions of `package` a simulated rollback can switch between, most recently installed
+/// first: those successful installs logged since it was last removed
+pub fn installed_versions(package: &str) -> Result<Vec<String>> {
+    let mut versions: Vec<String> = Vec::new();
+
+    for record in crate::history::read_records()? {
+        let LogEvent::Step { step, package: Some(name), version, ok: true, .. } = record.event else {
+            continue;
+        };
+        if name != package {
+            continue;
+        }
+
+        match (step.as_str(), version) {
+            ("install", Some(version)) => {
+                versions.retain(|v| *v != version);
+                versions.insert(0, version);
+            }
+            ("remove", _) => versions.clear(),
+            _ => {}
+        }
+    }
+
+    Ok(versions)
+}
+
+/// Reports commands instead of running them. Package queries answer as if nothing were
+/// installed, since the state database is all a simulated system has; every other command
+/// succeeds with no output.
+#[derive(Debug, Default)]
+pub struct SimulatedRunner;
+
+#[async_trait]
+impl CommandRunner for SimulatedRunner {
+    async fn output(&self, command: &CommandSpec) -> std::io::Result<Output> {
+        let line = command.command_line();
+        if is_query(&line) {
+            return Ok(runner::output(1, Vec::new(), Vec::new()));
+        }
+
+        action("run", &line);
+        Ok(runner::output(0, Vec::new(), Vec::new()))
+    }
+}
+
+fn is_query(line: &str) -> bool {
+    ["pacman -Q", "pacman -T", "pacman -Si", "flatpak info"]
+        .iter()
+        .any(|query| line.starts_with(query))
+}
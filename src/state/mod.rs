@@ -0,0 +1,222 @@
+//! Persistent record of packages installed through ArchBox
+
+use crate::{config::get_state_dir, package::{InstallOutcome, InstallScope, Package, PostInstallAction, Provenance}, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// A package installed by ArchBox and the files it put on the system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub installed_at: String,
+
+    /// Whether the package was asked for or pulled in by another package
+    #[serde(default)]
+    pub reason: InstallReason,
+
+    /// Whether it was installed for the current user or system-wide
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<InstallScope>,
+
+    /// Config files written during post-install
+    #[serde(default)]
+    pub config_files: Vec<PathBuf>,
+
+    /// Post-install changes that are still in effect
+    #[serde(default)]
+    pub post_install: Vec<PostInstallAction>,
+
+    /// Where the installed artifacts came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+impl InstalledPackage {
+    /// Files that exist only because of this package: config files and drop-ins it wrote,
+    /// and files it created to hold a managed block
+    pub fn owned_files(&self) -> Vec<PathBuf> {
+        let mut files = self.config_files.clone();
+
+        for action in &self.post_install {
+            let path = match action {
+                PostInstallAction::ConfigFile { path, existed: false, .. }
+                | PostInstallAction::ManagedBlock { path, created: true, .. }
+                | PostInstallAction::DropIn { path, .. } => path,
+                _ => continue,
+            };
+            if !files.contains(path) {
+                files.push(path.clone());
+            }
+        }
+
+        files
+    }
+}
+
+/// Why a package was installed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallReason {
+    /// Requested on the command line (records from before this was tracked count as explicit)
+    #[default]
+    Explicit,
+    /// Installed to satisfy another package's dependency
+    Dependency,
+}
+
+/// On-disk database of installed packages
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StateDb {
+    #[serde(skip)]
+    path: PathBuf,
+
+    #[serde(default)]
+    packages: HashMap<String, InstalledPackage>,
+
+    /// Provider chosen for each capability, set with `archbox alternatives set`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    alternatives: BTreeMap<String, String>,
+}
+
+impl StateDb {
+    /// Load the state database from the default location
+    pub fn load() -> Result<Self> {
+        Self::load_from(get_state_path())
+    }
+
+    /// Load the state database from a specific file, starting empty if it does not exist
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let mut state = if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            serde_json::from_str::<StateDb>(&content)?
+        } else {
+            StateDb::default()
+        };
+
+        state.path = path.to_path_buf();
+        Ok(state)
+    }
+
+    /// Write the database to its file. It is written next to it first and renamed into
+    /// place, so a crash halfway leaves the previous state rather than a truncated file.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        let file_name = self.path.file_name().and_then(|name| name.to_str()).unwrap_or("state.json");
+        let partial = self.path.with_file_name(format!(".{}.archbox-new", file_name));
+        std::fs::write(&partial, content)?;
+        std::fs::rename(&partial, &self.path)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InstalledPackage> {
+        self.packages.get(name)
+    }
+
+    pub fn packages(&self) -> &HashMap<String, InstalledPackage> {
+        &self.packages
+    }
+
+    /// Record a successful installation. An explicit install marks the package explicit;
+    /// otherwise an existing record keeps its reason and a new one is a dependency.
+    pub fn record_install(&mut self, package: &Package, outcome: &InstallOutcome, explicit: bool) {
+        let existing = self.packages.get(&package.name);
+        let reason = match (explicit, existing) {
+            (true, _) => InstallReason::Explicit,
+            (false, Some(existing)) => existing.reason,
+            (false, None) => InstallReason::Dependency,
+        };
+        let mut config_files = existing
+            .map(|existing| existing.config_files.clone())
+            .unwrap_or_default();
+        let mut post_install = existing
+            .map(|existing| existing.post_install.clone())
+            .unwrap_or_default();
+
+        for path in &outcome.config_files {
+            if !config_files.contains(path) {
+                config_files.push(path.clone());
+            }
+        }
+
+        // Keep the earliest record per target so it still describes the pre-ArchBox state
+        for action in &outcome.actions {
+            if !post_install.iter().any(|recorded| recorded.same_target(action)) {
+                post_install.push(action.clone());
+            }
+        }
+
+        self.packages.insert(package.name.clone(), InstalledPackage {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            installed_at: chrono::Utc::now().to_rfc3339(),
+            reason,
+            scope: Some(outcome.scope),
+            config_files,
+            post_install,
+            provenance: outcome.provenance.clone(),
+        });
+    }
+
+    /// Record that a different version of an installed package is now active
+    pub fn set_version(&mut self, name: &str, version: &str) -> bool {
+        match self.packages.get_mut(name) {
+            Some(record) => {
+                record.version = version.to_string();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mark an installed package as explicitly installed
+    pub fn mark_explicit(&mut self, name: &str) -> bool {
+        match self.packages.get_mut(name) {
+            Some(record) if record.reason != InstallReason::Explicit => {
+                record.reason = InstallReason::Explicit;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Provider chosen for a capability
+    pub fn alternative(&self, capability: &str) -> Option<&str> {
+        self.alternatives.get(capability).map(String::as_str)
+    }
+
+    /// Every capability with a chosen provider
+    pub fn alternatives(&self) -> &BTreeMap<String, String> {
+        &self.alternatives
+    }
+
+    /// Choose the provider of a capability, or forget the choice with `None`
+    pub fn set_alternative(&mut self, capability: &str, provider: Option<&str>) {
+        match provider {
+            Some(provider) => {
+                self.alternatives.insert(capability.to_string(), provider.to_string());
+            }
+            None => {
+                self.alternatives.remove(capability);
+            }
+        }
+    }
+
+    /// Forget a package after it has been removed
+    pub fn remove(&mut self, name: &str) -> Option<InstalledPackage> {
+        self.packages.remove(name)
+    }
+}
+
+fn get_state_path() -> PathBuf {
+    let dir = if crate::simulate::is_enabled() { crate::simulate::state_dir() } else { get_state_dir() };
+    dir.join("state.json")
+}
@@ -0,0 +1,83 @@
+//! Scratch directories for builds and other work of a transaction
+//!
+//! Each step gets a directory of its own under `installation.temp_dir`, so runs in parallel
+//! never share one. The directory is removed when the step succeeds and kept when it fails,
+//! with its path added to the error so the sources and build output can be inspected.
+//! `archbox clean` removes kept directories once they are old.
+
+use crate::config::{get_cache_dir, InstallationConfig};
+use crate::{Error, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+/// A work directory, removed when dropped unless kept
+#[derive(Debug)]
+pub struct WorkDir {
+    dir: tempfile::TempDir,
+}
+
+impl WorkDir {
+    /// A new, empty directory whose name starts with `label`, e.g. `build-neovim-Xa3f9q`
+    pub fn create(config: &InstallationConfig, label: &str) -> Result<Self> {
+        let root = root(config);
+        std::fs::create_dir_all(&root)?;
+
+        let dir = tempfile::Builder::new().prefix(&format!("{}-", label)).tempdir_in(&root)?;
+        debug!("Created work directory {}", dir.path().display());
+        Ok(Self { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Leave the directory in place, returning where it is
+    pub fn keep(self) -> PathBuf {
+        self.dir.keep()
+    }
+
+    /// Remove the directory when `result` is a success, or keep it and name it in the error.
+    /// An error that already names a kept directory, from a nested step, is passed on as is.
+    pub fn finish<T>(self, result: Result<T>) -> Result<T> {
+        match result {
+            Err(source) if !matches!(source, Error::WorkKept { .. }) => Err(Error::WorkKept {
+                source: Box::new(source),
+                path: self.keep(),
+            }),
+            result => result,
+        }
+    }
+}
+
+/// Where work directories go: `installation.temp_dir`, or `work` in the user's cache
+/// directory. Not the shared temporary directory, where another user could create it first
+/// and swap the sources that get built.
+pub fn root(config: &InstallationConfig) -> PathBuf {
+    config.temp_dir.clone().unwrap_or_else(|| get_cache_dir().join("work"))
+}
+
+/// Remove work directories last changed more than `max_age` ago, returning the ones removed
+pub fn clean(config: &InstallationConfig, max_age: Duration) -> Result<Vec<PathBuf>> {
+    let Ok(entries) = std::fs::read_dir(root(config)) else {
+        return Ok(Vec::new());
+    };
+
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let age = metadata.modified().ok().and_then(|modified| now.duration_since(modified).ok());
+        if !metadata.is_dir() || age.is_none_or(|age| age < max_age) {
+            continue;
+        }
+
+        std::fs::remove_dir_all(entry.path())?;
+        removed.push(entry.path());
+    }
+
+    removed.sort();
+    Ok(removed)
+}
@@ -0,0 +1,113 @@
+//! Update information read from the ELF section table of an AppImage
+
+use archbox::package::appimage::{read_update_info, zsync_update, UpdateInfo};
+use archbox::package::runner::{output, RecordingRunner};
+use std::path::PathBuf;
+
+/// A little-endian 64-bit ELF image with a `.shstrtab` and a `.upd_info` section, followed
+/// by `padding` bytes that must never be read
+fn elf_image(update_info: &str, padding: usize) -> Vec<u8> {
+    let strtab = b"\0.shstrtab\0.upd_info\0";
+    let strtab_offset = 0x40u64;
+    let info_offset = strtab_offset + strtab.len() as u64;
+    let table_offset = info_offset + update_info.len() as u64;
+
+    let mut image = vec![0u8; 0x40];
+    image[..6].copy_from_slice(b"\x7fELF\x02\x01");
+    image[0x28..0x30].copy_from_slice(&table_offset.to_le_bytes());
+    image[0x3A..0x3C].copy_from_slice(&0x40u16.to_le_bytes());
+    image[0x3C..0x3E].copy_from_slice(&3u16.to_le_bytes());
+    image[0x3E..0x40].copy_from_slice(&1u16.to_le_bytes());
+    image.extend_from_slice(strtab);
+    image.extend_from_slice(update_info.as_bytes());
+
+    for (name, offset, size) in [(0u32, 0u64, 0u64), (1, strtab_offset, strtab.len() as u64), (11, info_offset, update_info.len() as u64)] {
+        let mut entry = vec![0u8; 0x40];
+        entry[..4].copy_from_slice(&name.to_le_bytes());
+        entry[0x18..0x20].copy_from_slice(&offset.to_le_bytes());
+        entry[0x20..0x28].copy_from_slice(&size.to_le_bytes());
+        image.extend(entry);
+    }
+
+    image.resize(image.len() + padding, 0);
+    image
+}
+
+#[tokio::test]
+async fn update_information_is_read_from_its_section() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("demo.AppImage");
+    std::fs::write(&path, elf_image("zsync|https://example.com/demo.AppImage.zsync\0\0", 4096)).unwrap();
+
+    assert_eq!(read_update_info(&path).await.unwrap(), Some(UpdateInfo::Zsync {
+        url: "https://example.com/demo.AppImage.zsync".to_string(),
+    }));
+}
+
+#[tokio::test]
+async fn section_tables_pointing_outside_the_image_are_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("broken.AppImage");
+
+    // Section table offset at the very end of the address space
+    let mut image = elf_image("zsync|https://example.com/broken.zsync", 0);
+    image[0x28..0x30].copy_from_slice(&u64::MAX.to_le_bytes());
+    std::fs::write(&path, &image).unwrap();
+    assert_eq!(read_update_info(&path).await.unwrap(), None);
+
+    // A section whose offset and size overflow when added
+    let mut image = elf_image("zsync|https://example.com/broken.zsync", 0);
+    let entry = image.len() - 0x40;
+    image[entry + 0x18..entry + 0x20].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+    image[entry + 0x20..entry + 0x28].copy_from_slice(&16u64.to_le_bytes());
+    std::fs::write(&path, &image).unwrap();
+    assert_eq!(read_update_info(&path).await.unwrap(), None);
+
+    std::fs::write(&path, b"\x7fELF").unwrap();
+    assert_eq!(read_update_info(&path).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn zsync_replaces_the_image_with_the_one_it_assembled() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("demo.AppImage");
+    std::fs::write(&path, b"old").unwrap();
+    let runner = RecordingRunner::new();
+    runner.on("zsync", |command| {
+        let output_path = PathBuf::from(&command.args[3]);
+        std::fs::write(output_path, b"new")?;
+        Ok(output(0, Vec::new(), Vec::new()))
+    });
+
+    zsync_update(&runner, &path, "https://example.com/demo.AppImage.zsync").await.unwrap();
+
+    assert_eq!(runner.command_lines(), [format!(
+        "zsync -i {} -o {} https://example.com/demo.AppImage.zsync",
+        path.display(),
+        dir.path().join("demo.AppImage.part").display()
+    )]);
+    assert_eq!(std::fs::read(&path).unwrap(), b"new");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o755);
+    }
+}
+
+#[tokio::test]
+async fn failed_zsync_updates_leave_the_image_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("demo.AppImage");
+    std::fs::write(&path, b"old").unwrap();
+    let runner = RecordingRunner::new();
+    runner.on("zsync", |command| {
+        std::fs::write(PathBuf::from(&command.args[3]), b"partial")?;
+        Ok(output(1, Vec::new(), b"checksum mismatch".to_vec()))
+    });
+
+    let error = zsync_update(&runner, &path, "https://example.com/demo.AppImage.zsync").await.unwrap_err();
+
+    assert!(error.to_string().contains("checksum mismatch"), "{}", error);
+    assert_eq!(std::fs::read(&path).unwrap(), b"old");
+    assert!(!dir.path().join("demo.AppImage.part").exists());
+}
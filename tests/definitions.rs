@@ -0,0 +1,402 @@
+//! The definition loader against the bundled definitions, a corpus of valid and invalid
+//! examples, and generated input: random text, YAML-like token soup and mutated bundled
+//! definitions. Whatever it is given, the loader either loads definitions or says which
+//! file, package and field are wrong.
+//!
+//! Each file in `corpus/definitions/invalid` starts with an `# expect: ...` line holding
+//! part of the error it must produce.
+
+use archbox::package::DefinitionLoader;
+use archbox::Error;
+use proptest::prelude::*;
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+fn corpus(kind: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/definitions").join(kind);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Every definition shipped in `data/packages`, keyed by name
+fn bundled() -> &'static [(String, Value)] {
+    static BUNDLED: std::sync::OnceLock<Vec<(String, Value)>> = std::sync::OnceLock::new();
+    BUNDLED.get_or_init(|| {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/packages");
+        let mut definitions = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                continue;
+            }
+            let content = std::fs::read_to_string(path).unwrap();
+            let Value::Mapping(entries) = serde_yaml::from_str(&content).unwrap() else {
+                panic!("bundled definition files are maps of package names to definitions");
+            };
+            for (name, definition) in entries {
+                definitions.push((name.as_str().unwrap().to_string(), definition));
+            }
+        }
+        definitions.sort_by(|a, b| a.0.cmp(&b.0));
+        definitions
+    })
+}
+
+/// The reason given for rejecting a file, after checking it names the file
+fn rejection(error: Error, source: &Path) -> String {
+    let Error::Config(message) = error else {
+        panic!("expected a configuration error, got {:?}", error);
+    };
+    let prefix = format!("Invalid package definition in {}: ", source.display());
+    let reason = message
+        .strip_prefix(&prefix)
+        .unwrap_or_else(|| panic!("error doesn't name the file: {}", message));
+    assert!(!reason.trim().is_empty(), "error gives no reason: {}", message);
+    reason.to_string()
+}
+
+#[tokio::test]
+async fn bundled_definitions_load() {
+    let mut loader = DefinitionLoader::new();
+    loader
+        .load_from_directory(Path::new(env!("CARGO_MANIFEST_DIR")).join("data/packages"))
+        .await
+        .unwrap();
+
+    assert_eq!(loader.packages().len(), bundled().len());
+}
+
+#[tokio::test]
+async fn valid_corpus_loads() {
+    for path in corpus("valid") {
+        let mut loader = DefinitionLoader::new();
+        if let Err(e) = loader.load_definition_file(&path).await {
+            panic!("{} was rejected: {}", path.display(), e);
+        }
+        assert!(!loader.packages().is_empty(), "{} loaded nothing", path.display());
+    }
+}
+
+#[tokio::test]
+async fn definitions_for_a_newer_archbox_are_skipped() {
+    let path = corpus("valid").into_iter().find(|path| path.ends_with("newer-archbox.yaml")).unwrap();
+    let mut loader = DefinitionLoader::new();
+    loader.load_definition_file(&path).await.unwrap();
+
+    assert!(loader.get_package("fd").is_some());
+    assert!(loader.get_package("future-tool").is_none());
+    assert!(loader.not_found("future-tool").to_string().contains("requires archbox >=99.0"));
+}
+
+#[tokio::test]
+async fn invalid_corpus_is_rejected_with_the_reason() {
+    let files = corpus("invalid");
+    assert!(!files.is_empty());
+
+    for path in files {
+        let content = std::fs::read_to_string(&path).unwrap();
+        let expected = content
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("# expect: "))
+            .unwrap_or_else(|| panic!("{} has no `# expect:` line", path.display()));
+
+        let mut loader = DefinitionLoader::new();
+        let error = match loader.load_definition_file(&path).await {
+            Ok(()) => panic!("{} was accepted", path.display()),
+            Err(e) => e,
+        };
+        let reason = rejection(error, &path);
+
+        assert!(reason.contains(expected), "{}: expected `{}`, got `{}`", path.display(), expected, reason);
+        assert!(loader.packages().is_empty(), "{} was partly loaded", path.display());
+    }
+}
+
+#[tokio::test]
+async fn defaults_are_merged_into_each_definition_of_a_file() {
+    let path = corpus("valid").into_iter().find(|path| path.ends_with("defaults.yaml")).unwrap();
+    let mut loader = DefinitionLoader::new();
+    loader.load_definition_file(&path).await.unwrap();
+    assert!(loader.get_package("defaults").is_none());
+
+    let kubectx = loader.get_package("kubectx").unwrap();
+    assert_eq!(kubectx.name, "kubectx");
+    assert_eq!(kubectx.categories, ["kubernetes"]);
+    assert_eq!(kubectx.metadata.author.as_deref(), Some("Ahmet Alp Balkan"));
+    assert_eq!(kubectx.metadata.license.as_deref(), Some("Apache-2.0"));
+    assert!(matches!(kubectx.installation, archbox::package::Installation::Binary { .. }));
+
+    let helm = loader.get_package("helm").unwrap();
+    assert_eq!(helm.categories, ["kubernetes", "development"]);
+    assert_eq!(helm.metadata.author.as_deref(), Some("Kubernetes SIG CLI"));
+    assert!(matches!(helm.installation, archbox::package::Installation::Pacman { .. }));
+}
+
+#[tokio::test]
+async fn templates_fill_in_fields_shared_by_similar_definitions() {
+    let path = corpus("valid").into_iter().find(|path| path.ends_with("template.yaml")).unwrap();
+    let mut loader = DefinitionLoader::new();
+    loader.load_definition_file(&path).await.unwrap();
+
+    let ripgrep = loader.get_package("ripgrep").unwrap();
+    assert_eq!(ripgrep.categories, ["terminal"]);
+    let archbox::package::Installation::Binary { url, install_path, executable, .. } = &ripgrep.installation else {
+        panic!("ripgrep takes its binary installation from the template");
+    };
+    assert_eq!(url, "https://github.com/BurntSushi/ripgrep/releases/download/v14.1.0/ripgrep-{arch}-unknown-linux-musl.tar.gz");
+    assert_eq!(install_path, "~/.local/bin/ripgrep");
+    assert!(executable);
+
+    // A definition's own fields win over the template's
+    let bat = loader.get_package("bat").unwrap();
+    let archbox::package::Installation::Binary { url, install_path, .. } = &bat.installation else {
+        panic!("bat takes its binary installation from the template");
+    };
+    assert_eq!(url, "https://github.com/sharkdp/bat/releases/download/v0.24.0/bat-{arch}-unknown-linux-musl.tar.gz");
+    assert_eq!(install_path, "~/bin/bat");
+}
+
+#[tokio::test]
+async fn a_directory_loads_around_broken_files() {
+    let mut loader = DefinitionLoader::new();
+    loader
+        .load_from_directory(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus/definitions"))
+        .await
+        .unwrap();
+
+    let failed: Vec<PathBuf> = loader.failures().iter().map(|failure| failure.path.clone()).collect();
+    assert_eq!(failed, corpus("invalid"));
+    assert!(loader.failures().iter().all(|failure| !failure.reason.is_empty()));
+    assert!(loader.get_package("bat").is_some());
+    assert!(loader.get_package("kubectl").is_some());
+}
+
+#[tokio::test]
+async fn fields_can_be_overridden_for_one_run() {
+    let path = corpus("valid").into_iter().find(|path| path.ends_with("binary-per-arch.yaml")).unwrap();
+    let mut loader = DefinitionLoader::new();
+    loader.load_definition_file(&path).await.unwrap();
+
+    let url = "https://dl.k8s.io/release/v1.30.0/bin/linux/amd64/kubectl";
+    let overrides = ["version=1.30".to_string(), format!("installation.urls.x86_64={}", url), "kernel_module=true".to_string()];
+    loader.override_fields("kubectl", &overrides).unwrap();
+
+    let kubectl = loader.get_package("kubectl").unwrap();
+    assert_eq!(kubectl.version, "1.30");
+    assert!(kubectl.kernel_module);
+    let archbox::package::Installation::Binary { urls, .. } = &kubectl.installation else {
+        panic!("kubectl is still a binary");
+    };
+    assert_eq!(urls.as_ref().unwrap()["x86_64"], url);
+
+    for (field, reason) in [
+        ("installation.mirror=https://example.com", "has no field installation.mirror"),
+        ("version", "expected FIELD=VALUE"),
+        ("name=kubectl2", "the name can't be changed"),
+        ("version.major=1", "version has no fields"),
+        ("description=", "missing description"),
+    ] {
+        let error = loader.override_fields("kubectl", &[field.to_string()]).unwrap_err().to_string();
+        assert!(error.contains(reason), "{}: expected `{}`, got `{}`", field, reason, error);
+    }
+    assert_eq!(loader.get_package("kubectl").unwrap().version, "1.30");
+}
+
+/// Where in a definition a mutation applies
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(Value),
+    Index(usize),
+}
+
+/// Paths to every node below the root of `value`
+fn paths(value: &Value, prefix: &mut Vec<Segment>, found: &mut Vec<Vec<Segment>>) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map {
+                prefix.push(Segment::Key(key.clone()));
+                found.push(prefix.clone());
+                paths(child, prefix, found);
+                prefix.pop();
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, child) in items.iter().enumerate() {
+                prefix.push(Segment::Index(i));
+                found.push(prefix.clone());
+                paths(child, prefix, found);
+                prefix.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parent<'a>(value: &'a mut Value, path: &[Segment]) -> &'a mut Value {
+    path.iter().fold(value, |node, segment| match segment {
+        Segment::Key(key) => node.get_mut(key).unwrap(),
+        Segment::Index(i) => node.get_mut(*i).unwrap(),
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    Remove(usize),
+    Replace(usize, Value),
+    Insert(usize, String, Value),
+}
+
+impl Mutation {
+    fn apply(&self, definition: &mut Value) {
+        let mut found = Vec::new();
+        paths(definition, &mut Vec::new(), &mut found);
+        if found.is_empty() {
+            return;
+        }
+
+        let (Mutation::Remove(i) | Mutation::Replace(i, _) | Mutation::Insert(i, ..)) = self;
+        let mut path = found[*i % found.len()].clone();
+        let last = path.pop().unwrap();
+        let container = parent(definition, &path);
+
+        match (self, last) {
+            (Mutation::Remove(_), Segment::Key(key)) => {
+                container.as_mapping_mut().unwrap().remove(&key);
+            }
+            (Mutation::Remove(_), Segment::Index(i)) => {
+                container.as_sequence_mut().unwrap().remove(i);
+            }
+            (Mutation::Replace(_, replacement), segment) => {
+                *parent(container, &[segment]) = replacement.clone();
+            }
+            (Mutation::Insert(_, key, inserted), segment) => match parent(container, &[segment]) {
+                Value::Mapping(map) => {
+                    map.insert(Value::String(key.clone()), inserted.clone());
+                }
+                target => *target = inserted.clone(),
+            },
+        }
+    }
+}
+
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(|n| Value::Number(n.into())),
+        any::<f64>().prop_map(|n| Value::Number(n.into())),
+        "[a-z0-9_.~/-]{0,12}".prop_map(Value::String),
+        Just(Value::String(String::new())),
+        Just(Value::String("pacman".to_string())),
+        Just(Value::Sequence(Vec::new())),
+        Just(Value::Mapping(Mapping::new())),
+    ]
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    prop_oneof![
+        any::<usize>().prop_map(Mutation::Remove),
+        (any::<usize>(), scalar()).prop_map(|(i, value)| Mutation::Replace(i, value)),
+        (
+            any::<usize>(),
+            prop_oneof![Just("method".to_string()), Just("name".to_string()), "[a-z_]{1,10}"],
+            scalar()
+        )
+            .prop_map(|(i, key, value)| Mutation::Insert(i, key, value)),
+    ]
+}
+
+/// A bundled definition with a few mutations applied, as a one-entry definition file
+fn mutated_definition() -> impl Strategy<Value = (String, String)> {
+    (0..bundled().len(), prop::collection::vec(mutation(), 1..4)).prop_map(|(i, mutations)| {
+        let (name, definition) = &bundled()[i];
+        let mut definition = definition.clone();
+        for mutation in &mutations {
+            mutation.apply(&mut definition);
+        }
+
+        let mut file = Mapping::new();
+        file.insert(Value::String(name.clone()), definition);
+        (name.clone(), serde_yaml::to_string(&file).unwrap())
+    })
+}
+
+fn yaml_like() -> impl Strategy<Value = String> {
+    let token = prop_oneof![
+        Just("\n".to_string()),
+        Just("  ".to_string()),
+        Just(": ".to_string()),
+        Just("- ".to_string()),
+        Just("{".to_string()),
+        Just("}".to_string()),
+        Just("[".to_string()),
+        Just("]".to_string()),
+        Just(", ".to_string()),
+        Just("\"".to_string()),
+        Just("&a ".to_string()),
+        Just("*a".to_string()),
+        Just("!!str ".to_string()),
+        Just("---\n".to_string()),
+        Just("name".to_string()),
+        Just("version".to_string()),
+        Just("installation".to_string()),
+        Just("method".to_string()),
+        Just("pacman".to_string()),
+        Just("packages".to_string()),
+        Just("requires_archbox".to_string()),
+        "[a-z0-9.]{1,6}",
+    ];
+    prop::collection::vec(token, 0..60).prop_map(|tokens| tokens.concat())
+}
+
+fn check_loads_or_explains(content: &str) -> Result<(), TestCaseError> {
+    let source = Path::new("generated.yaml");
+    let mut loader = DefinitionLoader::new();
+
+    if let Err(error) = loader.load_definitions(content, source) {
+        let Error::Config(message) = &error else {
+            return Err(TestCaseError::fail(format!("unexpected error kind: {:?}", error)));
+        };
+        let prefix = format!("Invalid package definition in {}: ", source.display());
+        prop_assert!(message.starts_with(&prefix), "error doesn't name the file: {}", message);
+        prop_assert!(message.len() > prefix.len(), "error gives no reason: {}", message);
+        prop_assert!(loader.packages().is_empty());
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_text_is_loaded_or_explained(content in any::<String>()) {
+        check_loads_or_explains(&content)?;
+    }
+
+    #[test]
+    fn yaml_like_text_is_loaded_or_explained(content in yaml_like()) {
+        check_loads_or_explains(&content)?;
+    }
+
+    #[test]
+    fn mutated_definitions_are_loaded_or_rejected_naming_the_package((name, content) in mutated_definition()) {
+        let source = Path::new("mutated.yaml");
+        let mut loader = DefinitionLoader::new();
+
+        match loader.load_definitions(&content, source) {
+            Ok(()) => {
+                // A mutated requires_archbox can skip the definition
+                let loaded = loader.get_package(&name).is_some();
+                prop_assert!(loaded || loader.not_found(&name).to_string().contains("requires archbox"));
+            }
+            Err(error) => {
+                let reason = rejection(error, source);
+                prop_assert!(reason.contains(&name), "error doesn't name {}: {}", name, reason);
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! GPU and machine class detection, and the group packages they select
+
+use archbox::groups::{find_conflict, settle_conflicts, GroupManager, PackageGroup};
+use archbox::hardware::{classify, parse_lspci, parse_vulkaninfo, GpuVendor, Hardware, MachineClass};
+
+#[test]
+fn gpus_are_read_from_lspci_display_controllers() {
+    let lspci = "\
+00:00.0 0600: 8086:4621 (rev 02)
+00:02.0 0300: 8086:46a6 (rev 0c)
+00:14.0 0c03: 8086:51ed (rev 01)
+01:00.0 0302: 10de:25a0 (rev a1)
+";
+    assert_eq!(parse_lspci(lspci), [GpuVendor::Intel, GpuVendor::Nvidia]);
+}
+
+#[test]
+fn gpus_are_read_from_vulkaninfo_without_software_renderers() {
+    let vulkaninfo = "\
+Devices:
+========
+GPU0:
+        apiVersion         = 1.3.289
+        vendorID           = 0x1002
+        deviceName         = AMD Radeon RX 6700 XT (RADV NAVI22)
+GPU1:
+        vendorID           = 0x10005
+        deviceName         = llvmpipe (LLVM 18.1.8, 256 bits)
+";
+    assert_eq!(parse_vulkaninfo(vulkaninfo), [GpuVendor::Amd]);
+}
+
+#[test]
+fn gaming_group_picks_the_driver_for_each_gpu() {
+    let manager = GroupManager::new();
+    let gaming = manager.get_group("gaming").unwrap();
+    let with_gpu = |gpu| Hardware { gpus: vec![gpu], hypervisor: None };
+
+    assert!(gaming.packages_for(&with_gpu(GpuVendor::Nvidia)).contains(&"nvidia-driver".to_string()));
+    assert!(!gaming.packages_for(&with_gpu(GpuVendor::Nvidia)).contains(&"mesa-vulkan-radeon".to_string()));
+    assert!(gaming.packages_for(&with_gpu(GpuVendor::Amd)).contains(&"mesa-vulkan-radeon".to_string()));
+    assert_eq!(gaming.packages_for(&Hardware::default()), gaming.packages);
+}
+
+#[test]
+fn machines_are_classified_by_hypervisor_then_chassis_and_battery() {
+    assert_eq!(classify(Some("10\n"), true, Some("kvm")), MachineClass::VirtualMachine);
+    assert_eq!(classify(Some("10\n"), false, None), MachineClass::Laptop);
+    assert_eq!(classify(Some("3\n"), true, None), MachineClass::Laptop);
+    assert_eq!(classify(Some("3\n"), false, None), MachineClass::Desktop);
+    assert_eq!(classify(None, false, None), MachineClass::Desktop);
+}
+
+#[test]
+fn profiles_with_machine_groups_get_the_groups_for_the_machine() {
+    let manager = GroupManager::new();
+    let gamer = manager.get_profile("gamer").unwrap();
+    let names = |class| -> Vec<String> {
+        manager.profile_groups(gamer, class).into_iter().map(|group| group.name.clone()).collect()
+    };
+
+    assert_eq!(names(MachineClass::Laptop), ["gaming", "laptop"]);
+    assert_eq!(names(MachineClass::VirtualMachine), ["gaming", "vm-guest"]);
+    assert_eq!(names(MachineClass::Desktop), ["gaming"]);
+
+    let vm_guest = manager.get_group("vm-guest").unwrap();
+    let under = |hypervisor: &str| Hardware { gpus: vec![], hypervisor: Some(hypervisor.to_string()) };
+    assert_eq!(vm_guest.packages_for(&under("oracle")), ["virtualbox-guest-utils"]);
+    assert_eq!(vm_guest.packages_for(&under("kvm")), ["qemu-guest-agent"]);
+}
+
+#[test]
+fn conflicting_groups_are_settled_by_preference_or_choice() {
+    let group = |name: &str, packages: &[&str], conflicts: &[&str]| PackageGroup {
+        name: name.to_string(),
+        description: String::new(),
+        packages: packages.iter().map(|p| p.to_string()).collect(),
+        optional_packages: vec![],
+        conflicts: conflicts.iter().map(|c| c.to_string()).collect(),
+        category: None,
+        hardware_packages: vec![],
+        machine: None,
+    };
+    let pipewire = group("pipewire", &["pipewire", "wireplumber"], &["pulseaudio"]);
+    let pulseaudio = group("pulseaudio", &["pulseaudio", "pavucontrol"], &[]);
+    let media = group("media", &["mpv"], &[]);
+    let hardware = Hardware::default();
+    let names = |groups: Vec<&PackageGroup>| -> Vec<String> { groups.into_iter().map(|g| g.name.clone()).collect() };
+
+    let conflict = find_conflict(&[&media, &pulseaudio, &pipewire], &hardware).unwrap();
+    assert_eq!((conflict.first.as_str(), conflict.second.as_str()), ("pulseaudio", "pipewire"));
+    assert_eq!(conflict.packages, ["pulseaudio"]);
+
+    let preferred = settle_conflicts(vec![&media, &pulseaudio, &pipewire], &["pipewire".to_string()], &hardware, |_| {
+        panic!("a preference settles the conflict without asking")
+    });
+    assert_eq!(names(preferred.unwrap()), ["media", "pipewire"]);
+
+    let chosen = settle_conflicts(vec![&media, &pulseaudio, &pipewire], &[], &hardware, |conflict| Ok(conflict.first.clone()));
+    assert_eq!(names(chosen.unwrap()), ["media", "pulseaudio"]);
+
+    let refused = settle_conflicts(vec![&pulseaudio, &pipewire], &[], &hardware, |conflict| Err(conflict.error()));
+    assert!(refused.unwrap_err().to_string().contains("pulseaudio"));
+
+    assert!(find_conflict(&[&media, &pipewire], &hardware).is_none());
+}
+
+#[test]
+fn group_files_add_groups_and_profiles_and_replace_built_in_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audio.yaml");
+    std::fs::write(&path, "\
+groups:
+  - name: pipewire
+    description: PipeWire sound server
+    packages: [pipewire, wireplumber]
+    conflicts: [pulseaudio]
+  - name: development
+    packages: [helix]
+profiles:
+  - name: gamer
+    groups: [gaming, pipewire]
+").unwrap();
+
+    let mut manager = GroupManager::new();
+    manager.load_group_file(&path).unwrap();
+
+    let pipewire = manager.get_group("pipewire").unwrap();
+    assert_eq!(pipewire.packages, ["pipewire", "wireplumber"]);
+    assert!(manager.is_user_group("pipewire"));
+    assert_eq!(manager.get_group("development").unwrap().packages, ["helix"]);
+    assert!(!manager.is_user_group("media"));
+
+    let gamer = manager.get_profile("gamer").unwrap();
+    assert_eq!(gamer.groups, ["gaming", "pipewire"]);
+    assert!(gamer.post_install_script.is_none());
+    assert!(manager.is_user_profile("gamer"));
+    assert!(!manager.is_user_profile("developer"));
+
+    std::fs::write(&path, "groups: [development]").unwrap();
+    assert!(manager.load_group_file(&path).is_err());
+}
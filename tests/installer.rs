@@ -0,0 +1,1460 @@
+//! Every installation method, run against a recording command runner instead of the system
+
+use archbox::config::Config;
+use archbox::package::db_lock::LockWaitRunner;
+use archbox::package::runner::{output, CommandRunner, CommandSpec, RecordingRunner, SystemRunner};
+use archbox::package::{InstallScope, InstallStatus, Installer, Package, Provenance};
+use archbox::repository::pending_updates::{self, PendingUpdate, PendingUpdates};
+use archbox::repository::Manager;
+use archbox::state::StateDb;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// A private home directory shared by every test in this file. Tests use distinct package
+/// names so they can run in parallel.
+fn home() -> &'static Path {
+    static HOME: OnceLock<PathBuf> = OnceLock::new();
+    HOME.get_or_init(|| {
+        let home = tempfile::tempdir().unwrap().keep();
+        std::env::set_var("HOME", &home);
+        std::env::set_var("XDG_CONFIG_HOME", home.join(".config"));
+        std::env::set_var("XDG_DATA_HOME", home.join(".local/share"));
+        std::env::set_var("XDG_STATE_HOME", home.join(".local/state"));
+        std::env::set_var("XDG_CACHE_HOME", home.join(".cache"));
+        std::env::set_var("SHELL", "/bin/sh");
+        std::env::set_var("USER", "tester");
+        // makepkg is run through runuser for this user when the tests run as root
+        std::env::set_var("SUDO_USER", "tester");
+        std::env::set_var("NO_PROXY", "127.0.0.1");
+        home
+    })
+}
+
+fn config() -> Config {
+    home();
+    let mut config = Config::default();
+    config.package_paths.clear();
+    config.installation.review_pkgbuild = false;
+    config.installation.build_cache = false;
+    config.installation.compiler_cache = false;
+    config
+}
+
+fn package(yaml: &str) -> Package {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+fn installer(config: &Config, runner: &Arc<RecordingRunner>) -> Installer {
+    Installer::new(config).with_runner(Arc::clone(runner) as Arc<dyn CommandRunner>)
+}
+
+/// Serve `body` over HTTP on a local port for as long as the tests run
+fn serve(body: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                match stream.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buffer[..n]),
+                }
+            }
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(body);
+        }
+    });
+
+    format!("http://{}/download", address)
+}
+
+fn sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[tokio::test]
+async fn pacman_installs_with_pacman_sync() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Qi", 0, "\
+Name            : ripgrep
+Version         : 14.1.1-1
+Packager        : Jane Packager <jane@archlinux.org>
+Validated By    : Signature
+
+Name            : fd
+Version         : 10.2.0-1
+Packager        : Jane Packager <jane@archlinux.org>
+Validated By    : Signature
+");
+    let package = package(r#"
+name: pacman-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [ripgrep, fd], flags: [--asexplicit]}
+metadata: {}
+"#);
+
+    let outcome = installer(&config(), &runner).install(&package).await.unwrap();
+
+    assert_eq!(outcome.scope, InstallScope::System);
+    assert_eq!(runner.command_lines(), [
+        "pacman -S --needed --noconfirm --asexplicit ripgrep fd",
+        "pacman -Qi ripgrep fd",
+    ]);
+    assert_eq!(outcome.provenance, Some(Provenance {
+        source: "pacman: ripgrep 14.1.1-1, fd 10.2.0-1".to_string(),
+        signed_by: Some("Jane Packager <jane@archlinux.org>".to_string()),
+        ..Default::default()
+    }));
+}
+
+#[tokio::test]
+async fn download_estimates_leave_out_cached_packages() {
+    let runner = RecordingRunner::new();
+    runner.respond("pacman -Sp", 0, "1000 https://mirror.example.com/core/os/x86_64/a-1-1-x86_64.pkg.tar.zst\n\
+        500 file:///var/cache/pacman/pkg/b-1-1-x86_64.pkg.tar.zst\n");
+    let package = package(r#"
+name: size-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [a, b]}
+metadata: {}
+"#);
+
+    let estimates = archbox::package::size::estimate_sizes(&runner, std::slice::from_ref(&package)).await;
+
+    assert_eq!(estimates["size-demo"].download, Some(1000));
+    assert_eq!(runner.command_lines()[0], "pacman -Sp --needed --print-format %s %l a b");
+}
+
+#[tokio::test]
+async fn aur_installs_through_the_configured_helper() {
+    let runner = Arc::new(RecordingRunner::new());
+    let mut config = config();
+    config.aur_helper = Some("paru".to_string());
+    let package = package(r#"
+name: aur-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: aur, package: aur-demo-bin}
+metadata: {}
+"#);
+
+    installer(&config, &runner).install(&package).await.unwrap();
+
+    assert_eq!(runner.command_lines(), [
+        "which paru",
+        "paru -S --needed --noconfirm --skipreview aur-demo-bin",
+    ]);
+}
+
+#[tokio::test]
+async fn reviewed_aur_packages_are_built_from_the_reviewed_checkout_rather_than_by_a_helper() {
+    const PKGBUILD: &str = "pkgname=aur-reviewed-demo\n";
+    let runner = Arc::new(RecordingRunner::new());
+    runner.on("git clone --depth 1", |command| {
+        let build_dir = PathBuf::from(command.args.last().unwrap());
+        std::fs::create_dir_all(&build_dir)?;
+        std::fs::write(build_dir.join("PKGBUILD"), PKGBUILD)?;
+        std::fs::write(build_dir.join(".SRCINFO"), "pkgbase = aur-reviewed-demo\n")?;
+        Ok(output(0, Vec::new(), Vec::new()))
+    });
+    // Approved when it was last built, so the review passes without a prompt
+    let reviewed = archbox::config::get_state_dir().join("aur/aur-reviewed-demo/PKGBUILD");
+    std::fs::create_dir_all(reviewed.parent().unwrap()).unwrap();
+    std::fs::write(&reviewed, PKGBUILD).unwrap();
+    let mut config = config();
+    config.aur_helper = Some("paru".to_string());
+    config.installation.review_pkgbuild = true;
+    let package = package(r#"
+name: aur-reviewed-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: aur, package: aur-reviewed-demo}
+metadata: {}
+"#);
+
+    installer(&config, &runner).install(&package).await.unwrap();
+
+    let commands = runner.commands();
+    assert!(!runner.command_lines().iter().any(|line| line.starts_with("paru")), "{:?}", runner.command_lines());
+    let clone = commands.iter().find(|command| command.to_string().starts_with("git clone")).unwrap();
+    let makepkg = commands.last().unwrap();
+    assert!(makepkg.to_string().ends_with("makepkg -si --noconfirm --needed"));
+    assert_eq!(makepkg.current_dir.as_deref(), Some(Path::new(clone.args.last().unwrap())));
+}
+
+#[tokio::test]
+async fn aur_falls_back_to_the_native_backend_without_a_helper() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("which", 1, "");
+    runner.respond("which git", 0, "/usr/bin/git");
+    runner.respond("which makepkg", 0, "/usr/bin/makepkg");
+    runner.on("git clone --depth 1", |command| {
+        let build_dir = PathBuf::from(command.args.last().unwrap());
+        std::fs::create_dir_all(&build_dir)?;
+        std::fs::write(build_dir.join("PKGBUILD"), "pkgname=aur-native-demo\n")?;
+        std::fs::write(build_dir.join(".SRCINFO"), "pkgbase = aur-native-demo\n\tdepends = libdemo\n\tmakedepends = cmake>=3\n")?;
+        Ok(output(0, Vec::new(), Vec::new()))
+    });
+    runner.respond("pacman -T", 127, "libdemo\ncmake>=3\n");
+    let package = package(r#"
+name: aur-native-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: aur, package: aur-native-demo}
+metadata: {}
+"#);
+
+    installer(&config(), &runner).install(&package).await.unwrap();
+
+    let lines = runner.command_lines();
+    assert_eq!(&lines[..3], ["which yay", "which paru", "which git"]);
+    assert!(lines[4].starts_with("git clone --depth 1 https://aur.archlinux.org/aur-native-demo.git "));
+    assert_eq!(lines[5], "git rev-parse HEAD");
+    assert_eq!(lines[6], "pacman -T libdemo cmake");
+    assert!(lines.iter().any(|line| line.ends_with("pacman -S --needed --noconfirm --asdeps libdemo cmake")));
+    assert!(lines.last().unwrap().ends_with("makepkg -si --noconfirm --needed"));
+
+    let makepkg = runner.commands().pop().unwrap();
+    assert!(makepkg.current_dir.unwrap().ends_with("aur-native-demo"));
+}
+
+#[tokio::test]
+async fn binary_is_downloaded_verified_and_linked() {
+    const BODY: &[u8] = b"#!/bin/sh\necho demo\n";
+    let runner = Arc::new(RecordingRunner::new());
+    let url = serve(BODY);
+    let package = package(&format!(r#"
+name: binary-demo
+version: "2.0"
+description: demo
+categories: []
+dependencies: []
+installation: {{method: binary, url: "{}", checksum: "{}", install_path: "~/.local/bin/binary-demo"}}
+metadata: {{}}
+"#, url, sha256(BODY)));
+
+    let outcome = installer(&config(), &runner).install(&package).await.unwrap();
+
+    assert_eq!(outcome.scope, InstallScope::User);
+    assert!(runner.commands().is_empty());
+    let link = home().join(".local/bin/binary-demo");
+    assert_eq!(std::fs::read(&link).unwrap(), BODY);
+    assert_eq!(
+        std::fs::read_link(&link).unwrap(),
+        home().join(".local/opt/binary-demo/2.0/binary-demo")
+    );
+    assert_eq!(outcome.provenance, Some(Provenance {
+        source: url,
+        sha256: Some(sha256(BODY)),
+        ..Default::default()
+    }));
+}
+
+#[tokio::test]
+async fn binary_with_a_wrong_checksum_is_not_installed() {
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(&format!(r#"
+name: binary-bad-demo
+version: "2.0"
+description: demo
+categories: []
+dependencies: []
+installation: {{method: binary, url: "{}", checksum: "{}", install_path: "~/.local/bin/binary-bad-demo"}}
+metadata: {{}}
+"#, serve(b"tampered"), sha256(b"original")));
+
+    let error = installer(&config(), &runner).install(&package).await.unwrap_err();
+
+    assert!(error.to_string().contains("Checksum mismatch"), "{}", error);
+    assert!(!home().join(".local/bin/binary-bad-demo").exists());
+}
+
+#[tokio::test]
+async fn binary_without_a_build_for_this_architecture_uses_its_fallback() {
+    const BODY: &[u8] = b"#!/bin/sh\necho static\n";
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(&format!(r#"
+name: binary-fallback-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation:
+  method: binary
+  urls: {{sparc64: "http://127.0.0.1:9/sparc64"}}
+  install_path: "~/.local/bin/binary-fallback-demo"
+  fallbacks:
+    - {{method: binary, url: "http://127.0.0.1:9/s390x", arch: [s390x]}}
+    - {{method: binary, url: "{}", checksum: "{}"}}
+    - {{method: source, url: "https://github.com/example/fallback-demo.git", build_commands: [make], install_commands: [make install]}}
+metadata: {{}}
+"#, serve(BODY), sha256(BODY)));
+
+    installer(&config(), &runner).install(&package).await.unwrap();
+
+    assert!(runner.commands().is_empty());
+    assert_eq!(std::fs::read(home().join(".local/bin/binary-fallback-demo")).unwrap(), BODY);
+}
+
+#[tokio::test]
+async fn source_is_cloned_and_built_in_one_directory() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -T", 127, "cmake\n");
+    runner.respond("git rev-parse HEAD", 0, "0123456789abcdef0123456789abcdef01234567\n");
+    let package = package(r#"
+name: source-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies:
+  - {name: cmake, dep_type: build}
+installation:
+  method: source
+  url: https://github.com/example/source-demo.git
+  build_commands: [make]
+  install_commands: [make install PREFIX=~/.local]
+metadata: {}
+"#);
+
+    let outcome = installer(&config(), &runner).install(&package).await.unwrap();
+
+    let lines = runner.command_lines();
+    assert_eq!(lines[0], "pacman -T cmake");
+    assert!(lines[1].ends_with("pacman -S --needed --noconfirm --asdeps cmake"));
+    assert_eq!(&lines[2..], [
+        "git clone https://github.com/example/source-demo.git .",
+        "git rev-parse HEAD",
+        "sh -c make",
+        "sh -c make install PREFIX=~/.local",
+    ]);
+
+    let commands = runner.commands();
+    let clone_dir = commands[2].current_dir.clone().unwrap();
+    assert!(commands[3..].iter().all(|command| command.current_dir.as_ref() == Some(&clone_dir)));
+    let provenance = outcome.provenance.unwrap();
+    assert_eq!(provenance.source, "https://github.com/example/source-demo.git");
+    assert_eq!(provenance.commit.as_deref(), Some("0123456789abcdef0123456789abcdef01234567"));
+}
+
+#[tokio::test]
+async fn source_builds_are_cached_per_commit_and_restored() {
+    const COMMIT: &str = "89abcdef0123456789abcdef0123456789abcdef";
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("git ls-remote", 0, &format!("{}\tHEAD\n", COMMIT));
+    runner.respond("git rev-parse HEAD", 0, &format!("{}\n", COMMIT));
+    let mut config = config();
+    config.installation.build_cache = true;
+    let package = package(r#"
+name: cached-source-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation:
+  method: source
+  url: https://github.com/example/cached-source-demo.git
+  build_commands: [make]
+  install_commands: [make install]
+metadata: {}
+"#);
+
+    installer(&config, &runner).install(&package).await.unwrap();
+    let lines = runner.command_lines();
+    assert_eq!(lines[..4], [
+        "git ls-remote https://github.com/example/cached-source-demo.git HEAD",
+        "git clone https://github.com/example/cached-source-demo.git .",
+        "git rev-parse HEAD",
+        "sh -c make",
+    ]);
+    // The built tree is copied into the cache before installing
+    let store = &runner.commands()[4];
+    assert_eq!(store.program, "cp");
+    assert!(store.args[2].to_string_lossy().contains("cached-source-demo"));
+    assert_eq!(lines[5], "sh -c make install");
+
+    // The same commit is restored from the cache rather than cloned and built again
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("git ls-remote", 0, &format!("{}\tHEAD\n", COMMIT));
+    installer(&config, &runner).install(&package).await.unwrap();
+    let commands = runner.commands();
+    assert_eq!(runner.command_lines()[0], "git ls-remote https://github.com/example/cached-source-demo.git HEAD");
+    assert_eq!(commands[1].program, "cp");
+    let staging = PathBuf::from(&store.args[2]);
+    let key = staging.file_name().unwrap().to_str().unwrap().trim_start_matches('.').trim_end_matches(".partial");
+    assert_eq!(commands[1].args[1], format!("{}/.", staging.with_file_name(key).display()).as_str());
+    assert_eq!(runner.command_lines()[2..], ["sh -c make install"]);
+}
+
+#[tokio::test]
+async fn failed_builds_keep_their_work_directory_until_cleaned() {
+    let mut config = config();
+    config.installation.temp_dir = Some(home().join("work"));
+    let definition = |name: &str| package(&format!(r#"
+name: {}
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation:
+  method: source
+  url: https://github.com/example/{}.git
+  build_commands: [make]
+  install_commands: [make install]
+metadata: {{}}
+"#, name, name));
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("sh -c make", 2, "");
+    let error = installer(&config, &runner).install(&definition("workdir-failed-demo")).await.unwrap_err();
+    let archbox::Error::Logged { source, .. } = &error else {
+        panic!("expected the install log to be named: {}", error);
+    };
+    let archbox::Error::WorkKept { path, .. } = source.as_ref() else {
+        panic!("expected the work directory to be kept: {}", error);
+    };
+    assert!(path.starts_with(home().join("work")) && path.is_dir());
+    assert!(error.to_string().contains(&format!("work files kept in {}", path.display())), "{}", error);
+    assert_eq!(error.code(), "installation_failed");
+
+    let runner = Arc::new(RecordingRunner::new());
+    installer(&config, &runner).install(&definition("workdir-ok-demo")).await.unwrap();
+    let build_dir = runner.commands()[0].current_dir.clone().unwrap();
+    assert!(!build_dir.exists());
+
+    let removed = archbox::workdir::clean(&config.installation, std::time::Duration::ZERO).unwrap();
+    assert!(removed.contains(path));
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn failed_installs_name_a_log_with_the_full_output_of_every_command() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.on("pacman -S", |_| Ok(output(1, b"resolving dependencies...\n".to_vec(), b"error: target not found: logged-demo\n".to_vec())));
+    let package = package(r#"
+name: logged-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [logged-demo]}
+metadata: {}
+"#);
+
+    let error = installer(&config(), &runner).install(&package).await.unwrap_err();
+
+    let archbox::Error::Logged { log, .. } = &error else {
+        panic!("expected the install log to be named: {}", error);
+    };
+    assert!(log.starts_with(archbox::package::install_log::log_dir("logged-demo")));
+    assert!(error.to_string().ends_with(&format!("(full output in {})", log.display())), "{}", error);
+    let content = std::fs::read_to_string(log).unwrap();
+    assert!(content.contains("pacman -S --needed --noconfirm logged-demo\n"), "{}", content);
+    assert!(content.contains("--- stdout ---\nresolving dependencies...\n--- stderr ---\nerror: target not found: logged-demo\n--- exit status: 1 ---"), "{}", content);
+}
+
+#[tokio::test]
+async fn builds_that_run_too_long_fail_with_a_timeout() {
+    let mut config = config();
+    config.installation.build_timeout = 90;
+    let runner = Arc::new(RecordingRunner::new());
+    runner.on("sh -c make", |_| Err(std::io::ErrorKind::TimedOut.into()));
+    let package = package(r#"
+name: timeout-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation:
+  method: source
+  url: https://github.com/example/timeout-demo.git
+  build_commands: [make]
+  install_commands: [make install]
+metadata: {}
+"#);
+
+    let error = installer(&config, &runner).install(&package).await.unwrap_err();
+
+    let archbox::Error::Timeout { operation, seconds } = error.cause() else {
+        panic!("expected a timeout: {}", error);
+    };
+    assert_eq!((operation.as_str(), *seconds), ("sh -c make", 90));
+    let build = runner.commands().into_iter().find(|command| command.command_line() == "sh -c make").unwrap();
+    assert_eq!(build.timeout, Some(std::time::Duration::from_secs(90)));
+}
+
+#[tokio::test]
+async fn system_runner_stops_everything_the_command_started_on_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let pid_file = dir.path().join("pid");
+    let mut command = CommandSpec::new("sh");
+    command
+        .args(["-c", &format!("sleep 30 & echo $! > {}; wait", pid_file.display())])
+        .timeout(Some(std::time::Duration::from_millis(300)));
+
+    let started = std::time::Instant::now();
+    let error = SystemRunner.output(&command).await.unwrap_err();
+
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+    let stat = || std::fs::read_to_string(format!("/proc/{}/stat", pid.trim())).unwrap_or_default();
+    let stopped = || stat().is_empty() || stat().contains(") Z ");
+    for _ in 0..50 {
+        if stopped() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert!(stopped(), "the background sleep is still running: {}", stat());
+}
+
+#[tokio::test]
+async fn script_runs_with_its_interpreter() {
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(r#"
+name: script-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "echo hello", interpreter: /bin/sh}
+metadata: {}
+"#);
+
+    installer(&config(), &runner).install(&package).await.unwrap();
+
+    let commands = runner.commands();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].program, "/bin/sh");
+    assert_eq!(commands[0].args.len(), 1);
+}
+
+#[tokio::test]
+async fn package_env_is_expanded_into_scripts_and_post_install_commands() {
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(r#"
+name: script-env-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "echo hello", interpreter: /bin/sh}
+post_install:
+  commands: ["make install"]
+env:
+  CARGO_BUILD_JOBS: "4"
+  PREFIX: ~/.local
+metadata: {}
+"#);
+
+    installer(&config(), &runner).install(&package).await.unwrap();
+
+    let expected = [
+        ("CARGO_BUILD_JOBS".into(), "4".into()),
+        ("PREFIX".into(), home().join(".local").into_os_string()),
+    ];
+    let commands = runner.commands();
+    assert_eq!(commands.len(), 2);
+    assert!(commands.iter().all(|command| command.env == expected), "{:?}", commands);
+
+    let mut broken = package.clone();
+    broken.env.insert("PREFIX".to_string(), "$ARCHBOX_UNSET_DEMO/bin".to_string());
+    let error = installer(&config(), &runner).install(&broken).await.unwrap_err();
+    assert!(error.to_string().contains("Can't expand PREFIX"), "{}", error);
+}
+
+#[tokio::test]
+async fn script_runs_only_if_it_matches_its_pinned_hash() {
+    let definition = |sha256: &str| package(&format!(r#"
+name: script-pinned-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {{method: script, script: "echo hello", interpreter: /bin/sh, script_sha256: "{}"}}
+metadata: {{}}
+"#, sha256));
+
+    let runner = Arc::new(RecordingRunner::new());
+    let error = installer(&config(), &runner).install(&definition(&sha256(b"echo goodbye"))).await.unwrap_err();
+    assert!(error.to_string().contains("Script hash mismatch"), "{}", error);
+    assert!(runner.commands().is_empty());
+
+    let runner = Arc::new(RecordingRunner::new());
+    installer(&config(), &runner)
+        .install(&definition(&sha256(b"echo hello").to_uppercase()))
+        .await
+        .unwrap();
+    assert_eq!(runner.commands().len(), 1);
+}
+
+#[tokio::test]
+async fn appimage_is_downloaded_and_integrated() {
+    const BODY: &[u8] = b"AppImage";
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(&format!(r#"
+name: appimage-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {{method: appimage, url: "{}", integrate: true}}
+metadata: {{}}
+"#, serve(BODY)));
+
+    installer(&config(), &runner).install(&package).await.unwrap();
+
+    let path = home().join(".local/share/applications/appimage-demo.AppImage");
+    assert_eq!(std::fs::read(&path).unwrap(), BODY);
+    assert_eq!(runner.command_lines(), [format!(
+        "{} --appimage-extract-and-run --appimage-extract",
+        path.display()
+    )]);
+}
+
+#[tokio::test]
+async fn flatpak_installs_from_a_configured_remote() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("flatpak remotes --user", 0, "flathub\n");
+    runner.respond("flatpak info", 0, "5f1e9c0d\n");
+    let package = package(r#"
+name: flatpak-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: flatpak, id: org.example.Demo, scope: user}
+metadata: {}
+"#);
+
+    let outcome = installer(&config(), &runner).install(&package).await.unwrap();
+
+    assert_eq!(outcome.scope, InstallScope::User);
+    assert_eq!(runner.command_lines(), [
+        "which flatpak",
+        "flatpak remotes --user --columns=name",
+        "flatpak install -y --noninteractive --user flathub org.example.Demo",
+        "flatpak info --user --show-commit org.example.Demo",
+    ]);
+    let provenance = outcome.provenance.unwrap();
+    assert_eq!(provenance.source, "flatpak: flathub org.example.Demo");
+    assert_eq!(provenance.commit.as_deref(), Some("5f1e9c0d"));
+}
+
+#[tokio::test]
+async fn reinstall_installs_again_even_if_up_to_date() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("flatpak remotes --user", 0, "flathub\n");
+    let pacman = package(r#"
+name: pacman-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [ripgrep]}
+metadata: {}
+"#);
+    let flatpak = package(r#"
+name: flatpak-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: flatpak, id: org.example.Demo, scope: user}
+metadata: {}
+"#);
+
+    let installer = installer(&config(), &runner).with_reinstall(true);
+    installer.install(&pacman).await.unwrap();
+    installer.install(&flatpak).await.unwrap();
+
+    let lines = runner.command_lines();
+    assert_eq!(lines[0], "pacman -S --noconfirm ripgrep");
+    assert!(lines.contains(&"flatpak install -y --noninteractive --reinstall --user flathub org.example.Demo".to_string()));
+}
+
+#[tokio::test]
+async fn post_install_runs_commands_and_enables_services() {
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(r#"
+name: post-install-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "true", interpreter: /bin/sh}
+post_install:
+  commands: [echo configured]
+  config_files:
+    "{home}/.config/post-install-demo/settings.conf": "user = {user}\n"
+  enable_services: ["user:post-install-demo.service"]
+  user_groups: [video]
+metadata: {}
+"#);
+
+    let outcome = installer(&config(), &runner).install(&package).await.unwrap();
+
+    assert_eq!(&runner.command_lines()[1..], [
+        "sh -c echo configured",
+        "systemctl --user is-enabled post-install-demo.service",
+        "systemctl --user enable --now post-install-demo.service",
+        "id -nG tester",
+        "usermod -a -G video tester",
+    ]);
+    let settings = std::fs::read_to_string(home().join(".config/post-install-demo/settings.conf")).unwrap();
+    assert!(settings.contains("user = tester"), "{}", settings);
+    assert_eq!(outcome.actions.len(), 3);
+}
+
+#[tokio::test]
+async fn pacman_hooks_and_tmpfiles_are_written_and_removed() {
+    let runner = Arc::new(RecordingRunner::new());
+    let root = tempfile::tempdir().unwrap();
+    let package = package(r#"
+name: hook-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [hook-demo]}
+post_install:
+  pacman_hooks:
+    - name: refresh
+      description: Refreshing the demo index...
+      triggers:
+        - {type: package, operations: [install, upgrade], targets: [hook-demo]}
+      when: pre_transaction
+      exec: /usr/bin/hook-demo --refresh
+      depends: [coreutils]
+      abort_on_fail: true
+  tmpfiles: ["d /var/lib/hook-demo 0755 root root -"]
+metadata: {}
+"#);
+
+    let installer = installer(&config(), &runner).with_root(root.path());
+    let outcome = installer.install(&package).await.unwrap();
+
+    let hook = std::fs::read_to_string(root.path().join("etc/pacman.d/hooks/90-archbox-hook-demo-refresh.hook")).unwrap();
+    assert_eq!(hook, "\
+# Managed by ArchBox for hook-demo; changes will be overwritten
+[Trigger]
+Operation = Install
+Operation = Upgrade
+Type = Package
+Target = hook-demo
+
+[Action]
+Description = Refreshing the demo index...
+When = PreTransaction
+Exec = /usr/bin/hook-demo --refresh
+Depends = coreutils
+AbortOnFail
+");
+    let tmpfiles = root.path().join("etc/tmpfiles.d/archbox-hook-demo.conf");
+    assert!(std::fs::read_to_string(&tmpfiles).unwrap().ends_with("d /var/lib/hook-demo 0755 root root -\n"));
+
+    installer.revert_on_removal(&package.name, &outcome.actions).await;
+    assert!(!root.path().join("etc/pacman.d/hooks/90-archbox-hook-demo-refresh.hook").exists());
+    assert!(!tmpfiles.exists());
+}
+
+#[tokio::test]
+async fn firewall_ports_are_opened_in_the_running_firewall_and_closed_on_removal() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("firewall-cmd --state", 252, "not running\n");
+    runner.respond("ufw status", 0, "Status: active\n");
+    let package = package(r#"
+name: firewall-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [firewall-demo]}
+post_install:
+  firewall:
+    - {port: 22000, protocol: both}
+    - {port: 6000, to: 6010, protocol: udp}
+metadata: {}
+"#);
+
+    let installer = installer(&config(), &runner);
+    let outcome = installer.install(&package).await.unwrap();
+    let opened: Vec<_> = runner.command_lines().into_iter().filter(|line| line.starts_with("ufw allow")).collect();
+    assert_eq!(opened, [
+        "ufw allow 22000/tcp comment archbox:firewall-demo",
+        "ufw allow 22000/udp comment archbox:firewall-demo",
+        "ufw allow 6000:6010/udp comment archbox:firewall-demo",
+    ]);
+
+    installer.revert_on_removal(&package.name, &outcome.actions).await;
+    let closed: Vec<_> = runner.command_lines().into_iter().filter(|line| line.starts_with("ufw delete")).collect();
+    assert_eq!(closed, ["ufw delete allow 22000/tcp", "ufw delete allow 22000/udp", "ufw delete allow 6000:6010/udp"]);
+}
+
+#[tokio::test]
+async fn config_drift_is_reported_against_what_was_written_and_declared() {
+    let runner = Arc::new(RecordingRunner::new());
+    let definition = |color: &str| package(&format!(r#"
+name: drift-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {{method: script, script: "true", interpreter: /bin/sh}}
+post_install:
+  config_files:
+    "{{home}}/.config/drift-demo/settings.json": "{{\"color\": \"{}\"}}\n"
+    "{{home}}/.config/drift-demo/extra.conf": "size = 2\n"
+metadata: {{}}
+"#, color));
+    let installed = definition("blue");
+    let outcome = installer(&config(), &runner).install(&installed).await.unwrap();
+    let mut state = StateDb::load_from(home().join("drift-state.json")).unwrap();
+    state.record_install(&installed, &outcome, true);
+    let record = state.get("drift-demo").unwrap();
+
+    let files = archbox::drift::inspect(&installed, record, &config()).await.unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().all(|file| !file.locally_modified() && !file.differs_from_definition()));
+
+    let settings = home().join(".config/drift-demo/settings.json");
+    std::fs::write(&settings, "{\"color\": \"green\"}\n").unwrap();
+    let extra = home().join(".config/drift-demo/extra.conf");
+    std::fs::write(&extra, format!("{}# local comment\n", std::fs::read_to_string(&extra).unwrap())).unwrap();
+
+    let files = archbox::drift::inspect(&definition("red"), record, &config()).await.unwrap();
+    let extra = files.iter().find(|file| file.path == extra).unwrap();
+    assert!(extra.block && !extra.locally_modified() && !extra.differs_from_definition());
+    let settings = files.iter().find(|file| file.path == settings).unwrap();
+    assert!(settings.locally_modified() && settings.differs_from_definition());
+    assert_eq!(settings.deployed.as_deref(), Some("{\"color\": \"blue\"}\n"));
+    assert_eq!(settings.declared.as_deref(), Some("{\"color\": \"red\"}\n"));
+}
+
+#[tokio::test]
+async fn managed_blocks_are_updated_in_place_and_removed_leaving_the_rest_of_the_file() {
+    let runner = Arc::new(RecordingRunner::new());
+    let definition = |alias: &str| package(&format!(r#"
+name: block-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {{method: script, script: "true", interpreter: /bin/sh}}
+post_install:
+  config_files:
+    "{{home}}/.config/block-demo/aliases.sh": "alias bd={}\n"
+    "{{home}}/.config/block-demo/settings.unknown": "alias = {}\n"
+metadata: {{}}
+"#, alias, alias));
+    let aliases = home().join(".config/block-demo/aliases.sh");
+    std::fs::create_dir_all(aliases.parent().unwrap()).unwrap();
+    std::fs::write(&aliases, "alias ll='ls -l'\n").unwrap();
+    let installer = installer(&config(), &runner);
+
+    installer.install(&definition("block-demo")).await.unwrap();
+    let outcome = installer.install(&definition("block-demo-2")).await.unwrap();
+
+    assert_eq!(std::fs::read_to_string(&aliases).unwrap(), "\
+alias ll='ls -l'
+
+# >>> archbox:block-demo >>>
+alias bd=block-demo-2
+# <<< archbox:block-demo <<<
+");
+    // No comment syntax is known for the extension, so the file is ArchBox's alone
+    let unknown = home().join(".config/block-demo/settings.unknown");
+    assert_eq!(std::fs::read_to_string(&unknown).unwrap(), "alias = block-demo-2\n");
+
+    installer.revert_on_removal("block-demo", &outcome.actions).await;
+    assert_eq!(std::fs::read_to_string(&aliases).unwrap(), "alias ll='ls -l'\n");
+}
+
+#[tokio::test]
+async fn failed_post_install_is_rolled_back() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("systemctl --user enable", 1, "");
+    let package = package(r#"
+name: rollback-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "true", interpreter: /bin/sh}
+post_install:
+  config_files:
+    "{home}/.config/rollback-demo/settings.conf": "enabled = true\n"
+  enable_services: ["user:rollback-demo.service"]
+  user_groups: [video]
+metadata: {}
+"#);
+
+    let error = installer(&config(), &runner).install(&package).await.unwrap_err();
+
+    assert!(error.to_string().contains("rolled back"), "{}", error);
+    assert!(!home().join(".config/rollback-demo/settings.conf").exists());
+    assert!(!runner.command_lines().iter().any(|line| line.starts_with("usermod")));
+}
+
+#[tokio::test]
+async fn rollback_puts_back_what_was_there_without_backups() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("systemctl --user is-enabled rollback-keep.service", 0, "enabled\n");
+    runner.respond("systemctl --user enable --now rollback-broken.service", 1, "");
+    let package = package(r#"
+name: rollback-keep-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "true", interpreter: /bin/sh}
+post_install:
+  config_files:
+    "{home}/.config/rollback-keep-demo/settings.json": "{\"new\": true}\n"
+  enable_services: ["user:rollback-keep.service", "user:rollback-new.service", "user:rollback-broken.service"]
+metadata: {}
+"#);
+    let settings = home().join(".config/rollback-keep-demo/settings.json");
+    std::fs::create_dir_all(settings.parent().unwrap()).unwrap();
+    std::fs::write(&settings, "{\"old\": true}\n").unwrap();
+    let mut config = config();
+    config.installation.create_backups = false;
+
+    installer(&config, &runner).install(&package).await.unwrap_err();
+
+    assert_eq!(std::fs::read_to_string(&settings).unwrap(), "{\"old\": true}\n");
+    let disabled: Vec<_> = runner.command_lines().into_iter().filter(|line| line.contains(" disable ")).collect();
+    assert_eq!(disabled, ["systemctl --user disable --now rollback-new.service"]);
+}
+
+#[tokio::test]
+async fn rollback_keeps_a_drop_in_written_by_an_earlier_install() {
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("systemctl --root", 1, "");
+    let root = tempfile::tempdir().unwrap();
+    let tmpfiles = root.path().join("etc/tmpfiles.d/archbox-drop-in-keep-demo.conf");
+    std::fs::create_dir_all(tmpfiles.parent().unwrap()).unwrap();
+    std::fs::write(&tmpfiles, "d /var/lib/drop-in-keep-demo 0700 root root -\n").unwrap();
+    let package = package(r#"
+name: drop-in-keep-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "true", interpreter: /bin/sh}
+post_install:
+  tmpfiles: ["d /var/lib/drop-in-keep-demo 0755 root root -"]
+  enable_services: [drop-in-keep-demo.service]
+metadata: {}
+"#);
+    let mut config = config();
+    config.installation.create_backups = false;
+
+    installer(&config, &runner).with_root(root.path()).install(&package).await.unwrap_err();
+
+    assert_eq!(std::fs::read_to_string(&tmpfiles).unwrap(), "d /var/lib/drop-in-keep-demo 0700 root root -\n");
+}
+
+#[tokio::test]
+async fn manager_checks_and_installs_through_the_runner() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("manager.yaml"), r#"
+manager-installed-demo:
+  name: manager-installed-demo
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [manager-installed-demo]}
+  metadata: {}
+manager-new-demo:
+  name: manager-new-demo
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies:
+    - {name: libdemo, dep_type: system}
+  installation: {method: pacman, packages: [manager-new-demo]}
+  metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    runner.respond("pacman -Q manager-installed-demo", 0, "manager-installed-demo 1.0-1\n");
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+
+    assert!(manager.is_installed("manager-installed-demo").await.unwrap());
+    assert!(!manager.is_installed("manager-new-demo").await.unwrap());
+
+    let package = manager.loader.get_package("manager-new-demo").unwrap().clone();
+    manager.install_package(&package, false, true).await.unwrap();
+
+    assert!(manager.is_installed("manager-new-demo").await.unwrap());
+    let lines = runner.command_lines();
+    assert_eq!(&lines[lines.len() - 3..], [
+        "pacman -S --needed --noconfirm libdemo",
+        "pacman -S --needed --noconfirm manager-new-demo",
+        "pacman -Qi manager-new-demo",
+    ]);
+}
+
+#[tokio::test]
+async fn installing_a_package_drops_it_from_the_pending_updates() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("pending.yaml"), r#"
+name: pending-update-demo
+version: "2.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [pending-update-demo]}
+metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let pending = |name: &str| PendingUpdate {
+        name: name.to_string(),
+        installed: "1.0".to_string(),
+        available: "2.0".to_string(),
+    };
+    pending_updates::record(vec![pending("pending-update-demo"), pending("pending-update-other")]).unwrap();
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    let package = manager.loader.get_package("pending-update-demo").unwrap().clone();
+    manager.install_package(&package, false, true).await.unwrap();
+
+    let left = PendingUpdates::load();
+    assert!(left.checked_at.is_some());
+    let names: Vec<_> = left.packages.iter().map(|update| update.name.as_str()).collect();
+    assert_eq!(names, ["pending-update-other"]);
+}
+
+#[tokio::test]
+async fn dependency_resolutions_are_reused_until_the_definitions_change() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("resolve.yaml"), r#"
+resolve-cache-app:
+  name: resolve-cache-app
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies:
+    - {name: resolve-cache-lib, dep_type: package}
+  installation: {method: pacman, packages: [resolve-cache-app]}
+  metadata: {}
+resolve-cache-lib:
+  name: resolve-cache-lib
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [resolve-cache-lib]}
+  metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    let requested = ["resolve-cache-app".to_string()];
+    let names = |packages: Vec<Package>| packages.into_iter().map(|package| package.name).collect::<Vec<_>>();
+
+    assert_eq!(names(manager.resolve_packages(&requested).await.unwrap()), ["resolve-cache-lib", "resolve-cache-app"]);
+
+    // Only the cached closure can leave the dependency out
+    let index = archbox::cache::default_dir().join("index.json");
+    let mut content: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&index).unwrap()).unwrap();
+    let cached = content["resolutions"]
+        .as_object_mut()
+        .unwrap()
+        .values_mut()
+        .find(|entry| entry["packages"] == serde_json::json!(["resolve-cache-lib", "resolve-cache-app"]))
+        .unwrap();
+    cached["packages"] = serde_json::json!(["resolve-cache-app"]);
+    std::fs::write(&index, content.to_string()).unwrap();
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    assert_eq!(names(manager.resolve_packages(&requested).await.unwrap()), ["resolve-cache-app"]);
+
+    manager.override_definition("resolve-cache-lib", &["version=1.1".to_string()]).unwrap();
+    assert_eq!(names(manager.resolve_packages(&requested).await.unwrap()), ["resolve-cache-lib", "resolve-cache-app"]);
+}
+
+#[tokio::test]
+async fn optional_dependencies_are_offered_only_when_they_can_be_added() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("optional.yaml"), r#"
+optional-demo:
+  name: optional-demo
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies:
+    - {name: optional-demo-extra, dep_type: package, optional: true}
+    - {name: optional-demo-installed, dep_type: package, optional: true}
+    - {name: optional-demo-undefined, dep_type: package, optional: true}
+    - {name: optional-demo-lib, dep_type: system, optional: true}
+  installation: {method: pacman, packages: [optional-demo]}
+  metadata: {}
+optional-demo-extra:
+  name: optional-demo-extra
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [optional-demo-extra]}
+  metadata: {}
+optional-demo-installed:
+  name: optional-demo-installed
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [optional-demo-installed]}
+  metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    runner.respond("pacman -Q optional-demo-installed", 0, "optional-demo-installed 1.0-1\n");
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+
+    let packages = manager.resolve_packages(&["optional-demo".to_string()]).await.unwrap();
+    assert_eq!(packages.iter().map(|package| package.name.as_str()).collect::<Vec<_>>(), ["optional-demo"]);
+
+    let optional = manager.optional_dependencies(&packages).await.unwrap();
+    let offered: Vec<(&str, &str)> = optional.iter().map(|(package, by)| (package.name.as_str(), by.as_str())).collect();
+    assert_eq!(offered, [("optional-demo-extra", "optional-demo")]);
+}
+
+#[tokio::test]
+async fn manager_reuses_recent_pacman_checks() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("status.yaml"), r#"
+name: status-cache-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [status-cache-demo]}
+metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let first = Arc::new(RecordingRunner::new());
+    first.respond("pacman -Q status-cache-demo", 0, "status-cache-demo 1.0-1\n");
+    Manager::with_runner(&config, Arc::clone(&first) as Arc<dyn CommandRunner>).await.unwrap();
+    assert_eq!(first.command_lines(), ["pacman -Q status-cache-demo"]);
+
+    let second = Arc::new(RecordingRunner::new());
+    let manager = Manager::with_runner(&config, Arc::clone(&second) as Arc<dyn CommandRunner>).await.unwrap();
+    assert!(second.command_lines().is_empty());
+    assert!(manager.is_installed("status-cache-demo").await.unwrap());
+}
+
+#[tokio::test]
+async fn manager_reports_every_status_in_one_pass() {
+    let definitions = tempfile::tempdir().unwrap();
+    let write = |outdated_version: &str| std::fs::write(definitions.path().join("statuses.yaml"), format!(r#"
+statuses-installed-demo:
+  name: statuses-installed-demo
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {{method: pacman, packages: [statuses-installed-demo]}}
+  metadata: {{}}
+statuses-outdated-demo:
+  name: statuses-outdated-demo
+  version: "{}"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {{method: pacman, packages: [statuses-outdated-demo]}}
+  metadata: {{}}
+statuses-missing-demo:
+  name: statuses-missing-demo
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {{method: pacman, packages: [statuses-missing-demo]}}
+  metadata: {{}}
+statuses-broken-demo:
+  name: statuses-broken-demo
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {{method: pacman, packages: [statuses-broken-demo]}}
+  metadata: {{}}
+"#, outdated_version)).unwrap();
+    write("1.0");
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    runner.respond("pacman -Q statuses-installed-demo", 0, "statuses-installed-demo 1.0-1\n");
+    runner.on("pacman -Q statuses-broken-demo", |_| Err(std::io::Error::other("pacman crashed")));
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    let package = manager.loader.get_package("statuses-outdated-demo").unwrap().clone();
+    manager.install_package(&package, false, true).await.unwrap();
+
+    // A newer definition of what ArchBox installed is an available update
+    write("2.0");
+    runner.respond("pacman -Q statuses-outdated-demo", 0, "statuses-outdated-demo 1.0-1\n");
+    let manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    let checks = runner.command_lines().len();
+
+    let names = ["statuses-installed-demo", "statuses-outdated-demo", "statuses-missing-demo", "statuses-broken-demo"];
+    let statuses = manager.statuses(&names).await;
+
+    assert_eq!(runner.command_lines().len(), checks);
+    assert!(matches!(&statuses["statuses-installed-demo"], InstallStatus::Installed { version, .. } if version == "1.0-1"));
+    assert!(matches!(
+        &statuses["statuses-outdated-demo"],
+        InstallStatus::UpdateAvailable { current, available } if current == "1.0" && available == "2.0"
+    ));
+    assert!(matches!(statuses["statuses-missing-demo"], InstallStatus::NotInstalled));
+    assert!(matches!(&statuses["statuses-broken-demo"], InstallStatus::Error { message } if message.contains("pacman crashed")));
+}
+
+#[tokio::test]
+async fn groups_added_by_post_install_need_a_relogin_until_the_session_has_them() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("relogin.yaml"), r#"
+name: relogin-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: pacman, packages: [relogin-demo]}
+post_install:
+  user_groups: [relogin-demo-group]
+metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    runner.respond("id -nG", 0, "tester wheel\n");
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    let package = manager.loader.get_package("relogin-demo").unwrap().clone();
+    manager.install_package(&package, false, true).await.unwrap();
+
+    assert!(runner.command_lines().contains(&"usermod -a -G relogin-demo-group tester".to_string()));
+    assert_eq!(manager.pending_relogin_groups().await.unwrap(), ["relogin-demo-group"]);
+
+    runner.respond("id -nG", 0, "tester wheel relogin-demo-group\n");
+    assert!(manager.pending_relogin_groups().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn pacman_is_retried_once_another_transaction_releases_the_database_lock() {
+    let root = tempfile::tempdir().unwrap();
+    let locked = Arc::new(RecordingRunner::new());
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&attempts);
+    locked.on("pacman --sysroot", move |_| {
+        let stderr = b"error: failed to init transaction (unable to lock database)\n".to_vec();
+        Ok(match counter.fetch_add(1, Ordering::SeqCst) {
+            0 => output(1, Vec::new(), stderr),
+            _ => output(0, Vec::new(), Vec::new()),
+        })
+    });
+    let mut command = CommandSpec::new("pacman");
+    command.arg("--sysroot").arg(root.path()).args(["-S", "--noconfirm", "lock-demo"]);
+
+    let runner = LockWaitRunner::new(Arc::clone(&locked) as Arc<dyn CommandRunner>, Duration::from_secs(30));
+    assert!(runner.output(&command).await.unwrap().status.success());
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    attempts.store(0, Ordering::SeqCst);
+    let impatient = LockWaitRunner::new(Arc::clone(&locked) as Arc<dyn CommandRunner>, Duration::ZERO);
+    assert!(!impatient.output(&command).await.unwrap().status.success());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn commands_other_than_pacman_are_not_retried_on_a_lock_error() {
+    let locked = Arc::new(RecordingRunner::new());
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&attempts);
+    locked.on("flatpak", move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Ok(output(1, Vec::new(), b"error: unable to lock database\n".to_vec()))
+    });
+
+    let runner = LockWaitRunner::new(Arc::clone(&locked) as Arc<dyn CommandRunner>, Duration::from_secs(30))
+        .with_aur_helper(Some("paru".to_string()));
+    let command = CommandSpec::new("flatpak");
+    assert!(!runner.output(&command).await.unwrap().status.success());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn purge_deletes_the_config_files_a_package_wrote() {
+    let runner = Arc::new(RecordingRunner::new());
+    let package = package(r#"
+name: purge-demo
+version: "1.0"
+description: demo
+categories: []
+dependencies: []
+installation: {method: script, script: "true", interpreter: /bin/sh}
+post_install:
+  config_files:
+    "{home}/.config/purge-demo/settings.json": "{\"purge\": true}\n"
+metadata: {}
+"#);
+    let outcome = installer(&config(), &runner).install(&package).await.unwrap();
+    let mut state = StateDb::load_from(home().join("purge-state.json")).unwrap();
+    state.record_install(&package, &outcome, true);
+    state.save().unwrap();
+    let record = StateDb::load_from(home().join("purge-state.json")).unwrap().get("purge-demo").unwrap().clone();
+
+    let settings = home().join(".config/purge-demo/settings.json");
+    assert_eq!(record.config_files, std::slice::from_ref(&settings));
+    assert!(!home().join(".purge-state.json.archbox-new").exists());
+
+    // Purging backs the file up even with backups turned off for overwrites
+    archbox::cli::commands::remove::purge_config_files(&record).await;
+    assert!(!settings.exists());
+    let backups = archbox::backup::list_backups("purge-demo").unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].files, [settings]);
+}
+
+#[tokio::test]
+async fn removal_cascades_to_installed_dependents_before_their_dependencies() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("cascade.yaml"), r#"
+cascade-app:
+  name: cascade-app
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies:
+    - {name: cascade-lib, dep_type: package}
+  installation: {method: pacman, packages: [cascade-app]}
+  metadata: {}
+cascade-lib:
+  name: cascade-lib
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [cascade-lib]}
+  metadata: {}
+cascade-other:
+  name: cascade-other
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [cascade-other]}
+  metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+
+    let runner = Arc::new(RecordingRunner::new());
+    runner.respond("pacman -Q", 1, "");
+    let mut manager = Manager::with_runner(&config, Arc::clone(&runner) as Arc<dyn CommandRunner>).await.unwrap();
+    for name in ["cascade-lib", "cascade-app", "cascade-other"] {
+        let package = manager.loader.get_package(name).unwrap().clone();
+        manager.install_package(&package, false, true).await.unwrap();
+    }
+
+    let cascade = manager.removal_cascade(&["cascade-lib".to_string()]);
+    let names: Vec<_> = cascade.iter().map(|package| package.name.as_str()).collect();
+    assert_eq!(names, ["cascade-app", "cascade-lib"]);
+}
+
+#[tokio::test]
+async fn daemon_installs_keep_what_the_cli_installed_while_it_was_running() {
+    let definitions = tempfile::tempdir().unwrap();
+    std::fs::write(definitions.path().join("daemon.yaml"), r#"
+daemon-state-cli:
+  name: daemon-state-cli
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [daemon-state-cli]}
+  metadata: {}
+daemon-state-daemon:
+  name: daemon-state-daemon
+  version: "1.0"
+  description: demo
+  categories: []
+  dependencies: []
+  installation: {method: pacman, packages: [daemon-state-daemon]}
+  metadata: {}
+"#).unwrap();
+    let mut config = config();
+    config.package_paths = vec![definitions.path().to_path_buf()];
+    let manager = || async {
+        let runner = Arc::new(RecordingRunner::new());
+        runner.respond("pacman -Q", 1, "");
+        Manager::with_runner(&config, runner as Arc<dyn CommandRunner>).await.unwrap()
+    };
+
+    // The daemon loads the state before the CLI installs anything
+    let socket = home().join("daemon-state.sock");
+    let listener = archbox::daemon::bind(&socket).await.unwrap();
+    let app = archbox::App { config: config.clone(), repository: manager().await };
+    let daemon = tokio::spawn({
+        let socket = socket.clone();
+        async move { archbox::daemon::serve(app, listener, &socket, false).await }
+    });
+
+    let mut cli = manager().await;
+    let package = cli.loader.get_package("daemon-state-cli").unwrap().clone();
+    cli.install_package(&package, false, true).await.unwrap();
+
+    let stream = tokio::net::UnixStream::connect(&socket).await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let request = r#"{"jsonrpc": "2.0", "id": 1, "method": "install", "params": {"packages": ["daemon-state-daemon"]}}"#;
+    tokio::io::AsyncWriteExt::write_all(&mut writer, format!("{}\n", request).as_bytes()).await.unwrap();
+    let mut response = String::new();
+    tokio::io::AsyncBufReadExt::read_line(&mut tokio::io::BufReader::new(reader), &mut response).await.unwrap();
+    daemon.abort();
+
+    assert!(response.contains("\"ok\":true"), "{}", response);
+    let state = StateDb::load().unwrap();
+    assert!(state.get("daemon-state-cli").is_some());
+    assert!(state.get("daemon-state-daemon").is_some());
+}
@@ -0,0 +1,32 @@
+//! The transaction lock taken by commands that change the system
+
+use archbox::lock;
+use archbox::Error;
+use std::time::Duration;
+
+#[tokio::test]
+async fn second_transaction_fails_or_waits_until_the_first_finishes() {
+    let runtime = tempfile::tempdir().unwrap();
+    std::env::set_var("XDG_RUNTIME_DIR", runtime.path());
+
+    let first = lock::acquire("install ripgrep", false, |_| {}).await.unwrap();
+
+    let error = lock::acquire("remove fd", false, |_| {}).await.unwrap_err();
+    let Error::Locked { holder } = &error else {
+        panic!("expected the lock to be held: {}", error);
+    };
+    assert!(holder.starts_with(&format!("PID {} (install ripgrep) since ", std::process::id())), "{}", holder);
+
+    let (waiting, mut waited) = tokio::sync::oneshot::channel();
+    let second = tokio::spawn(lock::acquire("remove fd", true, move |holder: &str| {
+        let _ = waiting.send(holder.to_string());
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(waited.try_recv().unwrap().contains("(install ripgrep)"));
+    assert!(!second.is_finished());
+
+    drop(first);
+    let _second = tokio::time::timeout(Duration::from_secs(5), second).await.unwrap().unwrap().unwrap();
+    let content = std::fs::read_to_string(lock::lock_path()).unwrap();
+    assert!(content.ends_with("\tremove fd\n"), "{}", content);
+}